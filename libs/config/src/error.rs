@@ -0,0 +1,47 @@
+//! Lỗi khi load và kiểm tra cấu hình.
+
+use std::fmt;
+
+/// Một vấn đề cụ thể trong cấu hình, kèm đường dẫn tới field gây lỗi.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Đường dẫn field, ví dụ `oauth_clients.epic_sandbox.token_url`
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Lỗi trả về từ [`crate::load_settings`].
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// Không đọc hoặc deserialize được các nguồn cấu hình (YAML, biến môi trường).
+    #[error("could not load configuration: {0}")]
+    Load(#[from] Box<figment::Error>),
+    /// Cấu hình đọc được nhưng có giá trị không hợp lệ. Liệt kê mọi lỗi tìm thấy.
+    #[error("invalid configuration:{}", format_errors(.0))]
+    Invalid(Vec<ValidationError>),
+}
+
+fn format_errors(errors: &[ValidationError]) -> String {
+    errors.iter().map(|e| format!("\n  - {}", e)).collect()
+}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        ConfigError::Load(Box::new(err))
+    }
+}
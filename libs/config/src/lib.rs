@@ -3,22 +3,40 @@
 //! Thư viện cấu hình chung cho toàn workspace.
 //! Cho phép load `Settings` dễ dàng thông qua hàm `load()`.
 
+pub mod error;
 mod loader;
+mod secret;
 pub mod settings; // Giữ loader là private module
+mod validation;
 
 /// Rút gọn hàm load từ loader
-pub use loader::load as load_settings;
+pub use loader::{
+    load as load_settings, load_patient_summary as load_patient_summary_settings,
+    load_seds as load_seds_settings, source_files,
+};
 
 /// Xuất struct Settings để dễ sử dụng
-pub use settings::Settings;
+pub use settings::{Secret, Settings};
+
+pub use error::{ConfigError, ValidationError};
+pub use validation::SUPPORTED_JWT_ALGORITHMS;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use figment::{
+        Figment,
+        providers::{Format, Yaml},
+    };
 
     #[test]
     fn it_works() {
-        // TODO: Add actual tests for config loading
-        assert!(true);
+        let settings: Settings = Figment::new()
+            .merge(Yaml::string(
+                "port: 3000\nbase_url: \"http://localhost:3000\"\nsession_key: \"0123456789abcdef0123456789abcdef\"\noauth_clients: {}\n",
+            ))
+            .extract()
+            .unwrap();
+        assert!(settings.validate().is_ok());
     }
 }
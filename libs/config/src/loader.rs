@@ -1,5 +1,7 @@
-use crate::settings::Settings;
-use figment::error::Error as FigmentError;
+use std::path::PathBuf;
+
+use crate::error::ConfigError;
+use crate::settings::{PatientSummarySettings, SedsSettings, Settings};
 use figment::{
     Figment,
     providers::{Env, Format, Yaml},
@@ -10,32 +12,100 @@ use figment::{
 /// 2. config/{APP_ENV}.yaml (where APP_ENV defaults to "development")
 /// 3. environment variables prefixed with "APP_"
 ///
-/// # Panics
-/// Panics if it cannot read or deserialize the final Settings.
-pub fn load() -> Settings {
-    // 1. Determine the runtime environment (default = "development")
-    let current_env = std::env::var("APP_ENV").unwrap_or_else(|_| "development".into());
+/// Secret fields are then read from the files / environment variables they
+/// reference (see [`crate::Secret`]) and the result is validated.
+///
+/// # Errors
+/// Returns [`ConfigError::Load`] if the sources cannot be read or deserialized,
+/// and [`ConfigError::Invalid`] listing every invalid field otherwise.
+pub fn load() -> Result<Settings, ConfigError> {
+    // 1. Determine the config files for the runtime environment
+    let [default_file, env_file] = config_files();
 
     // 2. Build Figment object, merging providers in precedence order
     let figment = Figment::new()
         // Base config
-        .merge(Yaml::file("config/default.yaml"))
+        .merge(Yaml::file(default_file))
         // Env-specific override (e.g. config/production.yaml)
-        .merge(Yaml::file(&format!("config/{}.yaml", current_env)))
+        .merge(Yaml::file(env_file))
         // Override with environment variables.
         // Figment sẽ cố gắng map APP_OAUTH_CLIENTS_EPIC_SANDBOX_CLIENT_ID thành settings.oauth_clients.epic_sandbox.client_id
         .merge(Env::prefixed("APP_").split("_")); // Giữ prefix chung, figment sẽ xử lý lồng nhau
 
-    // 3. Extract into our Settings struct, returning a Result
-    figment
-        .extract::<Settings>()
-        .map_err(|e: FigmentError| {
-            eprintln!("\nFATAL: Could not load application configuration.");
-            eprintln!("Error details: {:#?}", e);
-            eprintln!("Please ensure 'config/default.yaml' exists and is correctly formatted.");
-            eprintln!("If using environment-specific config (e.g., 'config/{}.yaml'), check it as well.", current_env);
-            eprintln!("Also, verify that all required fields (like 'port', 'host', 'oauth2.client_id', etc.) are present in your configuration sources or environment variables (prefixed with APP_).\n");
-            e
-        })
-        .expect("Critical error loading application configuration. See details above.")
+    // 3. Extract into our Settings struct, resolve secret references, then check the values
+    let mut settings = figment.extract::<Settings>()?;
+    settings.resolve_secrets()?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Load the settings of patient-summary-service from the same YAML files as
+/// [`load`] (relative to the service directory) and `APP_` environment variables.
+///
+/// Keys contain underscores, so nested fields are separated by `__`, e.g.
+/// `APP_FHIR_BASE_URL` or `APP_TOKEN_EXCHANGE__CLIENT_SECRET`.
+///
+/// # Errors
+/// Same as [`load`].
+pub fn load_patient_summary() -> Result<PatientSummarySettings, ConfigError> {
+    let mut settings = service_figment().extract::<PatientSummarySettings>()?;
+    settings.resolve_secrets()?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Load the settings of seds-service, like [`load_patient_summary`].
+///
+/// # Errors
+/// Same as [`load`].
+pub fn load_seds() -> Result<SedsSettings, ConfigError> {
+    let settings = service_figment().extract::<SedsSettings>()?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn service_figment() -> Figment {
+    let [default_file, env_file] = config_files();
+    Figment::new()
+        .merge(Yaml::file(default_file))
+        .merge(Yaml::file(env_file))
+        .merge(Env::prefixed("APP_").split("__"))
+}
+
+/// The YAML files [`load`] reads: `config/default.yaml` and `config/{APP_ENV}.yaml`
+/// (where APP_ENV defaults to "development").
+fn config_files() -> [PathBuf; 2] {
+    let current_env = std::env::var("APP_ENV").unwrap_or_else(|_| "development".into());
+    [
+        PathBuf::from("config/default.yaml"),
+        PathBuf::from(format!("config/{}.yaml", current_env)),
+    ]
+}
+
+/// Every file the given settings were loaded from: the YAML config files and
+/// the secret files referenced by `*_file` fields.
+///
+/// Files that do not exist (such as an absent env-specific YAML) are included
+/// so that creating them can be noticed by a watcher.
+pub fn source_files(settings: &Settings) -> Vec<PathBuf> {
+    let mut files = config_files().to_vec();
+    files.extend(settings.session_key_file.iter().map(PathBuf::from));
+    for client in settings.oauth_clients.values() {
+        files.extend(client.client_secret_file.iter().map(PathBuf::from));
+        files.extend(client.private_key_pem_file.iter().map(PathBuf::from));
+        for key in &client.signing_keys {
+            files.extend(key.private_key_pem_file.iter().map(PathBuf::from));
+        }
+    }
+    if let Some(internal) = &settings.internal_tokens {
+        for key in &internal.signing_keys {
+            files.extend(key.private_key_pem_file.iter().map(PathBuf::from));
+        }
+        for client in internal.exchange_clients.values() {
+            files.extend(client.client_secret_file.iter().map(PathBuf::from));
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
 }
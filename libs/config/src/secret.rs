@@ -0,0 +1,288 @@
+//! Giá trị bí mật trong cấu hình (private key, client secret, session key).
+//!
+//! Một field bí mật có thể được cấu hình theo ba cách:
+//! - giá trị trực tiếp, có thể chứa tham chiếu biến môi trường `${NAME}`;
+//! - tham chiếu file `file:///run/secrets/epic.pem`;
+//! - field `<tên>_file` đi kèm, ví dụ `private_key_pem_file: /run/secrets/epic.pem`.
+//!
+//! Sau khi load, giá trị được bọc trong [`Secret`] để `{:?}` không in nội dung.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::error::{ConfigError, ValidationError};
+use crate::settings::{BearerAuthSettings, PatientSummarySettings, Settings};
+
+/// Chuỗi bí mật: `Debug` luôn in `[REDACTED]`, muốn đọc phải gọi [`Secret::expose`].
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Trả về giá trị thật. Không log kết quả của hàm này.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+
+    /// Thay `file://` và `${NAME}` bằng nội dung file / biến môi trường.
+    fn resolve_references(&self) -> Result<Secret, String> {
+        if let Some(path) = self.0.strip_prefix("file://") {
+            return read_secret_file(path);
+        }
+
+        let mut resolved = String::with_capacity(self.0.len());
+        let mut rest = self.0.as_str();
+        while let Some(start) = rest.find("${") {
+            let Some(len) = rest[start + 2..].find('}') else {
+                return Err("unterminated '${' reference".to_string());
+            };
+            let name = &rest[start + 2..start + 2 + len];
+            let value = std::env::var(name)
+                .map_err(|_| format!("environment variable '{}' is not set", name))?;
+            resolved.push_str(&rest[..start]);
+            resolved.push_str(&value);
+            rest = &rest[start + 3 + len..];
+        }
+        resolved.push_str(rest);
+        Ok(Secret(resolved))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+fn read_secret_file(path: &str) -> Result<Secret, String> {
+    std::fs::read_to_string(path)
+        // Bỏ newline cuối do `echo > file` hay secret mount thêm vào
+        .map(|content| Secret(content.trim_end_matches(['\r', '\n']).to_string()))
+        .map_err(|e| format!("cannot read secret file '{}': {}", path, e))
+}
+
+fn resolve_field(
+    errors: &mut Vec<ValidationError>,
+    field: &str,
+    value: &mut Option<Secret>,
+    file: Option<&str>,
+) {
+    match (value.as_ref(), file) {
+        (Some(_), Some(_)) => errors.push(ValidationError::new(
+            field,
+            format!("set either '{}' or '{}_file', not both", field, field),
+        )),
+        (None, Some(path)) => match read_secret_file(path) {
+            Ok(secret) => *value = Some(secret),
+            Err(e) => errors.push(ValidationError::new(format!("{}_file", field), e)),
+        },
+        (Some(secret), None) => match secret.resolve_references() {
+            Ok(secret) => *value = Some(secret),
+            Err(e) => errors.push(ValidationError::new(field, e)),
+        },
+        (None, None) => {}
+    }
+}
+
+impl Settings {
+    /// Đọc mọi field bí mật từ file / biến môi trường được tham chiếu.
+    pub fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        // `session_key` mặc định là chuỗi rỗng; rỗng + có `session_key_file` nghĩa là chưa đặt.
+        let mut session_key = Some(std::mem::take(&mut self.session_key))
+            .filter(|key| !key.0.is_empty() || self.session_key_file.is_none());
+        resolve_field(
+            &mut errors,
+            "session_key",
+            &mut session_key,
+            self.session_key_file.as_deref(),
+        );
+        self.session_key = session_key.unwrap_or_default();
+
+        let mut client_names: Vec<_> = self.oauth_clients.keys().cloned().collect();
+        client_names.sort();
+        for name in client_names {
+            let client = self
+                .oauth_clients
+                .get_mut(&name)
+                .expect("name comes from the same map");
+            resolve_field(
+                &mut errors,
+                &format!("oauth_clients.{}.client_secret", name),
+                &mut client.client_secret,
+                client.client_secret_file.as_deref(),
+            );
+            resolve_field(
+                &mut errors,
+                &format!("oauth_clients.{}.private_key_pem", name),
+                &mut client.private_key_pem,
+                client.private_key_pem_file.as_deref(),
+            );
+            for (index, key) in client.signing_keys.iter_mut().enumerate() {
+                resolve_field(
+                    &mut errors,
+                    &format!(
+                        "oauth_clients.{}.signing_keys[{}].private_key_pem",
+                        name, index
+                    ),
+                    &mut key.private_key_pem,
+                    key.private_key_pem_file.as_deref(),
+                );
+            }
+        }
+
+        if let Some(internal) = &mut self.internal_tokens {
+            for (index, key) in internal.signing_keys.iter_mut().enumerate() {
+                resolve_field(
+                    &mut errors,
+                    &format!("internal_tokens.signing_keys[{}].private_key_pem", index),
+                    &mut key.private_key_pem,
+                    key.private_key_pem_file.as_deref(),
+                );
+            }
+            let mut client_ids: Vec<_> = internal.exchange_clients.keys().cloned().collect();
+            client_ids.sort();
+            for id in client_ids {
+                let client = internal
+                    .exchange_clients
+                    .get_mut(&id)
+                    .expect("id comes from the same map");
+                resolve_field(
+                    &mut errors,
+                    &format!("internal_tokens.exchange_clients.{}.client_secret", id),
+                    &mut client.client_secret,
+                    client.client_secret_file.as_deref(),
+                );
+            }
+        }
+
+        if let Some(cache) = &mut self.summary_cache {
+            resolve_field(
+                &mut errors,
+                "summary_cache.encryption_key",
+                &mut cache.encryption_key,
+                cache.encryption_key_file.as_deref(),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+impl BearerAuthSettings {
+    /// Đọc client secret của introspection từ file / biến môi trường được tham chiếu.
+    pub fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+        resolve_bearer_auth(&mut errors, "", self);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+fn resolve_bearer_auth(
+    errors: &mut Vec<ValidationError>,
+    prefix: &str,
+    bearer: &mut BearerAuthSettings,
+) {
+    if let Some(introspection) = &mut bearer.introspection {
+        resolve_field(
+            errors,
+            &format!("{}introspection.client_secret", prefix),
+            &mut introspection.client_secret,
+            introspection.client_secret_file.as_deref(),
+        );
+    }
+}
+
+impl PatientSummarySettings {
+    /// Đọc mọi field bí mật từ file / biến môi trường được tham chiếu.
+    pub fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+        if let Some(exchange) = &mut self.token_exchange {
+            resolve_field(
+                &mut errors,
+                "token_exchange.client_secret",
+                &mut exchange.client_secret,
+                exchange.client_secret_file.as_deref(),
+            );
+        }
+        if let Some(bearer) = &mut self.bearer_auth {
+            resolve_bearer_auth(&mut errors, "bearer_auth.", bearer);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_is_redacted() {
+        let secret = Secret::new("super-secret");
+        assert_eq!(format!("{:?}", Some(secret)), "Some([REDACTED])");
+    }
+
+    #[test]
+    fn resolves_env_and_file_references() {
+        // SAFETY: tests in this module use a variable name no other test touches.
+        unsafe { std::env::set_var("CONFIG_LIB_TEST_SECRET", "s3cr3t") };
+        let secret = Secret::new("prefix-${CONFIG_LIB_TEST_SECRET}");
+        assert_eq!(
+            secret.resolve_references().unwrap().expose(),
+            "prefix-s3cr3t"
+        );
+
+        let missing = Secret::new("${CONFIG_LIB_TEST_MISSING}");
+        assert!(
+            missing
+                .resolve_references()
+                .unwrap_err()
+                .contains("CONFIG_LIB_TEST_MISSING")
+        );
+
+        let path = std::env::temp_dir().join("config_lib_secret_test.pem");
+        std::fs::write(&path, "-----BEGIN KEY-----\nabc\n-----END KEY-----\n").unwrap();
+        let from_file = Secret::new(format!("file://{}", path.display()));
+        assert_eq!(
+            from_file.resolve_references().unwrap().expose(),
+            "-----BEGIN KEY-----\nabc\n-----END KEY-----"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_value_and_file_together() {
+        let mut errors = Vec::new();
+        let mut value = Some(Secret::new("inline"));
+        resolve_field(
+            &mut errors,
+            "session_key",
+            &mut value,
+            Some("/run/secrets/key"),
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("not both"));
+    }
+}
@@ -1,19 +1,20 @@
 use std::collections::HashMap;
 
-use figment::{
-    Figment,
-    error::Error as FigmentError,
-    providers::{Env, Yaml},
-};
 use serde::Deserialize;
+use time::OffsetDateTime;
+
+pub use crate::secret::Secret;
 
 /// Cấu hình OAuth2
 #[derive(Debug, Deserialize, Clone)]
 pub struct OAuth2ClientSettings {
     /// Epic OAuth2 client ID
     pub client_id: String,
-    /// Epic OAuth2 client secret
-    pub client_secret: Option<String>,
+    /// Epic OAuth2 client secret (hỗ trợ `${ENV}` và `file://`)
+    pub client_secret: Option<Secret>,
+    /// Đọc client secret từ file, thay cho `client_secret`
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
     /// Epic OAuth2 authorize endpoint
     pub token_url: String,
     /// Redirect URI đã đăng ký trên Epic
@@ -24,9 +25,470 @@ pub struct OAuth2ClientSettings {
     pub scopes: Vec<String>,
     /// Audience yêu cầu
     pub audience: String,
-    pub private_key_pem: Option<String>, // Đường dẫn đến private key file
+    /// Private key (PEM) dùng ký client assertion (hỗ trợ `${ENV}` và `file://`)
+    pub private_key_pem: Option<Secret>,
+    /// Đọc private key từ file, thay cho `private_key_pem`
+    #[serde(default)]
+    pub private_key_pem_file: Option<String>,
+    #[serde(alias = "jwt_algorithm")]
     pub private_key_algorithm: Option<String>, // Thuật toán ký cho private key (ví dụ: RS384, ES384)
-    pub key_id: Option<String>,                // Key ID (kid) để sử dụng trong header JWT và JWKS
+    pub key_id: Option<String>, // Key ID (kid) để sử dụng trong header JWT và JWKS; bỏ trống = thumbprint
+    /// Các key ký bổ sung, dùng để xoay vòng key theo lịch
+    #[serde(default)]
+    pub signing_keys: Vec<SigningKeySettings>,
+    /// Thời hạn và dung sai đồng hồ của client assertion
+    #[serde(default)]
+    pub assertion: ClientAssertionSettings,
+    /// Endpoint Pushed Authorization Request (RFC 9126); đặt thì tham số authorize
+    /// được đẩy lên trước và trình duyệt chỉ mang `request_uri`
+    #[serde(default)]
+    pub par_url: Option<String>,
+    /// Xin token gắn với key DPoP của từng phiên (RFC 9449)
+    #[serde(default)]
+    pub dpop: bool,
+    /// Loại resource FHIR được gọi qua `/fhir/{tên client}/...` của gateway, tới
+    /// `audience`. Để trống thì không mở proxy cho client này.
+    #[serde(default)]
+    pub fhir_resource_types: Vec<String>,
+}
+
+impl OAuth2ClientSettings {
+    /// Mọi key ký của client: key khai báo bằng các field `private_key_*` (nếu có),
+    /// sau đó là các key trong `signing_keys`.
+    pub fn all_signing_keys(&self) -> Vec<SigningKeySettings> {
+        let legacy = self
+            .private_key_pem
+            .as_ref()
+            .filter(|pem| !pem.is_empty())
+            .map(|pem| SigningKeySettings {
+                private_key_pem: Some(pem.clone()),
+                private_key_pem_file: None,
+                kms_key: None,
+                algorithm: self.private_key_algorithm.clone().unwrap_or_default(),
+                key_id: self.key_id.clone(),
+                active_from: None,
+                retire_at: None,
+            });
+        legacy
+            .into_iter()
+            .chain(self.signing_keys.iter().cloned())
+            .collect()
+    }
+}
+
+/// Một key ký client assertion.
+///
+/// Xoay vòng key không downtime: thêm key mới với `active_from` trong tương lai
+/// (key được công bố trên JWKS ngay để provider kịp cache), rồi đặt `retire_at`
+/// cho key cũ, đủ muộn để các assertion nó đã ký hết hạn.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SigningKeySettings {
+    /// Private key (PEM) (hỗ trợ `${ENV}` và `file://`)
+    #[serde(default)]
+    pub private_key_pem: Option<Secret>,
+    /// Đọc private key từ file, thay cho `private_key_pem`
+    #[serde(default)]
+    pub private_key_pem_file: Option<String>,
+    /// Tên key trong KMS/HSM (xem `Settings::kms`), thay cho private key PEM
+    #[serde(default)]
+    pub kms_key: Option<String>,
+    /// Thuật toán ký: RS256, RS384, RS512, ES256 (P-256) hoặc ES384 (P-384)
+    pub algorithm: String,
+    /// Key ID; bỏ trống thì dùng thumbprint RFC 7638 của public key
+    #[serde(default)]
+    pub key_id: Option<String>,
+    /// Thời điểm (RFC 3339) key bắt đầu được dùng để ký; bỏ trống = ngay khi load
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub active_from: Option<OffsetDateTime>,
+    /// Thời điểm (RFC 3339) key bị gỡ khỏi JWKS và không còn dùng; bỏ trống = không bao giờ
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub retire_at: Option<OffsetDateTime>,
+}
+
+/// Trạng thái của một key tại một thời điểm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// Đã công bố trên JWKS, chưa tới `active_from`
+    Next,
+    /// Key đang được dùng để ký
+    Current,
+    /// Đã bị key mới hơn thay thế nhưng vẫn còn trên JWKS
+    Retiring,
+    /// Đã qua `retire_at`
+    Retired,
+}
+
+impl SigningKeySettings {
+    fn is_retired(&self, now: OffsetDateTime) -> bool {
+        self.retire_at.is_some_and(|at| at <= now)
+    }
+
+    fn is_active(&self, now: OffsetDateTime) -> bool {
+        !self.is_retired(now) && self.active_from.is_none_or(|at| at <= now)
+    }
+}
+
+/// Vị trí của key dùng để ký tại thời điểm `now`: trong các key đã active và
+/// chưa retire, key có `active_from` muộn nhất (key đứng trước thắng khi bằng nhau).
+fn current_index(keys: &[SigningKeySettings], now: OffsetDateTime) -> Option<usize> {
+    keys.iter()
+        .enumerate()
+        .filter(|(_, key)| key.is_active(now))
+        .max_by_key(|(index, key)| (key.active_from, std::cmp::Reverse(*index)))
+        .map(|(index, _)| index)
+}
+
+/// Key dùng để ký tại thời điểm `now`.
+pub fn current_signing_key(
+    keys: &[SigningKeySettings],
+    now: OffsetDateTime,
+) -> Option<&SigningKeySettings> {
+    current_index(keys, now).map(|index| &keys[index])
+}
+
+/// Trạng thái của từng key trong `keys` tại thời điểm `now`, theo cùng thứ tự.
+pub fn signing_key_statuses(keys: &[SigningKeySettings], now: OffsetDateTime) -> Vec<KeyStatus> {
+    let current = current_index(keys, now);
+    keys.iter()
+        .enumerate()
+        .map(|(index, key)| {
+            if key.is_retired(now) {
+                KeyStatus::Retired
+            } else if current == Some(index) {
+                KeyStatus::Current
+            } else if key.is_active(now) {
+                KeyStatus::Retiring
+            } else {
+                KeyStatus::Next
+            }
+        })
+        .collect()
+}
+
+/// Một service phía sau gateway (backend, agent, frontend, ...)
+#[derive(Debug, Deserialize, Clone)]
+pub struct UpstreamSettings {
+    /// Base URL của service, ví dụ "http://0.0.0.0:3010"
+    pub url: String,
+    /// Path dùng để kiểm tra service còn sống, nối vào sau `url`
+    #[serde(default = "default_health_path")]
+    pub health_path: String,
+    /// `aud` của token nội bộ gửi tới service này. Bỏ trống thì gateway chuyển
+    /// tiếp access token của Epic như cũ.
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+/// Cấu hình cho `/health/live` và `/health/ready`
+#[derive(Debug, Deserialize, Clone)]
+pub struct HealthSettings {
+    /// Thời gian tối đa cho mỗi lần kiểm tra một component (ms)
+    #[serde(default = "default_check_timeout_ms")]
+    pub check_timeout_ms: u64,
+    /// Kết quả kiểm tra được cache trong bao lâu (ms)
+    #[serde(default = "default_cache_ttl_ms")]
+    pub cache_ttl_ms: u64,
+    /// Có kiểm tra token endpoint của các OAuth provider hay không
+    #[serde(default = "default_true")]
+    pub check_oauth_providers: bool,
+    /// Các component quyết định readiness (ví dụ "upstream:patient_summary", "oauth:epic_sandbox").
+    /// Để trống (null) nghĩa là mọi component đều bắt buộc.
+    #[serde(default)]
+    pub readiness: Option<Vec<String>>,
+}
+
+fn default_check_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_cache_ttl_ms() -> u64 {
+    5000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            check_timeout_ms: default_check_timeout_ms(),
+            cache_ttl_ms: default_cache_ttl_ms(),
+            check_oauth_providers: true,
+            readiness: None,
+        }
+    }
+}
+
+/// Cấu hình cho việc tắt gateway (SIGTERM/SIGINT)
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShutdownSettings {
+    /// Thời gian chờ sau khi báo not-ready, trước khi ngừng nhận kết nối mới (ms).
+    /// Cho orchestrator kịp ngừng định tuyến request tới gateway.
+    #[serde(default)]
+    pub readiness_grace_ms: u64,
+    /// Thời hạn tối đa để xử lý nốt các request đang chạy (ms)
+    #[serde(default = "default_drain_timeout_ms")]
+    pub drain_timeout_ms: u64,
+}
+
+fn default_drain_timeout_ms() -> u64 {
+    30_000
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            readiness_grace_ms: 0,
+            drain_timeout_ms: default_drain_timeout_ms(),
+        }
+    }
+}
+
+/// TLS termination ngay tại gateway (rustls)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct TlsSettings {
+    /// Certificate chain (PEM)
+    pub cert_path: String,
+    /// Private key tương ứng (PEM)
+    pub key_path: String,
+    /// Chu kỳ kiểm tra cert/key trên đĩa để nạp lại khi thay đổi (giây), 0 = tắt
+    #[serde(default = "default_tls_reload_interval_secs")]
+    pub reload_interval_secs: u64,
+    /// Nếu đặt, mở thêm một cổng HTTP chỉ để redirect sang HTTPS
+    #[serde(default)]
+    pub http_redirect_port: Option<u16>,
+}
+
+fn default_tls_reload_interval_secs() -> u64 {
+    30
+}
+
+/// mTLS cho các call từ gateway tới service nội bộ (upstreams)
+#[derive(Debug, Deserialize, Clone)]
+pub struct UpstreamTlsSettings {
+    /// CA dùng để xác thực certificate của service nội bộ (PEM).
+    /// Bỏ trống thì dùng root CA của hệ thống.
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// Client certificate gateway trình ra (PEM)
+    pub client_cert_path: String,
+    /// Private key của client certificate (PEM)
+    pub client_key_path: String,
+}
+
+/// Signer kiểu KMS/HSM cho các key khai báo bằng `kms_key`: gateway chỉ gửi dữ
+/// liệu cần ký, không đọc private key.
+#[derive(Debug, Deserialize, Clone)]
+pub struct KmsSettings {
+    /// "software": stand-in cục bộ cho dev/test, đọc `<key_dir>/<kms_key>.pem`
+    pub provider: String,
+    /// Thư mục chứa key của provider "software"
+    #[serde(default)]
+    pub key_dir: Option<String>,
+}
+
+/// Client assertion (`private_key_jwt`) gửi tới token endpoint.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ClientAssertionSettings {
+    /// Thời hạn của assertion (giây); Epic không nhận `exp` quá 5 phút
+    #[serde(default = "default_assertion_lifetime_secs")]
+    pub lifetime_secs: u64,
+    /// Lùi `iat`/`nbf` bấy nhiêu giây để server có đồng hồ chậm hơn vẫn chấp nhận
+    #[serde(default = "default_clock_skew_secs")]
+    pub clock_skew_secs: u64,
+    /// Chỉnh lệch đồng hồ theo header `Date` của token endpoint
+    #[serde(default)]
+    pub use_server_date: bool,
+}
+
+fn default_assertion_lifetime_secs() -> u64 {
+    4 * 60 + 50
+}
+
+fn default_clock_skew_secs() -> u64 {
+    30
+}
+
+impl Default for ClientAssertionSettings {
+    fn default() -> Self {
+        Self {
+            lifetime_secs: default_assertion_lifetime_secs(),
+            clock_skew_secs: default_clock_skew_secs(),
+            use_server_date: false,
+        }
+    }
+}
+
+/// Token nội bộ do gateway phát hành cho các service phía sau.
+///
+/// Service chỉ cần tin một issuer (gateway) thay vì token của từng provider;
+/// token được ký bằng các key công bố trên `/.well-known/jwks.json`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct InternalTokenSettings {
+    /// `iss` của token; bỏ trống thì dùng `base_url`
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Thời hạn token (giây)
+    #[serde(default = "default_internal_token_lifetime_secs")]
+    pub lifetime_secs: u64,
+    /// Key ký token, xoay vòng theo lịch giống key của OAuth client
+    pub signing_keys: Vec<SigningKeySettings>,
+    /// Các service được gọi token exchange (RFC 8693), theo client id
+    #[serde(default)]
+    pub exchange_clients: HashMap<String, ExchangeClientSettings>,
+}
+
+fn default_internal_token_lifetime_secs() -> u64 {
+    300
+}
+
+/// Một service được phép đổi token tại `/oauth2/token`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExchangeClientSettings {
+    /// Secret để xác thực service (hỗ trợ `${ENV}` và `file://`)
+    #[serde(default)]
+    pub client_secret: Option<Secret>,
+    /// Đọc client secret từ file, thay cho `client_secret`
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
+    /// Audience mà service được đổi token sang: audience của một upstream (token
+    /// nội bộ mới) hoặc `audience` của một OAuth client (access token của provider)
+    pub audiences: Vec<String>,
+}
+
+/// Kiểm tra bearer token ở một service phía sau gateway.
+///
+/// JWT được kiểm tra bằng JWKS của issuer; token không phải JWT (opaque) được
+/// hỏi lại issuer qua introspection (RFC 7662) nếu có cấu hình.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BearerAuthSettings {
+    /// `iss` được chấp nhận, ví dụ "https://gateway.example.org"
+    pub issuer: String,
+    /// `aud` mà token phải chứa (audience của service này)
+    pub audience: String,
+    /// URL JWKS; bỏ trống thì dùng `<issuer>/.well-known/jwks.json`
+    #[serde(default)]
+    pub jwks_uri: Option<String>,
+    /// Chu kỳ tải lại JWKS ở background (giây)
+    #[serde(default = "default_jwks_refresh_secs")]
+    pub jwks_refresh_secs: u64,
+    /// Độ lệch đồng hồ cho phép khi kiểm tra `exp`/`nbf` (giây)
+    #[serde(default = "default_bearer_leeway_secs")]
+    pub leeway_secs: u64,
+    /// Scope mà mọi request phải có
+    #[serde(default)]
+    pub required_scopes: Vec<String>,
+    /// Introspection cho opaque token
+    #[serde(default)]
+    pub introspection: Option<IntrospectionSettings>,
+}
+
+fn default_jwks_refresh_secs() -> u64 {
+    300
+}
+
+fn default_bearer_leeway_secs() -> u64 {
+    30
+}
+
+impl BearerAuthSettings {
+    /// URL JWKS thực tế.
+    pub fn jwks_uri(&self) -> String {
+        self.jwks_uri.clone().unwrap_or_else(|| {
+            format!(
+                "{}/.well-known/jwks.json",
+                self.issuer.trim_end_matches('/')
+            )
+        })
+    }
+}
+
+/// Endpoint introspection (RFC 7662) và thông tin xác thực của service tại đó.
+#[derive(Debug, Deserialize, Clone)]
+pub struct IntrospectionSettings {
+    pub endpoint: String,
+    pub client_id: String,
+    /// Secret của service (hỗ trợ `${ENV}` và `file://`)
+    #[serde(default)]
+    pub client_secret: Option<Secret>,
+    /// Đọc client secret từ file, thay cho `client_secret`
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
+    /// Thời gian nhớ kết quả introspection của một token (giây), 0 = không nhớ
+    #[serde(default = "default_introspection_cache_secs")]
+    pub cache_secs: u64,
+}
+
+fn default_introspection_cache_secs() -> u64 {
+    60
+}
+
+/// Nạp lại cấu hình khi đang chạy (ngoài SIGHUP)
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReloadSettings {
+    /// Chu kỳ kiểm tra các file cấu hình và file secret để nạp lại khi thay đổi (giây), 0 = tắt
+    #[serde(default = "default_watch_interval_secs")]
+    pub watch_interval_secs: u64,
+}
+
+fn default_watch_interval_secs() -> u64 {
+    5
+}
+
+impl Default for ReloadSettings {
+    fn default() -> Self {
+        Self {
+            watch_interval_secs: default_watch_interval_secs(),
+        }
+    }
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+/// Cache summary bệnh nhân tại gateway, theo tenant, bệnh nhân và người dùng
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct SummaryCacheSettings {
+    /// Số entry tối đa giữ trong bộ nhớ; entry ít dùng nhất bị bỏ trước
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: usize,
+    /// Thời gian entry còn mới (giây) với loại resource không có trong `ttl_secs`
+    #[serde(default = "default_cache_ttl_secs")]
+    pub default_ttl_secs: u64,
+    /// Thời gian còn mới theo loại resource, ví dụ `PatientSummary: 120`
+    #[serde(default)]
+    pub ttl_secs: HashMap<String, u64>,
+    /// Sau khi hết mới, entry vẫn được trả thêm chừng này giây trong lúc làm mới ở nền
+    #[serde(default = "default_cache_stale_secs")]
+    pub stale_secs: u64,
+    /// Khóa mã hóa entry, ít nhất 32 ký tự (hỗ trợ `${ENV}` và `file://`). Bỏ trống
+    /// thì sinh ngẫu nhiên mỗi lần khởi động; bắt buộc khi dùng `redis_url`.
+    #[serde(default)]
+    pub encryption_key: Option<Secret>,
+    /// Đọc khóa mã hóa từ file, thay cho `encryption_key`
+    #[serde(default)]
+    pub encryption_key_file: Option<String>,
+    /// Redis (hoặc server tương thích) dùng chung giữa các instance, ví dụ "redis://cache:6379"
+    #[serde(default)]
+    pub redis_url: Option<String>,
+}
+
+fn default_cache_max_entries() -> usize {
+    1000
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_cache_stale_secs() -> u64 {
+    600
 }
 
 /// Cấu hình chung cho API Gateway
@@ -34,8 +496,197 @@ pub struct OAuth2ClientSettings {
 pub struct Settings {
     /// Cổng HTTP server
     pub port: u16,
-    /// Khóa bí mật để mã hóa session cookie (hex 32 bytes)
-    pub session_key: String,
+    /// Địa chỉ bind của server, "0.0.0.0" để lắng nghe trên mọi interface
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// URL cơ sở mà người dùng truy cập gateway, ví dụ "https://gateway.example.org"
+    pub base_url: String,
+    /// Khóa bí mật để mã hóa session cookie (hỗ trợ `${ENV}` và `file://`)
+    #[serde(default)]
+    pub session_key: Secret,
+    /// Đọc session key từ file, thay cho `session_key`
+    #[serde(default)]
+    pub session_key_file: Option<String>,
     /// OAuth2 config
     pub oauth_clients: HashMap<String, OAuth2ClientSettings>,
+    /// Các service phía sau gateway, theo tên (ví dụ "patient_summary", "frontend")
+    #[serde(default)]
+    pub upstreams: HashMap<String, UpstreamSettings>,
+    /// Cấu hình health check
+    #[serde(default)]
+    pub health: HealthSettings,
+    /// Cấu hình graceful shutdown
+    #[serde(default)]
+    pub shutdown: ShutdownSettings,
+    /// Bật HTTPS tại gateway khi có mục này
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+    /// Bật mTLS tới các upstream khi có mục này
+    #[serde(default)]
+    pub upstream_tls: Option<UpstreamTlsSettings>,
+    /// KMS/HSM dùng để ký client assertion
+    #[serde(default)]
+    pub kms: Option<KmsSettings>,
+    /// Bộ lọc log theo cú pháp `RUST_LOG`, ví dụ "info,api_gateway=debug".
+    /// Bỏ trống thì dùng biến môi trường `RUST_LOG` (mặc định "info").
+    #[serde(default)]
+    pub log_filter: Option<String>,
+    /// Cấu hình nạp lại khi đang chạy
+    #[serde(default)]
+    pub reload: ReloadSettings,
+    /// Phát hành token nội bộ cho các service phía sau
+    #[serde(default)]
+    pub internal_tokens: Option<InternalTokenSettings>,
+    /// Cache summary bệnh nhân; không có mục này thì mỗi lần xem đều gọi Epic
+    #[serde(default)]
+    pub summary_cache: Option<SummaryCacheSettings>,
+}
+
+impl Settings {
+    /// `iss` của token nội bộ.
+    pub fn internal_token_issuer(&self) -> Option<String> {
+        let internal = self.internal_tokens.as_ref()?;
+        Some(
+            internal
+                .issuer
+                .clone()
+                .unwrap_or_else(|| self.base_url.trim_end_matches('/').to_string()),
+        )
+    }
+}
+
+/// Cấu hình của patient-summary-service
+#[derive(Debug, Deserialize, Clone)]
+pub struct PatientSummarySettings {
+    /// Cổng HTTP server
+    #[serde(default = "default_patient_summary_port")]
+    pub port: u16,
+    /// Địa chỉ bind của server
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// Base URL của FHIR R4 server, mặc định Epic sandbox
+    #[serde(default = "default_fhir_base_url")]
+    pub fhir_base_url: String,
+    /// Đổi token nội bộ của gateway lấy access token của Epic; không có mục này
+    /// thì dùng nguyên bearer token gateway chuyển tới
+    #[serde(default)]
+    pub token_exchange: Option<TokenExchangeSettings>,
+    /// Base URL của patient-summary-agent, cho phần lời khuyên của bản in; bỏ
+    /// trống thì bản in không có lời khuyên
+    #[serde(default)]
+    pub advice_url: Option<String>,
+    /// Kiểm tra bearer token của các route `/patient_summary`; không có mục này
+    /// thì mọi request được chuyển thẳng tới handler
+    #[serde(default)]
+    pub bearer_auth: Option<BearerAuthSettings>,
+}
+
+fn default_patient_summary_port() -> u16 {
+    3010
+}
+
+fn default_fhir_base_url() -> String {
+    "https://fhir.epic.com/interconnect-fhir-oauth/api/FHIR/R4".to_string()
+}
+
+/// Client của một service tại endpoint token exchange (RFC 8693) của gateway.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TokenExchangeSettings {
+    /// Ví dụ "http://localhost:3000/oauth2/token"
+    pub url: String,
+    /// Tên client trong `internal_tokens.exchange_clients` của gateway
+    #[serde(default = "default_exchange_client_id")]
+    pub client_id: String,
+    /// Secret của service (hỗ trợ `${ENV}` và `file://`)
+    #[serde(default)]
+    pub client_secret: Option<Secret>,
+    /// Đọc client secret từ file, thay cho `client_secret`
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
+}
+
+fn default_exchange_client_id() -> String {
+    "patient-summary".to_string()
+}
+
+/// Cấu hình của seds-service (HL7 v2 qua MLLP)
+#[derive(Debug, Deserialize, Clone)]
+pub struct SedsSettings {
+    /// Cổng MLLP
+    #[serde(default = "default_seds_port")]
+    pub port: u16,
+    /// Địa chỉ bind của listener
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// Kích thước tối đa của một message (byte); frame lớn hơn làm đóng kết nối
+    #[serde(default = "default_max_message_bytes")]
+    pub max_message_bytes: usize,
+    /// Đóng kết nối không gửi gì trong khoảng này (giây)
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// File nhật ký sự kiện, tương đối với thư mục chạy service
+    #[serde(default = "default_event_log_path")]
+    pub event_log_path: String,
+}
+
+fn default_seds_port() -> u16 {
+    2575
+}
+
+fn default_max_message_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    300
+}
+
+fn default_event_log_path() -> String {
+    "var/seds-events.jsonl".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn key(
+        active_from: Option<OffsetDateTime>,
+        retire_at: Option<OffsetDateTime>,
+    ) -> SigningKeySettings {
+        SigningKeySettings {
+            private_key_pem: Some(Secret::new("pem")),
+            private_key_pem_file: None,
+            kms_key: None,
+            algorithm: "ES256".to_string(),
+            key_id: None,
+            active_from,
+            retire_at,
+        }
+    }
+
+    #[test]
+    fn rotation_moves_through_next_current_retiring_retired() {
+        let keys = [
+            key(None, Some(datetime!(2026-11-02 00:00 UTC))),
+            key(Some(datetime!(2026-11-01 00:00 UTC)), None),
+        ];
+        use KeyStatus::*;
+        assert_eq!(
+            signing_key_statuses(&keys, datetime!(2026-10-31 12:00 UTC)),
+            [Current, Next]
+        );
+        assert_eq!(
+            signing_key_statuses(&keys, datetime!(2026-11-01 12:00 UTC)),
+            [Retiring, Current]
+        );
+        assert_eq!(
+            signing_key_statuses(&keys, datetime!(2026-11-02 00:00 UTC)),
+            [Retired, Current]
+        );
+        assert!(std::ptr::eq(
+            current_signing_key(&keys, datetime!(2026-11-03 00:00 UTC)).unwrap(),
+            &keys[1]
+        ));
+    }
 }
@@ -0,0 +1,145 @@
+//! Bản ghi của một bulk export, lưu cùng các file tải về (`{job}/job.json`) để
+//! theo dõi tiến độ và chạy tiếp sau khi tiến trình khởi động lại.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::{ExportManifest, ManifestFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Server đã nhận kick-off, đang chuẩn bị file
+    InProgress,
+    /// Đã có manifest, đang tải file
+    Downloading,
+    Completed,
+    /// Server báo export lỗi; không chạy tiếp được
+    Failed,
+}
+
+/// Một file trong manifest (`output` hoặc `error`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputFile {
+    /// Loại resource, `OperationOutcome` với file lỗi
+    pub resource_type: String,
+    pub url: String,
+    /// Số resource server khai báo, nếu có
+    pub count: Option<u64>,
+    /// Key trong store
+    pub key: String,
+    /// Số byte đã tải
+    pub bytes: u64,
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportJob {
+    pub id: String,
+    pub group_id: String,
+    pub state: JobState,
+    /// URL trạng thái (`Content-Location` của kick-off)
+    pub status_url: String,
+    /// `X-Progress` của lần poll gần nhất
+    pub progress: Option<String>,
+    /// `transactionTime` của manifest: dữ liệu tính tới thời điểm này, dùng
+    /// làm `_since` cho lần export tiếp theo
+    pub transaction_time: Option<String>,
+    /// File tải về phải kèm access token
+    pub requires_access_token: bool,
+    pub outputs: Vec<OutputFile>,
+    pub errors: Vec<OutputFile>,
+    /// Lý do khi `Failed`
+    pub error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl ExportJob {
+    pub(crate) fn new(group_id: &str, status_url: String, now: OffsetDateTime) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            group_id: group_id.to_string(),
+            state: JobState::InProgress,
+            status_url,
+            progress: None,
+            transaction_time: None,
+            requires_access_token: false,
+            outputs: Vec::new(),
+            errors: Vec::new(),
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Key của bản ghi job trong store.
+    pub fn record_key(id: &str) -> String {
+        format!("{}/job.json", id)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, JobState::Completed | JobState::Failed)
+    }
+
+    /// Ghi nhận manifest và chuyển sang tải file. Key được đánh số theo loại
+    /// resource (`{job}/Patient-1.ndjson`, ...), file lỗi nằm trong `error/`.
+    pub(crate) fn apply_manifest(&mut self, manifest: ExportManifest) {
+        self.outputs = self.files(manifest.output, "");
+        self.errors = self.files(manifest.error, "error/");
+        self.transaction_time = Some(manifest.transaction_time);
+        self.requires_access_token = manifest.requires_access_token;
+        self.progress = None;
+        self.state = JobState::Downloading;
+    }
+
+    fn files(&self, files: Vec<ManifestFile>, prefix: &str) -> Vec<OutputFile> {
+        let mut numbers: HashMap<String, usize> = HashMap::new();
+        files
+            .into_iter()
+            .map(|file| {
+                let number = numbers.entry(file.resource_type.clone()).or_default();
+                *number += 1;
+                OutputFile {
+                    key: format!(
+                        "{}/{}{}-{}.ndjson",
+                        self.id, prefix, file.resource_type, number
+                    ),
+                    resource_type: file.resource_type,
+                    url: file.url,
+                    count: file.count,
+                    bytes: 0,
+                    complete: false,
+                }
+            })
+            .collect()
+    }
+
+    /// File thứ `index`, đếm `outputs` trước rồi tới `errors`.
+    pub(crate) fn file(&self, index: usize) -> &OutputFile {
+        match index.checked_sub(self.outputs.len()) {
+            Some(index) => &self.errors[index],
+            None => &self.outputs[index],
+        }
+    }
+
+    pub(crate) fn file_mut(&mut self, index: usize) -> &mut OutputFile {
+        match index.checked_sub(self.outputs.len()) {
+            Some(index) => &mut self.errors[index],
+            None => &mut self.outputs[index],
+        }
+    }
+
+    /// Tổng số byte đã tải của mọi file.
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.outputs
+            .iter()
+            .chain(&self.errors)
+            .map(|file| file.bytes)
+            .sum()
+    }
+}
@@ -0,0 +1,551 @@
+//! Client FHIR Bulk Data Access (`$export`) cho dữ liệu của cả một nhóm bệnh
+//! nhân, dùng token của SMART Backend Services.
+//!
+//! Kick-off `Group/{id}/$export` trả về URL trạng thái; URL này được poll theo
+//! `Retry-After` cho tới khi có manifest, rồi từng file NDJSON được tải dạng
+//! stream vào một [`OutputStore`]. Mỗi bước được ghi vào [`ExportJob`], nên job
+//! dừng giữa chừng chạy tiếp được: file tải dở được tải tiếp bằng `Range`.
+//!
+//! ```ignore
+//! let exporter = BulkExporter::new(fhir, Arc::new(backend), LocalDirectory::new("exports"));
+//! let request = ExportRequest {
+//!     types: vec!["Patient".to_string(), "Condition".to_string()],
+//!     ..Default::default()
+//! };
+//! let mut job = exporter.start_group_export("e3iabhmS8rsueyz7vaimuiaSmfGvi.QwjVXJANlPOgR83", &request).await?;
+//! exporter.run(&mut job).await?;
+//!
+//! // Sau khi khởi động lại
+//! let mut job = exporter.load_job(&job_id).await?.unwrap();
+//! exporter.run(&mut job).await?;
+//! ```
+
+pub mod job;
+pub mod store;
+
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use oauth2_lib::backend::BackendServicesClient;
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use url::Url;
+
+use crate::client::{Auth, FhirClient, FhirError};
+
+pub use job::{ExportJob, JobState, OutputFile};
+pub use store::{LocalDirectory, MemoryStore, OutputStore};
+
+/// Media type của file output.
+pub const FHIR_NDJSON: &str = "application/fhir+ndjson";
+/// Header tiến độ của URL trạng thái, ví dụ `50% complete`.
+pub const PROGRESS_HEADER: &str = "x-progress";
+
+/// Tham số kick-off.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportRequest {
+    /// `_type`; rỗng thì server chọn (thường là mọi loại trong compartment
+    /// Patient)
+    pub types: Vec<String>,
+    /// `_since` (FHIR instant): chỉ resource sửa sau thời điểm này, ví dụ
+    /// `transactionTime` của lần export trước
+    pub since: Option<String>,
+    /// `_typeFilter`, ví dụ `Observation?category=laboratory`
+    pub type_filters: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Chờ tối thiểu giữa hai lần poll; `Retry-After` ngắn hơn (kể cả `0` hay
+    /// một ngày đã qua) không làm poll dày hơn
+    pub poll_interval: Duration,
+    /// Thời gian tối đa chờ manifest, tính từ lúc kick-off; quá hạn thì job
+    /// chuyển sang `Failed`
+    pub max_wait: Duration,
+    /// Số lần thử tải một file; lần sau tải tiếp từ byte đã có
+    pub download_attempts: u32,
+    /// Chờ giữa hai lần thử tải
+    pub retry_delay: Duration,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            max_wait: Duration::from_secs(24 * 60 * 60),
+            download_attempts: 3,
+            retry_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Kết quả một lần poll URL trạng thái.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportStatus {
+    InProgress {
+        /// `X-Progress`
+        progress: Option<String>,
+        /// `Retry-After`
+        retry_after: Option<Duration>,
+    },
+    Complete(ExportManifest),
+}
+
+/// Manifest của export đã xong.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportManifest {
+    pub transaction_time: String,
+    #[serde(default)]
+    pub request: Option<String>,
+    #[serde(default)]
+    pub requires_access_token: bool,
+    #[serde(default)]
+    pub output: Vec<ManifestFile>,
+    /// File NDJSON `OperationOutcome` cho các lỗi trong lúc export
+    #[serde(default)]
+    pub error: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestFile {
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub url: String,
+    #[serde(default)]
+    pub count: Option<u64>,
+}
+
+/// Chạy bulk export trên một FHIR server, lưu kết quả vào `store`.
+#[derive(Debug)]
+pub struct BulkExporter<S> {
+    fhir: FhirClient,
+    tokens: Arc<BackendServicesClient>,
+    store: S,
+    options: ExportOptions,
+}
+
+impl<S: OutputStore> BulkExporter<S> {
+    pub fn new(fhir: FhirClient, tokens: Arc<BackendServicesClient>, store: S) -> Self {
+        Self {
+            fhir,
+            tokens,
+            store,
+            options: ExportOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Kick-off rồi chạy job tới khi xong. Job dừng vì lỗi vẫn nằm trong store;
+    /// dùng [`start_group_export`](Self::start_group_export) và
+    /// [`run`](Self::run) nếu cần chạy tiếp.
+    pub async fn export_group(
+        &self,
+        group_id: &str,
+        request: &ExportRequest,
+    ) -> Result<ExportJob, FhirError> {
+        let mut job = self.start_group_export(group_id, request).await?;
+        self.run(&mut job).await?;
+        Ok(job)
+    }
+
+    /// `GET [base]/Group/{id}/$export` với `Prefer: respond-async`; trả về job
+    /// mới, đã lưu vào store.
+    pub async fn start_group_export(
+        &self,
+        group_id: &str,
+        request: &ExportRequest,
+    ) -> Result<ExportJob, FhirError> {
+        let mut url = self.fhir.url(&["Group", group_id, "$export"])?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("_outputFormat", FHIR_NDJSON);
+            if !request.types.is_empty() {
+                query.append_pair("_type", &request.types.join(","));
+            }
+            if let Some(since) = &request.since {
+                query.append_pair("_since", since);
+            }
+            for filter in &request.type_filters {
+                query.append_pair("_typeFilter", filter);
+            }
+        }
+        let response = self
+            .send(|| {
+                self.fhir
+                    .request(Method::GET, url.clone())
+                    .header("prefer", "respond-async")
+            })
+            .await?;
+        let status = response.status();
+        let location = header_value(response.headers(), header::CONTENT_LOCATION);
+        if status != StatusCode::ACCEPTED {
+            let body = response.bytes().await?;
+            return Err(if status.is_success() {
+                FhirError::InvalidResponse(format!("$export returned {} instead of 202", status))
+            } else {
+                FhirError::from_response_body(status, &body)
+            });
+        }
+        let location = location.ok_or_else(|| {
+            FhirError::InvalidResponse("$export response has no Content-Location".to_string())
+        })?;
+        let status_url = self.authorized_url(&location)?;
+
+        let mut job = ExportJob::new(group_id, status_url.to_string(), OffsetDateTime::now_utc());
+        self.save(&mut job).await?;
+        Ok(job)
+    }
+
+    /// Chạy tiếp `job` từ trạng thái hiện tại: poll tới khi có manifest, rồi
+    /// tải các file chưa xong. Bản ghi được lưu sau mỗi bước.
+    ///
+    /// Lỗi tạm thời (mạng, token) trả về `Err` và giữ nguyên trạng thái để chạy
+    /// lại sau; server báo export lỗi thì job chuyển sang `Failed`.
+    pub async fn run(&self, job: &mut ExportJob) -> Result<(), FhirError> {
+        if job.state == JobState::InProgress {
+            self.wait_for_manifest(job).await?;
+        }
+        if job.state == JobState::Downloading {
+            self.download_all(job).await?;
+        }
+        Ok(())
+    }
+
+    /// Một lần poll URL trạng thái. Lỗi tạm thời của server được trả về như
+    /// export vẫn đang chạy; `Err` với status HTTP nghĩa là export thất bại.
+    pub async fn status(&self, status_url: &Url) -> Result<ExportStatus, FhirError> {
+        let response = self
+            .send(|| self.fhir.request(Method::GET, status_url.clone()))
+            .await?;
+        let status = response.status();
+        let retry_after = retry_after(response.headers(), SystemTime::now());
+        let progress = header_value(response.headers(), PROGRESS_HEADER);
+        let body = response.bytes().await?;
+        match status {
+            StatusCode::ACCEPTED => Ok(ExportStatus::InProgress {
+                progress,
+                retry_after,
+            }),
+            StatusCode::OK => serde_json::from_slice(&body)
+                .map(ExportStatus::Complete)
+                .map_err(|e| FhirError::InvalidResponse(format!("invalid manifest: {}", e))),
+            status => {
+                let error = FhirError::from_response_body(status, &body);
+                // Lỗi của lần poll, không phải của job: poll quá dày (`429`), server
+                // tạm quá tải (`503` kèm `Retry-After`), lỗi 5xx không kèm
+                // OperationOutcome (ví dụ `502`/`504` của proxy) hoặc
+                // OperationOutcome chỉ có issue type `transient`
+                let transient = status == StatusCode::TOO_MANY_REQUESTS
+                    || (status == StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some())
+                    || match error.outcome() {
+                        Some(outcome) => {
+                            !outcome.issue.is_empty()
+                                && outcome.issue.iter().all(|issue| {
+                                    TRANSIENT_ISSUE_TYPES.contains(&issue.code.as_str())
+                                })
+                        }
+                        None => status.is_server_error(),
+                    };
+                if transient {
+                    Ok(ExportStatus::InProgress {
+                        progress: None,
+                        retry_after,
+                    })
+                } else {
+                    Err(error)
+                }
+            }
+        }
+    }
+
+    /// Bản ghi job đã lưu trong store.
+    pub async fn load_job(&self, id: &str) -> Result<Option<ExportJob>, FhirError> {
+        let Some(bytes) = self.store.get(&ExportJob::record_key(id)).await? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| FhirError::Storage(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    async fn wait_for_manifest(&self, job: &mut ExportJob) -> Result<(), FhirError> {
+        let status_url = self.authorized_url(&job.status_url)?;
+        loop {
+            match self.status(&status_url).await {
+                Ok(ExportStatus::InProgress {
+                    progress,
+                    retry_after,
+                }) => {
+                    if progress.is_some() && progress != job.progress {
+                        job.progress = progress;
+                        self.save(job).await?;
+                    }
+                    let waited = Duration::try_from(OffsetDateTime::now_utc() - job.created_at)
+                        .unwrap_or_default();
+                    let Some(remaining) = self.options.max_wait.checked_sub(waited) else {
+                        let error = FhirError::ExportTimedOut(self.options.max_wait);
+                        job.state = JobState::Failed;
+                        job.error = Some(error.to_string());
+                        self.save(job).await?;
+                        return Err(error);
+                    };
+                    let wait = retry_after
+                        .unwrap_or_default()
+                        .max(self.options.poll_interval);
+                    tokio::time::sleep(wait.min(remaining)).await;
+                }
+                Ok(ExportStatus::Complete(manifest)) => {
+                    job.apply_manifest(manifest);
+                    self.save(job).await?;
+                    return Ok(());
+                }
+                // Server báo export thất bại (hoặc đã bị xóa); lỗi tạm thời đã được
+                // `status` coi như export vẫn chạy
+                Err(e) if e.status().is_some() && !e.is_unauthorized() => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e.to_string());
+                    self.save(job).await?;
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn download_all(&self, job: &mut ExportJob) -> Result<(), FhirError> {
+        for index in 0..job.outputs.len() + job.errors.len() {
+            let mut file = job.file(index).clone();
+            if file.complete {
+                continue;
+            }
+            let result = self.download(&mut file, job.requires_access_token).await;
+            *job.file_mut(index) = file;
+            self.save(job).await?;
+            result?;
+        }
+        job.state = JobState::Completed;
+        self.save(job).await
+    }
+
+    /// Tải `file`, thử lại lỗi tạm thời; mỗi lần thử tải tiếp từ byte đã có.
+    async fn download(&self, file: &mut OutputFile, requires_token: bool) -> Result<(), FhirError> {
+        // Không gửi token tới host lạ; file không cần token thì tải ở đâu cũng được
+        let url = if requires_token {
+            self.authorized_url(&file.url)?
+        } else {
+            self.fhir.base_url().join(&file.url)?
+        };
+        let mut attempt = 1;
+        loop {
+            match self.download_once(&url, file, requires_token).await {
+                Err(e) if attempt < self.options.download_attempts && is_transient(&e) => {
+                    attempt += 1;
+                    tokio::time::sleep(self.options.retry_delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn download_once(
+        &self,
+        url: &Url,
+        file: &mut OutputFile,
+        requires_token: bool,
+    ) -> Result<(), FhirError> {
+        let offset = self.store.len(&file.key).await?;
+        file.bytes = offset;
+        let build = || {
+            let request = self
+                .fhir
+                .http()
+                .get(url.clone())
+                .header(header::ACCEPT, FHIR_NDJSON);
+            if offset > 0 {
+                request.header(header::RANGE, format!("bytes={}-", offset))
+            } else {
+                request
+            }
+        };
+        let mut response = if requires_token {
+            self.send(build).await?
+        } else {
+            build().send().await?
+        };
+
+        match response.status() {
+            StatusCode::OK => {
+                // Server bỏ qua `Range`: tải lại từ đầu
+                if offset > 0 {
+                    self.store.put(&file.key, &[]).await?;
+                    file.bytes = 0;
+                }
+            }
+            StatusCode::PARTIAL_CONTENT => {
+                let range = header_value(response.headers(), header::CONTENT_RANGE);
+                if range.as_deref().and_then(range_start) != Some(offset) {
+                    return Err(FhirError::InvalidResponse(format!(
+                        "expected bytes from {} of {}, got range {:?}",
+                        offset, url, range
+                    )));
+                }
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                let range = header_value(response.headers(), header::CONTENT_RANGE);
+                // Lần trước đã tải đủ, chỉ chưa kịp ghi nhận
+                if range.as_deref().and_then(complete_length) == Some(offset) {
+                    file.complete = true;
+                    return Ok(());
+                }
+                // File trên server đã đổi: lần chạy sau tải lại từ đầu
+                self.store.put(&file.key, &[]).await?;
+                file.bytes = 0;
+                return Err(FhirError::InvalidResponse(format!(
+                    "{} changed during download",
+                    url
+                )));
+            }
+            status => {
+                let body = response.bytes().await?;
+                return Err(FhirError::from_response_body(status, &body));
+            }
+        }
+
+        while let Some(chunk) = response.chunk().await? {
+            self.store.append(&file.key, &chunk).await?;
+            file.bytes += chunk.len() as u64;
+        }
+        file.complete = true;
+        Ok(())
+    }
+
+    /// Gửi request kèm token backend; token bị từ chối (`401`) thì xin token
+    /// mới và thử lại một lần.
+    async fn send(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, FhirError> {
+        let tokens = self.tokens.token().await?;
+        let response = self.fhir.execute(Auth::Tokens(&tokens), build()).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        self.tokens.invalidate();
+        let tokens = self.tokens.token().await?;
+        self.fhir.execute(Auth::Tokens(&tokens), build()).await
+    }
+
+    /// URL (tương đối với base) được gửi kèm token: phải cùng origin với FHIR
+    /// server.
+    fn authorized_url(&self, url: &str) -> Result<Url, FhirError> {
+        let url = self.fhir.base_url().join(url)?;
+        if url.origin() != self.fhir.base_url().origin() {
+            return Err(FhirError::InvalidResponse(format!(
+                "refusing to send the access token outside of {}: {}",
+                self.fhir.base_url(),
+                url
+            )));
+        }
+        Ok(url)
+    }
+
+    async fn save(&self, job: &mut ExportJob) -> Result<(), FhirError> {
+        job.updated_at = OffsetDateTime::now_utc();
+        let bytes = serde_json::to_vec_pretty(job).map_err(io::Error::other)?;
+        self.store
+            .put(&ExportJob::record_key(&job.id), &bytes)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Issue type con của `transient`: server báo lỗi tạm thời khi poll.
+const TRANSIENT_ISSUE_TYPES: [&str; 7] = [
+    "transient",
+    "lock-error",
+    "no-store",
+    "exception",
+    "timeout",
+    "incomplete",
+    "throttled",
+];
+
+/// Lỗi tải file mà thử lại có thể thành công.
+fn is_transient(error: &FhirError) -> bool {
+    match error {
+        FhirError::Transport(_) => true,
+        FhirError::Outcome { status, .. } | FhirError::Status { status, .. } => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        _ => false,
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// `Retry-After` dạng số giây hoặc HTTP-date.
+fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+/// Byte đầu của `Content-Range: bytes 100-199/200`.
+fn range_start(value: &str) -> Option<u64> {
+    let range = value.strip_prefix("bytes ")?;
+    range.split_once('-')?.0.trim().parse().ok()
+}
+
+/// Độ dài đầy đủ của `Content-Range: bytes */200`.
+fn complete_length(value: &str) -> Option<u64> {
+    value.strip_prefix("bytes */")?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_retry_after_and_content_range() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+        headers.insert(header::RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(120)));
+        // 2023-11-14T22:13:20Z + 30s
+        headers.insert(
+            header::RETRY_AFTER,
+            "Tue, 14 Nov 2023 22:13:50 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(30)));
+        headers.insert(
+            header::RETRY_AFTER,
+            "Tue, 14 Nov 2023 22:00:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::ZERO));
+
+        assert_eq!(range_start("bytes 100-199/200"), Some(100));
+        assert_eq!(range_start("bytes */200"), None);
+        assert_eq!(complete_length("bytes */200"), Some(200));
+    }
+}
@@ -0,0 +1,189 @@
+//! Nơi lưu file NDJSON và bản ghi job của bulk export.
+//!
+//! Key có dạng đường dẫn tương đối (`{job}/Patient-1.ndjson`). File được ghi
+//! nối tiếp theo từng chunk tải về, nên độ dài hiện có là vị trí để tải tiếp.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use tokio::io::AsyncWriteExt;
+
+pub trait OutputStore: Send + Sync {
+    /// Số byte đã có của `key`; 0 nếu chưa có.
+    fn len(&self, key: &str) -> impl Future<Output = io::Result<u64>> + Send;
+
+    /// Ghi nối `bytes` vào cuối `key`, tạo mới nếu chưa có.
+    fn append(&self, key: &str, bytes: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Ghi đè toàn bộ `key`.
+    fn put(&self, key: &str, bytes: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Nội dung của `key`; `None` nếu chưa có.
+    fn get(&self, key: &str) -> impl Future<Output = io::Result<Option<Vec<u8>>>> + Send;
+}
+
+/// File trong một thư mục cục bộ; `/` trong key là thư mục con.
+#[derive(Debug, Clone)]
+pub struct LocalDirectory {
+    root: PathBuf,
+}
+
+impl LocalDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Đường dẫn của `key`; key thoát ra ngoài thư mục gốc bị từ chối.
+    pub fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid storage key '{}'", key),
+            ));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    async fn create_parent(path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) => tokio::fs::create_dir_all(parent).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl OutputStore for LocalDirectory {
+    async fn len(&self, key: &str) -> io::Result<u64> {
+        match tokio::fs::metadata(self.path(key)?).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn append(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        Self::create_parent(&path).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(bytes).await?;
+        file.flush().await
+    }
+
+    async fn put(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        Self::create_parent(&path).await?;
+        // Ghi ra file tạm rồi đổi tên: bản ghi job không bao giờ bị ghi dở
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, bytes).await?;
+        tokio::fs::rename(temporary, path).await
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Object store trong bộ nhớ, thay cho bucket (S3, GCS, ...) khi chưa có.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key của mọi object, theo thứ tự.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.objects.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    pub fn object(&self, key: &str) -> Option<Vec<u8>> {
+        self.objects.lock().unwrap().get(key).cloned()
+    }
+}
+
+impl OutputStore for MemoryStore {
+    async fn len(&self, key: &str) -> io::Result<u64> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .map_or(0, |object| object.len() as u64))
+    }
+
+    async fn append(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.objects
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .extend_from_slice(bytes);
+        Ok(())
+    }
+
+    async fn put(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.objects
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.object(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_directory_appends_and_rejects_escaping_keys() {
+        let root = tempfile::tempdir().unwrap();
+        let store = LocalDirectory::new(root.path());
+        assert_eq!(store.len("job/Patient-1.ndjson").await.unwrap(), 0);
+        store.append("job/Patient-1.ndjson", b"{}\n").await.unwrap();
+        store.append("job/Patient-1.ndjson", b"{}\n").await.unwrap();
+        assert_eq!(store.len("job/Patient-1.ndjson").await.unwrap(), 6);
+        store.put("job/job.json", b"{\"a\":1}").await.unwrap();
+        store.put("job/job.json", b"{}").await.unwrap();
+        assert_eq!(
+            store.get("job/job.json").await.unwrap().as_deref(),
+            Some(&b"{}"[..])
+        );
+        assert!(store.get("job/missing").await.unwrap().is_none());
+
+        for key in ["../outside", "/etc/passwd", "job/../../x", ""] {
+            assert_eq!(
+                store.append(key, b"x").await.unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+    }
+}
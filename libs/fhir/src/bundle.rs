@@ -0,0 +1,287 @@
+//! `Bundle`: kết quả search, document, transaction.
+
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{Identifier, Instant, Meta};
+use crate::resource::{FhirResource, Resource};
+use crate::resources::OperationOutcome;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<Identifier>,
+    /// `searchset`, `document`, `collection`, `transaction`, ...
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<Instant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<BundleLink>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entry: Vec<BundleEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleLink {
+    /// `self`, `next`, `previous`, ...
+    pub relation: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<Resource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<BundleEntrySearch>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<BundleEntryRequest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<BundleEntryResponse>,
+}
+
+impl BundleEntry {
+    /// Entry là kết quả chính của search; entry không có `search.mode` cũng tính.
+    pub fn is_match(&self) -> bool {
+        self.search
+            .as_ref()
+            .and_then(|search| search.mode.as_deref())
+            .is_none_or(|mode| mode == "match")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BundleEntrySearch {
+    /// `match`, `include`, `outcome`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEntryRequest {
+    pub method: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEntryResponse {
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<Instant>,
+}
+
+impl Bundle {
+    /// Bundle rỗng loại `type_`.
+    pub fn new(type_: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            meta: None,
+            identifier: None,
+            type_: type_.into(),
+            timestamp: None,
+            total: None,
+            link: Vec::new(),
+            entry: Vec::new(),
+        }
+    }
+
+    /// Thêm một resource, `fullUrl` là `{base}/{type}/{id}` nếu có `base` và id.
+    pub fn push(&mut self, resource: impl Into<Resource>, base: Option<&str>) {
+        let resource = resource.into();
+        let full_url = match (base, resource.id()) {
+            (Some(base), Some(id)) => Some(format!(
+                "{}/{}/{}",
+                base.trim_end_matches('/'),
+                resource.resource_type(),
+                id
+            )),
+            _ => None,
+        };
+        self.entry.push(BundleEntry {
+            full_url,
+            resource: Some(resource),
+            ..BundleEntry::default()
+        });
+    }
+
+    /// URL của link có `relation`.
+    pub fn link(&self, relation: &str) -> Option<&str> {
+        self.link
+            .iter()
+            .find(|link| link.relation == relation)
+            .map(|link| link.url.as_str())
+    }
+
+    /// Trang kế tiếp của kết quả search.
+    pub fn next_link(&self) -> Option<&str> {
+        self.link("next")
+    }
+
+    pub fn resources(&self) -> impl Iterator<Item = &Resource> {
+        self.entry
+            .iter()
+            .filter_map(|entry| entry.resource.as_ref())
+    }
+
+    /// Resource loại `T` trong bundle, kể cả entry `_include`.
+    pub fn resources_of<T: FhirResource>(&self) -> impl Iterator<Item = &T> {
+        self.resources().filter_map(T::as_resource)
+    }
+
+    /// Resource loại `T` là kết quả chính của search (`search.mode` khác
+    /// `include` và `outcome`).
+    pub fn matches<T: FhirResource>(&self) -> impl Iterator<Item = &T> {
+        self.entry
+            .iter()
+            .filter(|entry| entry.is_match())
+            .filter_map(|entry| entry.resource.as_ref())
+            .filter_map(T::as_resource)
+    }
+
+    /// Chuyển các kết quả chính loại `T` ra khỏi bundle, bỏ entry `_include`.
+    pub fn into_matches<T: FhirResource>(self) -> Vec<T> {
+        self.entry
+            .into_iter()
+            .filter(BundleEntry::is_match)
+            .filter_map(|entry| entry.resource)
+            .filter_map(T::from_resource)
+            .collect()
+    }
+
+    /// Chuyển các resource loại `T` ra khỏi bundle.
+    pub fn into_resources_of<T: FhirResource>(self) -> Vec<T> {
+        self.entry
+            .into_iter()
+            .filter_map(|entry| entry.resource)
+            .filter_map(T::from_resource)
+            .collect()
+    }
+
+    /// `OperationOutcome` kèm theo kết quả (cảnh báo của Epic khi search).
+    pub fn outcomes(&self) -> impl Iterator<Item = &OperationOutcome> {
+        self.resources_of::<OperationOutcome>()
+    }
+
+    /// Resource mà `reference` trỏ tới, tìm theo `fullUrl` hoặc `type/id`.
+    pub fn resolve(&self, reference: &str) -> Option<&Resource> {
+        self.entry.iter().find_map(|entry| {
+            let resource = entry.resource.as_ref()?;
+            let by_url = entry.full_url.as_deref() == Some(reference);
+            let by_id = resource
+                .id()
+                .is_some_and(|id| reference == format!("{}/{}", resource.resource_type(), id));
+            (by_url || by_id).then_some(resource)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{Condition, Patient};
+    use serde_json::json;
+
+    #[test]
+    fn reads_a_searchset_with_includes_outcomes_and_unknown_types() {
+        let bundle: Bundle = serde_json::from_value(json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "total": 1,
+            "link": [
+                { "relation": "self", "url": "https://fhir.example/R4/Condition?patient=p1" },
+                { "relation": "next", "url": "https://fhir.example/R4/Condition?patient=p1&page=2" }
+            ],
+            "entry": [
+                {
+                    "fullUrl": "https://fhir.example/R4/Condition/c1",
+                    "resource": {
+                        "resourceType": "Condition",
+                        "id": "c1",
+                        "clinicalStatus": { "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-clinical", "code": "active" }] },
+                        "code": { "text": "Hypertension" },
+                        "subject": { "reference": "Patient/p1" },
+                        "onsetDateTime": "2019-04-01"
+                    },
+                    "search": { "mode": "match" }
+                },
+                {
+                    "fullUrl": "https://fhir.example/R4/Patient/p1",
+                    "resource": { "resourceType": "Patient", "id": "p1", "gender": "female" },
+                    "search": { "mode": "include" }
+                },
+                {
+                    "resource": { "resourceType": "Basic", "id": "b1", "code": { "text": "Epic only" } }
+                },
+                {
+                    "resource": {
+                        "resourceType": "OperationOutcome",
+                        "issue": [{ "severity": "warning", "code": "processing", "details": { "text": "Some results were filtered" } }]
+                    },
+                    "search": { "mode": "outcome" }
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            bundle.next_link(),
+            Some("https://fhir.example/R4/Condition?patient=p1&page=2")
+        );
+        let conditions: Vec<&Condition> = bundle.matches().collect();
+        assert_eq!(conditions.len(), 1);
+        assert!(conditions[0].is_active());
+        assert_eq!(bundle.matches::<Patient>().count(), 0);
+        assert_eq!(bundle.resources_of::<Patient>().count(), 1);
+        assert_eq!(
+            bundle.resolve("Patient/p1").map(Resource::resource_type),
+            Some("Patient")
+        );
+        assert_eq!(
+            bundle
+                .outcomes()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["Some results were filtered (warning)"]
+        );
+
+        // Loại chưa có kiểu riêng được giữ nguyên
+        let json = serde_json::to_value(&bundle).unwrap();
+        assert_eq!(json["entry"][2]["resource"]["code"]["text"], "Epic only");
+        assert_eq!(json["entry"][0]["resource"]["resourceType"], "Condition");
+        assert_eq!(serde_json::from_value::<Bundle>(json).unwrap(), bundle);
+    }
+
+    #[test]
+    fn builds_a_bundle() {
+        let mut bundle = Bundle::new("collection");
+        bundle.push(
+            Patient {
+                id: Some("p1".to_string()),
+                ..Patient::default()
+            },
+            Some("https://fhir.example/R4/"),
+        );
+        assert_eq!(
+            bundle.entry[0].full_url.as_deref(),
+            Some("https://fhir.example/R4/Patient/p1")
+        );
+        assert_eq!(bundle.into_resources_of::<Patient>().len(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+use oauth2_lib::dpop::DpopError;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::resources::OperationOutcome;
+
+/// Số ký tự của body lỗi (không phải OperationOutcome) được giữ lại.
+const MAX_BODY_EXCERPT: usize = 200;
+
+#[derive(Debug, Error)]
+pub enum FhirError {
+    #[error("invalid FHIR URL: {0}")]
+    Url(#[from] url::ParseError),
+    /// Không gọi được FHIR server
+    #[error("FHIR request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    /// Không tạo được DPoP proof cho token gắn key
+    #[error("FHIR request authorization failed: {0}")]
+    Dpop(#[from] DpopError),
+    /// Server trả về `OperationOutcome` thay cho kết quả
+    #[error("FHIR server returned {status}: {outcome}")]
+    Outcome {
+        status: StatusCode,
+        outcome: Box<OperationOutcome>,
+    },
+    /// Server trả về lỗi không kèm `OperationOutcome` (ví dụ trang lỗi của proxy)
+    #[error("FHIR server returned {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    /// Response không phải resource mong đợi
+    #[error("invalid FHIR response: {0}")]
+    InvalidResponse(String),
+    /// Search còn link `next` sau `max_pages` trang; kết quả đọc được không đầy đủ
+    #[error("{resource_type} search has more than {pages} pages")]
+    TooManyPages { resource_type: String, pages: usize },
+    /// Không lấy được token SMART Backend Services
+    #[error("backend services token request failed: {0}")]
+    Token(#[from] oauth2_lib::epic::error::Error),
+    /// Không ghi/đọc được nơi lưu file bulk export
+    #[error("bulk export storage failed: {0}")]
+    Storage(#[from] std::io::Error),
+    /// Bulk export chưa có manifest sau `ExportOptions::max_wait`
+    #[error("bulk export did not finish within {0:?}")]
+    ExportTimedOut(std::time::Duration),
+}
+
+impl FhirError {
+    /// Lỗi cho một response không thành công.
+    pub(crate) fn from_response_body(status: StatusCode, body: &[u8]) -> Self {
+        match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(value) if value["resourceType"] == "OperationOutcome" => {
+                match serde_json::from_value(value) {
+                    Ok(outcome) => {
+                        return Self::Outcome {
+                            status,
+                            outcome: Box::new(outcome),
+                        };
+                    }
+                    Err(e) => return Self::InvalidResponse(e.to_string()),
+                }
+            }
+            _ => {}
+        }
+        Self::Status {
+            status,
+            body: String::from_utf8_lossy(body)
+                .chars()
+                .take(MAX_BODY_EXCERPT)
+                .collect(),
+        }
+    }
+
+    /// HTTP status của response lỗi.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Outcome { status, .. } | Self::Status { status, .. } => Some(*status),
+            Self::Transport(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    pub fn outcome(&self) -> Option<&OperationOutcome> {
+        match self {
+            Self::Outcome { outcome, .. } => Some(outcome),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(StatusCode::NOT_FOUND | StatusCode::GONE)
+        )
+    }
+
+    /// `If-Match` không khớp: resource đã bị sửa từ lần đọc trước.
+    pub fn is_precondition_failed(&self) -> bool {
+        self.status() == Some(StatusCode::PRECONDITION_FAILED)
+    }
+
+    /// Access token hết hạn, bị thu hồi hoặc thiếu scope.
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+        )
+    }
+}
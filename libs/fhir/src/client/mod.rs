@@ -0,0 +1,346 @@
+//! Client FHIR REST (R4) bất đồng bộ.
+//!
+//! Dùng chung `reqwest::Client` và token của `oauth2_lib`: token gắn DPoP được
+//! gửi qua [`TokenSet::send`] nên client không cần biết loại token.
+//!
+//! ```ignore
+//! let client = FhirClient::new(http, "https://fhir.epic.com/interconnect-fhir-oauth/api/FHIR/R4")?;
+//! let patient = client.read::<Patient>(Auth::Tokens(&tokens), "erXuFYUfucBZaryVksYEcMg3").await?;
+//!
+//! let query = SearchParams::of::<Condition>().patient("erXuFYUfucBZaryVksYEcMg3");
+//! let mut pages = std::pin::pin!(client.search_pages(Auth::Tokens(&tokens), &query));
+//! while let Some(bundle) = pages.try_next().await? {
+//!     for condition in bundle.matches::<Condition>() { /* ... */ }
+//! }
+//! ```
+
+pub mod error;
+pub mod search;
+
+use std::sync::Arc;
+
+use futures::{Stream, TryStreamExt, stream};
+use oauth2_lib::clock::{Clock, MonotonicClock};
+use oauth2_lib::epic::tokens::TokenSet;
+use reqwest::header::{self, HeaderValue};
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+use crate::bundle::Bundle;
+use crate::resource::{FhirResource, Resource};
+
+pub use error::FhirError;
+pub use search::{Prefix, SearchParams};
+
+/// Media type của FHIR JSON.
+pub const FHIR_JSON: &str = "application/fhir+json";
+/// Số trang mặc định tối đa đọc cho một search.
+pub const DEFAULT_MAX_PAGES: usize = 20;
+
+/// Cách gắn access token vào request.
+#[derive(Debug, Clone, Copy)]
+pub enum Auth<'a> {
+    /// Server mở (ví dụ sandbox), không gửi `Authorization`
+    Anonymous,
+    /// Access token có sẵn, gửi dạng `Authorization: Bearer`
+    Bearer(&'a str),
+    /// Token của session, gửi bearer hoặc DPoP tùy token
+    Tokens(&'a TokenSet),
+}
+
+/// Resource kèm thông tin phiên bản từ header của response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned<R> {
+    pub resource: R,
+    /// `ETag`, ví dụ `W/"3"`; gửi lại nguyên văn trong `If-None-Match`/`If-Match`
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl<R> Versioned<R> {
+    /// `versionId` lấy từ ETag (`W/"3"` -> `3`).
+    pub fn version_id(&self) -> Option<&str> {
+        let etag = self.etag.as_deref()?;
+        let etag = etag.strip_prefix("W/").unwrap_or(etag);
+        Some(etag.trim_matches('"'))
+    }
+}
+
+/// Kết quả của một read có điều kiện (`If-None-Match`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conditional<R> {
+    Modified(Versioned<R>),
+    /// `304 Not Modified`: bản đang giữ vẫn là bản mới nhất
+    NotModified,
+}
+
+/// Client cho một FHIR base URL.
+#[derive(Debug, Clone)]
+pub struct FhirClient {
+    http: reqwest::Client,
+    base_url: Url,
+    clock: Arc<dyn Clock>,
+    max_pages: usize,
+}
+
+impl FhirClient {
+    pub fn new(http: reqwest::Client, base_url: &str) -> Result<Self, FhirError> {
+        let mut base_url = Url::parse(base_url)?;
+        if base_url.cannot_be_a_base() {
+            return Err(FhirError::Url(
+                url::ParseError::RelativeUrlWithCannotBeABaseBase,
+            ));
+        }
+        base_url.set_query(None);
+        base_url.set_fragment(None);
+        Ok(Self {
+            http,
+            base_url,
+            clock: Arc::new(MonotonicClock::new()),
+            max_pages: DEFAULT_MAX_PAGES,
+        })
+    }
+
+    /// Đồng hồ dùng cho `iat` của DPoP proof.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Số trang tối đa [`search_pages`](Self::search_pages) đọc; server có link
+    /// `next` lặp vòng không làm search chạy mãi.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// `GET [base]/{type}/{id}`
+    pub async fn read<R: FhirResource>(
+        &self,
+        auth: Auth<'_>,
+        id: &str,
+    ) -> Result<Versioned<R>, FhirError> {
+        let url = self.url(&[R::TYPE, id])?;
+        let response = self.execute(auth, self.request(Method::GET, url)).await?;
+        read_resource(response).await
+    }
+
+    /// `GET [base]/{type}/{id}/_history/{vid}`: một phiên bản cụ thể.
+    pub async fn vread<R: FhirResource>(
+        &self,
+        auth: Auth<'_>,
+        id: &str,
+        version_id: &str,
+    ) -> Result<Versioned<R>, FhirError> {
+        let url = self.url(&[R::TYPE, id, "_history", version_id])?;
+        let response = self.execute(auth, self.request(Method::GET, url)).await?;
+        read_resource(response).await
+    }
+
+    /// Read kèm `If-None-Match: {etag}`; server trả `304` nếu resource chưa đổi.
+    pub async fn read_if_none_match<R: FhirResource>(
+        &self,
+        auth: Auth<'_>,
+        id: &str,
+        etag: &str,
+    ) -> Result<Conditional<R>, FhirError> {
+        let url = self.url(&[R::TYPE, id])?;
+        let request = self
+            .request(Method::GET, url)
+            .header(header::IF_NONE_MATCH, etag);
+        let response = self.execute(auth, request).await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+        read_resource(response).await.map(Conditional::Modified)
+    }
+
+    /// `PUT [base]/{type}/{id}`. Với `if_match`, server từ chối (`412`) nếu
+    /// resource đã bị sửa sau lần đọc có ETag đó.
+    pub async fn update<R: FhirResource>(
+        &self,
+        auth: Auth<'_>,
+        resource: &R,
+        if_match: Option<&str>,
+    ) -> Result<Versioned<R>, FhirError> {
+        let id = resource.id().ok_or_else(|| {
+            FhirError::InvalidResponse(format!("cannot update {} without id", R::TYPE))
+        })?;
+        let url = self.url(&[R::TYPE, id])?;
+        let body = serde_json::to_vec(&resource.to_json())
+            .map_err(|e| FhirError::InvalidResponse(e.to_string()))?;
+        let mut request = self
+            .request(Method::PUT, url)
+            .header(header::CONTENT_TYPE, FHIR_JSON)
+            .header("prefer", "return=representation")
+            .body(body);
+        if let Some(etag) = if_match {
+            request = request.header(header::IF_MATCH, etag);
+        }
+        let response = self.execute(auth, request).await?;
+        read_resource(response).await
+    }
+
+    /// Một trang kết quả search.
+    pub async fn search(&self, auth: Auth<'_>, query: &SearchParams) -> Result<Bundle, FhirError> {
+        let url = self.search_url(query)?;
+        self.fetch_bundle(auth, url).await
+    }
+
+    /// Tất cả các trang của một search, lần theo `Bundle.link[next]`.
+    ///
+    /// Link `next` phải nằm dưới base URL: token không bao giờ được gửi tới
+    /// server khác chỉ vì server FHIR trả về một link lạ. Sau `max_pages` trang
+    /// mà vẫn còn link `next`, stream kết thúc bằng [`FhirError::TooManyPages`]
+    /// thay vì âm thầm cắt bớt kết quả.
+    pub fn search_pages<'a>(
+        &'a self,
+        auth: Auth<'a>,
+        query: &SearchParams,
+    ) -> impl Stream<Item = Result<Bundle, FhirError>> + 'a {
+        let first = self.search_url(query);
+        let resource_type = query.resource_type().to_string();
+        stream::try_unfold((Some(first), 1), move |(next, page)| {
+            let resource_type = resource_type.clone();
+            async move {
+                let url = match next {
+                    Some(url) => url?,
+                    None => return Ok(None),
+                };
+                let bundle = self.fetch_bundle(auth, url).await?;
+                // Link lạ chỉ làm hỏng trang kế tiếp, trang này vẫn được trả về.
+                let mut next = bundle.next_link().map(|link| self.next_url(link));
+                if next.is_some() && page >= self.max_pages {
+                    next = Some(Err(FhirError::TooManyPages {
+                        resource_type,
+                        pages: page,
+                    }));
+                }
+                Ok(Some((bundle, (next, page + 1))))
+            }
+        })
+    }
+
+    /// Kết quả chính (`search.mode` = `match`) của mọi trang. Resource
+    /// `_include`/`_revinclude` bị bỏ; dùng [`search_pages`](Self::search_pages)
+    /// nếu cần chúng. Search quá `max_pages` trang là lỗi
+    /// [`FhirError::TooManyPages`].
+    pub async fn search_all<R: FhirResource>(
+        &self,
+        auth: Auth<'_>,
+        query: &SearchParams,
+    ) -> Result<Vec<R>, FhirError> {
+        self.search_pages(auth, query)
+            .map_ok(|bundle| stream::iter(bundle.into_matches::<R>().into_iter().map(Ok)))
+            .try_flatten()
+            .try_collect()
+            .await
+    }
+
+    pub(crate) fn url(&self, segments: &[&str]) -> Result<Url, FhirError> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| FhirError::Url(url::ParseError::RelativeUrlWithCannotBeABaseBase))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    fn search_url(&self, query: &SearchParams) -> Result<Url, FhirError> {
+        let mut url = self.url(&[query.resource_type()])?;
+        if !query.params().is_empty() {
+            url.query_pairs_mut().extend_pairs(query.params());
+        }
+        Ok(url)
+    }
+
+    fn next_url(&self, link: &str) -> Result<Url, FhirError> {
+        let url = self.base_url.join(link)?;
+        let base_path = self.base_url.path().trim_end_matches('/');
+        let under_base = url
+            .path()
+            .strip_prefix(base_path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        if url.origin() != self.base_url.origin() || !under_base {
+            return Err(FhirError::InvalidResponse(format!(
+                "next link outside of {}: {}",
+                self.base_url, url
+            )));
+        }
+        Ok(url)
+    }
+
+    pub(crate) fn request(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, url)
+            .header(header::ACCEPT, FHIR_JSON)
+    }
+
+    async fn fetch_bundle(&self, auth: Auth<'_>, url: Url) -> Result<Bundle, FhirError> {
+        let response = self.execute(auth, self.request(Method::GET, url)).await?;
+        read_resource(response)
+            .await
+            .map(|versioned| versioned.resource)
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        auth: Auth<'_>,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, FhirError> {
+        let request = match auth {
+            Auth::Anonymous => request,
+            Auth::Bearer(token) => request.bearer_auth(token),
+            Auth::Tokens(tokens) => {
+                let request = request.build()?;
+                return Ok(tokens.send(&self.http, request, self.clock.now()).await?);
+            }
+        };
+        Ok(request.send().await?)
+    }
+}
+
+async fn read_resource<R: FhirResource>(
+    response: reqwest::Response,
+) -> Result<Versioned<R>, FhirError> {
+    let status = response.status();
+    let header = |name: header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(header::ETAG);
+    let last_modified = header(header::LAST_MODIFIED);
+    let body = response.bytes().await?;
+    if !status.is_success() {
+        return Err(FhirError::from_response_body(status, &body));
+    }
+    let resource: Resource =
+        serde_json::from_slice(&body).map_err(|e| FhirError::InvalidResponse(e.to_string()))?;
+    let resource = match resource {
+        Resource::OperationOutcome(outcome) => {
+            return Err(FhirError::Outcome { status, outcome });
+        }
+        resource => {
+            let resource_type = resource.resource_type().to_string();
+            R::from_resource(resource).ok_or_else(|| {
+                FhirError::InvalidResponse(format!("expected {}, got {}", R::TYPE, resource_type))
+            })?
+        }
+    };
+    Ok(Versioned {
+        resource,
+        etag,
+        last_modified,
+    })
+}
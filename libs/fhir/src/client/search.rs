@@ -0,0 +1,181 @@
+//! Tham số search của FHIR REST (`GET [base]/{type}?...`).
+
+use crate::resource::FhirResource;
+
+/// Prefix so sánh của tham số kiểu `date`, `number`, `quantity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefix {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    /// Bắt đầu sau
+    Sa,
+    /// Kết thúc trước
+    Eb,
+    /// Xấp xỉ
+    Ap,
+}
+
+impl Prefix {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Eq => "eq",
+            Self::Ne => "ne",
+            Self::Gt => "gt",
+            Self::Lt => "lt",
+            Self::Ge => "ge",
+            Self::Le => "le",
+            Self::Sa => "sa",
+            Self::Eb => "eb",
+            Self::Ap => "ap",
+        }
+    }
+}
+
+/// Loại resource cần search và các tham số, theo thứ tự thêm vào.
+///
+/// ```ignore
+/// let query = SearchParams::of::<Observation>()
+///     .patient("erXuFYUfucBZaryVksYEcMg3")
+///     .token("category", Some(OBSERVATION_CATEGORY), "laboratory")
+///     .date("date", Prefix::Ge, "2023-01-01")
+///     .include("Observation", "performer", None)
+///     .count(50);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParams {
+    resource_type: String,
+    params: Vec<(String, String)>,
+}
+
+impl SearchParams {
+    pub fn new(resource_type: impl Into<String>) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            params: Vec::new(),
+        }
+    }
+
+    pub fn of<R: FhirResource>() -> Self {
+        Self::new(R::TYPE)
+    }
+
+    pub fn resource_type(&self) -> &str {
+        &self.resource_type
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Tham số bất kỳ, giá trị đã theo cú pháp FHIR.
+    pub fn param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.push((name.into(), value.into()));
+        self
+    }
+
+    /// `patient={id}`
+    pub fn patient(self, id: &str) -> Self {
+        self.param("patient", id)
+    }
+
+    /// Tham số kiểu `token`: `system|code`, hoặc chỉ `code`.
+    pub fn token(self, name: &str, system: Option<&str>, code: &str) -> Self {
+        let value = match system {
+            Some(system) => format!("{}|{}", system, code),
+            None => code.to_string(),
+        };
+        self.param(name, value)
+    }
+
+    /// Tham số kiểu `date`, ví dụ `date=ge2023-01-01`.
+    pub fn date(self, name: &str, prefix: Prefix, value: &str) -> Self {
+        self.param(name, format!("{}{}", prefix.as_str(), value))
+    }
+
+    /// Tham số kiểu `reference`: `{type}/{id}`.
+    pub fn reference(self, name: &str, resource_type: &str, id: &str) -> Self {
+        self.param(name, format!("{}/{}", resource_type, id))
+    }
+
+    /// `_include={source}:{param}[:{target}]`: kèm resource mà kết quả tham chiếu tới.
+    pub fn include(self, source_type: &str, param: &str, target_type: Option<&str>) -> Self {
+        self.param("_include", include_value(source_type, param, target_type))
+    }
+
+    /// `_revinclude={source}:{param}[:{target}]`: kèm resource tham chiếu tới kết quả.
+    pub fn revinclude(self, source_type: &str, param: &str, target_type: Option<&str>) -> Self {
+        self.param(
+            "_revinclude",
+            include_value(source_type, param, target_type),
+        )
+    }
+
+    /// `_count`: số kết quả mỗi trang.
+    pub fn count(self, count: u32) -> Self {
+        self.param("_count", count.to_string())
+    }
+
+    /// `_sort`, giảm dần nếu `descending`.
+    pub fn sort(self, param: &str, descending: bool) -> Self {
+        let value = if descending {
+            format!("-{}", param)
+        } else {
+            param.to_string()
+        };
+        self.param("_sort", value)
+    }
+}
+
+fn include_value(source_type: &str, param: &str, target_type: Option<&str>) -> String {
+    match target_type {
+        Some(target) => format!("{}:{}:{}", source_type, param, target),
+        None => format!("{}:{}", source_type, param),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::Observation;
+
+    #[test]
+    fn builds_typed_parameters() {
+        let query = SearchParams::of::<Observation>()
+            .patient("p1")
+            .token(
+                "category",
+                Some("http://terminology.hl7.org/CodeSystem/observation-category"),
+                "laboratory",
+            )
+            .date("date", Prefix::Ge, "2023-01-01")
+            .include("Observation", "performer", Some("Practitioner"))
+            .revinclude("Provenance", "target", None)
+            .sort("date", true)
+            .count(50);
+        assert_eq!(query.resource_type(), "Observation");
+        let params: Vec<(&str, &str)> = query
+            .params()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            params,
+            [
+                ("patient", "p1"),
+                (
+                    "category",
+                    "http://terminology.hl7.org/CodeSystem/observation-category|laboratory"
+                ),
+                ("date", "ge2023-01-01"),
+                ("_include", "Observation:performer:Practitioner"),
+                ("_revinclude", "Provenance:target"),
+                ("_sort", "-date"),
+                ("_count", "50"),
+            ]
+        );
+    }
+}
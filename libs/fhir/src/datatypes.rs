@@ -0,0 +1,525 @@
+//! Kiểu dữ liệu phức hợp của FHIR R4 (Coding, CodeableConcept, Reference, ...).
+//!
+//! Kiểu nguyên thủy `date`, `dateTime`, `instant` giữ nguyên chuỗi: FHIR cho
+//! phép ngày không đầy đủ (`2023`, `2023-06`) mà kiểu thời gian của Rust không
+//! biểu diễn được.
+
+use serde::{Deserialize, Serialize};
+
+use crate::extension::Extension;
+
+/// `date`: `YYYY`, `YYYY-MM` hoặc `YYYY-MM-DD`.
+pub type Date = String;
+/// `dateTime`: ngày không đầy đủ hoặc thời điểm có múi giờ.
+pub type DateTime = String;
+/// `instant`: thời điểm đầy đủ có múi giờ.
+pub type Instant = String;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<Instant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profile: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security: Vec<Coding>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag: Vec<Coding>,
+}
+
+/// Phần mô tả cho người đọc (`text`) của một resource.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Narrative {
+    pub status: String,
+    /// XHTML
+    pub div: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Coding {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_selected: Option<bool>,
+}
+
+impl Coding {
+    pub fn new(system: impl Into<String>, code: impl Into<String>) -> Self {
+        Self {
+            system: Some(system.into()),
+            code: Some(code.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_display(mut self, display: impl Into<String>) -> Self {
+        self.display = Some(display.into());
+        self
+    }
+
+    pub fn is(&self, system: &str, code: &str) -> bool {
+        self.system.as_deref() == Some(system) && self.code.as_deref() == Some(code)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodeableConcept {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coding: Vec<Coding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl CodeableConcept {
+    pub fn from_coding(coding: Coding) -> Self {
+        Self {
+            coding: vec![coding],
+            text: None,
+        }
+    }
+
+    pub fn from_text(text: impl Into<String>) -> Self {
+        Self {
+            coding: Vec::new(),
+            text: Some(text.into()),
+        }
+    }
+
+    /// Có coding `system|code`.
+    pub fn has_coding(&self, system: &str, code: &str) -> bool {
+        self.coding.iter().any(|coding| coding.is(system, code))
+    }
+
+    /// Code đầu tiên thuộc `system`.
+    pub fn code_in(&self, system: &str) -> Option<&str> {
+        self.coding
+            .iter()
+            .find(|coding| coding.system.as_deref() == Some(system))
+            .and_then(|coding| coding.code.as_deref())
+    }
+
+    /// Chuỗi hiển thị: `text`, hoặc `display` rồi `code` của coding đầu tiên có giá trị.
+    pub fn display(&self) -> Option<&str> {
+        self.text.as_deref().or_else(|| {
+            self.coding
+                .iter()
+                .find_map(|coding| coding.display.as_deref().or(coding.code.as_deref()))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Identifier {
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    /// `Patient/123`, URL tuyệt đối hoặc `#contained`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<Identifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+}
+
+impl Reference {
+    /// `{resource_type}/{id}`
+    pub fn to(resource_type: &str, id: &str) -> Self {
+        Self {
+            reference: Some(format!("{}/{}", resource_type, id)),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_display(mut self, display: impl Into<String>) -> Self {
+        self.display = Some(display.into());
+        self
+    }
+
+    /// Loại resource và id của một reference dạng `Patient/123`, kể cả URL
+    /// tuyệt đối và `_history`. `None` với reference `#contained` hoặc chỉ có
+    /// `identifier`.
+    pub fn target(&self) -> Option<(&str, &str)> {
+        let reference = self.reference.as_deref()?;
+        if reference.starts_with('#') {
+            return None;
+        }
+        let path = reference.split(['?', '#']).next().unwrap_or(reference);
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let end = match segments.iter().rposition(|segment| *segment == "_history") {
+            Some(history) => history,
+            None => segments.len(),
+        };
+        match segments[..end] {
+            [.., resource_type, id] if is_resource_type(resource_type) => Some((resource_type, id)),
+            _ => None,
+        }
+    }
+
+    /// Id của reference nếu nó trỏ tới `resource_type`.
+    pub fn id_of(&self, resource_type: &str) -> Option<&str> {
+        self.target()
+            .filter(|(target_type, _)| *target_type == resource_type)
+            .map(|(_, id)| id)
+    }
+}
+
+/// Tên loại resource của FHIR bắt đầu bằng chữ hoa (`Patient`, `CarePlan`).
+fn is_resource_type(segment: &str) -> bool {
+    segment
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_uppercase())
+        && segment.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Period {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime>,
+}
+
+/// `Quantity`, cũng dùng cho `Age`, `Duration`, `SimpleQuantity`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// `<`, `<=`, `>=`, `>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(comparator) = &self.comparator {
+            write!(f, "{}", comparator)?;
+        }
+        if let Some(value) = self.value {
+            write!(f, "{}", value)?;
+        }
+        match self.unit.as_deref().or(self.code.as_deref()) {
+            Some(unit) if self.value.is_some() => write!(f, " {}", unit),
+            Some(unit) => write!(f, "{}", unit),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Range {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<Quantity>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ratio {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numerator: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denominator: Option<Quantity>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HumanName {
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub given: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefix: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suffix: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+}
+
+impl HumanName {
+    /// `text`, hoặc ghép `prefix given family suffix`.
+    pub fn display(&self) -> Option<String> {
+        if let Some(text) = &self.text {
+            return Some(text.clone());
+        }
+        let parts: Vec<&str> = self
+            .prefix
+            .iter()
+            .chain(&self.given)
+            .chain(&self.family)
+            .chain(&self.suffix)
+            .map(String::as_str)
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Address {
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub district: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContactPoint {
+    /// `phone`, `email`, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_reference: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_string: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime>,
+    pub text: String,
+}
+
+/// Nội dung đính kèm: `data` (base64) hoặc `url` tới Binary.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingRepeat {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds_period: Option<Period>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<f64>,
+    /// `s`, `min`, `h`, `d`, `wk`, `mo`, `a`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event: Vec<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<TimingRepeat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+}
+
+/// `dose[x]` của `Dosage.doseAndRate`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Dose {
+    #[serde(rename = "doseQuantity")]
+    Quantity(Quantity),
+    #[serde(rename = "doseRange")]
+    Range(Range),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DoseAndRate {
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<CodeableConcept>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub dose: Option<Dose>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dosage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patient_instruction: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_needed_boolean: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dose_and_rate: Vec<DoseAndRate>,
+}
+
+/// `effective[x]` của Observation và DiagnosticReport.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Effective {
+    #[serde(rename = "effectiveDateTime")]
+    DateTime(DateTime),
+    #[serde(rename = "effectivePeriod")]
+    Period(Period),
+    #[serde(rename = "effectiveInstant")]
+    Instant(Instant),
+    #[serde(rename = "effectiveTiming")]
+    Timing(Timing),
+}
+
+impl Effective {
+    /// Thời điểm bắt đầu, dùng để sắp xếp.
+    pub fn start(&self) -> Option<&str> {
+        match self {
+            Self::DateTime(value) | Self::Instant(value) => Some(value),
+            Self::Period(period) => period.start.as_deref(),
+            Self::Timing(timing) => timing.event.first().map(String::as_str),
+        }
+    }
+}
+
+/// `onset[x]` của Condition và AllergyIntolerance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Onset {
+    #[serde(rename = "onsetDateTime")]
+    DateTime(DateTime),
+    #[serde(rename = "onsetAge")]
+    Age(Quantity),
+    #[serde(rename = "onsetPeriod")]
+    Period(Period),
+    #[serde(rename = "onsetRange")]
+    Range(Range),
+    #[serde(rename = "onsetString")]
+    String(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_targets() {
+        let relative = Reference::to("Patient", "erXuFYUfucBZaryVksYEcMg3");
+        assert_eq!(
+            relative.target(),
+            Some(("Patient", "erXuFYUfucBZaryVksYEcMg3"))
+        );
+
+        let absolute = Reference {
+            reference: Some(
+                "https://fhir.epic.com/interconnect-fhir-oauth/api/FHIR/R4/Encounter/e1/_history/2"
+                    .to_string(),
+            ),
+            ..Reference::default()
+        };
+        assert_eq!(absolute.id_of("Encounter"), Some("e1"));
+        assert_eq!(absolute.id_of("Patient"), None);
+
+        let contained = Reference {
+            reference: Some("#med1".to_string()),
+            ..Reference::default()
+        };
+        assert_eq!(contained.target(), None);
+    }
+
+    #[test]
+    fn display_helpers() {
+        let concept = CodeableConcept::from_coding(
+            Coding::new("http://snomed.info/sct", "38341003").with_display("Hypertension"),
+        );
+        assert!(concept.has_coding("http://snomed.info/sct", "38341003"));
+        assert_eq!(concept.display(), Some("Hypertension"));
+
+        let name = HumanName {
+            family: Some("Lopez".to_string()),
+            given: vec!["Camila".to_string(), "Maria".to_string()],
+            ..HumanName::default()
+        };
+        assert_eq!(name.display().as_deref(), Some("Camila Maria Lopez"));
+
+        let quantity = Quantity {
+            value: Some(5.4),
+            unit: Some("%".to_string()),
+            ..Quantity::default()
+        };
+        assert_eq!(quantity.to_string(), "5.4 %");
+    }
+}
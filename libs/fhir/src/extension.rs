@@ -0,0 +1,150 @@
+//! Extension của FHIR, gồm cả extension riêng của Epic.
+//!
+//! `value[x]` có hơn 50 kiểu nên được giữ nguyên dạng JSON và đọc qua các hàm
+//! `value_*`: extension lạ (ví dụ `http://open.epic.com/FHIR/StructureDefinition/...`)
+//! không bao giờ làm hỏng việc đọc resource, và được ghi lại nguyên vẹn.
+
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::datatypes::{CodeableConcept, Coding, Period, Quantity, Reference};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extension {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Extension lồng nhau (extension phức hợp như US Core race/ethnicity)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    /// `value[x]`, ví dụ `{"valueCoding": {...}}`
+    #[serde(flatten)]
+    pub value: BTreeMap<String, Value>,
+}
+
+impl Extension {
+    /// Extension có `value{kind}`, ví dụ `Extension::new(url, "String", json!("x"))`.
+    pub fn new(url: impl Into<String>, kind: &str, value: Value) -> Self {
+        Self {
+            url: url.into(),
+            id: None,
+            extension: Vec::new(),
+            value: BTreeMap::from([(format!("value{}", kind), value)]),
+        }
+    }
+
+    /// Kiểu và giá trị của `value[x]`, ví dụ `("Coding", {...})`.
+    pub fn value(&self) -> Option<(&str, &Value)> {
+        self.value
+            .iter()
+            .find_map(|(key, value)| key.strip_prefix("value").map(|kind| (kind, value)))
+    }
+
+    /// `value{kind}` đọc thành `T`; `None` nếu extension có kiểu khác.
+    pub fn value_as<T: DeserializeOwned>(&self, kind: &str) -> Option<T> {
+        let value = self.value.get(&format!("value{}", kind))?;
+        T::deserialize(value).ok()
+    }
+
+    /// `valueString`, `valueCode`, `valueUri`, `valueDateTime`, ... (mọi kiểu nguyên thủy dạng chuỗi).
+    pub fn value_str(&self) -> Option<&str> {
+        self.value().and_then(|(_, value)| value.as_str())
+    }
+
+    pub fn value_bool(&self) -> Option<bool> {
+        self.value.get("valueBoolean").and_then(Value::as_bool)
+    }
+
+    pub fn value_coding(&self) -> Option<Coding> {
+        self.value_as("Coding")
+    }
+
+    pub fn value_codeable_concept(&self) -> Option<CodeableConcept> {
+        self.value_as("CodeableConcept")
+    }
+
+    pub fn value_reference(&self) -> Option<Reference> {
+        self.value_as("Reference")
+    }
+
+    pub fn value_period(&self) -> Option<Period> {
+        self.value_as("Period")
+    }
+
+    pub fn value_quantity(&self) -> Option<Quantity> {
+        self.value_as("Quantity")
+    }
+
+    /// Extension con đầu tiên có `url` (extension phức hợp dùng url tương đối như `ombCategory`).
+    pub fn extension(&self, url: &str) -> Option<&Extension> {
+        self.extension.iter().find(|extension| extension.url == url)
+    }
+}
+
+/// Resource hoặc kiểu dữ liệu có `extension` và `modifierExtension`.
+pub trait HasExtensions {
+    fn extensions(&self) -> &[Extension];
+
+    fn modifier_extensions(&self) -> &[Extension] {
+        &[]
+    }
+
+    /// Extension đầu tiên có `url`.
+    fn extension(&self, url: &str) -> Option<&Extension> {
+        self.extensions()
+            .iter()
+            .find(|extension| extension.url == url)
+    }
+
+    /// Mọi extension có `url` (extension có thể lặp lại).
+    fn extensions_by_url<'a>(&'a self, url: &'a str) -> impl Iterator<Item = &'a Extension> + 'a {
+        self.extensions()
+            .iter()
+            .filter(move |extension| extension.url == url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_nested_and_unknown_extensions() {
+        let race: Extension = serde_json::from_value(json!({
+            "url": "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race",
+            "extension": [
+                {
+                    "url": "ombCategory",
+                    "valueCoding": { "system": "urn:oid:2.16.840.1.113883.6.238", "code": "2106-3", "display": "White" }
+                },
+                { "url": "text", "valueString": "White" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(race.value(), None);
+        assert_eq!(
+            race.extension("ombCategory")
+                .and_then(Extension::value_coding)
+                .and_then(|coding| coding.code),
+            Some("2106-3".to_string())
+        );
+        assert_eq!(
+            race.extension("text").and_then(Extension::value_str),
+            Some("White")
+        );
+
+        // Kiểu value[x] không có hàm riêng vẫn đọc và ghi lại được
+        let epic = json!({
+            "url": "http://open.epic.com/FHIR/StructureDefinition/extension/calculated-pronouns-to-use-for-text",
+            "valueAddress": { "city": "Madison" }
+        });
+        let extension: Extension = serde_json::from_value(epic.clone()).unwrap();
+        assert_eq!(extension.value().map(|(kind, _)| kind), Some("Address"));
+        assert_eq!(extension.value_codeable_concept(), None);
+        assert_eq!(serde_json::to_value(&extension).unwrap(), epic);
+    }
+}
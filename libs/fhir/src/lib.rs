@@ -0,0 +1,36 @@
+//! Kiểu FHIR R4 có serde cho các resource mà patient summary dùng.
+//!
+//! Đọc được JSON của Epic: trường lạ bị bỏ qua, extension (kể cả của Epic)
+//! được giữ nguyên và đọc qua [`HasExtensions`], `value[x]` là enum theo kiểu,
+//! loại resource chưa có kiểu riêng nằm trong [`Resource::Other`].
+//!
+//! ```ignore
+//! let bundle: Bundle = serde_json::from_slice(&body)?;
+//! for condition in bundle.matches::<Condition>() {
+//!     println!("{:?}", condition.code.as_ref().and_then(CodeableConcept::display));
+//! }
+//! ```
+
+pub mod bulk;
+pub mod bundle;
+pub mod client;
+pub mod datatypes;
+pub mod extension;
+pub mod resource;
+pub mod resources;
+
+pub use bundle::{Bundle, BundleEntry, BundleLink};
+pub use client::{Auth, FhirClient, FhirError, SearchParams};
+pub use datatypes::{CodeableConcept, Coding, Period, Quantity, Reference};
+pub use extension::{Extension, HasExtensions};
+pub use resource::{FhirResource, Resource};
+pub use resources::{
+    AllergyIntolerance, CarePlan, Composition, Condition, DiagnosticReport, DocumentReference,
+    Encounter, Immunization, MedicationRequest, Observation, OperationOutcome, Patient, Procedure,
+};
+
+#[cfg(test)]
+mod tests {
+    mod bulk_tests;
+    mod client_tests;
+}
@@ -0,0 +1,224 @@
+//! [`Resource`]: một resource bất kỳ, phân loại theo `resourceType`.
+//!
+//! Các struct resource không tự ghi `resourceType`; khi serialize qua
+//! [`Resource`] (trong Bundle, `contained`) trường này được thêm vào. Loại chưa
+//! có kiểu riêng được giữ nguyên JSON trong [`Resource::Other`].
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::bundle::Bundle;
+use crate::extension::{Extension, HasExtensions};
+use crate::resources::{
+    AllergyIntolerance, CarePlan, Composition, Condition, DiagnosticReport, DocumentReference,
+    Encounter, Immunization, MedicationRequest, Observation, OperationOutcome, Patient, Procedure,
+};
+
+/// Resource có kiểu riêng trong crate.
+pub trait FhirResource: Serialize + DeserializeOwned + Into<Resource> + 'static {
+    /// Giá trị `resourceType`
+    const TYPE: &'static str;
+
+    fn id(&self) -> Option<&str>;
+
+    /// Resource nếu `resource` thuộc loại này.
+    fn from_resource(resource: Resource) -> Option<Self>;
+
+    fn as_resource(resource: &Resource) -> Option<&Self>;
+
+    /// JSON đầy đủ, có `resourceType`.
+    fn to_json(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Value::Object(map) = &mut value {
+            map.insert(
+                "resourceType".to_string(),
+                Value::String(Self::TYPE.to_string()),
+            );
+        }
+        value
+    }
+}
+
+/// Ghi resource kèm `resourceType`.
+#[derive(Serialize)]
+struct Tagged<'a, T> {
+    #[serde(rename = "resourceType")]
+    resource_type: &'static str,
+    #[serde(flatten)]
+    resource: &'a T,
+}
+
+macro_rules! resources {
+    ($($name:ident),* $(,)?) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Resource {
+            $($name(Box<$name>),)*
+            /// Loại chưa có kiểu riêng (Medication, Practitioner, ...), giữ nguyên JSON
+            Other(Value),
+        }
+
+        impl Resource {
+            pub fn resource_type(&self) -> &str {
+                match self {
+                    $(Self::$name(_) => $name::TYPE,)*
+                    Self::Other(value) => value["resourceType"].as_str().unwrap_or_default(),
+                }
+            }
+
+            pub fn id(&self) -> Option<&str> {
+                match self {
+                    $(Self::$name(resource) => resource.id(),)*
+                    Self::Other(value) => value["id"].as_str(),
+                }
+            }
+        }
+
+        $(
+            impl FhirResource for $name {
+                const TYPE: &'static str = stringify!($name);
+
+                fn id(&self) -> Option<&str> {
+                    self.id.as_deref()
+                }
+
+                fn from_resource(resource: Resource) -> Option<Self> {
+                    match resource {
+                        Resource::$name(resource) => Some(*resource),
+                        _ => None,
+                    }
+                }
+
+                fn as_resource(resource: &Resource) -> Option<&Self> {
+                    match resource {
+                        Resource::$name(resource) => Some(resource),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl From<$name> for Resource {
+                fn from(resource: $name) -> Self {
+                    Self::$name(Box::new(resource))
+                }
+            }
+        )*
+
+        impl Serialize for Resource {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    $(Self::$name(resource) => Tagged {
+                        resource_type: $name::TYPE,
+                        resource: resource.as_ref(),
+                    }
+                    .serialize(serializer),)*
+                    Self::Other(value) => value.serialize(serializer),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Resource {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = Value::deserialize(deserializer)?;
+                let Some(resource_type) = value["resourceType"].as_str() else {
+                    return Err(D::Error::missing_field("resourceType"));
+                };
+                match resource_type {
+                    $(stringify!($name) => {
+                        let id = value["id"].as_str().unwrap_or_default().to_string();
+                        serde_json::from_value(value)
+                            .map(|resource| Self::$name(Box::new(resource)))
+                            .map_err(|e| {
+                                D::Error::custom(format!("invalid {} '{}': {}", stringify!($name), id, e))
+                            })
+                    })*
+                    _ => Ok(Self::Other(value)),
+                }
+            }
+        }
+    };
+}
+
+resources!(
+    Patient,
+    Encounter,
+    CarePlan,
+    Procedure,
+    Condition,
+    Observation,
+    MedicationRequest,
+    AllergyIntolerance,
+    DiagnosticReport,
+    DocumentReference,
+    Immunization,
+    Composition,
+    OperationOutcome,
+    Bundle,
+);
+
+macro_rules! domain_resources {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl HasExtensions for $name {
+                fn extensions(&self) -> &[Extension] {
+                    &self.extension
+                }
+
+                fn modifier_extensions(&self) -> &[Extension] {
+                    &self.modifier_extension
+                }
+            }
+        )*
+    };
+}
+
+// Bundle không phải DomainResource nên không có extension
+domain_resources!(
+    Patient,
+    Encounter,
+    CarePlan,
+    Procedure,
+    Condition,
+    Observation,
+    MedicationRequest,
+    AllergyIntolerance,
+    DiagnosticReport,
+    DocumentReference,
+    Immunization,
+    Composition,
+    OperationOutcome,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn dispatches_on_resource_type() {
+        let resource: Resource = serde_json::from_value(json!({
+            "resourceType": "Encounter",
+            "id": "e1",
+            "status": "finished",
+            "class": { "system": "http://terminology.hl7.org/CodeSystem/v3-ActCode", "code": "AMB" }
+        }))
+        .unwrap();
+        assert_eq!(resource.resource_type(), "Encounter");
+        assert_eq!(resource.id(), Some("e1"));
+        let encounter = Encounter::from_resource(resource).unwrap();
+        assert_eq!(encounter.to_json()["resourceType"], "Encounter");
+
+        let error = serde_json::from_value::<Resource>(json!({
+            "resourceType": "Encounter",
+            "id": "e2"
+        }))
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("invalid Encounter 'e2'"),
+            "{}",
+            error
+        );
+
+        assert!(serde_json::from_value::<Resource>(json!({ "id": "x" })).is_err());
+    }
+}
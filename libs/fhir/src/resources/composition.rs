@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{CodeableConcept, DateTime, Identifier, Meta, Narrative, Reference};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+/// Hệ mã LOINC, dùng cho `Composition.type` và mã của từng section.
+pub const LOINC: &str = "http://loinc.org";
+
+/// Phần đầu của một document (`Bundle` loại `document`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Composition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<Identifier>,
+    /// `preliminary`, `final`, `amended`, `entered-in-error`
+    pub status: String,
+    #[serde(rename = "type")]
+    pub type_: CodeableConcept,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    pub date: DateTime,
+    pub author: Vec<Reference>,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidentiality: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attester: Vec<CompositionAttester>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custodian: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub section: Vec<CompositionSection>,
+}
+
+impl Composition {
+    /// Section cấp cao nhất có mã LOINC `code`.
+    pub fn section(&self, code: &str) -> Option<&CompositionSection> {
+        self.section.iter().find(|section| {
+            section
+                .code
+                .as_ref()
+                .is_some_and(|concept| concept.has_coding(LOINC, code))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompositionAttester {
+    /// `personal`, `professional`, `legal`, `official`
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub party: Option<Reference>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositionSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub author: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    /// `working`, `snapshot`, `changes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ordered_by: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entry: Vec<Reference>,
+    /// Lý do section không có entry, ví dụ `nilknown`, `unavailable`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub empty_reason: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub section: Vec<CompositionSection>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::FhirResource;
+    use serde_json::json;
+
+    #[test]
+    fn finds_sections_by_loinc_code() {
+        let json = json!({
+            "resourceType": "Composition",
+            "id": "ips-1",
+            "status": "final",
+            "type": { "coding": [{ "system": LOINC, "code": "60591-5" }] },
+            "subject": { "reference": "Patient/erXuFYUfucBZaryVksYEcMg3" },
+            "date": "2024-05-01T10:00:00Z",
+            "author": [{ "display": "SEDS" }],
+            "title": "International Patient Summary",
+            "section": [{
+                "title": "Allergies and Intolerances",
+                "code": { "coding": [{ "system": LOINC, "code": "48765-2" }] },
+                "text": { "status": "generated", "div": "<div xmlns=\"http://www.w3.org/1999/xhtml\">None</div>" },
+                "emptyReason": { "coding": [{ "code": "nilknown" }] }
+            }]
+        });
+        let composition: Composition = serde_json::from_value(json.clone()).unwrap();
+        let allergies = composition.section("48765-2").unwrap();
+        assert!(allergies.entry.is_empty());
+        assert!(allergies.empty_reason.is_some());
+        assert!(composition.section("11450-4").is_none());
+        assert_eq!(composition.to_json(), json);
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{
+    Annotation, CodeableConcept, DateTime, Identifier, Meta, Narrative, Onset, Period, Quantity,
+    Range, Reference,
+};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+/// Hệ mã của `Condition.clinicalStatus`.
+pub const CONDITION_CLINICAL: &str = "http://terminology.hl7.org/CodeSystem/condition-clinical";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clinical_status: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_status: Option<CodeableConcept>,
+    /// `problem-list-item`, `encounter-diagnosis`, `health-concern`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_site: Vec<CodeableConcept>,
+    pub subject: Reference,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub onset: Option<Onset>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub abatement: Option<ConditionAbatement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded_date: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asserter: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+impl Condition {
+    /// `clinicalStatus` là `active`, `recurrence` hoặc `relapse`.
+    pub fn is_active(&self) -> bool {
+        self.clinical_status
+            .as_ref()
+            .and_then(|status| status.code_in(CONDITION_CLINICAL))
+            .is_some_and(|code| matches!(code, "active" | "recurrence" | "relapse"))
+    }
+}
+
+/// `abatement[x]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConditionAbatement {
+    #[serde(rename = "abatementDateTime")]
+    DateTime(DateTime),
+    #[serde(rename = "abatementAge")]
+    Age(Quantity),
+    #[serde(rename = "abatementPeriod")]
+    Period(Period),
+    #[serde(rename = "abatementRange")]
+    Range(Range),
+    #[serde(rename = "abatementString")]
+    String(String),
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{
+    Attachment, CodeableConcept, Coding, Identifier, Instant, Meta, Narrative, Period, Reference,
+};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentReference {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    /// `current`, `superseded`, `entered-in-error`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_status: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<Instant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub author: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticator: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custodian: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security_label: Vec<CodeableConcept>,
+    pub content: Vec<DocumentReferenceContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<DocumentReferenceContext>,
+}
+
+impl DocumentReference {
+    /// Attachment đầu tiên có `contentType` bắt đầu bằng `content_type`
+    /// (ví dụ `application/xml` cho C-CDA, `text/html`).
+    pub fn attachment(&self, content_type: &str) -> Option<&Attachment> {
+        self.content
+            .iter()
+            .map(|content| &content.attachment)
+            .find(|attachment| {
+                attachment
+                    .content_type
+                    .as_deref()
+                    .is_some_and(|value| value.starts_with(content_type))
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentReferenceContent {
+    pub attachment: Attachment,
+    /// Ví dụ `urn:hl7-org:sdwg:ccda-structuredBody:2.1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Coding>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentReferenceContext {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encounter: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facility_type: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub practice_setting: Option<CodeableConcept>,
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{
+    Annotation, CodeableConcept, Date, DateTime, Identifier, Meta, Narrative, Quantity, Reference,
+};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Immunization {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    /// `completed`, `entered-in-error`, `not-done`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_reason: Option<CodeableConcept>,
+    pub vaccine_code: CodeableConcept,
+    pub patient: Reference,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten)]
+    pub occurrence: ImmunizationOccurrence,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_source: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lot_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dose_quantity: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<ImmunizationPerformer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_subpotent: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocol_applied: Vec<ImmunizationProtocolApplied>,
+}
+
+/// `occurrence[x]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImmunizationOccurrence {
+    #[serde(rename = "occurrenceDateTime")]
+    DateTime(DateTime),
+    #[serde(rename = "occurrenceString")]
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImmunizationPerformer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<CodeableConcept>,
+    pub actor: Reference,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImmunizationProtocolApplied {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_disease: Vec<CodeableConcept>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub dose_number: Option<ImmunizationDoseNumber>,
+}
+
+/// `doseNumber[x]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImmunizationDoseNumber {
+    #[serde(rename = "doseNumberPositiveInt")]
+    PositiveInt(u32),
+    #[serde(rename = "doseNumberString")]
+    String(String),
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{
+    Annotation, CodeableConcept, DateTime, Dosage, Identifier, Meta, Narrative, Period, Quantity,
+    Reference,
+};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicationRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    /// Epic thường đặt `Medication` ở đây và tham chiếu bằng `#id`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    /// `active`, `on-hold`, `cancelled`, `completed`, `stopped`, ...
+    pub status: String,
+    /// `proposal`, `plan`, `order`, ...
+    pub intent: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(flatten)]
+    pub medication: MedicationRequestMedication,
+    pub subject: Reference,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authored_on: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requester: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_reference: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dosage_instruction: Vec<Dosage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispense_request: Option<MedicationRequestDispenseRequest>,
+}
+
+impl MedicationRequest {
+    /// Tên thuốc: `medicationCodeableConcept`, hoặc `display` của reference,
+    /// hoặc `code` của `Medication` contained mà reference trỏ tới.
+    pub fn medication_display(&self) -> Option<&str> {
+        match &self.medication {
+            MedicationRequestMedication::CodeableConcept(concept) => concept.display(),
+            MedicationRequestMedication::Reference(reference) => {
+                reference.display.as_deref().or_else(|| {
+                    let id = reference.reference.as_deref()?.strip_prefix('#')?;
+                    self.contained.iter().find_map(|resource| match resource {
+                        Resource::Other(value)
+                            if value["resourceType"] == "Medication" && value["id"] == id =>
+                        {
+                            value["code"]["text"]
+                                .as_str()
+                                .or_else(|| value["code"]["coding"][0]["display"].as_str())
+                        }
+                        _ => None,
+                    })
+                })
+            }
+        }
+    }
+}
+
+/// `medication[x]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MedicationRequestMedication {
+    #[serde(rename = "medicationCodeableConcept")]
+    CodeableConcept(CodeableConcept),
+    #[serde(rename = "medicationReference")]
+    Reference(Box<Reference>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicationRequestDispenseRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_period: Option<Period>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_of_repeats_allowed: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_supply_duration: Option<Quantity>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn resolves_contained_medication() {
+        let request: MedicationRequest = serde_json::from_value(json!({
+            "resourceType": "MedicationRequest",
+            "id": "mr-1",
+            "contained": [{
+                "resourceType": "Medication",
+                "id": "med1",
+                "code": { "coding": [{ "system": "http://www.nlm.nih.gov/research/umls/rxnorm", "code": "197361", "display": "amlodipine 5 MG Oral Tablet" }] }
+            }],
+            "status": "active",
+            "intent": "order",
+            "medicationReference": { "reference": "#med1" },
+            "subject": { "reference": "Patient/erXuFYUfucBZaryVksYEcMg3" },
+            "dosageInstruction": [{
+                "text": "Take 1 tablet by mouth daily",
+                "timing": { "repeat": { "frequency": 1, "period": 1.0, "periodUnit": "d" } },
+                "doseAndRate": [{ "doseQuantity": { "value": 1.0, "unit": "tablet" } }]
+            }]
+        }))
+        .unwrap();
+        assert_eq!(
+            request.medication_display(),
+            Some("amlodipine 5 MG Oral Tablet")
+        );
+        assert!(matches!(
+            request.dosage_instruction[0].dose_and_rate[0].dose,
+            Some(crate::datatypes::Dose::Quantity(_))
+        ));
+
+        // medication[x] bắt buộc
+        assert!(
+            serde_json::from_value::<MedicationRequest>(json!({
+                "status": "active",
+                "intent": "order",
+                "subject": { "reference": "Patient/1" }
+            }))
+            .is_err()
+        );
+    }
+}
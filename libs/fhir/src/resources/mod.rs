@@ -0,0 +1,41 @@
+//! Resource R4 dùng cho patient summary.
+
+pub mod allergy_intolerance;
+pub mod care_plan;
+pub mod composition;
+pub mod condition;
+pub mod diagnostic_report;
+pub mod document_reference;
+pub mod encounter;
+pub mod immunization;
+pub mod medication_request;
+pub mod observation;
+pub mod operation_outcome;
+pub mod patient;
+pub mod procedure;
+
+pub use allergy_intolerance::{AllergyIntolerance, AllergyIntoleranceReaction};
+pub use care_plan::{CarePlan, CarePlanActivity, CarePlanActivityDetail};
+pub use composition::{Composition, CompositionAttester, CompositionSection};
+pub use condition::{Condition, ConditionAbatement};
+pub use diagnostic_report::DiagnosticReport;
+pub use document_reference::{
+    DocumentReference, DocumentReferenceContent, DocumentReferenceContext,
+};
+pub use encounter::{
+    Encounter, EncounterDiagnosis, EncounterHospitalization, EncounterLocation,
+    EncounterParticipant,
+};
+pub use immunization::{
+    Immunization, ImmunizationDoseNumber, ImmunizationOccurrence, ImmunizationPerformer,
+    ImmunizationProtocolApplied,
+};
+pub use medication_request::{
+    MedicationRequest, MedicationRequestDispenseRequest, MedicationRequestMedication,
+};
+pub use observation::{
+    Observation, ObservationComponent, ObservationReferenceRange, ObservationValue,
+};
+pub use operation_outcome::{OperationOutcome, OperationOutcomeIssue};
+pub use patient::{Patient, PatientCommunication, PatientDeceased};
+pub use procedure::{Procedure, ProcedurePerformed, ProcedurePerformer};
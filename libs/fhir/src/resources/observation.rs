@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{
+    Annotation, CodeableConcept, DateTime, Effective, Identifier, Instant, Meta, Narrative, Period,
+    Quantity, Range, Ratio, Reference,
+};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Observation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub based_on: Vec<Reference>,
+    /// `registered`, `preliminary`, `final`, `amended`, ...
+    pub status: String,
+    /// `vital-signs`, `laboratory`, `social-history`, ...
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    pub code: CodeableConcept,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub effective: Option<Effective>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued: Option<Instant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<Reference>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ObservationValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_absent_reason: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interpretation: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_site: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_range: Vec<ObservationReferenceRange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub has_member: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_from: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub component: Vec<ObservationComponent>,
+}
+
+/// `value[x]` của Observation và của từng component.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ObservationValue {
+    #[serde(rename = "valueQuantity")]
+    Quantity(Quantity),
+    #[serde(rename = "valueCodeableConcept")]
+    CodeableConcept(CodeableConcept),
+    #[serde(rename = "valueString")]
+    String(String),
+    #[serde(rename = "valueBoolean")]
+    Boolean(bool),
+    #[serde(rename = "valueInteger")]
+    Integer(i64),
+    #[serde(rename = "valueRange")]
+    Range(Range),
+    #[serde(rename = "valueRatio")]
+    Ratio(Ratio),
+    #[serde(rename = "valueTime")]
+    Time(String),
+    #[serde(rename = "valueDateTime")]
+    DateTime(DateTime),
+    #[serde(rename = "valuePeriod")]
+    Period(Period),
+}
+
+impl std::fmt::Display for ObservationValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Quantity(quantity) => write!(f, "{}", quantity),
+            Self::CodeableConcept(concept) => {
+                write!(f, "{}", concept.display().unwrap_or_default())
+            }
+            Self::String(value) | Self::Time(value) | Self::DateTime(value) => {
+                write!(f, "{}", value)
+            }
+            Self::Boolean(value) => write!(f, "{}", value),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Range(range) => {
+                let bound = |quantity: &Option<Quantity>| {
+                    quantity
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                };
+                write!(f, "{} - {}", bound(&range.low), bound(&range.high))
+            }
+            Self::Ratio(ratio) => {
+                let part = |quantity: &Option<Quantity>| {
+                    quantity
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                };
+                write!(f, "{}/{}", part(&ratio.numerator), part(&ratio.denominator))
+            }
+            Self::Period(period) => write!(
+                f,
+                "{} - {}",
+                period.start.as_deref().unwrap_or_default(),
+                period.end.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObservationReferenceRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<Quantity>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObservationComponent {
+    pub code: CodeableConcept,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ObservationValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_absent_reason: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interpretation: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_range: Vec<ObservationReferenceRange>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn choice_types_round_trip() {
+        let json = json!({
+            "id": "bp-1",
+            "status": "final",
+            "category": [{ "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/observation-category", "code": "vital-signs" }] }],
+            "code": { "coding": [{ "system": "http://loinc.org", "code": "85354-9" }], "text": "Blood Pressure" },
+            "subject": { "reference": "Patient/erXuFYUfucBZaryVksYEcMg3" },
+            "effectiveDateTime": "2023-06-02T08:10:00Z",
+            "component": [
+                {
+                    "code": { "text": "Systolic" },
+                    "valueQuantity": { "value": 118.0, "unit": "mm[Hg]", "system": "http://unitsofmeasure.org", "code": "mm[Hg]" }
+                },
+                {
+                    "code": { "text": "Diastolic" },
+                    "dataAbsentReason": { "text": "Not measured" }
+                }
+            ]
+        });
+        let observation: Observation = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            observation.effective.as_ref().and_then(Effective::start),
+            Some("2023-06-02T08:10:00Z")
+        );
+        assert_eq!(observation.value, None);
+        assert_eq!(
+            observation.component[0]
+                .value
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("118 mm[Hg]")
+        );
+        assert_eq!(observation.component[1].value, None);
+        assert_eq!(serde_json::to_value(&observation).unwrap(), json);
+
+        let coded: Observation = serde_json::from_value(json!({
+            "status": "final",
+            "code": { "text": "Smoking status" },
+            "effectivePeriod": { "start": "2020-01-01" },
+            "valueCodeableConcept": { "text": "Never smoker" }
+        }))
+        .unwrap();
+        assert_eq!(
+            coded.value,
+            Some(ObservationValue::CodeableConcept(
+                CodeableConcept::from_text("Never smoker")
+            ))
+        );
+        assert!(matches!(coded.effective, Some(Effective::Period(_))));
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{CodeableConcept, Meta, Narrative};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+/// Lỗi hoặc cảnh báo của FHIR server; Epic còn đặt nó trong Bundle của search.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationOutcome {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default)]
+    pub issue: Vec<OperationOutcomeIssue>,
+}
+
+impl OperationOutcome {
+    /// Có issue mức `error` hoặc `fatal`.
+    pub fn has_errors(&self) -> bool {
+        self.issue
+            .iter()
+            .any(|issue| matches!(issue.severity.as_str(), "error" | "fatal"))
+    }
+}
+
+impl std::fmt::Display for OperationOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let messages: Vec<String> = self.issue.iter().map(ToString::to_string).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationOutcomeIssue {
+    /// `fatal`, `error`, `warning`, `information`
+    pub severity: String,
+    /// `invalid`, `not-found`, `forbidden`, `processing`, ...
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expression: Vec<String>,
+}
+
+impl std::fmt::Display for OperationOutcomeIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = self
+            .diagnostics
+            .as_deref()
+            .or_else(|| self.details.as_ref().and_then(CodeableConcept::display))
+            .unwrap_or(&self.code);
+        write!(f, "{} ({})", message, self.severity)
+    }
+}
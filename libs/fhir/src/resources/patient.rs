@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::{
+    Address, CodeableConcept, ContactPoint, Date, DateTime, HumanName, Identifier, Meta, Narrative,
+    Reference,
+};
+use crate::extension::Extension;
+use crate::resource::Resource;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Patient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub name: Vec<HumanName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub telecom: Vec<ContactPoint>,
+    /// `male`, `female`, `other`, `unknown`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<Date>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub deceased: Option<PatientDeceased>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marital_status: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub communication: Vec<PatientCommunication>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub general_practitioner: Vec<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub managing_organization: Option<Reference>,
+}
+
+impl Patient {
+    /// Tên chính thức (`use` = `official`), nếu không thì tên đầu tiên.
+    pub fn official_name(&self) -> Option<&HumanName> {
+        self.name
+            .iter()
+            .find(|name| name.use_.as_deref() == Some("official"))
+            .or_else(|| self.name.first())
+    }
+}
+
+/// `deceased[x]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PatientDeceased {
+    #[serde(rename = "deceasedBoolean")]
+    Boolean(bool),
+    #[serde(rename = "deceasedDateTime")]
+    DateTime(DateTime),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatientCommunication {
+    pub language: CodeableConcept,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::HasExtensions;
+    use serde_json::json;
+
+    const LEGAL_SEX: &str = "http://open.epic.com/FHIR/StructureDefinition/extension/legal-sex";
+
+    #[test]
+    fn reads_an_epic_patient_with_extensions() {
+        let patient: Patient = serde_json::from_value(json!({
+            "resourceType": "Patient",
+            "id": "erXuFYUfucBZaryVksYEcMg3",
+            "extension": [
+                {
+                    "url": LEGAL_SEX,
+                    "valueCodeableConcept": {
+                        "coding": [{ "system": "urn:oid:1.2.840.114350.1.13.0.1.7.10.698084.130.657370.19999000", "code": "female", "display": "female" }]
+                    }
+                },
+                {
+                    "url": "http://hl7.org/fhir/us/core/StructureDefinition/us-core-birthsex",
+                    "valueCode": "F"
+                }
+            ],
+            "identifier": [{ "use": "usual", "system": "urn:oid:1.2.840.114350.1.13.0.1.7.5.737384.0", "value": "E4007" }],
+            "active": true,
+            "name": [
+                { "use": "usual", "text": "Lopez, Camila" },
+                { "use": "official", "family": "Lopez", "given": ["Camila", "Maria"] }
+            ],
+            "gender": "female",
+            "birthDate": "1987-09-12",
+            "_birthDate": { "extension": [{ "url": "http://open.epic.com/FHIR/StructureDefinition/extension/birth-time", "valueTime": "08:30:00" }] },
+            "deceasedBoolean": false,
+            "communication": [{ "language": { "text": "English" }, "preferred": true }],
+            "generalPractitioner": [{ "reference": "Practitioner/eM5CWtq15N0WJeuCet5bJlQ3", "type": "Practitioner", "display": "Physician Family Medicine, MD" }]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            patient
+                .official_name()
+                .and_then(HumanName::display)
+                .as_deref(),
+            Some("Camila Maria Lopez")
+        );
+        assert_eq!(patient.deceased, Some(PatientDeceased::Boolean(false)));
+        assert_eq!(
+            patient
+                .extension(LEGAL_SEX)
+                .and_then(Extension::value_codeable_concept)
+                .and_then(|concept| concept.display().map(str::to_string)),
+            Some("female".to_string())
+        );
+        assert_eq!(
+            patient.general_practitioner[0].id_of("Practitioner"),
+            Some("eM5CWtq15N0WJeuCet5bJlQ3")
+        );
+
+        let json = serde_json::to_value(&patient).unwrap();
+        assert_eq!(json["deceasedBoolean"], false);
+        assert_eq!(json["birthDate"], "1987-09-12");
+        assert_eq!(json["extension"][1]["valueCode"], "F");
+    }
+}
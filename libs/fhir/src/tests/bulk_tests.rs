@@ -0,0 +1,489 @@
+//! `BulkExporter` against an in-process bulk data stub, with backend services
+//! tokens from the mock SMART server.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::Json;
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, RawQuery, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use config_lib::Settings;
+use futures::stream;
+use security::test_support::P256_PEM;
+use security::{ClientKeys, KeyStore};
+use serde_json::{Value, json};
+use smart_mock::{MockClient, MockSmartServer};
+use time::OffsetDateTime;
+
+use crate::bulk::{
+    BulkExporter, ExportJob, ExportOptions, ExportRequest, JobState, LocalDirectory, MemoryStore,
+    OutputStore,
+};
+use crate::client::{FhirClient, FhirError};
+use oauth2_lib::backend::{BackendServicesClient, BackendServicesConfig};
+
+const PATIENTS: &str =
+    "{\"resourceType\":\"Patient\",\"id\":\"p1\"}\n{\"resourceType\":\"Patient\",\"id\":\"p2\"}\n";
+const MORE_PATIENTS: &str = "{\"resourceType\":\"Patient\",\"id\":\"p3\"}\n";
+const CONDITIONS: &str =
+    "{\"resourceType\":\"Condition\",\"id\":\"c1\",\"subject\":{\"reference\":\"Patient/p1\"}}\n";
+const ERRORS: &str = "{\"resourceType\":\"OperationOutcome\",\"issue\":[{\"severity\":\"warning\",\"code\":\"not-found\"}]}\n";
+/// Bytes of `PATIENTS` sent before the first download breaks off.
+const INTERRUPTED_AT: usize = 20;
+
+#[derive(Default)]
+struct Recorded {
+    base_url: String,
+    tokens: Vec<String>,
+    kick_off_query: Option<String>,
+    status_polls: usize,
+    reject_next_poll: bool,
+    /// `(file, Range)` of every download
+    downloads: Vec<(String, Option<String>)>,
+    interrupted: bool,
+}
+
+struct Stub {
+    smart: MockSmartServer,
+    recorded: Mutex<Recorded>,
+}
+
+type Shared = Arc<Stub>;
+
+fn fhir_json(status: StatusCode, body: Value) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/fhir+json")],
+        Json(body),
+    )
+        .into_response()
+}
+
+fn outcome(code: &str, diagnostics: &str) -> Value {
+    json!({
+        "resourceType": "OperationOutcome",
+        "issue": [{ "severity": "error", "code": code, "diagnostics": diagnostics }]
+    })
+}
+
+fn unauthorized(diagnostics: &str) -> Response {
+    fhir_json(StatusCode::UNAUTHORIZED, outcome("login", diagnostics))
+}
+
+fn authorized(stub: &Stub, headers: &HeaderMap) -> bool {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    match stub.smart.access_token(token) {
+        Some(issued)
+            if issued
+                .scope
+                .split(' ')
+                .any(|scope| scope == "system/*.read") =>
+        {
+            let mut recorded = stub.recorded.lock().unwrap();
+            if !recorded.tokens.iter().any(|seen| seen == token) {
+                recorded.tokens.push(token.to_string());
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+async fn kick_off(
+    State(stub): State<Shared>,
+    Path(group): Path<String>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Response {
+    if !authorized(&stub, &headers) {
+        return unauthorized("invalid access token");
+    }
+    if headers.get("prefer").and_then(|value| value.to_str().ok()) != Some("respond-async") {
+        return fhir_json(
+            StatusCode::BAD_REQUEST,
+            outcome("invalid", "Prefer: respond-async is required"),
+        );
+    }
+    if !["panel", "broken", "stalled", "flaky"].contains(&group.as_str()) {
+        return fhir_json(
+            StatusCode::NOT_FOUND,
+            outcome("not-found", "Group not found"),
+        );
+    }
+    let mut recorded = stub.recorded.lock().unwrap();
+    recorded.kick_off_query = query;
+    let location = format!("{}bulkstatus/{}", recorded.base_url, group);
+    (StatusCode::ACCEPTED, [(header::CONTENT_LOCATION, location)]).into_response()
+}
+
+async fn export_status(
+    State(stub): State<Shared>,
+    Path(job): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    {
+        let mut recorded = stub.recorded.lock().unwrap();
+        if recorded.reject_next_poll {
+            // Token bị thu hồi giữa chừng
+            recorded.reject_next_poll = false;
+            return unauthorized("token revoked");
+        }
+    }
+    if !authorized(&stub, &headers) {
+        return unauthorized("invalid access token");
+    }
+    let mut recorded = stub.recorded.lock().unwrap();
+    recorded.status_polls += 1;
+    if recorded.status_polls == 1 || job == "stalled" {
+        return (
+            StatusCode::ACCEPTED,
+            [
+                (header::RETRY_AFTER.as_str(), "0"),
+                ("x-progress", "50% complete"),
+            ],
+        )
+            .into_response();
+    }
+    if job == "flaky" && recorded.status_polls == 2 {
+        // Proxy trước server quá tải: không `Retry-After`, body không phải JSON
+        return (StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable").into_response();
+    }
+    if job == "flaky" && recorded.status_polls == 3 {
+        return (StatusCode::GATEWAY_TIMEOUT, "<html>Gateway Timeout</html>").into_response();
+    }
+    if job == "broken" {
+        return fhir_json(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            outcome("processing", "export failed"),
+        );
+    }
+    let file = |name: &str| format!("{}files/{}", recorded.base_url, name);
+    fhir_json(
+        StatusCode::OK,
+        json!({
+            "transactionTime": "2026-10-19T08:00:00Z",
+            "request": format!("{}Group/panel/$export", recorded.base_url),
+            "requiresAccessToken": true,
+            "output": [
+                { "type": "Patient", "url": file("patients-a.ndjson"), "count": 2 },
+                { "type": "Patient", "url": file("patients-b.ndjson"), "count": 1 },
+                { "type": "Condition", "url": file("conditions.ndjson"), "count": 1 }
+            ],
+            "error": [{ "type": "OperationOutcome", "url": file("errors.ndjson") }]
+        }),
+    )
+}
+
+/// `patients-a.ndjson` breaks off after `INTERRUPTED_AT` bytes the first time;
+/// `Range` requests get the rest.
+async fn download(
+    State(stub): State<Shared>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if !authorized(&stub, &headers) {
+        return unauthorized("invalid access token");
+    }
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let mut recorded = stub.recorded.lock().unwrap();
+    recorded.downloads.push((name.clone(), range.clone()));
+    let content = match name.as_str() {
+        "patients-a.ndjson" => PATIENTS,
+        "patients-b.ndjson" => MORE_PATIENTS,
+        "conditions.ndjson" => CONDITIONS,
+        "errors.ndjson" => ERRORS,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let ndjson = [(header::CONTENT_TYPE, "application/fhir+ndjson")];
+
+    if let Some(start) = range
+        .as_deref()
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.parse::<usize>().ok())
+    {
+        let content_range = format!("bytes {}-{}/{}", start, content.len() - 1, content.len());
+        return (
+            StatusCode::PARTIAL_CONTENT,
+            ndjson,
+            [(header::CONTENT_RANGE, content_range)],
+            content[start..].to_string(),
+        )
+            .into_response();
+    }
+    if name == "patients-a.ndjson" && !recorded.interrupted {
+        recorded.interrupted = true;
+        let partial = stream::iter([Ok::<_, std::io::Error>(Bytes::from_static(
+            &PATIENTS.as_bytes()[..INTERRUPTED_AT],
+        ))]);
+        // Chờ cho phần đầu tới client rồi mới cắt kết nối
+        let broken = stream::once(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err(std::io::Error::other("connection reset"))
+        });
+        return (
+            StatusCode::OK,
+            ndjson,
+            Body::from_stream(futures::StreamExt::chain(partial, broken)),
+        )
+            .into_response();
+    }
+    (StatusCode::OK, ndjson, content.to_string()).into_response()
+}
+
+async fn backend_client(smart: &MockSmartServer) -> Arc<BackendServicesClient> {
+    let settings: Settings = serde_json::from_value(json!({
+        "port": 3000,
+        "base_url": "http://localhost:3000",
+        "oauth_clients": {
+            "backend": {
+                "client_id": "bulk-client",
+                "token_url": smart.token_url(),
+                "auth_url": smart.authorize_url(),
+                "redirect_uri": "http://localhost:3000/unused",
+                "scopes": [],
+                "audience": smart.fhir_base_url(),
+                "signing_keys": [{ "private_key_pem": P256_PEM, "algorithm": "ES256" }],
+            },
+        },
+    }))
+    .unwrap();
+    let keys = KeyStore::load(&settings, None).await.unwrap();
+    smart.register_client(MockClient::with_jwks(
+        "bulk-client",
+        "http://localhost:3000/unused",
+        keys.jwks(OffsetDateTime::now_utc()),
+    ));
+    let signing_keys: Arc<ClientKeys> = keys.client("backend").unwrap();
+    Arc::new(
+        BackendServicesClient::new(BackendServicesConfig::new(
+            "bulk-client".to_string(),
+            smart.token_url(),
+            vec!["system/*.read".to_string()],
+            signing_keys,
+        ))
+        .unwrap(),
+    )
+}
+
+async fn serve<S: OutputStore>(store: S) -> (BulkExporter<S>, Shared) {
+    let smart = MockSmartServer::start().await;
+    let tokens = backend_client(&smart).await;
+    let stub = Arc::new(Stub {
+        smart,
+        recorded: Mutex::default(),
+    });
+    let app = Router::new()
+        .route("/fhir/R4/Group/{id}/$export", get(kick_off))
+        .route("/fhir/R4/bulkstatus/{job}", get(export_status))
+        .route("/fhir/R4/files/{name}", get(download))
+        .with_state(stub.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let base_url = format!("http://{}/fhir/R4/", addr);
+    stub.recorded.lock().unwrap().base_url = base_url.clone();
+
+    let fhir = FhirClient::new(reqwest::Client::new(), &base_url).unwrap();
+    let exporter = BulkExporter::new(fhir, tokens, store).with_options(ExportOptions {
+        poll_interval: Duration::from_millis(10),
+        max_wait: Duration::from_secs(10),
+        download_attempts: 3,
+        retry_delay: Duration::ZERO,
+    });
+    (exporter, stub)
+}
+
+#[tokio::test]
+async fn exports_group_and_resumes_interrupted_downloads() {
+    let (exporter, stub) = serve(MemoryStore::new()).await;
+    stub.recorded.lock().unwrap().reject_next_poll = true;
+    let request = ExportRequest {
+        types: vec!["Patient".to_string(), "Condition".to_string()],
+        since: Some("2026-01-01T00:00:00Z".to_string()),
+        type_filters: vec!["Condition?clinical-status=active".to_string()],
+    };
+
+    let job = exporter.export_group("panel", &request).await.unwrap();
+
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.group_id, "panel");
+    assert_eq!(
+        job.transaction_time.as_deref(),
+        Some("2026-10-19T08:00:00Z")
+    );
+    assert!(job.requires_access_token);
+    let keys: Vec<&str> = job.outputs.iter().map(|file| file.key.as_str()).collect();
+    assert_eq!(
+        keys,
+        [
+            format!("{}/Patient-1.ndjson", job.id),
+            format!("{}/Patient-2.ndjson", job.id),
+            format!("{}/Condition-1.ndjson", job.id),
+        ]
+    );
+    assert_eq!(
+        job.errors[0].key,
+        format!("{}/error/OperationOutcome-1.ndjson", job.id)
+    );
+    assert!(
+        job.outputs
+            .iter()
+            .chain(&job.errors)
+            .all(|file| file.complete)
+    );
+    assert_eq!(
+        job.downloaded_bytes() as usize,
+        PATIENTS.len() + MORE_PATIENTS.len() + CONDITIONS.len() + ERRORS.len()
+    );
+
+    let store = exporter.store();
+    for (key, content) in [
+        (&job.outputs[0].key, PATIENTS),
+        (&job.outputs[1].key, MORE_PATIENTS),
+        (&job.outputs[2].key, CONDITIONS),
+        (&job.errors[0].key, ERRORS),
+    ] {
+        assert_eq!(store.object(key).as_deref(), Some(content.as_bytes()));
+    }
+    assert_eq!(exporter.load_job(&job.id).await.unwrap(), Some(job.clone()));
+
+    let recorded = stub.recorded.lock().unwrap();
+    let query = recorded.kick_off_query.as_deref().unwrap();
+    assert!(query.contains("_outputFormat=application%2Ffhir%2Bndjson"));
+    assert!(query.contains("_type=Patient%2CCondition"));
+    assert!(query.contains("_since=2026-01-01T00%3A00%3A00Z"));
+    assert!(query.contains("_typeFilter=Condition%3Fclinical-status%3Dactive"));
+    assert_eq!(recorded.status_polls, 2);
+    // Poll bị từ chối `401` dùng token mới
+    assert_eq!(recorded.tokens.len(), 2);
+    let patients: Vec<&Option<String>> = recorded
+        .downloads
+        .iter()
+        .filter(|(name, _)| name == "patients-a.ndjson")
+        .map(|(_, range)| range)
+        .collect();
+    assert_eq!(
+        patients,
+        [&None, &Some(format!("bytes={}-", INTERRUPTED_AT))]
+    );
+}
+
+#[tokio::test]
+async fn job_record_in_local_directory_survives_restart() {
+    let root = tempfile::tempdir().unwrap();
+    let (exporter, _stub) = serve(LocalDirectory::new(root.path())).await;
+
+    let job = exporter
+        .start_group_export("panel", &ExportRequest::default())
+        .await
+        .unwrap();
+    let saved = exporter.load_job(&job.id).await.unwrap().unwrap();
+    assert_eq!(saved.state, JobState::InProgress);
+    assert!(saved.status_url.ends_with("/fhir/R4/bulkstatus/panel"));
+
+    // Tiến trình khác chạy tiếp từ bản ghi
+    let mut resumed = exporter.load_job(&job.id).await.unwrap().unwrap();
+    exporter.run(&mut resumed).await.unwrap();
+    assert!(resumed.is_finished());
+
+    let saved = exporter.load_job(&job.id).await.unwrap().unwrap();
+    assert_eq!(saved.state, JobState::Completed);
+    assert_eq!(saved.progress, None);
+    assert_eq!(
+        std::fs::read_to_string(root.path().join(&job.id).join("Patient-1.ndjson")).unwrap(),
+        PATIENTS
+    );
+    assert_eq!(
+        std::fs::read_to_string(
+            root.path()
+                .join(&job.id)
+                .join("error")
+                .join("OperationOutcome-1.ndjson")
+        )
+        .unwrap(),
+        ERRORS
+    );
+    assert!(root.path().join(ExportJob::record_key(&job.id)).is_file());
+}
+
+#[tokio::test]
+async fn failed_exports_are_recorded() {
+    let (exporter, _stub) = serve(MemoryStore::new()).await;
+
+    let error = exporter
+        .export_group("unknown", &ExportRequest::default())
+        .await
+        .unwrap_err();
+    assert!(error.is_not_found());
+    assert!(exporter.store().keys().is_empty());
+
+    let mut job = exporter
+        .start_group_export("broken", &ExportRequest::default())
+        .await
+        .unwrap();
+    let error = exporter.run(&mut job).await.unwrap_err();
+    assert!(
+        matches!(error, FhirError::Outcome { status, .. } if status == StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert_eq!(job.state, JobState::Failed);
+    assert_eq!(job.progress.as_deref(), Some("50% complete"));
+    let saved = exporter.load_job(&job.id).await.unwrap().unwrap();
+    assert_eq!(saved.state, JobState::Failed);
+    assert!(saved.error.unwrap().contains("export failed"));
+}
+
+#[tokio::test]
+async fn keeps_polling_through_transient_status_errors() {
+    let (exporter, stub) = serve(MemoryStore::new()).await;
+
+    let mut job = exporter
+        .start_group_export("flaky", &ExportRequest::default())
+        .await
+        .unwrap();
+    exporter.run(&mut job).await.unwrap();
+
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.error, None);
+    // 202, 503, 504 rồi manifest
+    assert_eq!(stub.recorded.lock().unwrap().status_polls, 4);
+    let saved = exporter.load_job(&job.id).await.unwrap().unwrap();
+    assert_eq!(saved.state, JobState::Completed);
+}
+
+#[tokio::test]
+async fn fails_exports_that_outlast_the_maximum_wait() {
+    let (exporter, stub) = serve(MemoryStore::new()).await;
+    // URL trạng thái luôn trả `Retry-After: 0`
+    let exporter = exporter.with_options(ExportOptions {
+        poll_interval: Duration::from_millis(50),
+        max_wait: Duration::from_millis(300),
+        download_attempts: 3,
+        retry_delay: Duration::ZERO,
+    });
+
+    let mut job = exporter
+        .start_group_export("stalled", &ExportRequest::default())
+        .await
+        .unwrap();
+    let error = exporter.run(&mut job).await.unwrap_err();
+    assert!(matches!(error, FhirError::ExportTimedOut(_)), "{}", error);
+    assert_eq!(job.state, JobState::Failed);
+    // Poll cách nhau ít nhất `poll_interval` dù server gửi `Retry-After: 0`
+    let polls = stub.recorded.lock().unwrap().status_polls;
+    assert!((2..=8).contains(&polls), "{} polls", polls);
+    let saved = exporter.load_job(&job.id).await.unwrap().unwrap();
+    assert_eq!(saved.state, JobState::Failed);
+    assert!(saved.error.unwrap().contains("did not finish"));
+}
@@ -0,0 +1,409 @@
+//! `FhirClient` against an in-process FHIR stub.
+
+use std::sync::{Arc, Mutex};
+
+use axum::Json;
+use axum::Router;
+use axum::extract::{Path, RawQuery, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use config_lib::Secret;
+use futures::{StreamExt, TryStreamExt};
+use serde_json::{Value, json};
+use time::OffsetDateTime;
+
+use crate::client::{Auth, Conditional, FhirClient, FhirError, SearchParams};
+use crate::resources::{Condition, Observation, Patient};
+use oauth2_lib::epic::tokens::TokenSet;
+
+const PATIENT_ID: &str = "erXuFYUfucBZaryVksYEcMg3";
+const CURRENT_ETAG: &str = "W/\"2\"";
+
+#[derive(Default)]
+struct Stub {
+    authorization: Mutex<Vec<String>>,
+    queries: Mutex<Vec<String>>,
+}
+
+type Shared = Arc<Stub>;
+
+fn fhir_json(status: StatusCode, body: Value) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/fhir+json")],
+        Json(body),
+    )
+        .into_response()
+}
+
+fn outcome(code: &str, diagnostics: &str) -> Value {
+    json!({
+        "resourceType": "OperationOutcome",
+        "issue": [{ "severity": "error", "code": code, "diagnostics": diagnostics }]
+    })
+}
+
+fn patient(version_id: &str) -> Value {
+    json!({
+        "resourceType": "Patient",
+        "id": PATIENT_ID,
+        "meta": { "versionId": version_id },
+        "name": [{ "use": "official", "family": "Lopez", "given": ["Camila"] }]
+    })
+}
+
+fn condition(id: &str) -> Value {
+    json!({
+        "resourceType": "Condition",
+        "id": id,
+        "subject": { "reference": format!("Patient/{}", PATIENT_ID) },
+        "asserter": { "reference": "Practitioner/pr1" },
+        "code": { "text": id }
+    })
+}
+
+fn record(stub: &Stub, headers: &HeaderMap) {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    stub.authorization
+        .lock()
+        .unwrap()
+        .push(authorization.to_string());
+}
+
+async fn read_patient(
+    State(stub): State<Shared>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    record(&stub, &headers);
+    if id != PATIENT_ID {
+        return fhir_json(
+            StatusCode::NOT_FOUND,
+            outcome("not-found", "Resource Patient/missing is not known"),
+        );
+    }
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|etag| etag == CURRENT_ETAG)
+    {
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+    let mut response = fhir_json(StatusCode::OK, patient("2"));
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, CURRENT_ETAG.parse().unwrap());
+    headers.insert(
+        header::LAST_MODIFIED,
+        "Mon, 05 Jun 2023 10:00:00 GMT".parse().unwrap(),
+    );
+    response
+}
+
+async fn update_patient(
+    State(stub): State<Shared>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    record(&stub, &headers);
+    if headers
+        .get(header::IF_MATCH)
+        .is_some_and(|etag| etag != CURRENT_ETAG)
+    {
+        return fhir_json(
+            StatusCode::PRECONDITION_FAILED,
+            outcome("conflict", "Version mismatch"),
+        );
+    }
+    let mut updated = body;
+    updated["meta"] = json!({ "versionId": "3" });
+    let mut response = fhir_json(StatusCode::OK, updated);
+    response
+        .headers_mut()
+        .insert(header::ETAG, "W/\"3\"".parse().unwrap());
+    response
+}
+
+async fn vread_patient(
+    State(stub): State<Shared>,
+    Path((_, version_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    record(&stub, &headers);
+    let mut response = fhir_json(StatusCode::OK, patient(&version_id));
+    response.headers_mut().insert(
+        header::ETAG,
+        format!("W/\"{}\"", version_id).parse().unwrap(),
+    );
+    response
+}
+
+/// Hai trang: trang đầu có một kết quả và một Practitioner `_include`. Với
+/// `_tag=loop`, link `next` trỏ lại chính trang đó.
+async fn search_conditions(
+    State(stub): State<Shared>,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+) -> Response {
+    record(&stub, &headers);
+    let query = query.unwrap_or_default();
+    stub.queries.lock().unwrap().push(query.clone());
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if query.contains("_tag=loop") {
+        return fhir_json(
+            StatusCode::OK,
+            json!({
+                "resourceType": "Bundle",
+                "type": "searchset",
+                "link": [{ "relation": "next", "url": format!("http://{}/fhir/R4/Condition?{}", host, query) }],
+                "entry": [{ "resource": condition("c1"), "search": { "mode": "match" } }]
+            }),
+        );
+    }
+    if query.contains("page=2") {
+        return fhir_json(
+            StatusCode::OK,
+            json!({
+                "resourceType": "Bundle",
+                "type": "searchset",
+                "entry": [{ "resource": condition("c2"), "search": { "mode": "match" } }]
+            }),
+        );
+    }
+    fhir_json(
+        StatusCode::OK,
+        json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "total": 2,
+            "link": [
+                { "relation": "self", "url": format!("http://{}/fhir/R4/Condition?{}", host, query) },
+                { "relation": "next", "url": format!("http://{}/fhir/R4/Condition?page=2", host) }
+            ],
+            "entry": [
+                { "resource": condition("c1"), "search": { "mode": "match" } },
+                {
+                    "resource": { "resourceType": "Practitioner", "id": "pr1" },
+                    "search": { "mode": "include" }
+                }
+            ]
+        }),
+    )
+}
+
+/// Trang có link `next` trỏ sang server khác.
+async fn search_observations(State(stub): State<Shared>, headers: HeaderMap) -> Response {
+    record(&stub, &headers);
+    fhir_json(
+        StatusCode::OK,
+        json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "link": [{ "relation": "next", "url": "https://attacker.example/fhir/R4/Observation?page=2" }],
+            "entry": []
+        }),
+    )
+}
+
+async fn serve() -> (FhirClient, Shared) {
+    let stub = Shared::default();
+    let app = Router::new()
+        .route(
+            "/fhir/R4/Patient/{id}",
+            get(read_patient).put(update_patient),
+        )
+        .route("/fhir/R4/Patient/{id}/_history/{vid}", get(vread_patient))
+        .route("/fhir/R4/Condition", get(search_conditions))
+        .route("/fhir/R4/Observation", get(search_observations))
+        .with_state(stub.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let client =
+        FhirClient::new(reqwest::Client::new(), &format!("http://{}/fhir/R4/", addr)).unwrap();
+    (client, stub)
+}
+
+#[tokio::test]
+async fn read_returns_etag_and_honours_if_none_match() {
+    let (client, stub) = serve().await;
+
+    let read = client
+        .read::<Patient>(Auth::Bearer("at-1"), PATIENT_ID)
+        .await
+        .unwrap();
+    assert_eq!(read.resource.id.as_deref(), Some(PATIENT_ID));
+    assert_eq!(read.etag.as_deref(), Some(CURRENT_ETAG));
+    assert_eq!(read.version_id(), Some("2"));
+    assert_eq!(
+        read.last_modified.as_deref(),
+        Some("Mon, 05 Jun 2023 10:00:00 GMT")
+    );
+
+    let again = client
+        .read_if_none_match::<Patient>(Auth::Bearer("at-1"), PATIENT_ID, CURRENT_ETAG)
+        .await
+        .unwrap();
+    assert_eq!(again, Conditional::NotModified);
+
+    let stale = client
+        .read_if_none_match::<Patient>(Auth::Bearer("at-1"), PATIENT_ID, "W/\"1\"")
+        .await
+        .unwrap();
+    assert!(
+        matches!(stale, Conditional::Modified(versioned) if versioned.version_id() == Some("2"))
+    );
+
+    assert_eq!(
+        *stub.authorization.lock().unwrap(),
+        ["Bearer at-1", "Bearer at-1", "Bearer at-1"]
+    );
+}
+
+#[tokio::test]
+async fn vread_and_conditional_update() {
+    let (client, _stub) = serve().await;
+
+    let first = client
+        .vread::<Patient>(Auth::Anonymous, PATIENT_ID, "1")
+        .await
+        .unwrap();
+    assert_eq!(
+        first
+            .resource
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.version_id.as_deref()),
+        Some("1")
+    );
+    assert_eq!(first.version_id(), Some("1"));
+
+    let stale = client
+        .update(Auth::Anonymous, &first.resource, first.etag.as_deref())
+        .await
+        .unwrap_err();
+    assert!(stale.is_precondition_failed());
+    assert_eq!(
+        stale.outcome().unwrap().issue[0].diagnostics.as_deref(),
+        Some("Version mismatch")
+    );
+
+    let current = client
+        .read::<Patient>(Auth::Anonymous, PATIENT_ID)
+        .await
+        .unwrap();
+    let updated = client
+        .update(Auth::Anonymous, &current.resource, current.etag.as_deref())
+        .await
+        .unwrap();
+    assert_eq!(updated.version_id(), Some("3"));
+}
+
+#[tokio::test]
+async fn search_follows_next_links_and_keeps_includes() {
+    let (client, stub) = serve().await;
+    let tokens = TokenSet {
+        access_token: Secret::new("session-at".to_string()),
+        refresh_token: None,
+        expires_at: OffsetDateTime::now_utc() + time::Duration::hours(1),
+        dpop: None,
+    };
+    let query = SearchParams::of::<Condition>().patient(PATIENT_ID).include(
+        "Condition",
+        "asserter",
+        Some("Practitioner"),
+    );
+
+    let pages: Vec<_> = client
+        .search_pages(Auth::Tokens(&tokens), &query)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].matches::<Condition>().count(), 1);
+    assert!(pages[0].resolve("Practitioner/pr1").is_some());
+
+    let all = client
+        .search_all::<Condition>(Auth::Tokens(&tokens), &query)
+        .await
+        .unwrap();
+    let ids: Vec<_> = all.iter().filter_map(|c| c.id.as_deref()).collect();
+    assert_eq!(ids, ["c1", "c2"]);
+
+    let queries = stub.queries.lock().unwrap();
+    assert_eq!(
+        queries[0],
+        format!(
+            "patient={}&_include=Condition%3Aasserter%3APractitioner",
+            PATIENT_ID
+        )
+    );
+    assert_eq!(queries[1], "page=2");
+    assert!(
+        stub.authorization
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|value| value == "Bearer session-at")
+    );
+}
+
+#[tokio::test]
+async fn search_stops_after_max_pages() {
+    let (client, stub) = serve().await;
+    let client = client.with_max_pages(3);
+    let query = SearchParams::of::<Condition>().param("_tag", "loop");
+
+    let error = client
+        .search_all::<Condition>(Auth::Bearer("at-1"), &query)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, FhirError::TooManyPages { ref resource_type, pages: 3 } if resource_type == "Condition"),
+        "{error}"
+    );
+    assert_eq!(stub.queries.lock().unwrap().len(), 3);
+
+    // Các trang đã đọc vẫn được trả về trước lỗi
+    let pages: Vec<_> = client
+        .search_pages(Auth::Bearer("at-1"), &query)
+        .collect()
+        .await;
+    assert_eq!(pages.len(), 4);
+    assert!(pages[..3].iter().all(Result::is_ok));
+    assert!(pages[3].is_err());
+}
+
+#[tokio::test]
+async fn next_link_to_another_origin_is_rejected() {
+    let (client, stub) = serve().await;
+    let query = SearchParams::of::<Observation>().patient(PATIENT_ID);
+
+    let mut pages = std::pin::pin!(client.search_pages(Auth::Bearer("at-1"), &query));
+    let first = pages.try_next().await.unwrap();
+    assert!(first.is_some());
+    let error = pages.try_next().await.unwrap_err();
+    assert!(
+        matches!(error, FhirError::InvalidResponse(ref message) if message.contains("attacker.example"))
+    );
+    assert_eq!(stub.authorization.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn operation_outcome_becomes_an_error() {
+    let (client, _stub) = serve().await;
+
+    let error = client
+        .read::<Patient>(Auth::Bearer("at-1"), "missing")
+        .await
+        .unwrap_err();
+    assert!(error.is_not_found());
+    let outcome = error.outcome().unwrap();
+    assert!(outcome.has_errors());
+    assert_eq!(outcome.issue[0].code, "not-found");
+}
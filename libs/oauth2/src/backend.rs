@@ -0,0 +1,212 @@
+//! SMART Backend Services: system-level access tokens for jobs that run
+//! without a user, e.g. bulk data exports.
+//!
+//! The client credentials grant (RFC 6749, section 4.4) authenticated with a
+//! `private_key_jwt` assertion; the token is cached and replaced shortly before
+//! it expires. There is no refresh token.
+//!
+//! ```ignore
+//! let client = BackendServicesClient::new(BackendServicesConfig::new(
+//!     "cid".to_string(),
+//!     "https://fhir.epic.com/interconnect-fhir-oauth/oauth2/token".to_string(),
+//!     vec!["system/Group.read".to_string(), "system/Patient.read".to_string()],
+//!     key_store.client("epic_backend").unwrap(),
+//! ))?;
+//! let tokens = client.token().await?;
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use config_lib::settings::ClientAssertionSettings;
+use oauth2::basic::{
+    BasicErrorResponse, BasicErrorResponseType, BasicRevocationErrorResponse,
+    BasicTokenIntrospectionResponse,
+};
+use oauth2::{
+    Client, ClientId, EndpointNotSet, EndpointSet, Scope, StandardRevocableToken, TokenUrl,
+};
+use security::ClientKeys;
+use time::Duration;
+
+use crate::clock::{Clock, DriftCorrectedClock, MonotonicClock};
+use crate::epic::client::{ClientAssertionClaims, DateObservingClient};
+use crate::epic::error::Error as EpicError;
+use crate::epic::smart::SmartTokenResponse;
+use crate::epic::tokens::TokenSet;
+use crate::jwt_bearer::CLIENT_ASSERTION_TYPE;
+
+/// A cached token is replaced when it expires within this margin, so that a
+/// request started with it does not fail halfway.
+pub const TOKEN_RENEWAL_MARGIN: Duration = Duration::minutes(1);
+
+/// Registration of a backend services client.
+#[derive(Debug, Clone)]
+pub struct BackendServicesConfig {
+    /// The client ID assigned by the authorization server.
+    pub client_id: String,
+    /// The token endpoint URL; also the `aud` of the client assertion.
+    pub token_url: String,
+    /// System scopes to request, e.g. `system/Group.read`.
+    pub scopes: Vec<String>,
+    /// Keys signing the client assertion; backend services clients must use
+    /// `private_key_jwt`.
+    pub signing_keys: Arc<ClientKeys>,
+    /// Lifetime, clock skew and drift correction for the client assertion.
+    pub assertion: ClientAssertionSettings,
+}
+
+impl BackendServicesConfig {
+    /// Creates a configuration with the default assertion settings.
+    pub fn new(
+        client_id: String,
+        token_url: String,
+        scopes: Vec<String>,
+        signing_keys: Arc<ClientKeys>,
+    ) -> Self {
+        Self {
+            client_id,
+            token_url,
+            scopes,
+            signing_keys,
+            assertion: ClientAssertionSettings::default(),
+        }
+    }
+}
+
+/// Obtains and caches the access token of one backend services client.
+///
+/// Shared by every job of the client: `Send + Sync`, with the token behind a
+/// mutex that is never held across a request.
+#[derive(Debug)]
+pub struct BackendServicesClient {
+    config: BackendServicesConfig,
+    oauth_client: Client<
+        BasicErrorResponse,
+        SmartTokenResponse,
+        BasicTokenIntrospectionResponse,
+        StandardRevocableToken,
+        BasicRevocationErrorResponse,
+        EndpointNotSet,
+        EndpointNotSet,
+        EndpointNotSet,
+        EndpointNotSet,
+        EndpointSet,
+    >,
+    /// HTTP client for the token endpoint, which must not redirect.
+    http: reqwest::Client,
+    clock: Arc<DriftCorrectedClock>,
+    cached: Mutex<Option<TokenSet>>,
+}
+
+impl BackendServicesClient {
+    /// # Errors
+    ///
+    /// Returns `EpicError::UrlParse` if the token URL is invalid, and
+    /// `EpicError::Reqwest` if the HTTP client cannot be built.
+    pub fn new(config: BackendServicesConfig) -> Result<Self, EpicError> {
+        Self::with_clock(config, Arc::new(MonotonicClock::new()))
+    }
+
+    /// Like [`BackendServicesClient::new`], reading the time from `clock`.
+    pub fn with_clock(
+        config: BackendServicesConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, EpicError> {
+        let oauth_client = Client::new(ClientId::new(config.client_id.clone()))
+            .set_token_uri(TokenUrl::new(config.token_url.clone())?);
+        let http = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        Ok(Self {
+            config,
+            oauth_client,
+            http,
+            clock: Arc::new(DriftCorrectedClock::new(clock)),
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// The current access token, requesting a new one when none is cached or
+    /// the cached one expires within [`TOKEN_RENEWAL_MARGIN`].
+    ///
+    /// # Errors
+    ///
+    /// `EpicError::TokenEndpoint` if the server rejects the request (see
+    /// [`EpicError::class`]), `EpicError::Transport` or
+    /// `EpicError::InvalidResponse` otherwise.
+    pub async fn token(&self) -> Result<TokenSet, EpicError> {
+        let now = self.clock.now();
+        if let Some(tokens) = self
+            .cached
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|tokens| !tokens.expires_within(now, TOKEN_RENEWAL_MARGIN))
+        {
+            return Ok(tokens.clone());
+        }
+        let response = self.request_new_token().await?;
+        let tokens = TokenSet::from_response(&response, self.clock.now());
+        *self.cached.lock().unwrap() = Some(tokens.clone());
+        Ok(tokens)
+    }
+
+    /// Drops the cached token, e.g. after the resource server rejected it; the
+    /// next [`BackendServicesClient::token`] requests a new one.
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+
+    async fn request_new_token(&self) -> Result<SmartTokenResponse, EpicError> {
+        let offset_before = self.clock.offset();
+        let mut result = self.request_token().await;
+        // The assertion was rejected and the `Date` header shows clock skew: re-sign and retry once
+        if let Err(EpicError::TokenEndpoint(response)) = &result {
+            if response.error == BasicErrorResponseType::InvalidClient
+                && self.clock.offset() != offset_before
+            {
+                tracing::warn!(
+                    "Client assertion rejected, retrying with clock offset {}s",
+                    self.clock.offset().whole_seconds()
+                );
+                result = self.request_token().await;
+            }
+        }
+        result.inspect_err(|e| {
+            tracing::error!("Backend services token error ({:?}): {}", e.class(), e);
+        })
+    }
+
+    /// Sends one client credentials request with a freshly signed assertion.
+    async fn request_token(&self) -> Result<SmartTokenResponse, EpicError> {
+        let now = self.clock.now();
+        let claims = ClientAssertionClaims::new(
+            &self.config.client_id,
+            &self.config.token_url,
+            &self.config.assertion,
+            now,
+        );
+        let assertion = self
+            .config
+            .signing_keys
+            .sign_jwt(&claims, now)
+            .await
+            .map_err(|e| EpicError::JwtEncodingError(e.to_string()))?;
+
+        let mut request = self.oauth_client.exchange_client_credentials();
+        for scope in &self.config.scopes {
+            request = request.add_scope(Scope::new(scope.clone()));
+        }
+        let http = DateObservingClient {
+            http: &self.http,
+            clock: &self.clock,
+            observe_date: self.config.assertion.use_server_date,
+            dpop: None,
+        };
+        Ok(request
+            .add_extra_param("client_assertion_type", CLIENT_ASSERTION_TYPE)
+            .add_extra_param("client_assertion", assertion)
+            .request_async(&http)
+            .await?)
+    }
+}
@@ -0,0 +1,168 @@
+//! Clocks used to timestamp and validate JWTs.
+//!
+//! Everything that reads "now" for assertions goes through [`Clock`], so tests
+//! can drive time explicitly and the client can correct for drift against the
+//! authorization server.
+
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use time::{Duration, OffsetDateTime};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// Wall-clock time that never goes backwards.
+///
+/// The system time is read once at construction; afterwards time advances with
+/// [`Instant`], so NTP steps or manual clock changes on the host do not make
+/// assertions jump back in time.
+#[derive(Debug)]
+pub struct MonotonicClock {
+    anchor: OffsetDateTime,
+    started: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            anchor: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> OffsetDateTime {
+        self.anchor + self.started.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Intended for tests.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<OffsetDateTime>,
+}
+
+impl ManualClock {
+    pub fn new(now: OffsetDateTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+
+    pub fn set(&self, now: OffsetDateTime) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Wraps a clock and shifts it by the offset observed from a server's `Date`
+/// header, so timestamps line up with the server's notion of "now".
+#[derive(Debug)]
+pub struct DriftCorrectedClock {
+    inner: Arc<dyn Clock>,
+    /// Server time minus local time, in seconds.
+    offset_secs: AtomicI64,
+}
+
+impl DriftCorrectedClock {
+    pub fn new(inner: Arc<dyn Clock>) -> Self {
+        Self {
+            inner,
+            offset_secs: AtomicI64::new(0),
+        }
+    }
+
+    /// The correction currently applied.
+    pub fn offset(&self) -> Duration {
+        Duration::seconds(self.offset_secs.load(Ordering::Relaxed))
+    }
+
+    /// Records the server time from an HTTP `Date` header value.
+    ///
+    /// The header only has one-second resolution, so differences of a second
+    /// or less are treated as no drift. Returns `false` if the value could not
+    /// be parsed.
+    pub fn observe_date_header(&self, value: &str) -> bool {
+        let Ok(server) = httpdate::parse_http_date(value) else {
+            return false;
+        };
+        self.observe(OffsetDateTime::from(server));
+        true
+    }
+
+    /// Records that the server reported `server_now` at the current local time.
+    pub fn observe(&self, server_now: OffsetDateTime) {
+        let offset = (server_now - self.inner.now()).whole_seconds();
+        let offset = if offset.abs() <= 1 { 0 } else { offset };
+        let previous = self.offset_secs.swap(offset, Ordering::Relaxed);
+        if previous != offset {
+            tracing::info!("Clock drift against authorization server: {}s", offset);
+        }
+    }
+}
+
+impl Clock for DriftCorrectedClock {
+    fn now(&self) -> OffsetDateTime {
+        self.inner.now() + self.offset()
+    }
+}
+
+/// Seconds since the Unix epoch, as used in JWT `NumericDate` claims.
+pub fn unix_timestamp(at: OffsetDateTime) -> u64 {
+    at.unix_timestamp().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn monotonic_clock_does_not_go_backwards() {
+        let clock = MonotonicClock::new();
+        let first = clock.now();
+        assert!(clock.now() >= first);
+    }
+
+    #[test]
+    fn corrects_drift_from_date_header() {
+        let local = Arc::new(ManualClock::new(datetime!(2026-10-19 08:00:00 UTC)));
+        let clock = DriftCorrectedClock::new(local.clone());
+
+        assert!(clock.observe_date_header("Mon, 19 Oct 2026 08:02:00 GMT"));
+        assert_eq!(clock.offset(), Duration::minutes(2));
+        assert_eq!(clock.now(), datetime!(2026-10-19 08:02:00 UTC));
+
+        local.advance(Duration::seconds(10));
+        assert_eq!(clock.now(), datetime!(2026-10-19 08:02:10 UTC));
+
+        // Differences within the header's one-second resolution are ignored.
+        assert!(clock.observe_date_header("Mon, 19 Oct 2026 08:00:11 GMT"));
+        assert_eq!(clock.offset(), Duration::ZERO);
+
+        assert!(!clock.observe_date_header("yesterday"));
+        assert_eq!(clock.offset(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,341 @@
+//! DPoP: sender-constrained access tokens (RFC 9449).
+//!
+//! A [`DpopKey`] is generated per session and never leaves the process. Token
+//! requests and every request made with a DPoP-bound access token carry a fresh
+//! proof signed with it. A server may demand a nonce (`use_dpop_nonce`): the
+//! last `DPoP-Nonce` seen per origin is remembered and the request is sent once
+//! more with it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::StatusCode;
+use security::jwk::PublicJwk;
+use security::{KeyError, Signer, SoftwareSigner};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use time::{Duration, OffsetDateTime};
+use url::Url;
+use uuid::Uuid;
+
+/// Request header carrying the proof (`DPoP`), lowercase as `http` header names are.
+pub const DPOP_HEADER: &str = "dpop";
+/// Response header carrying a server-provided nonce (`DPoP-Nonce`).
+pub const DPOP_NONCE_HEADER: &str = "dpop-nonce";
+/// `token_type` and `Authorization` scheme of a DPoP-bound access token.
+pub const TOKEN_TYPE_DPOP: &str = "DPoP";
+/// OAuth error code of a token endpoint that demands a nonce.
+pub const DPOP_NONCE_ERROR: &str = "use_dpop_nonce";
+/// `typ` header of a proof JWT.
+const PROOF_TYPE: &str = "dpop+jwt";
+
+/// Errors while creating, sending or verifying DPoP proofs.
+#[derive(Debug, Error)]
+pub enum DpopError {
+    #[error("DPoP key error: {0}")]
+    Key(#[from] KeyError),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("invalid DPoP proof: {0}")]
+    InvalidProof(String),
+}
+
+/// Claims of a DPoP proof (RFC 9449, section 4.2).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofClaims {
+    pub jti: String,
+    pub htm: String,
+    pub htu: String,
+    pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Hash of the access token, on resource requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ath: Option<String>,
+}
+
+/// A proof that passed [`verify_proof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedProof {
+    /// JWK thumbprint of the proof key, the `cnf.jkt` of bound tokens.
+    pub jkt: String,
+    pub claims: ProofClaims,
+}
+
+/// Ephemeral ES256 key of one session, with the nonces servers handed out.
+pub struct DpopKey {
+    signer: SoftwareSigner,
+    /// Last `DPoP-Nonce` per origin.
+    nonces: Mutex<HashMap<String, String>>,
+}
+
+impl fmt::Debug for DpopKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DpopKey")
+            .field("jkt", &self.jkt())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DpopKey {
+    /// Generates a fresh P-256 key.
+    pub fn generate() -> Result<Self, KeyError> {
+        Ok(Self {
+            signer: SoftwareSigner::generate_es256()?,
+            nonces: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// JWK thumbprint (RFC 7638) of the public key.
+    pub fn jkt(&self) -> String {
+        self.signer.public_jwk().thumbprint()
+    }
+
+    /// Proof for a `method` request to `url`, with `ath` when `access_token`
+    /// is sent and the last nonce the server of `url` handed out.
+    pub fn proof(
+        &self,
+        method: &str,
+        url: &str,
+        access_token: Option<&str>,
+        now: OffsetDateTime,
+    ) -> Result<String, DpopError> {
+        let url = Url::parse(url).map_err(|e| DpopError::InvalidProof(e.to_string()))?;
+        let claims = ProofClaims {
+            jti: Uuid::new_v4().to_string(),
+            htm: method.to_uppercase(),
+            htu: htu(&url),
+            iat: now.unix_timestamp(),
+            nonce: self.nonce(&url),
+            ath: access_token.map(access_token_hash),
+        };
+        let header = json!({
+            "typ": PROOF_TYPE,
+            "alg": self.signer.algorithm().as_str(),
+            "jwk": public_jwk_json(self.signer.public_jwk()),
+        });
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).map_err(KeyError::from)?),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).map_err(KeyError::from)?)
+        );
+        let signature = self.signer.sign_now(signing_input.as_bytes())?;
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Records the `DPoP-Nonce` of a response from `url`; returns whether it changed.
+    pub fn observe_nonce(&self, url: &str, headers: &HeaderMap) -> bool {
+        let (Ok(url), Some(nonce)) = (
+            Url::parse(url),
+            headers
+                .get(DPOP_NONCE_HEADER)
+                .and_then(|value| value.to_str().ok()),
+        ) else {
+            return false;
+        };
+        let previous = self
+            .nonces
+            .lock()
+            .unwrap()
+            .insert(url.origin().ascii_serialization(), nonce.to_string());
+        previous.as_deref() != Some(nonce)
+    }
+
+    /// Sends `request` with `Authorization: DPoP <access_token>` and a proof,
+    /// once more if the server answers with a new nonce (RFC 9449, section 9).
+    ///
+    /// A request whose body cannot be cloned (a stream) is sent once.
+    pub async fn send(
+        &self,
+        http: &reqwest::Client,
+        request: reqwest::Request,
+        access_token: &str,
+        now: OffsetDateTime,
+    ) -> Result<reqwest::Response, DpopError> {
+        let url = request.url().to_string();
+        let method = request.method().to_string();
+        let mut retry = request.try_clone();
+        let mut request = request;
+        loop {
+            let proof = self.proof(&method, &url, Some(access_token), now)?;
+            let headers = request.headers_mut();
+            headers.insert(
+                AUTHORIZATION,
+                header_value(&format!("DPoP {}", access_token))?,
+            );
+            headers.insert(DPOP_HEADER, header_value(&proof)?);
+            let response = http.execute(request).await?;
+            let new_nonce = self.observe_nonce(&url, response.headers());
+            match retry.take() {
+                Some(next) if new_nonce && is_nonce_challenge(response.status()) => {
+                    request = next;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    fn nonce(&self, url: &Url) -> Option<String> {
+        self.nonces
+            .lock()
+            .unwrap()
+            .get(&url.origin().ascii_serialization())
+            .cloned()
+    }
+}
+
+/// A nonce is demanded with `400 use_dpop_nonce` by authorization servers and
+/// `401` with `WWW-Authenticate: DPoP error="use_dpop_nonce"` by resource servers.
+pub fn is_nonce_challenge(status: StatusCode) -> bool {
+    status == StatusCode::BAD_REQUEST || status == StatusCode::UNAUTHORIZED
+}
+
+/// `ath` claim: base64url SHA-256 of the access token.
+pub fn access_token_hash(access_token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()))
+}
+
+/// Verifies a proof received with a `method` request to `url` at `now`.
+///
+/// Checks `typ`, an asymmetric `alg` with the embedded `jwk`, `htm`, `htu`, an
+/// `iat` within `max_age` and, when `access_token` is given, `ath`. Nonce and
+/// `jti` replay checks are left to the caller.
+pub fn verify_proof(
+    proof: &str,
+    method: &str,
+    url: &str,
+    access_token: Option<&str>,
+    now: OffsetDateTime,
+    max_age: Duration,
+) -> Result<VerifiedProof, DpopError> {
+    let invalid = |message: &str| DpopError::InvalidProof(message.to_string());
+    let header = decode_header(proof).map_err(|e| DpopError::InvalidProof(e.to_string()))?;
+    if header.typ.as_deref() != Some(PROOF_TYPE) {
+        return Err(invalid("typ must be dpop+jwt"));
+    }
+    if !matches!(
+        header.alg,
+        Algorithm::ES256
+            | Algorithm::ES384
+            | Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+    ) {
+        return Err(invalid("alg must be asymmetric"));
+    }
+    let jwk: Jwk = header.jwk.ok_or_else(|| invalid("jwk is missing"))?;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|e| DpopError::InvalidProof(e.to_string()))?;
+    let mut validation = Validation::new(header.alg);
+    validation.validate_exp = false;
+    validation.required_spec_claims.clear();
+    let claims = decode::<ProofClaims>(proof, &key, &validation)
+        .map_err(|e| DpopError::InvalidProof(e.to_string()))?
+        .claims;
+
+    let url = Url::parse(url).map_err(|e| DpopError::InvalidProof(e.to_string()))?;
+    if !claims.htm.eq_ignore_ascii_case(method) || claims.htu != htu(&url) {
+        return Err(invalid("htm/htu do not match the request"));
+    }
+    if (now.unix_timestamp() - claims.iat).abs() > max_age.whole_seconds() {
+        return Err(invalid("iat is too far from the current time"));
+    }
+    if let Some(access_token) = access_token {
+        if claims.ath.as_deref() != Some(access_token_hash(access_token).as_str()) {
+            return Err(invalid("ath does not match the access token"));
+        }
+    }
+    let jkt = jwk_thumbprint(&serde_json::to_value(&jwk).map_err(KeyError::from)?)
+        .ok_or_else(|| invalid("unsupported jwk"))?;
+    Ok(VerifiedProof { jkt, claims })
+}
+
+/// `htu`: the URL without query and fragment (RFC 9449, section 4.2).
+fn htu(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.set_fragment(None);
+    url.to_string()
+}
+
+/// Public members only: the proof header must not carry `kid`, `use` or private parts.
+fn public_jwk_json(jwk: &PublicJwk) -> Value {
+    match jwk {
+        PublicJwk::Rsa { n, e } => json!({ "kty": "RSA", "n": n, "e": e }),
+        PublicJwk::Ec { crv, x, y } => json!({ "kty": "EC", "crv": crv, "x": x, "y": y }),
+    }
+}
+
+/// RFC 7638 thumbprint of a JWK given as JSON.
+fn jwk_thumbprint(jwk: &Value) -> Option<String> {
+    let member = |name: &str| jwk.get(name)?.as_str().map(str::to_string);
+    let public = match jwk.get("kty")?.as_str()? {
+        "EC" => PublicJwk::Ec {
+            crv: match jwk.get("crv")?.as_str()? {
+                "P-256" => "P-256",
+                "P-384" => "P-384",
+                _ => return None,
+            },
+            x: member("x")?,
+            y: member("y")?,
+        },
+        "RSA" => PublicJwk::Rsa {
+            n: member("n")?,
+            e: member("e")?,
+        },
+        _ => return None,
+    };
+    Some(public.thumbprint())
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, DpopError> {
+    HeaderValue::from_str(value).map_err(|e| DpopError::InvalidProof(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proof_round_trip() {
+        let key = DpopKey::generate().unwrap();
+        let now = OffsetDateTime::now_utc();
+        let url = "https://fhir.example.org/api/FHIR/R4/Patient/123?_format=json";
+
+        let mut headers = HeaderMap::new();
+        headers.insert(DPOP_NONCE_HEADER, HeaderValue::from_static("n-1"));
+        assert!(key.observe_nonce(url, &headers));
+        assert!(!key.observe_nonce(url, &headers));
+
+        let proof = key.proof("get", url, Some("at-1"), now).unwrap();
+        let verified = verify_proof(
+            &proof,
+            "GET",
+            "https://fhir.example.org/api/FHIR/R4/Patient/123",
+            Some("at-1"),
+            now,
+            Duration::seconds(60),
+        )
+        .unwrap();
+        assert_eq!(verified.jkt, key.jkt());
+        assert_eq!(verified.claims.nonce.as_deref(), Some("n-1"));
+
+        assert!(verify_proof(&proof, "POST", url, None, now, Duration::seconds(60)).is_err());
+        assert!(
+            verify_proof(&proof, "GET", url, Some("at-2"), now, Duration::seconds(60)).is_err()
+        );
+        let later = now + Duration::minutes(5);
+        assert!(verify_proof(&proof, "GET", url, None, later, Duration::seconds(60)).is_err());
+    }
+}
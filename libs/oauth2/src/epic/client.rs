@@ -1,31 +1,44 @@
 //! Client for handling OAuth2 authentication with Epic FHIR.
 
 use oauth2::basic::{
-    BasicClient, BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
-    BasicTokenResponse,
+    BasicErrorResponse, BasicErrorResponseType, BasicRevocationErrorResponse,
+    BasicTokenIntrospectionResponse,
 };
 use oauth2::{
-    AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, EmptyExtraTokenFields,
-    EndpointNotSet, EndpointSet, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
-    StandardRevocableToken, StandardTokenResponse, TokenResponse, TokenUrl,
+    AsyncHttpClient, AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken,
+    EndpointNotSet, EndpointSet, HttpClientError, HttpRequest, HttpResponse, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, Scope, StandardRevocableToken, TokenResponse,
+    TokenUrl,
 };
 
+use crate::clock::{unix_timestamp, Clock, DriftCorrectedClock, MonotonicClock};
+use crate::dpop::{DpopError, DpopKey, DPOP_HEADER, DPOP_NONCE_ERROR};
 use crate::epic::config::EpicFhirConfig;
-use crate::epic::error::Error as EpicError;
-use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
-use serde::Deserialize;
+use crate::epic::error::{body_excerpt, Error as EpicError, OAuthErrorResponse};
+use crate::epic::smart::SmartTokenResponse;
+use crate::epic::tokens::TokenSet;
+use crate::jwt_bearer::CLIENT_ASSERTION_TYPE;
+use config_lib::settings::ClientAssertionSettings;
+use config_lib::Secret;
+use security::ClientKeys;
 use serde::{Deserialize as SerdeDeserialize, Serialize};
-use std::time::Instant;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
 use url::Url;
 use uuid::Uuid;
 /// An OAuth2 client specifically for Epic FHIR.
 ///
+/// The client is immutable and `Send + Sync`: one instance is shared by every
+/// login without locking. Tokens belong to the user's session
+/// ([`TokenSet`](crate::epic::tokens::TokenSet)), not to the client.
 #[derive(Debug)]
 pub struct EpicFhirClient {
     config: EpicFhirConfig,
     oauth_client: Client<
         BasicErrorResponse,
-        BasicTokenResponse,
+        SmartTokenResponse,
         BasicTokenIntrospectionResponse,
         StandardRevocableToken,
         BasicRevocationErrorResponse,
@@ -35,14 +48,16 @@ pub struct EpicFhirClient {
         EndpointNotSet,
         EndpointSet,
     >,
-    access_token: Option<oauth2::AccessToken>, // Stores the current access token
-    refresh_token: Option<oauth2::RefreshToken>, // Stores the current refresh token
-    expires_at: Option<Instant>,               // Time when the access token expires
+    /// HTTP client for the token endpoint, reused so connections are pooled.
+    http: reqwest::Client,
+    /// Clock for assertion timestamps, corrected by the token endpoint's `Date` header
+    /// when `assertion.use_server_date` is set.
+    clock: Arc<DriftCorrectedClock>,
 }
 
 /// Claims for the JWT used in `private_key_jwt` client authentication.
 #[derive(Debug, Serialize, SerdeDeserialize)] // Use SerdeDeserialize to avoid conflict
-struct ClientAssertionClaims {
+pub(crate) struct ClientAssertionClaims {
     iss: String, // Issuer: client_id
     sub: String, // Subject: client_id
     aud: String, // Audience: token endpoint URL
@@ -52,32 +67,63 @@ struct ClientAssertionClaims {
     nbf: u64,    // Not Before: timestamp (seconds since epoch)
 }
 
+impl ClientAssertionClaims {
+    /// Claims of an assertion for `client_id` issued at `now`.
+    ///
+    /// `iat`/`nbf` are backdated by the configured clock skew so that a server
+    /// whose clock is slightly behind still accepts the assertion; `exp` is
+    /// `lifetime_secs` from now.
+    pub(crate) fn new(
+        client_id: &str,
+        token_url: &str,
+        settings: &ClientAssertionSettings,
+        now: OffsetDateTime,
+    ) -> Self {
+        let issued_at = now - Duration::seconds(settings.clock_skew_secs as i64);
+        let expiration = now + Duration::seconds(settings.lifetime_secs as i64);
+        Self {
+            iss: client_id.to_string(),
+            sub: client_id.to_string(),
+            aud: token_url.to_string(), // Audience is the token endpoint URL
+            jti: Uuid::new_v4().to_string(), // Unique token ID
+            exp: unix_timestamp(expiration),
+            iat: unix_timestamp(issued_at),
+            nbf: unix_timestamp(issued_at),
+        }
+    }
+}
+
 impl EpicFhirClient {
     /// Creates a new `EpicFhirClient` from the given configuration.
     ///
     /// # Errors
     ///
-    /// Returns `EpicError::UrlParse` if any of the URLs in the configuration are invalid.
+    /// Returns `EpicError::UrlParse` if any of the URLs in the configuration are invalid,
+    /// and `EpicError::Reqwest` if the HTTP client cannot be built.
     pub fn new(config: EpicFhirConfig) -> Result<Self, EpicError> {
+        Self::with_clock(config, Arc::new(MonotonicClock::new()))
+    }
+
+    /// Like [`EpicFhirClient::new`], reading the time from `clock`.
+    pub fn with_clock(config: EpicFhirConfig, clock: Arc<dyn Clock>) -> Result<Self, EpicError> {
         let client_id = ClientId::new(config.client_id.clone());
         let auth_url = AuthUrl::new(config.auth_url.clone())?;
         let token_url = TokenUrl::new(config.token_url.clone())?;
         let redirect_url = RedirectUrl::new(config.redirect_url.clone())?;
 
-        let mut oauth_client_builder = BasicClient::new(client_id)
+        let mut oauth_client_builder = Client::new(client_id)
             .set_auth_uri(auth_url)
             .set_token_uri(token_url)
             .set_redirect_uri(redirect_url);
 
-        // If not using private_key_jwt (indicated by presence of private_key_pem and key_id)
+        // If not using private_key_jwt (indicated by configured signing keys)
         // AND a client_secret is provided, configure the BasicClient to use it.
         // This will typically result in HTTP Basic Authentication for the token endpoint.
-        if config.private_key_pem.is_none() || config.key_id.is_none() {
-            // Based on the compiler error, config.client_secret is String, not Option<String>.
-            // We check if the client_secret string is not empty before using it.
-            if !config.client_secret.is_empty() {
+        if config.signing_keys.is_none() {
+            // Public clients (PKCE only) have no secret.
+            if let Some(secret) = config.client_secret.as_ref().filter(|s| !s.is_empty()) {
                 oauth_client_builder = oauth_client_builder
-                    .set_client_secret(ClientSecret::new(config.client_secret.clone()));
+                    .set_client_secret(ClientSecret::new(secret.expose().to_string()));
             }
         }
         // If private_key_jwt is used, client_secret on BasicClient should NOT be set,
@@ -85,24 +131,40 @@ impl EpicFhirClient {
 
         let oauth_client = oauth_client_builder;
 
+        // The token endpoint must not redirect (RFC 6749, section 3.2).
+        let http = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        // Cấu hình không đổi theo phiên: log một lần khi dựng client
+        tracing::info!(
+            "Epic OAuth2 client: client_id={:?} redirect_url={:?} token_url={:?} auth_url={:?} audience={:?} scopes={:?} private_key_jwt={}",
+            config.client_id,
+            config.redirect_url,
+            config.token_url,
+            config.auth_url,
+            config.audience,
+            config.scopes,
+            config.signing_keys.is_some()
+        );
+
         Ok(Self {
             config,
             oauth_client,
-            access_token: None,
-            refresh_token: None,
-            expires_at: None,
+            http,
+            clock: Arc::new(DriftCorrectedClock::new(clock)),
         })
     }
 
     /// Generates the authorization URL to redirect the user to.
     ///
-    /// This method prepares the PKCE challenge and CSRF token, storing them
-    /// internally for later verification.
+    /// This method prepares the PKCE challenge and CSRF token. Nothing is kept
+    /// in the client: the caller stores both in the user's session for the callback.
     ///
     /// # Returns
     ///
-    /// A tuple containing the `Url` to redirect the user to and the `String`
-    /// secret of the CSRF token (which the calling application should store, e.g., in a session).
+    /// A tuple containing the `Url` to redirect the user to, the `String`
+    /// secret of the CSRF token and the PKCE verifier.
     pub fn get_authorization_url(&self) -> Result<(Url, String, String), EpicError> {
         let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
         let csrf_token = CsrfToken::new_random();
@@ -128,6 +190,73 @@ impl EpicFhirClient {
         ))
     }
 
+    /// Like [`EpicFhirClient::get_authorization_url`], but with `par_url`
+    /// configured the parameters are pushed to the authorization server first
+    /// (RFC 9126) and the returned URL only carries `client_id` and `request_uri`.
+    ///
+    /// # Errors
+    ///
+    /// `EpicError::PushedAuthorization` if the server rejects the request,
+    /// `EpicError::Transport` or `EpicError::InvalidResponse` otherwise.
+    pub async fn authorization_url(&self) -> Result<(Url, String, String), EpicError> {
+        let (auth_url, csrf_token, pkce_verifier) = self.get_authorization_url()?;
+        let Some(par_url) = &self.config.par_url else {
+            return Ok((auth_url, csrf_token, pkce_verifier));
+        };
+
+        // The pushed request is authenticated like a token request.
+        let mut params: Vec<(String, String)> = auth_url.query_pairs().into_owned().collect();
+        params.extend(
+            self.client_assertion_params()
+                .await?
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value)),
+        );
+        let mut request = self.http.post(par_url).form(&params);
+        if self.config.signing_keys.is_none() {
+            if let Some(secret) = self.config.client_secret.as_ref().filter(|s| !s.is_empty()) {
+                request = request.basic_auth(&self.config.client_id, Some(secret.expose()));
+            }
+        }
+
+        let transport =
+            |e: reqwest::Error| EpicError::Transport(HttpClientError::Reqwest(Box::new(e)));
+        let response = request.send().await.map_err(transport)?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(transport)?;
+        if !status.is_success() {
+            return Err(match serde_json::from_slice::<BasicErrorResponse>(&body) {
+                Ok(error) => EpicError::PushedAuthorization(OAuthErrorResponse::from(&error)),
+                Err(_) => EpicError::InvalidResponse(format!(
+                    "PAR endpoint returned {} (body: {:?})",
+                    status,
+                    body_excerpt(&body)
+                )),
+            });
+        }
+        let pushed: PushedAuthorizationResponse = serde_json::from_slice(&body).map_err(|e| {
+            EpicError::InvalidResponse(format!("{} (body: {:?})", e, body_excerpt(&body)))
+        })?;
+
+        let mut redirect_url = Url::parse(&self.config.auth_url)?;
+        redirect_url
+            .query_pairs_mut()
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("request_uri", &pushed.request_uri);
+        Ok((redirect_url, csrf_token, pkce_verifier))
+    }
+
+    /// A fresh key for one session's DPoP-bound tokens, or `None` unless `dpop`
+    /// is configured. The same key must be passed to every token request of the session.
+    pub fn new_dpop_key(&self) -> Result<Option<Arc<DpopKey>>, EpicError> {
+        if !self.config.dpop {
+            return Ok(None);
+        }
+        DpopKey::generate()
+            .map(|key| Some(Arc::new(key)))
+            .map_err(|e| EpicError::JwtKeyError(e.to_string()))
+    }
+
     /// Exchanges an authorization code for an access token.
     ///
     /// This method should be called after the user has been redirected back to
@@ -139,214 +268,299 @@ impl EpicFhirClient {
     ///
     /// * `auth_code`: The authorization code received from Epic.
     /// * `received_state`: The state parameter received from Epic (must match the stored CSRF token secret).
+    /// * `dpop`: The session's key from [`EpicFhirClient::new_dpop_key`]; token
+    ///   requests then carry a DPoP proof and the server may bind the tokens to it.
     ///
     /// # Errors
     ///
-    /// Returns `EpicError` if CSRF/PKCE validation fails or the token exchange fails.
+    /// Returns `EpicError::CsrfMismatch` if the state does not match, and for a
+    /// failed token request `EpicError::TokenEndpoint` (the OAuth error response),
+    /// `EpicError::Transport` or `EpicError::InvalidResponse`; see
+    /// [`EpicError::class`] to tell retryable failures from ones that need a new login.
+    ///
+    /// The full token response is returned so callers can read the granted
+    /// scopes and the SMART launch context (`patient`, `id_token`).
     pub async fn exchange_code(
         &self,
         auth_code: String,
         expected_csrf: String,
         expected_pkce: String,
         received_state: String,
-    ) -> Result<oauth2::AccessToken, EpicError> {
-        // Log toàn bộ cấu hình OAuth2
-        tracing::info!("Epic OAuth2 config:");
-        tracing::info!("  client_id: {:?}", self.config.client_id);
-        tracing::info!("  redirect_url: {:?}", self.config.redirect_url);
-        tracing::info!("  token_url: {:?}", self.config.token_url);
-        tracing::info!("  auth_url: {:?}", self.config.auth_url);
-        tracing::info!("  audience: {:?}", self.config.audience);
-        tracing::info!("  scopes: {:?}", self.config.scopes);
-        tracing::info!("  private_key_pem: {:?}", self.config.private_key_pem.is_some());
-        tracing::info!("  key_id: {:?}", self.config.key_id);
-        tracing::info!("  jwt_algorithm: {:?}", self.config.jwt_algorithm);
-
+        dpop: Option<&DpopKey>,
+    ) -> Result<SmartTokenResponse, EpicError> {
         // Kiểm tra CSRF
         if expected_csrf != received_state {
-            tracing::error!(
-                "CSRF mismatch: expected {:?}, received {:?}",
-                expected_csrf,
-                received_state
-            );
+            tracing::error!("CSRF mismatch: state does not match the session");
             return Err(EpicError::CsrfMismatch);
         }
 
-        let pkce_verifier = PkceCodeVerifier::new(expected_pkce.clone());
-
-        let mut token_request_builder = self
-            .oauth_client
-            .exchange_code(AuthorizationCode::new(auth_code.clone()))
-            .set_pkce_verifier(pkce_verifier);
-
-        // Log các tham số gửi lên token endpoint
-        tracing::info!(
-            "Token request params:\n  client_id: {:?}\n  redirect_uri: {:?}\n  code: {:?}\n  pkce_verifier: {:?}",
+        tracing::debug!(
+            "Token request: client_id={:?}, redirect_uri={:?}",
             self.config.client_id,
-            self.config.redirect_url,
-            auth_code,
-            expected_pkce
+            self.config.redirect_url
         );
 
-        // Nếu dùng private_key_jwt, log nội dung JWT assertion và claims
-        if let (Some(private_key_pem), Some(key_id), Some(jwt_algorithm_str)) = (
-            self.config.private_key_pem.as_ref(),
-            self.config.key_id.as_ref(),
-            self.config.jwt_algorithm.as_ref(),
-        ) {
-            let (client_assertion, claims) =
-                self.create_client_assertion_jwt_debug(private_key_pem, key_id, jwt_algorithm_str)?;
-
-            tracing::info!(
-                "Using private_key_jwt:\n  key_id: {:?}\n  algorithm: {:?}\n  assertion: {}\n  claims: {:#?}",
-                key_id,
-                jwt_algorithm_str,
-                client_assertion,
-                claims
-            );
-
-            token_request_builder = token_request_builder
-                .add_extra_param(
-                    "client_assertion_type",
-                    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
-                )
-                .add_extra_param("client_assertion", client_assertion);
+        let offset_before = self.clock.offset();
+        let mut result = self.request_token(&auth_code, &expected_pkce, dpop).await;
+        // Server đòi nonce DPoP (đã ghi nhận từ header `DPoP-Nonce`): ký lại proof và assertion
+        if dpop.is_some() && is_nonce_error(&result) {
+            result = self.request_token(&auth_code, &expected_pkce, dpop).await;
+        }
+        // Assertion bị từ chối và header `Date` cho thấy đồng hồ lệch: ký lại và thử một lần nữa
+        if let Err(EpicError::TokenEndpoint(response)) = &result {
+            if response.error == BasicErrorResponseType::InvalidClient
+                && self.config.signing_keys.is_some()
+                && self.clock.offset() != offset_before
+            {
+                tracing::warn!(
+                    "Client assertion rejected, retrying with clock offset {}s",
+                    self.clock.offset().whole_seconds()
+                );
+                result = self.request_token(&auth_code, &expected_pkce, dpop).await;
+            }
         }
 
-        let http_client = reqwest::ClientBuilder::new()
-            .redirect(reqwest::redirect::Policy::none())
-            .build()
-            .expect("Client should build");
-
-        let token_result = token_request_builder
-            .request_async(&http_client)
-            .await
-            .map_err(|e| {
-                tracing::error!("Epic token exchange error: {:#?}", e);
-                EpicError::TokenNotFound
-            })?;
+        let token_result = result.inspect_err(|e| {
+            tracing::error!("Epic token exchange error ({:?}): {}", e.class(), e);
+        })?;
 
         tracing::info!(
-            "Token exchange success: access_token={:?}, expires_in={:?}",
-            token_result.access_token().secret(),
+            "Token exchange success: expires_in={:?}",
             token_result.expires_in()
         );
 
-        Ok(token_result.access_token().clone())
+        Ok(token_result)
     }
 
-    /// Creates a signed JWT for `private_key_jwt` client authentication.
-    fn create_client_assertion_jwt(
+    /// Exchanges a refresh token for a new access token (RFC 6749, section 6).
+    ///
+    /// The caller stores the result in the session's
+    /// [`TokenSet`](crate::epic::tokens::TokenSet); `dpop` is the key the tokens
+    /// are bound to, if any.
+    pub async fn refresh_token(
         &self,
-        private_key_pem: &str,
-        key_id: &str,
-        algorithm_str: &str,
-    ) -> Result<String, EpicError> {
-        let algorithm = match algorithm_str.to_uppercase().as_str() {
-            "RS256" => Algorithm::RS256,
-            "RS384" => Algorithm::RS384,
-            "RS512" => Algorithm::RS512,
-            "ES256" => Algorithm::ES256,
-            "ES384" => Algorithm::ES384,
-            _ => return Err(EpicError::Other("Unsupported JWT algorithm".to_string())),
-        };
+        refresh_token: &Secret,
+        dpop: Option<&DpopKey>,
+    ) -> Result<SmartTokenResponse, EpicError> {
+        let mut result = self.request_refresh(refresh_token, dpop).await;
+        if dpop.is_some() && is_nonce_error(&result) {
+            result = self.request_refresh(refresh_token, dpop).await;
+        }
+        result.inspect_err(|e| {
+            tracing::error!("Epic token refresh error ({:?}): {}", e.class(), e);
+        })
+    }
 
-        let encoding_key = match algorithm {
-            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
-                EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
-                    .map_err(|e| EpicError::JwtKeyError(e.to_string()))?
-            }
-            Algorithm::ES256 | Algorithm::ES384 => {
-                EncodingKey::from_ec_pem(private_key_pem.as_bytes())
-                    .map_err(|e| EpicError::JwtKeyError(e.to_string()))?
-            }
-            _ => {
-                return Err(EpicError::JwtKeyError(
-                    "Algorithm not supported for PEM key type".to_string(),
-                ))
-            }
-        };
+    /// The FHIR base URL the client's tokens are issued for (`audience`).
+    pub fn fhir_base_url(&self) -> &str {
+        &self.config.audience
+    }
+
+    /// Sends a request to the FHIR server with a session's `tokens` (bearer or
+    /// DPoP-bound). Uses the shared HTTP client, which does not follow redirects,
+    /// so the access token is never replayed to another host.
+    pub async fn send(
+        &self,
+        tokens: &TokenSet,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, DpopError> {
+        tokens.send(&self.http, request, self.clock.now()).await
+    }
+
+    /// Sends one refresh token request.
+    async fn request_refresh(
+        &self,
+        refresh_token: &Secret,
+        dpop: Option<&DpopKey>,
+    ) -> Result<SmartTokenResponse, EpicError> {
+        let refresh_token = RefreshToken::new(refresh_token.expose().to_string());
+        let mut token_request_builder = self.oauth_client.exchange_refresh_token(&refresh_token);
+        for (name, value) in self.client_assertion_params().await? {
+            token_request_builder = token_request_builder.add_extra_param(name, value);
+        }
+        Ok(token_request_builder
+            .request_async(&self.token_http_client(dpop))
+            .await?)
+    }
 
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| EpicError::TimeError(e.to_string()))?
-            .as_secs();
-
-        // JWT `exp` claim should be no more than 5 minutes in the future.
-        let expiration = now + (4 * 60 + 50); // Approx 4m50s to be safe.
-
-        let claims = ClientAssertionClaims {
-            iss: self.config.client_id.clone(),
-            sub: self.config.client_id.clone(),
-            aud: self.config.token_url.clone(), // Audience is the token endpoint URL
-            jti: Uuid::new_v4().to_string(),    // Unique token ID
-            exp: expiration,
-            iat: now,
-            nbf: now, // Not before, can be same as iat
+    /// Sends one authorization-code token request, signing a fresh client
+    /// assertion if `private_key_jwt` is configured.
+    async fn request_token(
+        &self,
+        auth_code: &str,
+        pkce_verifier: &str,
+        dpop: Option<&DpopKey>,
+    ) -> Result<SmartTokenResponse, EpicError> {
+        let mut token_request_builder = self
+            .oauth_client
+            .exchange_code(AuthorizationCode::new(auth_code.to_string()))
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier.to_string()));
+        for (name, value) in self.client_assertion_params().await? {
+            token_request_builder = token_request_builder.add_extra_param(name, value);
+        }
+        Ok(token_request_builder
+            .request_async(&self.token_http_client(dpop))
+            .await?)
+    }
+
+    /// `client_assertion` parameters for the token endpoint, empty unless
+    /// `private_key_jwt` is configured.
+    async fn client_assertion_params(&self) -> Result<Vec<(&'static str, String)>, EpicError> {
+        // Nếu dùng private_key_jwt, ký bằng key đang active theo lịch xoay vòng
+        let Some(keys) = &self.config.signing_keys else {
+            return Ok(Vec::new());
         };
+        let (client_assertion, claims) = self.create_client_assertion_jwt(keys).await?;
+        // Chỉ log kid/jti: assertion đã ký dùng được tới khi hết hạn
+        tracing::debug!(
+            "Signed client assertion: kid={:?}, jti={}",
+            jsonwebtoken::decode_header(&client_assertion)
+                .ok()
+                .and_then(|header| header.kid),
+            claims.jti
+        );
+        Ok(vec![
+            ("client_assertion_type", CLIENT_ASSERTION_TYPE.to_string()),
+            ("client_assertion", client_assertion),
+        ])
+    }
 
-        let mut header = Header::new(algorithm);
-        header.kid = Some(key_id.to_string()); // Key ID for JWKS lookup
+    /// The shared HTTP client, recording the server's `Date` header when
+    /// `assertion.use_server_date` is set and adding DPoP proofs signed by `dpop`.
+    fn token_http_client<'a>(&'a self, dpop: Option<&'a DpopKey>) -> DateObservingClient<'a> {
+        DateObservingClient {
+            http: &self.http,
+            clock: &self.clock,
+            observe_date: self.config.assertion.use_server_date,
+            dpop,
+        }
+    }
 
-        encode(&header, &claims, &encoding_key)
-            .map_err(|e| EpicError::JwtEncodingError(e.to_string()))
+    /// Claims of a client assertion issued now.
+    fn client_assertion_claims(&self) -> ClientAssertionClaims {
+        ClientAssertionClaims::new(
+            &self.config.client_id,
+            &self.config.token_url,
+            &self.config.assertion,
+            self.clock.now(),
+        )
     }
 
-    /// Tạo JWT assertion và trả về cả claims để debug
-    fn create_client_assertion_jwt_debug(
+    /// Creates a signed JWT for `private_key_jwt` client authentication.
+    ///
+    /// The assertion is signed by the client's current key; key material stays
+    /// inside the key store (or KMS), only the signature comes back.
+    async fn create_client_assertion_jwt(
         &self,
-        private_key_pem: &str,
-        key_id: &str,
-        algorithm_str: &str,
+        keys: &ClientKeys,
     ) -> Result<(String, ClientAssertionClaims), EpicError> {
-        let algorithm = match algorithm_str.to_uppercase().as_str() {
-            "RS256" => Algorithm::RS256,
-            "RS384" => Algorithm::RS384,
-            "RS512" => Algorithm::RS512,
-            "ES256" => Algorithm::ES256,
-            "ES384" => Algorithm::ES384,
-            _ => return Err(EpicError::Other("Unsupported JWT algorithm".to_string())),
-        };
+        let claims = self.client_assertion_claims();
+        let jwt = keys
+            .sign_jwt(&claims, self.clock.now())
+            .await
+            .map_err(|e| EpicError::JwtEncodingError(e.to_string()))?;
+
+        Ok((jwt, claims))
+    }
+}
+
+/// Response of the pushed authorization request endpoint (RFC 9126, section 2.2).
+#[derive(Debug, SerdeDeserialize)]
+struct PushedAuthorizationResponse {
+    request_uri: String,
+}
 
-        let encoding_key = match algorithm {
-            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
-                EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
-                    .map_err(|e| EpicError::JwtKeyError(e.to_string()))?
+/// HTTP client for the token endpoint that records the server's `Date` header
+/// and, with a DPoP key, signs a proof for each request and records `DPoP-Nonce`.
+pub(crate) struct DateObservingClient<'a> {
+    pub(crate) http: &'a reqwest::Client,
+    pub(crate) clock: &'a DriftCorrectedClock,
+    pub(crate) observe_date: bool,
+    pub(crate) dpop: Option<&'a DpopKey>,
+}
+
+impl<'c> AsyncHttpClient<'c> for DateObservingClient<'_> {
+    type Error = HttpClientError<reqwest::Error>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<HttpResponse, Self::Error>> + Send + Sync + 'c>>;
+
+    fn call(&'c self, request: HttpRequest) -> Self::Future {
+        Box::pin(async move {
+            let mut request = request;
+            let url = request.uri().to_string();
+            if let Some(key) = self.dpop {
+                let proof = key
+                    .proof(request.method().as_str(), &url, None, self.clock.now())
+                    .map_err(|e| HttpClientError::Other(e.to_string()))?;
+                let proof = oauth2::http::HeaderValue::from_str(&proof)
+                    .map_err(|e| HttpClientError::Other(e.to_string()))?;
+                request.headers_mut().insert(DPOP_HEADER, proof);
             }
-            Algorithm::ES256 | Algorithm::ES384 => {
-                EncodingKey::from_ec_pem(private_key_pem.as_bytes())
-                    .map_err(|e| EpicError::JwtKeyError(e.to_string()))?
+            let response = self.http.call(request).await?;
+            let date = response
+                .headers()
+                .get(oauth2::http::header::DATE)
+                .and_then(|value| value.to_str().ok());
+            if let (true, Some(date)) = (self.observe_date, date) {
+                self.clock.observe_date_header(date);
             }
-            _ => {
-                return Err(EpicError::JwtKeyError(
-                    "Algorithm not supported for PEM key type".to_string(),
-                ))
+            // Nonce mới được dùng cho proof kế tiếp (xem `is_nonce_error`)
+            if let Some(key) = self.dpop {
+                key.observe_nonce(&url, response.headers());
             }
-        };
-
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| EpicError::TimeError(e.to_string()))?
-            .as_secs();
-
-        let expiration = now + (4 * 60 + 50);
-
-        let claims = ClientAssertionClaims {
-            iss: self.config.client_id.clone(),
-            sub: self.config.client_id.clone(),
-            aud: self.config.token_url.clone(),
-            jti: Uuid::new_v4().to_string(),
-            exp: expiration,
-            iat: now,
-            nbf: now,
-        };
-
-        let mut header = Header::new(algorithm);
-        header.kid = Some(key_id.to_string());
+            Ok(response)
+        })
+    }
+}
 
-        let jwt = encode(&header, &claims, &encoding_key)
-            .map_err(|e| EpicError::JwtEncodingError(e.to_string()))?;
+/// The server asked for a DPoP nonce (RFC 9449, section 8). The nonce was recorded
+/// from the response, so the request can be rebuilt, with a fresh client
+/// assertion, and sent once more.
+fn is_nonce_error(result: &Result<SmartTokenResponse, EpicError>) -> bool {
+    matches!(result, Err(EpicError::TokenEndpoint(response))
+        if response.error == BasicErrorResponseType::Extension(DPOP_NONCE_ERROR.to_string()))
+}
 
-        Ok((jwt, claims))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use config_lib::settings::ClientAssertionSettings;
+    use time::macros::datetime;
+
+    #[test]
+    fn assertion_claims_follow_lifetime_and_skew() {
+        let config = EpicFhirConfig::new(
+            "cid".to_string(),
+            None,
+            "https://fhir.example.org/authorize".to_string(),
+            "https://fhir.example.org/token".to_string(),
+            "http://localhost:3000/callback".to_string(),
+            vec![],
+            "https://fhir.example.org/R4".to_string(),
+            None,
+            ClientAssertionSettings {
+                lifetime_secs: 120,
+                clock_skew_secs: 15,
+                use_server_date: true,
+            },
+        );
+        let now = datetime!(2026-10-19 08:00:00 UTC);
+        let client = EpicFhirClient::with_clock(config, Arc::new(ManualClock::new(now))).unwrap();
+
+        let claims = client.client_assertion_claims();
+        assert_eq!(claims.exp, unix_timestamp(now) + 120);
+        assert_eq!(claims.iat, unix_timestamp(now) - 15);
+        assert_eq!(claims.nbf, claims.iat);
+        assert_ne!(claims.jti, client.client_assertion_claims().jti);
+
+        // The token endpoint is two minutes ahead of us.
+        client
+            .clock
+            .observe_date_header("Mon, 19 Oct 2026 08:02:00 GMT");
+        assert_eq!(
+            client.client_assertion_claims().exp,
+            unix_timestamp(now) + 240
+        );
     }
 }
@@ -1,12 +1,17 @@
 //! Configuration for the Epic FHIR OAuth2 client.
 
+use config_lib::settings::ClientAssertionSettings;
+use config_lib::Secret;
+use security::ClientKeys;
+use std::sync::Arc;
+
 /// Configuration parameters required to connect to Epic FHIR's OAuth2 provider.
 #[derive(Debug, Clone)]
 pub struct EpicFhirConfig {
     /// The client ID assigned by Epic.
     pub client_id: String,
     /// The client secret assigned by Epic (if applicable for the OAuth2 flow).
-    pub client_secret: String,
+    pub client_secret: Option<Secret>,
     /// The Epic OAuth2 authorization endpoint URL.
     /// e.g., "https://fhir.epic.com/interconnect-fhir-oauth/oauth2/authorize"
     pub auth_url: String,
@@ -21,25 +26,33 @@ pub struct EpicFhirConfig {
     /// The audience parameter required by Epic, typically the token URL or FHIR server base URL.
     /// e.g., "https://fhir.epic.com/interconnect-fhir-oauth/oauth2/token"
     pub audience: String,
-    pub private_key_pem: Option<String>,
-    pub key_id: Option<String>,
-    pub jwt_algorithm: Option<String>,
+    /// Keys for `private_key_jwt` client authentication; the one that is
+    /// current at request time signs the assertion. `None` for secret/public clients.
+    pub signing_keys: Option<Arc<ClientKeys>>,
+    /// Lifetime, clock skew and drift correction for the client assertion.
+    pub assertion: ClientAssertionSettings,
+    /// Pushed Authorization Request endpoint (RFC 9126). When set, the
+    /// authorization parameters are pushed first and the browser only carries
+    /// the returned `request_uri`.
+    pub par_url: Option<String>,
+    /// Request DPoP-bound tokens (RFC 9449) with a per-session key.
+    pub dpop: bool,
 }
 
 impl EpicFhirConfig {
     /// Creates a new `EpicFhirConfig`.
     /// All parameters are mandatory as they are essential for the OAuth2 flow with Epic.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         client_id: String,
-        client_secret: String,
+        client_secret: Option<Secret>,
         auth_url: String,
         token_url: String,
         redirect_url: String,
         scopes: Vec<String>,
         audience: String,
-        private_key_pem: Option<String>,
-        key_id: Option<String>,
-        jwt_algorithm: Option<String>,
+        signing_keys: Option<Arc<ClientKeys>>,
+        assertion: ClientAssertionSettings,
     ) -> Self {
         Self {
             client_id,
@@ -49,9 +62,22 @@ impl EpicFhirConfig {
             redirect_url,
             scopes,
             audience,
-            private_key_pem,
-            key_id,
-            jwt_algorithm,
+            signing_keys,
+            assertion,
+            par_url: None,
+            dpop: false,
         }
     }
+
+    /// Pushes authorization requests to `par_url`.
+    pub fn with_par_url(mut self, par_url: impl Into<String>) -> Self {
+        self.par_url = Some(par_url.into());
+        self
+    }
+
+    /// Requests DPoP-bound tokens.
+    pub fn with_dpop(mut self) -> Self {
+        self.dpop = true;
+        self
+    }
 }
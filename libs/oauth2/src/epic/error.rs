@@ -1,7 +1,6 @@
 //! Error types for the Epic FHIR OAuth2 client.
 
 use axum::response::{IntoResponse, Response};
-use oauth2::reqwest::Error as OAuth2ReqwestError; // Alias for clarity
 use oauth2::url;
 use oauth2::{
     basic::{BasicErrorResponse, BasicErrorResponseType},
@@ -26,13 +25,16 @@ pub enum Error {
     TokenNotFound,
     /// CSRF token mismatch during the OAuth2 flow.
     CsrfMismatch,
-    /// Specific error during the token exchange phase.
-    TokenExchange(
-        RequestTokenError<
-            HttpClientError<reqwest::Error>,
-            StandardErrorResponse<BasicErrorResponseType>,
-        >,
-    ),
+    /// The token endpoint answered with an OAuth error response (RFC 6749, section 5.2).
+    TokenEndpoint(OAuthErrorResponse),
+    /// The pushed authorization request endpoint (RFC 9126) answered with an
+    /// OAuth error response.
+    PushedAuthorization(OAuthErrorResponse),
+    /// The token endpoint could not be reached or the connection failed mid-request.
+    Transport(HttpClientError<reqwest::Error>),
+    /// The token endpoint answered with something that is neither a token nor an
+    /// OAuth error response (e.g. an HTML error page from a proxy).
+    InvalidResponse(String),
     Other(String),
     JwtKeyError(String),
     JwtEncodingError(String),
@@ -48,7 +50,10 @@ impl std::fmt::Display for Error {
             Error::MissingState(s) => write!(f, "Missing state: {}", s),
             Error::TokenNotFound => write!(f, "Access token not found"),
             Error::CsrfMismatch => write!(f, "CSRF token mismatch"),
-            Error::TokenExchange(e) => write!(f, "Token HTTP client error: {}", e),
+            Error::TokenEndpoint(e) => write!(f, "Token endpoint error: {}", e),
+            Error::PushedAuthorization(e) => write!(f, "Pushed authorization request error: {}", e),
+            Error::Transport(e) => write!(f, "Token endpoint unreachable: {}", e),
+            Error::InvalidResponse(s) => write!(f, "Invalid token endpoint response: {}", s),
             Error::Other(s) => write!(f, "Other error: {}", s),
             Error::JwtKeyError(s) => write!(f, "JWT key error: {}", s),
             Error::JwtEncodingError(s) => write!(f, "JWT encoding error: {}", s),
@@ -62,7 +67,7 @@ impl std::error::Error for Error {
         match self {
             Error::UrlParse(e) => Some(e),
             Error::Reqwest(e) => Some(e),
-            Error::TokenExchange(e) => Some(e),
+            Error::Transport(e) => Some(e),
             _ => None,
         }
     }
@@ -100,7 +105,133 @@ impl
             StandardErrorResponse<BasicErrorResponseType>,
         >,
     ) -> Self {
-        Error::TokenExchange(err)
+        match err {
+            RequestTokenError::ServerResponse(response) => {
+                Error::TokenEndpoint(OAuthErrorResponse::from(&response))
+            }
+            // Only `DateObservingClient` produces `Other`: the DPoP proof could not be built
+            RequestTokenError::Request(HttpClientError::Other(s)) => {
+                Error::JwtEncodingError(format!("DPoP proof: {}", s))
+            }
+            RequestTokenError::Request(e) => Error::Transport(e),
+            RequestTokenError::Parse(e, body) => {
+                Error::InvalidResponse(format!("{} (body: {:?})", e, body_excerpt(&body)))
+            }
+            // The request could not be built locally, so nothing was sent
+            RequestTokenError::Other(s) if s.starts_with(PREPARE_REQUEST_FAILED) => Error::Other(s),
+            // Empty body or a non-JSON `Content-Type` from the server
+            RequestTokenError::Other(s) => Error::InvalidResponse(s),
+        }
+    }
+}
+
+/// Prefix of the `oauth2` crate's [`RequestTokenError::Other`] when the request
+/// itself could not be built.
+const PREPARE_REQUEST_FAILED: &str = "failed to prepare request";
+
+/// Characters of an unparseable token endpoint body kept in [`Error::InvalidResponse`].
+const MAX_BODY_EXCERPT: usize = 200;
+
+/// The start of an unparseable response body, for [`Error::InvalidResponse`].
+pub(crate) fn body_excerpt(body: &[u8]) -> String {
+    String::from_utf8_lossy(body)
+        .chars()
+        .take(MAX_BODY_EXCERPT)
+        .collect()
+}
+
+/// Error response of the token endpoint (RFC 6749, section 5.2).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthErrorResponse {
+    /// `error`; codes outside RFC 6749 (e.g. `temporarily_unavailable`) are
+    /// [`BasicErrorResponseType::Extension`].
+    pub error: BasicErrorResponseType,
+    /// `error_description`
+    pub description: Option<String>,
+    /// `error_uri`
+    pub uri: Option<String>,
+}
+
+impl From<&BasicErrorResponse> for OAuthErrorResponse {
+    fn from(response: &BasicErrorResponse) -> Self {
+        Self {
+            error: response.error().clone(),
+            description: response.error_description().cloned(),
+            uri: response.error_uri().cloned(),
+        }
+    }
+}
+
+impl OAuthErrorResponse {
+    fn class(&self) -> ErrorClass {
+        match &self.error {
+            BasicErrorResponseType::InvalidGrant | BasicErrorResponseType::InvalidRequest => {
+                ErrorClass::UserActionable
+            }
+            BasicErrorResponseType::Extension(code) => match code.as_str() {
+                "temporarily_unavailable" | "server_error" | "slow_down" => ErrorClass::Retryable,
+                "access_denied" | "login_required" | "consent_required" => {
+                    ErrorClass::UserActionable
+                }
+                _ => ErrorClass::Fatal,
+            },
+            _ => ErrorClass::Fatal,
+        }
+    }
+}
+
+impl std::fmt::Display for OAuthErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error.as_ref())?;
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
+        }
+        if let Some(uri) = &self.uri {
+            write!(f, " ({})", uri)?;
+        }
+        Ok(())
+    }
+}
+
+/// What the caller can do about an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Transient: the same request may succeed later (network fault, 5xx,
+    /// `temporarily_unavailable`).
+    Retryable,
+    /// The user has to start over, e.g. log in again because the code or
+    /// refresh token expired, was already used, or consent was denied.
+    UserActionable,
+    /// Misconfiguration or a bug; neither retrying nor logging in again helps
+    /// (`invalid_client`, `unauthorized_client`, unsupported grant, a client
+    /// assertion or DPoP proof that cannot be signed, ...).
+    Fatal,
+}
+
+impl Error {
+    /// Classifies the error for retry and user-facing handling.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::Transport(_) | Error::InvalidResponse(_) => ErrorClass::Retryable,
+            Error::TokenEndpoint(response) | Error::PushedAuthorization(response) => {
+                response.class()
+            }
+            Error::CsrfMismatch | Error::MissingState(_) | Error::TokenNotFound => {
+                ErrorClass::UserActionable
+            }
+            Error::UrlParse(_)
+            | Error::OAuth2(_)
+            | Error::Reqwest(_)
+            | Error::Other(_)
+            | Error::JwtKeyError(_)
+            | Error::JwtEncodingError(_)
+            | Error::TimeError(_) => ErrorClass::Fatal,
+        }
+    }
+
+    /// Shorthand for `self.class() == ErrorClass::Retryable`.
+    pub fn is_retryable(&self) -> bool {
+        self.class() == ErrorClass::Retryable
     }
 }
 #[derive(Debug)]
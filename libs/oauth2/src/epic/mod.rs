@@ -3,3 +3,5 @@
 pub mod client;
 pub mod config;
 pub mod error;
+pub mod smart;
+pub mod tokens;
@@ -0,0 +1,70 @@
+//! SMART on FHIR additions to the OAuth2 token response.
+
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use oauth2::basic::BasicTokenType;
+use oauth2::{ExtraTokenFields, StandardTokenResponse};
+use serde::{Deserialize, Serialize};
+
+/// Launch context returned next to the access token (SMART App Launch, "Launch context").
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmartTokenFields {
+    /// FHIR id of the patient in context, when a `launch/patient` scope was granted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patient: Option<String>,
+    /// FHIR id of the encounter in context.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<String>,
+    /// OpenID Connect ID token, when `openid` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for SmartTokenFields {}
+
+/// Token response of a SMART authorization server.
+pub type SmartTokenResponse = StandardTokenResponse<SmartTokenFields, BasicTokenType>;
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+}
+
+impl SmartTokenFields {
+    /// `sub` of the ID token.
+    ///
+    /// The signature is not checked: the token was received directly from the
+    /// token endpoint over TLS (OpenID Connect Core 1.0, section 3.1.3.7).
+    pub fn id_token_subject(&self) -> Option<String> {
+        let id_token = self.id_token.as_deref()?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.insecure_disable_signature_validation();
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        validation.required_spec_claims.clear();
+        decode::<IdTokenClaims>(id_token, &DecodingKey::from_secret(&[]), &validation)
+            .ok()
+            .map(|data| data.claims.sub)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_launch_context_and_subject() {
+        // {"alg":"RS256"} . {"sub":"practitioner-1","aud":"cid"} . (no signature check)
+        let body = r#"{
+            "access_token": "at",
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "scope": "openid launch/patient patient/*.read",
+            "patient": "erXuFYUfucBZaryVksYEcMg3",
+            "id_token": "eyJhbGciOiJSUzI1NiJ9.eyJzdWIiOiJwcmFjdGl0aW9uZXItMSIsImF1ZCI6ImNpZCJ9.c2ln"
+        }"#;
+        let response: SmartTokenResponse = serde_json::from_str(body).unwrap();
+        let fields = response.extra_fields();
+        assert_eq!(fields.patient.as_deref(), Some("erXuFYUfucBZaryVksYEcMg3"));
+        assert_eq!(fields.id_token_subject().as_deref(), Some("practitioner-1"));
+    }
+}
@@ -0,0 +1,132 @@
+//! Tokens issued to one signed-in user.
+//!
+//! [`EpicFhirClient`](crate::epic::client::EpicFhirClient) is shared by every
+//! session and holds no token state; each session keeps its own [`TokenSet`].
+
+use std::sync::Arc;
+
+use config_lib::Secret;
+use oauth2::basic::BasicTokenType;
+use oauth2::TokenResponse;
+use time::{Duration, OffsetDateTime};
+
+use crate::dpop::{DpopError, DpopKey, TOKEN_TYPE_DPOP};
+use crate::epic::smart::SmartTokenResponse;
+
+/// Lifetime assumed when the token endpoint does not return `expires_in`.
+pub const DEFAULT_TOKEN_LIFETIME: Duration = Duration::hours(1);
+
+/// Access and refresh token of one session.
+#[derive(Debug, Clone)]
+pub struct TokenSet {
+    pub access_token: Secret,
+    pub refresh_token: Option<Secret>,
+    pub expires_at: OffsetDateTime,
+    /// Key the access token is bound to (DPoP); `None` for bearer tokens.
+    pub dpop: Option<Arc<DpopKey>>,
+}
+
+impl TokenSet {
+    /// Token state after a successful token response received at `now`.
+    pub fn from_response(response: &SmartTokenResponse, now: OffsetDateTime) -> Self {
+        let lifetime = response
+            .expires_in()
+            .and_then(|expires_in| Duration::try_from(expires_in).ok())
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        Self {
+            access_token: Secret::new(response.access_token().secret().clone()),
+            refresh_token: response
+                .refresh_token()
+                .map(|token| Secret::new(token.secret().clone())),
+            expires_at: now + lifetime,
+            dpop: None,
+        }
+    }
+
+    /// Binds the tokens to `key` if the server issued a DPoP-bound token
+    /// (`token_type` `DPoP`). A server that ignored the proof returns a bearer
+    /// token, which stays unbound.
+    pub fn bound_to(mut self, response: &SmartTokenResponse, key: Option<Arc<DpopKey>>) -> Self {
+        let bound = matches!(
+            response.token_type(),
+            BasicTokenType::Extension(token_type) if token_type.eq_ignore_ascii_case(TOKEN_TYPE_DPOP)
+        );
+        self.dpop = key.filter(|_| bound);
+        self
+    }
+
+    /// Applies the response to a refresh request. The authorization server may
+    /// omit `refresh_token` (RFC 6749, section 6), in which case the old one stays valid.
+    pub fn refreshed(&self, response: &SmartTokenResponse, now: OffsetDateTime) -> Self {
+        let mut tokens = Self::from_response(response, now).bound_to(response, self.dpop.clone());
+        if tokens.refresh_token.is_none() {
+            tokens.refresh_token = self.refresh_token.clone();
+        }
+        tokens
+    }
+
+    /// Whether the access token expires within `margin` of `now`.
+    pub fn expires_within(&self, now: OffsetDateTime, margin: Duration) -> bool {
+        self.expires_at <= now + margin
+    }
+
+    /// Sends `request` with the access token: `Authorization: Bearer`, or
+    /// `Authorization: DPoP` with a proof when the token is DPoP-bound.
+    pub async fn send(
+        &self,
+        http: &reqwest::Client,
+        mut request: reqwest::Request,
+        now: OffsetDateTime,
+    ) -> Result<reqwest::Response, DpopError> {
+        let access_token = self.access_token.expose();
+        match &self.dpop {
+            Some(key) => key.send(http, request, access_token, now).await,
+            None => {
+                let value = format!("Bearer {}", access_token)
+                    .parse()
+                    .map_err(|_| DpopError::InvalidProof("invalid access token".to_string()))?;
+                request
+                    .headers_mut()
+                    .insert(reqwest::header::AUTHORIZATION, value);
+                Ok(http.execute(request).await?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn response(body: serde_json::Value) -> SmartTokenResponse {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn refresh_keeps_the_previous_refresh_token() {
+        let now = datetime!(2026-10-19 08:00 UTC);
+        let tokens = TokenSet::from_response(
+            &response(serde_json::json!({
+                "access_token": "at-1",
+                "token_type": "Bearer",
+                "expires_in": 300,
+                "refresh_token": "rt-1",
+            })),
+            now,
+        );
+        assert!(!tokens.expires_within(now, Duration::minutes(1)));
+        assert!(tokens.expires_within(now + Duration::minutes(4), Duration::minutes(1)));
+
+        let refreshed = tokens.refreshed(
+            &response(serde_json::json!({ "access_token": "at-2", "token_type": "Bearer" })),
+            now + Duration::minutes(5),
+        );
+        assert_eq!(refreshed.access_token.expose(), "at-2");
+        assert_eq!(refreshed.refresh_token.unwrap().expose(), "rt-1");
+        assert_eq!(
+            refreshed.expires_at,
+            now + Duration::minutes(5) + DEFAULT_TOKEN_LIFETIME
+        );
+    }
+}
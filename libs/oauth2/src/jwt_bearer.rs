@@ -0,0 +1,423 @@
+//! Verification of JWT bearer assertions (RFC 7523).
+//!
+//! Used when we are the party receiving `private_key_jwt` client assertions,
+//! e.g. from partner applications. Besides signature, audience and time checks,
+//! every assertion must carry a `jti` that has not been seen before while the
+//! assertion is still valid.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use thiserror::Error;
+use time::{Duration, OffsetDateTime};
+
+use crate::clock::Clock;
+
+/// `client_assertion_type` for JWT bearer client authentication.
+pub const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// Algorithms accepted for assertions; symmetric and `none` are never accepted.
+const ACCEPTED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+];
+
+/// Reasons an assertion is rejected.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AssertionError {
+    #[error("malformed assertion: {0}")]
+    Malformed(String),
+    #[error("algorithm {0:?} is not accepted")]
+    UnsupportedAlgorithm(Algorithm),
+    #[error("unknown issuer '{0}'")]
+    UnknownIssuer(String),
+    #[error("no key of issuer '{0}' matches the assertion")]
+    UnknownKey(String),
+    #[error("invalid assertion: {0}")]
+    Invalid(String),
+    #[error("issuer and subject must both be the client id")]
+    SubjectMismatch,
+    #[error("assertion has expired")]
+    Expired,
+    #[error("assertion is not valid yet")]
+    NotYetValid,
+    #[error("assertion lifetime exceeds {0} seconds")]
+    LifetimeTooLong(i64),
+    #[error("assertion has no jti")]
+    MissingJti,
+    #[error("assertion jti has already been used")]
+    Replayed,
+}
+
+/// Claims of a verified assertion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedAssertion {
+    pub issuer: String,
+    pub subject: String,
+    pub jti: String,
+    pub expires_at: OffsetDateTime,
+}
+
+/// Remembers the `jti` values seen per issuer until the assertions expire.
+#[async_trait]
+pub trait JtiStore: Send + Sync {
+    /// Records `jti` for `issuer`, kept until `expires_at`.
+    ///
+    /// Returns `false` if it was already recorded and has not expired yet.
+    async fn insert_if_absent(
+        &self,
+        issuer: &str,
+        jti: &str,
+        expires_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> bool;
+}
+
+/// In-process [`JtiStore`]. Expired entries are dropped on insert.
+///
+/// Only suitable for a single instance; replicas need a shared store.
+#[derive(Debug, Default)]
+pub struct MemoryJtiStore {
+    seen: Mutex<HashMap<(String, String), OffsetDateTime>>,
+}
+
+impl MemoryJtiStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl JtiStore for MemoryJtiStore {
+    async fn insert_if_absent(
+        &self,
+        issuer: &str,
+        jti: &str,
+        expires_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expiry| *expiry > now);
+        let key = (issuer.to_string(), jti.to_string());
+        if seen.contains_key(&key) {
+            return false;
+        }
+        seen.insert(key, expires_at);
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AssertionClaims {
+    iss: String,
+    sub: String,
+    exp: i64,
+    #[serde(default)]
+    nbf: Option<i64>,
+    #[serde(default)]
+    iat: Option<i64>,
+    #[serde(default)]
+    jti: Option<String>,
+}
+
+/// Verifies JWT bearer assertions signed by registered issuers.
+pub struct JwtBearerVerifier {
+    audiences: Vec<String>,
+    max_lifetime: Duration,
+    leeway: Duration,
+    clock: Arc<dyn Clock>,
+    jti_store: Arc<dyn JtiStore>,
+    issuers: HashMap<String, JwkSet>,
+}
+
+impl JwtBearerVerifier {
+    /// Accepts assertions whose `aud` contains one of `audiences` (usually our
+    /// token endpoint URL). Lifetime defaults to 5 minutes, leeway to 30 seconds.
+    pub fn new(
+        audiences: Vec<String>,
+        clock: Arc<dyn Clock>,
+        jti_store: Arc<dyn JtiStore>,
+    ) -> Self {
+        Self {
+            audiences,
+            max_lifetime: Duration::minutes(5),
+            leeway: Duration::seconds(30),
+            clock,
+            jti_store,
+            issuers: HashMap::new(),
+        }
+    }
+
+    /// Longest accepted distance between now and `exp`.
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = max_lifetime;
+        self
+    }
+
+    /// Tolerance for clock differences with the issuer.
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Trusts assertions issued by `issuer` and signed by one of `keys`.
+    pub fn register_issuer(&mut self, issuer: impl Into<String>, keys: JwkSet) {
+        self.issuers.insert(issuer.into(), keys);
+    }
+
+    /// Verifies a client assertion for `client_id` (`iss` and `sub` must both
+    /// equal the client id, RFC 7523 section 3).
+    pub async fn verify_client_assertion(
+        &self,
+        assertion: &str,
+        client_id: &str,
+    ) -> Result<VerifiedAssertion, AssertionError> {
+        let verified = self.verify(assertion).await?;
+        if verified.issuer != client_id || verified.subject != client_id {
+            return Err(AssertionError::SubjectMismatch);
+        }
+        Ok(verified)
+    }
+
+    /// Verifies an assertion from any registered issuer and records its `jti`.
+    pub async fn verify(&self, assertion: &str) -> Result<VerifiedAssertion, AssertionError> {
+        let header =
+            decode_header(assertion).map_err(|e| AssertionError::Malformed(e.to_string()))?;
+        if !ACCEPTED_ALGORITHMS.contains(&header.alg) {
+            return Err(AssertionError::UnsupportedAlgorithm(header.alg));
+        }
+
+        // The issuer selects the key set, so read it before checking the signature.
+        let mut unverified = Validation::new(header.alg);
+        unverified.insecure_disable_signature_validation();
+        unverified.validate_exp = false;
+        unverified.validate_aud = false;
+        unverified.required_spec_claims.clear();
+        let issuer =
+            decode::<AssertionClaims>(assertion, &DecodingKey::from_secret(&[]), &unverified)
+                .map_err(|e| AssertionError::Malformed(e.to_string()))?
+                .claims
+                .iss;
+
+        let keys = self
+            .issuers
+            .get(&issuer)
+            .ok_or_else(|| AssertionError::UnknownIssuer(issuer.clone()))?;
+        let jwk = match header.kid.as_deref() {
+            Some(kid) => keys.find(kid),
+            None if keys.keys.len() == 1 => keys.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| AssertionError::UnknownKey(issuer.clone()))?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|e| AssertionError::Invalid(e.to_string()))?;
+
+        // Time claims are checked below against our clock instead of the system time.
+        let mut validation = Validation::new(header.alg);
+        validation.validate_exp = false;
+        validation.set_audience(&self.audiences);
+        validation.set_required_spec_claims(&["iss", "sub", "aud", "exp"]);
+        let claims = decode::<AssertionClaims>(assertion, &key, &validation)
+            .map_err(|e| AssertionError::Invalid(e.to_string()))?
+            .claims;
+
+        let now = self.clock.now();
+        let expires_at = timestamp(claims.exp)?;
+        if expires_at + self.leeway <= now {
+            return Err(AssertionError::Expired);
+        }
+        if expires_at - now > self.max_lifetime + self.leeway {
+            return Err(AssertionError::LifetimeTooLong(
+                self.max_lifetime.whole_seconds(),
+            ));
+        }
+        for not_before in [claims.nbf, claims.iat].into_iter().flatten() {
+            if timestamp(not_before)? > now + self.leeway {
+                return Err(AssertionError::NotYetValid);
+            }
+        }
+
+        let jti = claims
+            .jti
+            .filter(|jti| !jti.is_empty())
+            .ok_or(AssertionError::MissingJti)?;
+        if !self
+            .jti_store
+            .insert_if_absent(&claims.iss, &jti, expires_at + self.leeway, now)
+            .await
+        {
+            return Err(AssertionError::Replayed);
+        }
+
+        Ok(VerifiedAssertion {
+            issuer: claims.iss,
+            subject: claims.sub,
+            jti,
+            expires_at,
+        })
+    }
+}
+
+fn timestamp(seconds: i64) -> Result<OffsetDateTime, AssertionError> {
+    OffsetDateTime::from_unix_timestamp(seconds)
+        .map_err(|e| AssertionError::Malformed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{unix_timestamp, ManualClock};
+    use security::test_support::P256_PEM;
+    use security::{sign_jwt, Signer, SigningAlgorithm, SoftwareSigner};
+    use serde_json::json;
+    use time::macros::datetime;
+
+    const AUDIENCE: &str = "https://gateway.example.org/oauth2/token";
+
+    struct Fixture {
+        clock: Arc<ManualClock>,
+        signer: SoftwareSigner,
+        verifier: JwtBearerVerifier,
+    }
+
+    fn fixture() -> Fixture {
+        let clock = Arc::new(ManualClock::new(datetime!(2026-10-19 08:00 UTC)));
+        let signer = SoftwareSigner::from_pem(P256_PEM, SigningAlgorithm::ES256).unwrap();
+        let jwks: JwkSet = serde_json::from_value(json!({
+            "keys": [signer.public_jwk().to_json("partner-1", SigningAlgorithm::ES256)],
+        }))
+        .unwrap();
+        let mut verifier = JwtBearerVerifier::new(
+            vec![AUDIENCE.to_string()],
+            clock.clone(),
+            Arc::new(MemoryJtiStore::new()),
+        );
+        verifier.register_issuer("partner-app", jwks);
+        Fixture {
+            clock,
+            signer,
+            verifier,
+        }
+    }
+
+    async fn assertion(fixture: &Fixture, jti: &str, lifetime: Duration) -> String {
+        let now = fixture.clock.now();
+        let claims = json!({
+            "iss": "partner-app",
+            "sub": "partner-app",
+            "aud": AUDIENCE,
+            "jti": jti,
+            "iat": unix_timestamp(now),
+            "exp": unix_timestamp(now + lifetime),
+        });
+        sign_jwt(&fixture.signer, "partner-1", &claims)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn accepts_each_jti_once() {
+        let fixture = fixture();
+        let jwt = assertion(&fixture, "a-1", Duration::minutes(4)).await;
+
+        let verified = fixture
+            .verifier
+            .verify_client_assertion(&jwt, "partner-app")
+            .await
+            .unwrap();
+        assert_eq!(verified.jti, "a-1");
+        assert_eq!(
+            fixture.verifier.verify(&jwt).await,
+            Err(AssertionError::Replayed)
+        );
+
+        let other = assertion(&fixture, "a-2", Duration::minutes(4)).await;
+        assert!(fixture.verifier.verify(&other).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn checks_time_against_the_clock() {
+        let fixture = fixture();
+        let long = assertion(&fixture, "long", Duration::minutes(30)).await;
+        assert_eq!(
+            fixture.verifier.verify(&long).await,
+            Err(AssertionError::LifetimeTooLong(300))
+        );
+
+        let jwt = assertion(&fixture, "late", Duration::minutes(4)).await;
+        fixture.clock.advance(Duration::minutes(5));
+        assert_eq!(
+            fixture.verifier.verify(&jwt).await,
+            Err(AssertionError::Expired)
+        );
+
+        // Issued by a client whose clock runs ahead of ours by more than the leeway.
+        let early = assertion(&fixture, "early", Duration::minutes(2)).await;
+        fixture.clock.advance(Duration::minutes(-2));
+        assert_eq!(
+            fixture.verifier.verify(&early).await,
+            Err(AssertionError::NotYetValid)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_client_and_unknown_issuer() {
+        let fixture = fixture();
+        let jwt = assertion(&fixture, "x", Duration::minutes(4)).await;
+        assert_eq!(
+            fixture
+                .verifier
+                .verify_client_assertion(&jwt, "someone-else")
+                .await,
+            Err(AssertionError::SubjectMismatch)
+        );
+
+        let mut verifier = JwtBearerVerifier::new(
+            vec![AUDIENCE.to_string()],
+            fixture.clock.clone(),
+            Arc::new(MemoryJtiStore::new()),
+        );
+        verifier.register_issuer("another-app", JwkSet { keys: vec![] });
+        assert_eq!(
+            verifier.verify(&jwt).await,
+            Err(AssertionError::UnknownIssuer("partner-app".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_store_forgets_expired_jtis() {
+        let store = MemoryJtiStore::new();
+        let now = datetime!(2026-10-19 08:00 UTC);
+        assert!(
+            store
+                .insert_if_absent("iss", "1", now + Duration::minutes(1), now)
+                .await
+        );
+        assert!(
+            !store
+                .insert_if_absent("iss", "1", now + Duration::minutes(1), now)
+                .await
+        );
+        let later = now + Duration::minutes(2);
+        assert!(
+            store
+                .insert_if_absent("iss", "2", later + Duration::minutes(1), later)
+                .await
+        );
+        assert_eq!(store.len(), 1);
+    }
+}
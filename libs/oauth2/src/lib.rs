@@ -1 +1,10 @@
+pub mod backend;
+pub mod clock;
+pub mod dpop;
 pub mod epic;
+pub mod jwt_bearer;
+
+#[cfg(test)]
+mod tests {
+    mod client_tests;
+}
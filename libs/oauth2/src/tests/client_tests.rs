@@ -1,20 +1,437 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use config_lib::OAuth2Settings;
-
-    #[tokio::test]
-    async fn test_authorize_url() {
-        let cfg = OAuth2Settings {
-            client_id: "cid".into(),
-            client_secret: "secret".into(),
-            authorize_url: "https://example.com/authorize".into(),
-            token_url: "https://example.com/token".into(),
-            redirect_uri: "https://app/callback".into(),
-        };
-        let client = EpicOAuth2::new(cfg);
-        let url = client.authorize_url("state123").unwrap();
-        assert!(url.contains("response_type=code"));
-        assert!(url.contains("state=state123"));
+//! `EpicFhirClient` against the in-process mock SMART server.
+
+use std::sync::Arc;
+
+use config_lib::settings::ClientAssertionSettings;
+use config_lib::{Secret, Settings};
+use oauth2::basic::BasicTokenType;
+use oauth2::TokenResponse;
+use security::test_support::P256_PEM;
+use security::{ClientKeys, KeyStore};
+use serde_json::json;
+use smart_mock::{Failure, MockClient, MockSmartServer, DEFAULT_PATIENT};
+use time::OffsetDateTime;
+
+use crate::backend::{BackendServicesClient, BackendServicesConfig};
+use crate::epic::client::EpicFhirClient;
+use crate::epic::config::EpicFhirConfig;
+use crate::epic::error::{Error as EpicError, ErrorClass};
+use crate::epic::tokens::TokenSet;
+use oauth2::basic::BasicErrorResponseType;
+
+const REDIRECT: &str = "http://localhost:3000/epic-sandbox/callback";
+
+fn config(server: &MockSmartServer, client_secret: Option<&str>) -> EpicFhirConfig {
+    EpicFhirConfig::new(
+        "cid".to_string(),
+        client_secret.map(Secret::new),
+        server.authorize_url(),
+        server.token_url(),
+        REDIRECT.to_string(),
+        vec![
+            "openid".to_string(),
+            "launch/patient".to_string(),
+            "patient/*.read".to_string(),
+        ],
+        server.fhir_base_url(),
+        None,
+        ClientAssertionSettings::default(),
+    )
+}
+
+/// Signing keys of client `cid`, registered on `server` with their JWKS.
+async fn register_signing_keys(server: &MockSmartServer) -> Arc<ClientKeys> {
+    let settings: Settings = serde_json::from_value(json!({
+        "port": 3000,
+        "base_url": "http://localhost:3000",
+        "oauth_clients": {
+            "epic": {
+                "client_id": "cid",
+                "token_url": server.token_url(),
+                "auth_url": server.authorize_url(),
+                "redirect_uri": REDIRECT,
+                "scopes": [],
+                "audience": server.fhir_base_url(),
+                "signing_keys": [{ "private_key_pem": P256_PEM, "algorithm": "ES256" }],
+            },
+        },
+    }))
+    .unwrap();
+    let store = KeyStore::load(&settings, None).await.unwrap();
+    server.register_client(MockClient::with_jwks(
+        "cid",
+        REDIRECT,
+        store.jwks(OffsetDateTime::now_utc()),
+    ));
+    store.client("epic").unwrap()
+}
+
+/// Client using `private_key_jwt`, registered on `server` with its JWKS.
+async fn private_key_jwt_client(server: &MockSmartServer) -> EpicFhirClient {
+    let mut config = config(server, None);
+    config.signing_keys = Some(register_signing_keys(server).await);
+    EpicFhirClient::new(config).unwrap()
+}
+
+/// Follows the authorization URL like a browser with auto-consent and returns the
+/// callback's `code` and `state` with the expected CSRF token and PKCE verifier.
+async fn authorize(client: &EpicFhirClient) -> (String, String, String, String) {
+    let (url, csrf, pkce) = client.authorization_url().await.unwrap();
+    let http = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = http.get(url).send().await.unwrap();
+    let location = url::Url::parse(response.headers()["location"].to_str().unwrap()).unwrap();
+    let param = |name: &str| {
+        location
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_default()
+    };
+    (param("code"), param("state"), csrf, pkce)
+}
+
+#[tokio::test]
+async fn authorization_url_carries_pkce_and_audience() {
+    let server = MockSmartServer::start().await;
+    let client = EpicFhirClient::new(config(&server, None)).unwrap();
+    let (url, csrf, _) = client.get_authorization_url().unwrap();
+    let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    let has = |name: &str, value: &str| query.iter().any(|(k, v)| k == name && v == value);
+    assert!(has("response_type", "code"));
+    assert!(has("state", &csrf));
+    assert!(has("aud", &server.fhir_base_url()));
+    assert!(has("code_challenge_method", "S256"));
+}
+
+#[tokio::test]
+async fn exchanges_code_with_private_key_jwt_and_refreshes() {
+    let server = MockSmartServer::start().await;
+    let client = private_key_jwt_client(&server).await;
+
+    let (code, state, csrf, pkce) = authorize(&client).await;
+    let response = client
+        .exchange_code(code, csrf, pkce, state, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.extra_fields().patient.as_deref(),
+        Some(DEFAULT_PATIENT)
+    );
+    assert!(server
+        .access_token(response.access_token().secret())
+        .is_some());
+
+    let refresh_token = Secret::new(response.refresh_token().unwrap().secret());
+    let refreshed = client.refresh_token(&refresh_token, None).await.unwrap();
+    assert_ne!(
+        refreshed.access_token().secret(),
+        response.access_token().secret()
+    );
+    // The old refresh token was rotated out.
+    assert!(client.refresh_token(&refresh_token, None).await.is_err());
+}
+
+#[tokio::test]
+async fn concurrent_logins_share_one_client() {
+    let server = MockSmartServer::start().await;
+    let client = Arc::new(private_key_jwt_client(&server).await);
+
+    // Each login keeps its own CSRF token and PKCE verifier; the client holds no
+    // per-login state.
+    let logins: Vec<_> = (0..8)
+        .map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let (code, state, csrf, pkce) = authorize(&client).await;
+                client.exchange_code(code, csrf, pkce, state, None).await
+            })
+        })
+        .collect();
+    let mut access_tokens = Vec::new();
+    for login in logins {
+        let response = login.await.unwrap().unwrap();
+        access_tokens.push(response.access_token().secret().clone());
+    }
+    assert!(access_tokens
+        .iter()
+        .all(|token| server.access_token(token).is_some()));
+    access_tokens.sort();
+    access_tokens.dedup();
+    assert_eq!(access_tokens.len(), 8);
+
+    // A verifier from another login does not redeem the code.
+    let (code, state, csrf, _) = authorize(&client).await;
+    let (_, _, _, other_pkce) = authorize(&client).await;
+    assert!(client
+        .exchange_code(code, csrf, other_pkce, state, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn rejects_state_mismatch_and_server_errors() {
+    let server = MockSmartServer::start().await;
+    server.register_client(MockClient::with_secret("cid", REDIRECT, "s3cret"));
+    let client = EpicFhirClient::new(config(&server, Some("s3cret"))).unwrap();
+
+    let (code, _, csrf, pkce) = authorize(&client).await;
+    assert!(matches!(
+        client
+            .exchange_code(
+                code.clone(),
+                csrf.clone(),
+                pkce.clone(),
+                "forged".to_string(),
+                None
+            )
+            .await,
+        Err(EpicError::CsrfMismatch)
+    ));
+
+    server.fail_next(Failure::InvalidClient);
+    assert!(client
+        .exchange_code(code, csrf.clone(), pkce, csrf, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn token_errors_keep_the_oauth_response_and_are_classified() {
+    let server = MockSmartServer::start().await;
+    server.register_client(MockClient::with_secret("cid", REDIRECT, "s3cret"));
+    let client = EpicFhirClient::new(config(&server, Some("s3cret"))).unwrap();
+    let (code, state, csrf, pkce) = authorize(&client).await;
+    let exchange = || {
+        client.exchange_code(
+            code.clone(),
+            csrf.clone(),
+            pkce.clone(),
+            state.clone(),
+            None,
+        )
+    };
+
+    server.fail_next(Failure::InvalidGrant);
+    let err = exchange().await.unwrap_err();
+    match &err {
+        EpicError::TokenEndpoint(response) => {
+            assert_eq!(response.error, BasicErrorResponseType::InvalidGrant);
+            assert!(response.description.is_some());
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+    assert_eq!(err.class(), ErrorClass::UserActionable);
+
+    server.fail_next(Failure::Unavailable);
+    let err = exchange().await.unwrap_err();
+    assert!(matches!(&err, EpicError::TokenEndpoint(response)
+            if response.error == BasicErrorResponseType::Extension("temporarily_unavailable".into())));
+    assert!(err.is_retryable());
+
+    server.fail_next(Failure::MalformedResponse);
+    let err = exchange().await.unwrap_err();
+    assert!(matches!(err, EpicError::InvalidResponse(_)));
+    assert!(err.is_retryable());
+
+    server.fail_next(Failure::InvalidClient);
+    assert_eq!(exchange().await.unwrap_err().class(), ErrorClass::Fatal);
+
+    // Nothing listens on port 1.
+    let mut unreachable = config(&server, Some("s3cret"));
+    unreachable.token_url = "http://127.0.0.1:1/oauth2/token".to_string();
+    let err = EpicFhirClient::new(unreachable)
+        .unwrap()
+        .exchange_code(
+            code.clone(),
+            csrf.clone(),
+            pkce.clone(),
+            state.clone(),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, EpicError::Transport(_)));
+    assert!(err.is_retryable());
+
+    // The failures did not consume the code.
+    assert!(exchange().await.is_ok());
+}
+
+#[test]
+fn local_request_failures_are_fatal() {
+    type TokenError = oauth2::RequestTokenError<
+        oauth2::HttpClientError<reqwest::Error>,
+        oauth2::basic::BasicErrorResponse,
+    >;
+
+    let err = EpicError::from(TokenError::Other(
+        "failed to prepare request: invalid header".to_string(),
+    ));
+    assert!(matches!(err, EpicError::Other(_)));
+    assert_eq!(err.class(), ErrorClass::Fatal);
+
+    // A response the server should not have sent stays retryable
+    let err = EpicError::from(TokenError::Other(
+        "server returned empty response body".to_string(),
+    ));
+    assert!(err.is_retryable());
+
+    let err = EpicError::from(TokenError::Request(oauth2::HttpClientError::Other(
+        "key unavailable".to_string(),
+    )));
+    assert!(matches!(&err, EpicError::JwtEncodingError(s) if s.contains("DPoP proof")));
+    assert_eq!(err.class(), ErrorClass::Fatal);
+}
+
+#[tokio::test]
+async fn pushes_the_authorization_request() {
+    let server = MockSmartServer::start().await;
+    server.register_client(MockClient::with_secret("cid", REDIRECT, "s3cret"));
+    server.require_par(true);
+    let client =
+        EpicFhirClient::new(config(&server, Some("s3cret")).with_par_url(server.par_url()))
+            .unwrap();
+
+    let (url, _, _) = client.authorization_url().await.unwrap();
+    let names: Vec<String> = url
+        .query_pairs()
+        .map(|(name, _)| name.into_owned())
+        .collect();
+    assert_eq!(names, ["client_id", "request_uri"]);
+
+    let (code, state, csrf, pkce) = authorize(&client).await;
+    assert!(client
+        .exchange_code(code, csrf, pkce, state, None)
+        .await
+        .is_ok());
+
+    let wrong_secret =
+        EpicFhirClient::new(config(&server, Some("guess")).with_par_url(server.par_url())).unwrap();
+    let err = wrong_secret.authorization_url().await.unwrap_err();
+    assert!(matches!(&err, EpicError::PushedAuthorization(response)
+            if response.error == BasicErrorResponseType::InvalidClient));
+    assert_eq!(err.class(), ErrorClass::Fatal);
+}
+
+#[tokio::test]
+async fn dpop_bound_tokens_follow_server_nonces() {
+    let server = MockSmartServer::start().await;
+    server.register_client(MockClient::public("cid", REDIRECT));
+    server.require_dpop_nonce("n-1");
+    let client = EpicFhirClient::new(config(&server, None).with_dpop()).unwrap();
+    let key = client.new_dpop_key().unwrap().unwrap();
+
+    // The first proof has no nonce; the client signs again after `use_dpop_nonce`.
+    let (code, state, csrf, pkce) = authorize(&client).await;
+    let response = client
+        .exchange_code(code, csrf, pkce, state, Some(&key))
+        .await
+        .unwrap();
+    // `oauth2` lowercases token types it does not know.
+    assert_eq!(
+        response.token_type(),
+        &BasicTokenType::Extension("dpop".to_string())
+    );
+    let issued = server
+        .access_token(response.access_token().secret())
+        .unwrap();
+    assert_eq!(issued.jkt, Some(key.jkt()));
+    let now = OffsetDateTime::now_utc();
+    let tokens = TokenSet::from_response(&response, now).bound_to(&response, Some(key.clone()));
+    assert!(tokens.dpop.is_some());
+
+    let http = reqwest::Client::new();
+    let patient_url = format!("{}/Patient/{}", server.fhir_base_url(), DEFAULT_PATIENT);
+    let read = || async {
+        tokens
+            .send(
+                &http,
+                http.get(&patient_url).build().unwrap(),
+                OffsetDateTime::now_utc(),
+            )
+            .await
+            .unwrap()
+            .status()
+    };
+    assert_eq!(read().await, reqwest::StatusCode::OK);
+    // The resource server rotates its nonce: 401 `use_dpop_nonce`, then a retry.
+    server.require_dpop_nonce("n-2");
+    assert_eq!(read().await, reqwest::StatusCode::OK);
+
+    // Presented as a bearer token, the bound token is useless.
+    let stolen = http
+        .get(&patient_url)
+        .bearer_auth(response.access_token().secret())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stolen.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // The refresh token is bound to the same key.
+    let refresh_token = Secret::new(response.refresh_token().unwrap().secret());
+    let refreshed = client
+        .refresh_token(&refresh_token, Some(&key))
+        .await
+        .unwrap();
+    assert_eq!(
+        tokens.refreshed(&refreshed, now).dpop.map(|key| key.jkt()),
+        Some(key.jkt())
+    );
+    let other_key = Arc::new(crate::dpop::DpopKey::generate().unwrap());
+    let refresh_token = Secret::new(refreshed.refresh_token().unwrap().secret());
+    assert!(client
+        .refresh_token(&refresh_token, Some(&other_key))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn backend_services_tokens_are_cached_and_renewed() {
+    let server = MockSmartServer::start().await;
+    let keys = register_signing_keys(&server).await;
+    let client = BackendServicesClient::new(BackendServicesConfig::new(
+        "cid".to_string(),
+        server.token_url(),
+        vec!["system/Group.read".to_string()],
+        keys.clone(),
+    ))
+    .unwrap();
+
+    let tokens = client.token().await.unwrap();
+    assert!(tokens.refresh_token.is_none());
+    let issued = server.access_token(tokens.access_token.expose()).unwrap();
+    assert_eq!(issued.subject, "cid");
+    assert_eq!(issued.scope, "system/Group.read");
+    let cached = client.token().await.unwrap();
+    assert_eq!(cached.access_token.expose(), tokens.access_token.expose());
+
+    client.invalidate();
+    let renewed = client.token().await.unwrap();
+    assert_ne!(renewed.access_token.expose(), tokens.access_token.expose());
+
+    // A token that expires within the renewal margin is never handed out.
+    server.set_token_lifetime(time::Duration::seconds(30));
+    client.invalidate();
+    let short = client.token().await.unwrap();
+    assert_ne!(
+        client.token().await.unwrap().access_token.expose(),
+        short.access_token.expose()
+    );
+
+    let patient_scoped = BackendServicesClient::new(BackendServicesConfig::new(
+        "cid".to_string(),
+        server.token_url(),
+        vec!["patient/*.read".to_string()],
+        keys,
+    ))
+    .unwrap();
+    match patient_scoped.token().await {
+        Err(EpicError::TokenEndpoint(response)) => {
+            assert_eq!(response.error, BasicErrorResponseType::InvalidScope)
+        }
+        other => panic!("expected invalid_scope, got {:?}", other.map(|_| ())),
     }
 }
@@ -0,0 +1,139 @@
+//! Mã hóa entry và băm key: cả bộ nhớ lẫn Redis không chứa PHI ở dạng rõ.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+
+use crate::cache::key::CacheKey;
+
+const NONCE_LEN: usize = 12;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub(crate) struct CacheCipher {
+    aead: Aes256Gcm,
+    index_key: [u8; 32],
+}
+
+impl CacheCipher {
+    /// Khóa AES và khóa băm đều dẫn xuất từ `secret`, mỗi khóa một nhãn.
+    pub(crate) fn new(secret: &[u8]) -> Self {
+        let aead_key = derive(secret, b"summary-cache/encryption");
+        Self {
+            aead: Aes256Gcm::new(&aead_key.into()),
+            index_key: derive(secret, b"summary-cache/index"),
+        }
+    }
+
+    /// Khóa ngẫu nhiên, chỉ sống trong process này.
+    pub(crate) fn random() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self::new(&secret)
+    }
+
+    pub(crate) fn entry_key(&self, key: &CacheKey) -> String {
+        self.hash(
+            "summary",
+            &[
+                &key.tenant,
+                &key.patient,
+                &key.subject,
+                &key.scope,
+                &key.resource_type,
+            ],
+        )
+    }
+
+    pub(crate) fn patient_index(&self, tenant: &str, patient: &str) -> String {
+        self.hash("summary:patient", &[tenant, patient])
+    }
+
+    pub(crate) fn user_index(&self, tenant: &str, subject: &str) -> String {
+        self.hash("summary:user", &[tenant, subject])
+    }
+
+    /// `nonce || ciphertext`, gắn với `storage_key` qua AAD để không thể tráo
+    /// entry của người này sang key của người khác.
+    pub(crate) fn seal(&self, storage_key: &str, plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: plaintext,
+            aad: storage_key.as_bytes(),
+        };
+        let ciphertext = self
+            .aead
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        sealed
+    }
+
+    /// `None` nếu entry bị sửa, bị tráo key hoặc mã hóa bằng khóa khác.
+    pub(crate) fn open(&self, storage_key: &str, sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: storage_key.as_bytes(),
+        };
+        self.aead.decrypt(Nonce::from_slice(nonce), payload).ok()
+    }
+
+    fn hash(&self, prefix: &str, parts: &[&str]) -> String {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.index_key)
+            .expect("HMAC accepts keys of any length");
+        for part in parts {
+            // Độ dài đi trước mỗi phần để ("ab", "c") khác ("a", "bc")
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part.as_bytes());
+        }
+        format!(
+            "{}:{}",
+            prefix,
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        )
+    }
+}
+
+fn derive(secret: &[u8], label: &[u8]) -> [u8; 32] {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(label);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_entry_is_bound_to_its_key() {
+        let cipher = CacheCipher::new(b"0123456789abcdef0123456789abcdef");
+        let key = CacheKey::new("epic", "p1", "u1", "patient/*.read", "PatientSummary");
+        let storage_key = cipher.entry_key(&key);
+        assert!(!storage_key.contains("p1"));
+
+        let sealed = cipher.seal(&storage_key, b"{\"name\":\"Camila\"}");
+        assert!(!sealed.windows(6).any(|window| window == b"Camila"));
+        assert_eq!(
+            cipher.open(&storage_key, &sealed).as_deref(),
+            Some(&b"{\"name\":\"Camila\"}"[..])
+        );
+
+        let other = cipher.entry_key(&CacheKey {
+            subject: "u2".into(),
+            ..key
+        });
+        assert!(cipher.open(&other, &sealed).is_none());
+        assert!(CacheCipher::random().open(&storage_key, &sealed).is_none());
+    }
+}
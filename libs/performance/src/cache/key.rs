@@ -0,0 +1,32 @@
+/// Định danh một entry: cùng bệnh nhân nhưng khác người xem hoặc khác scope
+/// là hai entry riêng, vì dữ liệu trả về phụ thuộc quyền của người xem.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// OAuth client (tenant) mà session đăng nhập qua
+    pub tenant: String,
+    pub patient: String,
+    /// `sub` của người dùng đang xem
+    pub subject: String,
+    /// Scope đã được cấp, nguyên văn
+    pub scope: String,
+    /// Loại resource, quyết định TTL
+    pub resource_type: String,
+}
+
+impl CacheKey {
+    pub fn new(
+        tenant: impl Into<String>,
+        patient: impl Into<String>,
+        subject: impl Into<String>,
+        scope: impl Into<String>,
+        resource_type: impl Into<String>,
+    ) -> Self {
+        Self {
+            tenant: tenant.into(),
+            patient: patient.into(),
+            subject: subject.into(),
+            scope: scope.into(),
+            resource_type: resource_type.into(),
+        }
+    }
+}
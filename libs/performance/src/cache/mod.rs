@@ -0,0 +1,683 @@
+//! Cache summary bệnh nhân theo kiểu stale-while-revalidate.
+//!
+//! Entry còn mới được trả ngay; entry đã cũ nhưng chưa quá `stale` được trả
+//! ngay trong khi một task nền lấy bản mới; ngoài khoảng đó thì lấy trực tiếp.
+//! Mỗi entry được mã hóa AES-256-GCM và lưu dưới key đã băm, cả trong bộ nhớ
+//! (LRU có giới hạn) lẫn ở [`CacheStore`] dùng chung nếu có.
+//!
+//! ```ignore
+//! let key = CacheKey::new("epic_sandbox", patient, subject, scope, "PatientSummary");
+//! let cached = cache
+//!     .get_or_refresh(&key, OffsetDateTime::now_utc(), move || async move { fetch().await })
+//!     .await?;
+//! ```
+
+mod crypto;
+pub mod key;
+pub mod store;
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use config_lib::settings::SummaryCacheSettings;
+use lru::LruCache;
+use time::OffsetDateTime;
+
+use crate::cache::crypto::CacheCipher;
+
+pub use key::CacheKey;
+pub use store::{CacheStore, LocalStore, RedisStore, StoreError};
+
+/// Hạn sống của entry theo loại resource.
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    pub default_ttl: Duration,
+    pub ttl: HashMap<String, Duration>,
+    /// Thời gian entry hết mới vẫn được trả trong lúc làm mới ở nền
+    pub stale: Duration,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            default_ttl: Duration::from_secs(300),
+            ttl: HashMap::new(),
+            stale: Duration::from_secs(600),
+        }
+    }
+}
+
+impl CachePolicy {
+    pub fn from_settings(settings: &SummaryCacheSettings) -> Self {
+        Self {
+            default_ttl: Duration::from_secs(settings.default_ttl_secs),
+            ttl: settings
+                .ttl_secs
+                .iter()
+                .map(|(resource_type, secs)| (resource_type.clone(), Duration::from_secs(*secs)))
+                .collect(),
+            stale: Duration::from_secs(settings.stale_secs),
+        }
+    }
+
+    pub fn ttl(&self, resource_type: &str) -> Duration {
+        self.ttl
+            .get(resource_type)
+            .copied()
+            .unwrap_or(self.default_ttl)
+    }
+
+    /// Thời gian sống dài nhất của một entry, dùng cho index ở store.
+    fn max_lifetime(&self) -> Duration {
+        self.ttl
+            .values()
+            .copied()
+            .fold(self.default_ttl, Duration::max)
+            + self.stale
+    }
+}
+
+/// Nguồn của giá trị trả về.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Entry còn mới
+    Hit,
+    /// Entry đã cũ, bản mới đang được lấy ở nền
+    Stale,
+    /// Không có entry dùng được, giá trị vừa được lấy
+    Miss,
+}
+
+impl CacheStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Stale => "stale",
+            CacheStatus::Miss => "miss",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cached {
+    pub value: Vec<u8>,
+    pub status: CacheStatus,
+}
+
+/// Cache dùng chung; clone chỉ tăng reference count.
+#[derive(Clone)]
+pub struct SummaryCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    cipher: CacheCipher,
+    policy: CachePolicy,
+    memory: Mutex<Memory>,
+    store: Option<Arc<dyn CacheStore>>,
+    /// Key đang được làm mới ở nền, để mỗi key chỉ có một task
+    refreshing: Mutex<HashSet<String>>,
+    /// Tăng sau mỗi lần invalidate. Giá trị lấy về trước lần invalidate không
+    /// được ghi vào cache, kẻo dữ liệu vừa bị xóa (ví dụ khi logout) quay lại.
+    generation: AtomicU64,
+}
+
+impl SummaryCache {
+    /// `secret` bỏ trống thì dùng khóa ngẫu nhiên: entry chỉ đọc được trong process này.
+    pub fn new(
+        policy: CachePolicy,
+        max_entries: NonZeroUsize,
+        secret: Option<&[u8]>,
+        store: Option<Arc<dyn CacheStore>>,
+    ) -> Self {
+        let cipher = secret.map_or_else(CacheCipher::random, CacheCipher::new);
+        Self {
+            inner: Arc::new(Inner {
+                cipher,
+                policy,
+                memory: Mutex::new(Memory::new(max_entries)),
+                store,
+                refreshing: Mutex::new(HashSet::new()),
+                generation: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Cache từ mục `summary_cache`; có `redis_url` thì dùng [`RedisStore`].
+    pub fn from_settings(settings: &SummaryCacheSettings) -> Result<Self, StoreError> {
+        let store = match settings.redis_url.as_deref() {
+            Some(url) => Some(Arc::new(RedisStore::open(url)?) as Arc<dyn CacheStore>),
+            None => None,
+        };
+        Ok(Self::new(
+            CachePolicy::from_settings(settings),
+            NonZeroUsize::new(settings.max_entries).unwrap_or(NonZeroUsize::MIN),
+            settings
+                .encryption_key
+                .as_ref()
+                .map(|key| key.expose().as_bytes()),
+            store,
+        ))
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.inner.policy
+    }
+
+    /// Số entry đang giữ trong bộ nhớ.
+    pub fn len(&self) -> usize {
+        self.inner.memory.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Entry còn dùng được (mới hoặc trong khoảng stale), không kích hoạt làm mới.
+    pub async fn get(&self, key: &CacheKey, now: OffsetDateTime) -> Option<Cached> {
+        let storage_key = self.inner.cipher.entry_key(key);
+        let sealed = self
+            .inner
+            .memory
+            .lock()
+            .unwrap()
+            .entries
+            .get(&storage_key)
+            .map(|entry| entry.sealed.clone());
+        if let Some(cached) = sealed.and_then(|sealed| self.read(&storage_key, &sealed, now)) {
+            return Some(cached);
+        }
+
+        let store = self.inner.store.as_ref()?;
+        let sealed = match store.get(&storage_key).await {
+            Ok(sealed) => sealed?,
+            Err(e) => {
+                tracing::warn!(error = %e, "summary cache store unavailable");
+                return None;
+            }
+        };
+        let cached = self.read(&storage_key, &sealed, now)?;
+        let generation = self.inner.generation.load(Ordering::SeqCst);
+        self.remember(key, storage_key, sealed, generation);
+        Some(cached)
+    }
+
+    /// Ghi `value` cho `key`, tính hạn từ `now`.
+    pub async fn insert(&self, key: &CacheKey, value: &[u8], now: OffsetDateTime) {
+        let generation = self.inner.generation.load(Ordering::SeqCst);
+        self.insert_if_current(key, value, now, generation).await;
+    }
+
+    /// Trả entry nếu còn dùng được, ngược lại gọi `fetch` và ghi kết quả.
+    ///
+    /// Với entry stale, `fetch` chạy trong một task nền và lỗi của nó chỉ được ghi log.
+    pub async fn get_or_refresh<F, Fut, E>(
+        &self,
+        key: &CacheKey,
+        now: OffsetDateTime,
+        fetch: F,
+    ) -> Result<Cached, E>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<u8>, E>> + Send + 'static,
+        E: std::fmt::Debug + Send + 'static,
+    {
+        match self.get(key, now).await {
+            Some(cached) if cached.status == CacheStatus::Hit => Ok(cached),
+            Some(cached) => {
+                self.spawn_refresh(key.clone(), now, fetch);
+                Ok(cached)
+            }
+            None => {
+                let generation = self.inner.generation.load(Ordering::SeqCst);
+                let value = fetch().await?;
+                self.insert_if_current(key, &value, now, generation).await;
+                Ok(Cached {
+                    value,
+                    status: CacheStatus::Miss,
+                })
+            }
+        }
+    }
+
+    /// Xóa một entry.
+    pub async fn invalidate(&self, key: &CacheKey) {
+        let storage_key = self.inner.cipher.entry_key(key);
+        {
+            let mut memory = self.inner.memory.lock().unwrap();
+            self.inner.generation.fetch_add(1, Ordering::SeqCst);
+            memory.remove(&storage_key);
+        }
+        self.delete_from_store(vec![storage_key]).await;
+    }
+
+    /// Xóa mọi entry của một bệnh nhân, cho mọi người xem; gọi khi dữ liệu bệnh nhân đổi.
+    pub async fn invalidate_patient(&self, tenant: &str, patient: &str) {
+        let index = self.inner.cipher.patient_index(tenant, patient);
+        self.invalidate_index(&index, |memory| &mut memory.patients)
+            .await;
+    }
+
+    /// Xóa mọi entry mà một người dùng đã xem; gọi khi logout.
+    pub async fn invalidate_user(&self, tenant: &str, subject: &str) {
+        let index = self.inner.cipher.user_index(tenant, subject);
+        self.invalidate_index(&index, |memory| &mut memory.users)
+            .await;
+    }
+
+    async fn invalidate_index(
+        &self,
+        index: &str,
+        select: impl FnOnce(&mut Memory) -> &mut HashMap<String, HashSet<String>>,
+    ) {
+        let mut keys: Vec<String> = {
+            let mut memory = self.inner.memory.lock().unwrap();
+            self.inner.generation.fetch_add(1, Ordering::SeqCst);
+            let keys = select(&mut memory).remove(index).unwrap_or_default();
+            for key in &keys {
+                memory.remove(key);
+            }
+            keys.into_iter().collect()
+        };
+        if let Some(store) = &self.inner.store {
+            match store.take_index(index).await {
+                Ok(members) => keys.extend(members),
+                Err(e) => tracing::warn!(error = %e, "summary cache store unavailable"),
+            }
+        }
+        self.delete_from_store(keys).await;
+    }
+
+    fn spawn_refresh<F, Fut, E>(&self, key: CacheKey, now: OffsetDateTime, fetch: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<u8>, E>> + Send + 'static,
+        E: std::fmt::Debug + Send + 'static,
+    {
+        let storage_key = self.inner.cipher.entry_key(&key);
+        if !self
+            .inner
+            .refreshing
+            .lock()
+            .unwrap()
+            .insert(storage_key.clone())
+        {
+            return;
+        }
+        let generation = self.inner.generation.load(Ordering::SeqCst);
+        let cache = self.clone();
+        tokio::spawn(async move {
+            match fetch().await {
+                Ok(value) => cache.insert_if_current(&key, &value, now, generation).await,
+                Err(e) => tracing::warn!(error = ?e, "summary cache refresh failed"),
+            }
+            cache.inner.refreshing.lock().unwrap().remove(&storage_key);
+        });
+    }
+
+    async fn insert_if_current(
+        &self,
+        key: &CacheKey,
+        value: &[u8],
+        now: OffsetDateTime,
+        generation: u64,
+    ) {
+        let fresh_until = now + self.inner.policy.ttl(&key.resource_type);
+        let expires_at = fresh_until + self.inner.policy.stale;
+        let storage_key = self.inner.cipher.entry_key(key);
+        let mut plaintext = Vec::with_capacity(16 + value.len());
+        plaintext.extend_from_slice(&fresh_until.unix_timestamp().to_be_bytes());
+        plaintext.extend_from_slice(&expires_at.unix_timestamp().to_be_bytes());
+        plaintext.extend_from_slice(value);
+        let sealed = self.inner.cipher.seal(&storage_key, &plaintext);
+
+        if !self.remember(key, storage_key.clone(), sealed.clone(), generation) {
+            return;
+        }
+        let Some(store) = &self.inner.store else {
+            return;
+        };
+        let lifetime = self.inner.policy.max_lifetime();
+        let written = async {
+            let ttl = (expires_at - now).unsigned_abs();
+            store.set(&storage_key, &sealed, ttl).await?;
+            let patient_index = self.inner.cipher.patient_index(&key.tenant, &key.patient);
+            store
+                .add_to_index(&patient_index, &storage_key, lifetime)
+                .await?;
+            let user_index = self.inner.cipher.user_index(&key.tenant, &key.subject);
+            store
+                .add_to_index(&user_index, &storage_key, lifetime)
+                .await
+        };
+        if let Err(e) = written.await {
+            tracing::warn!(error = %e, "summary cache store unavailable");
+        }
+        // Một lần invalidate chen vào giữa lúc đang ghi store: xóa bản vừa ghi.
+        if self.inner.generation.load(Ordering::SeqCst) != generation {
+            self.delete_from_store(vec![storage_key]).await;
+        }
+    }
+
+    /// Ghi vào bộ nhớ nếu chưa có lần invalidate nào kể từ `generation`.
+    fn remember(
+        &self,
+        key: &CacheKey,
+        storage_key: String,
+        sealed: Vec<u8>,
+        generation: u64,
+    ) -> bool {
+        let mut memory = self.inner.memory.lock().unwrap();
+        if self.inner.generation.load(Ordering::SeqCst) != generation {
+            return false;
+        }
+        let entry = Entry {
+            sealed,
+            patient_index: self.inner.cipher.patient_index(&key.tenant, &key.patient),
+            user_index: self.inner.cipher.user_index(&key.tenant, &key.subject),
+        };
+        memory.insert(storage_key, entry);
+        true
+    }
+
+    fn read(&self, storage_key: &str, sealed: &[u8], now: OffsetDateTime) -> Option<Cached> {
+        let plaintext = self.inner.cipher.open(storage_key, sealed)?;
+        if plaintext.len() < 16 {
+            return None;
+        }
+        let (times, value) = plaintext.split_at(16);
+        let fresh_until = i64::from_be_bytes(times[..8].try_into().ok()?);
+        let expires_at = i64::from_be_bytes(times[8..].try_into().ok()?);
+        let now = now.unix_timestamp();
+        let status = if now < fresh_until {
+            CacheStatus::Hit
+        } else if now < expires_at {
+            CacheStatus::Stale
+        } else {
+            return None;
+        };
+        Some(Cached {
+            value: value.to_vec(),
+            status,
+        })
+    }
+
+    async fn delete_from_store(&self, keys: Vec<String>) {
+        let Some(store) = &self.inner.store else {
+            return;
+        };
+        if let Err(e) = store.delete(&keys).await {
+            tracing::warn!(error = %e, "summary cache store unavailable");
+        }
+    }
+}
+
+struct Entry {
+    sealed: Vec<u8>,
+    patient_index: String,
+    user_index: String,
+}
+
+/// LRU cùng hai index, giữ đồng bộ với nhau dưới một lock.
+struct Memory {
+    entries: LruCache<String, Entry>,
+    patients: HashMap<String, HashSet<String>>,
+    users: HashMap<String, HashSet<String>>,
+}
+
+impl Memory {
+    fn new(max_entries: NonZeroUsize) -> Self {
+        Self {
+            entries: LruCache::new(max_entries),
+            patients: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, storage_key: String, entry: Entry) {
+        add_to_index(&mut self.patients, &entry.patient_index, &storage_key);
+        add_to_index(&mut self.users, &entry.user_index, &storage_key);
+        // `push` trả về entry bị đẩy ra do đầy, hoặc bản cũ của chính key này
+        if let Some((evicted_key, evicted)) = self.entries.push(storage_key.clone(), entry)
+            && evicted_key != storage_key
+        {
+            self.unindex(&evicted_key, &evicted);
+        }
+    }
+
+    fn remove(&mut self, storage_key: &str) {
+        if let Some(entry) = self.entries.pop(storage_key) {
+            self.unindex(storage_key, &entry);
+        }
+    }
+
+    fn unindex(&mut self, storage_key: &str, entry: &Entry) {
+        remove_from_index(&mut self.patients, &entry.patient_index, storage_key);
+        remove_from_index(&mut self.users, &entry.user_index, storage_key);
+    }
+}
+
+fn add_to_index(indexes: &mut HashMap<String, HashSet<String>>, index: &str, storage_key: &str) {
+    indexes
+        .entry(index.to_string())
+        .or_default()
+        .insert(storage_key.to_string());
+}
+
+fn remove_from_index(
+    indexes: &mut HashMap<String, HashSet<String>>,
+    index: &str,
+    storage_key: &str,
+) {
+    if let Some(keys) = indexes.get_mut(index) {
+        keys.remove(storage_key);
+        if keys.is_empty() {
+            indexes.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn at(secs: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(1_700_000_000 + secs).unwrap()
+    }
+
+    fn key(patient: &str, subject: &str) -> CacheKey {
+        CacheKey::new("epic", patient, subject, "patient/*.read", "PatientSummary")
+    }
+
+    fn cache(max_entries: usize, store: Option<Arc<dyn CacheStore>>) -> SummaryCache {
+        let policy = CachePolicy {
+            default_ttl: Duration::from_secs(60),
+            ttl: HashMap::from([("Observation".to_string(), Duration::from_secs(10))]),
+            stale: Duration::from_secs(30),
+        };
+        SummaryCache::new(
+            policy,
+            NonZeroUsize::new(max_entries).unwrap(),
+            Some(SECRET),
+            store,
+        )
+    }
+
+    async fn wait_for_refresh(cache: &SummaryCache) {
+        while !cache.inner.refreshing.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    fn counting_fetch(
+        calls: &Arc<AtomicUsize>,
+        value: &'static str,
+    ) -> impl FnOnce() -> std::future::Ready<Result<Vec<u8>, String>> + Send + 'static {
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(Ok(value.as_bytes().to_vec()))
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_stale_entry_while_refreshing_in_background() {
+        let cache = cache(10, None);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let key = key("p1", "u1");
+
+        let first = cache
+            .get_or_refresh(&key, at(0), counting_fetch(&calls, "v1"))
+            .await
+            .unwrap();
+        assert_eq!(first.status, CacheStatus::Miss);
+
+        let hit = cache
+            .get_or_refresh(&key, at(59), counting_fetch(&calls, "unused"))
+            .await
+            .unwrap();
+        assert_eq!(
+            (hit.value.as_slice(), hit.status),
+            (&b"v1"[..], CacheStatus::Hit)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let stale = cache
+            .get_or_refresh(&key, at(60), counting_fetch(&calls, "v2"))
+            .await
+            .unwrap();
+        assert_eq!(
+            (stale.value.as_slice(), stale.status),
+            (&b"v1"[..], CacheStatus::Stale)
+        );
+        wait_for_refresh(&cache).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let refreshed = cache.get(&key, at(61)).await.unwrap();
+        assert_eq!(
+            (refreshed.value.as_slice(), refreshed.status),
+            (&b"v2"[..], CacheStatus::Hit)
+        );
+
+        // Hết cả khoảng stale: lấy lại trực tiếp
+        let expired = cache
+            .get_or_refresh(&key, at(60 + 90), counting_fetch(&calls, "v3"))
+            .await
+            .unwrap();
+        assert_eq!(
+            (expired.value.as_slice(), expired.status),
+            (&b"v3"[..], CacheStatus::Miss)
+        );
+    }
+
+    #[tokio::test]
+    async fn ttl_depends_on_resource_type() {
+        let cache = cache(10, None);
+        let observations = CacheKey {
+            resource_type: "Observation".into(),
+            ..key("p1", "u1")
+        };
+        cache.insert(&observations, b"obs", at(0)).await;
+        assert_eq!(
+            cache.get(&observations, at(10)).await.unwrap().status,
+            CacheStatus::Stale
+        );
+        assert!(cache.get(&observations, at(40)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn invalidates_by_patient_and_by_user() {
+        let cache = cache(10, None);
+        cache.insert(&key("p1", "u1"), b"a", at(0)).await;
+        cache.insert(&key("p1", "u2"), b"b", at(0)).await;
+        cache.insert(&key("p2", "u1"), b"c", at(0)).await;
+
+        cache.invalidate_patient("epic", "p1").await;
+        assert!(cache.get(&key("p1", "u1"), at(1)).await.is_none());
+        assert!(cache.get(&key("p1", "u2"), at(1)).await.is_none());
+        assert!(cache.get(&key("p2", "u1"), at(1)).await.is_some());
+
+        cache.insert(&key("p1", "u2"), b"b", at(0)).await;
+        cache.invalidate_user("epic", "u1").await;
+        assert!(cache.get(&key("p2", "u1"), at(1)).await.is_none());
+        assert!(cache.get(&key("p1", "u2"), at(1)).await.is_some());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn eviction_keeps_indexes_in_sync() {
+        let cache = cache(2, None);
+        cache.insert(&key("p1", "u1"), b"a", at(0)).await;
+        cache.insert(&key("p2", "u1"), b"b", at(0)).await;
+        cache.insert(&key("p3", "u1"), b"c", at(0)).await;
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key("p1", "u1"), at(1)).await.is_none());
+        let memory = cache.inner.memory.lock().unwrap();
+        assert_eq!(memory.patients.len(), 2);
+        assert_eq!(memory.users.values().map(HashSet::len).sum::<usize>(), 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_started_before_logout_is_discarded() {
+        let cache = cache(10, None);
+        let key = key("p1", "u1");
+        cache.insert(&key, b"v1", at(0)).await;
+
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let stale = cache
+            .get_or_refresh(&key, at(70), move || async move {
+                released.await.ok();
+                Ok::<_, String>(b"v2".to_vec())
+            })
+            .await
+            .unwrap();
+        assert_eq!(stale.status, CacheStatus::Stale);
+
+        cache.invalidate_user("epic", "u1").await;
+        release.send(()).unwrap();
+        wait_for_refresh(&cache).await;
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn shared_store_holds_only_encrypted_entries() {
+        let store = Arc::new(LocalStore::new());
+        let first = cache(10, Some(store.clone()));
+        let second = cache(10, Some(store.clone()));
+
+        first
+            .insert(&key("p1", "u1"), b"{\"name\":\"Camila Lopez\"}", at(0))
+            .await;
+        assert_eq!(store.len(), 1);
+        assert!(
+            store
+                .raw_values()
+                .iter()
+                .all(|raw| !raw.windows(6).any(|window| window == b"Camila"))
+        );
+
+        let shared = second.get(&key("p1", "u1"), at(1)).await.unwrap();
+        assert_eq!(shared.value, b"{\"name\":\"Camila Lopez\"}");
+
+        // Logout ở instance này xóa cả bản trong store
+        first.invalidate_user("epic", "u1").await;
+        assert!(store.is_empty());
+
+        // Khóa khác không đọc được entry của store
+        first.insert(&key("p1", "u1"), b"x", at(0)).await;
+        let stranger = SummaryCache::new(
+            CachePolicy::default(),
+            NonZeroUsize::MIN,
+            None,
+            Some(store.clone()),
+        );
+        assert!(stranger.get(&key("p1", "u1"), at(1)).await.is_none());
+    }
+}
@@ -0,0 +1,205 @@
+//! Tầng lưu trữ dùng chung giữa các instance (Redis hoặc server tương thích).
+//!
+//! Store chỉ thấy key đã băm và entry đã mã hóa. [`LocalStore`] là stand-in
+//! chạy trong process, dùng cho dev và test.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use tokio::sync::OnceCell;
+
+/// Lỗi từ tầng lưu trữ; cache coi như miss và chỉ ghi log.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+}
+
+/// Key-value có hạn sống, kèm tập key (index) để xóa theo bệnh nhân/người dùng.
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError>;
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), StoreError>;
+
+    async fn delete(&self, keys: &[String]) -> Result<(), StoreError>;
+
+    /// Thêm `member` vào index `index` và đặt lại hạn sống của index thành `ttl`.
+    async fn add_to_index(
+        &self,
+        index: &str,
+        member: &str,
+        ttl: Duration,
+    ) -> Result<(), StoreError>;
+
+    /// Lấy và xóa toàn bộ index.
+    async fn take_index(&self, index: &str) -> Result<Vec<String>, StoreError>;
+}
+
+/// Redis qua một multiplexed connection, mở ở lần dùng đầu tiên.
+pub struct RedisStore {
+    client: redis::Client,
+    connection: OnceCell<MultiplexedConnection>,
+}
+
+impl RedisStore {
+    /// Chỉ kiểm tra URL, chưa kết nối.
+    pub fn open(url: &str) -> Result<Self, StoreError> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: OnceCell::new(),
+        })
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, StoreError> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| self.client.get_multiplexed_async_connection())
+            .await?;
+        Ok(connection.clone())
+    }
+}
+
+#[async_trait]
+impl CacheStore for RedisStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        let mut connection = self.connection().await?;
+        Ok(redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut connection)
+            .await?)
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), StoreError> {
+        let mut connection = self.connection().await?;
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async::<()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<(), StoreError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let mut connection = self.connection().await?;
+        redis::cmd("DEL")
+            .arg(keys)
+            .query_async::<()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_to_index(
+        &self,
+        index: &str,
+        member: &str,
+        ttl: Duration,
+    ) -> Result<(), StoreError> {
+        let mut connection = self.connection().await?;
+        redis::pipe()
+            .atomic()
+            .cmd("SADD")
+            .arg(index)
+            .arg(member)
+            .ignore()
+            .cmd("PEXPIRE")
+            .arg(index)
+            .arg(ttl.as_millis().max(1) as u64)
+            .ignore()
+            .query_async::<()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn take_index(&self, index: &str) -> Result<Vec<String>, StoreError> {
+        let mut connection = self.connection().await?;
+        let (members,): (Vec<String>,) = redis::pipe()
+            .atomic()
+            .cmd("SMEMBERS")
+            .arg(index)
+            .cmd("DEL")
+            .arg(index)
+            .ignore()
+            .query_async(&mut connection)
+            .await?;
+        Ok(members)
+    }
+}
+
+/// Stand-in cục bộ cho Redis. Không tự hết hạn entry: cache kiểm tra hạn
+/// trong từng entry nên kết quả vẫn đúng, chỉ tốn bộ nhớ hơn.
+#[derive(Default)]
+pub struct LocalStore {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+    indexes: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl LocalStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Mọi giá trị đang lưu, để test kiểm tra không có PHI ở dạng rõ.
+    pub fn raw_values(&self) -> Vec<Vec<u8>> {
+        self.values.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl CacheStore for LocalStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: &[u8], _ttl: Duration) -> Result<(), StoreError> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<(), StoreError> {
+        let mut values = self.values.lock().unwrap();
+        for key in keys {
+            values.remove(key);
+        }
+        Ok(())
+    }
+
+    async fn add_to_index(
+        &self,
+        index: &str,
+        member: &str,
+        _ttl: Duration,
+    ) -> Result<(), StoreError> {
+        self.indexes
+            .lock()
+            .unwrap()
+            .entry(index.to_string())
+            .or_default()
+            .insert(member.to_string());
+        Ok(())
+    }
+
+    async fn take_index(&self, index: &str) -> Result<Vec<String>, StoreError> {
+        let members = self.indexes.lock().unwrap().remove(index);
+        Ok(members.into_iter().flatten().collect())
+    }
+}
@@ -1,14 +1,5 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+//! Các thành phần giúp gateway trả lời nhanh hơn mà không lộ dữ liệu bệnh nhân.
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub mod cache;
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub use cache::{CacheKey, CachePolicy, CacheStatus, Cached, SummaryCache};
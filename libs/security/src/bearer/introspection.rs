@@ -0,0 +1,215 @@
+//! Kiểm tra opaque token bằng OAuth 2.0 Token Introspection (RFC 7662).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use config_lib::Secret;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::error::BearerError;
+use crate::internal_token::Actor;
+
+/// Số kết quả tối đa giữ trong bộ nhớ đệm.
+const MAX_CACHED_RESPONSES: usize = 10_000;
+
+/// `aud` có thể là một chuỗi hoặc một mảng (RFC 7519 mục 4.1.3).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            Audience::One(aud) => vec![aud],
+            Audience::Many(auds) => auds,
+        }
+    }
+}
+
+/// Response của endpoint introspection (RFC 7662 mục 2.2).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub token_type: Option<String>,
+    #[serde(default)]
+    pub exp: Option<i64>,
+    #[serde(default)]
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub aud: Option<Audience>,
+    #[serde(default)]
+    pub iss: Option<String>,
+    /// Bối cảnh SMART, nếu authorization server trả về
+    #[serde(default)]
+    pub patient: Option<String>,
+    #[serde(default)]
+    pub sid: Option<String>,
+    #[serde(default)]
+    pub act: Option<Actor>,
+}
+
+/// Gọi endpoint introspection, xác thực bằng `client_secret_basic`.
+pub struct Introspector {
+    endpoint: String,
+    client_id: String,
+    client_secret: Secret,
+    http: reqwest::Client,
+    cache_ttl: Duration,
+    /// Kết quả `active: true` theo SHA-256 của token, không giữ token gốc
+    cache: Mutex<HashMap<[u8; 32], (Instant, IntrospectionResponse)>>,
+}
+
+impl std::fmt::Debug for Introspector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Introspector")
+            .field("endpoint", &self.endpoint)
+            .field("client_id", &self.client_id)
+            .field("cache_ttl", &self.cache_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Introspector {
+    pub fn new(
+        endpoint: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: Secret,
+        http: reqwest::Client,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            client_id: client_id.into(),
+            client_secret,
+            http,
+            cache_ttl: Duration::ZERO,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Nhớ kết quả của token còn hiệu lực trong `ttl` (không quá `exp` của token).
+    /// Token bị thu hồi trong khoảng này vẫn được chấp nhận, nên giữ `ttl` ngắn.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Hỏi authorization server về `token`. Token không còn hiệu lực trả về
+    /// response có `active: false`.
+    pub async fn introspect(&self, token: &str) -> Result<IntrospectionResponse, BearerError> {
+        let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        if let Some(response) = self.cached(&digest) {
+            return Ok(response);
+        }
+
+        let unavailable = |e: reqwest::Error| BearerError::Unavailable(e.to_string());
+        let response: IntrospectionResponse = self
+            .http
+            .post(&self.endpoint)
+            .basic_auth(
+                form_encode(&self.client_id),
+                Some(form_encode(self.client_secret.expose())),
+            )
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[("token", token), ("token_type_hint", "access_token")])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(unavailable)?
+            .json()
+            .await
+            .map_err(unavailable)?;
+
+        if response.active && !self.cache_ttl.is_zero() {
+            self.store(digest, &response);
+        }
+        Ok(response)
+    }
+
+    fn cached(&self, digest: &[u8; 32]) -> Option<IntrospectionResponse> {
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(digest) {
+            Some((expires_at, response)) if *expires_at > Instant::now() => Some(response.clone()),
+            Some(_) => {
+                cache.remove(digest);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn store(&self, digest: [u8; 32], response: &IntrospectionResponse) {
+        let mut ttl = self.cache_ttl;
+        if let Some(exp) = response.exp {
+            let remaining = exp - OffsetDateTime::now_utc().unix_timestamp();
+            ttl = ttl.min(Duration::from_secs(remaining.max(0) as u64));
+        }
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_RESPONSES {
+            cache.retain(|_, (expires_at, _)| *expires_at > now);
+            if cache.len() >= MAX_CACHED_RESPONSES {
+                cache.clear();
+            }
+        }
+        cache.insert(digest, (now + ttl, response.clone()));
+    }
+}
+
+/// Client id và secret phải được form-urlencode trước khi ghép vào header
+/// `Basic` (RFC 6749 mục 2.3.1).
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Json;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn form_encodes_client_credentials() {
+        let app = axum::Router::new().route(
+            "/introspect",
+            post(|headers: HeaderMap| async move {
+                let encoded = headers["authorization"]
+                    .to_str()
+                    .unwrap()
+                    .trim_start_matches("Basic ")
+                    .to_string();
+                let credentials = String::from_utf8(STANDARD.decode(encoded).unwrap()).unwrap();
+                Json(json!({
+                    "active": credentials == "patient%3Asummary:s%2F3+cret%25",
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/introspect", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let introspector = Introspector::new(
+            url,
+            "patient:summary",
+            Secret::new("s/3 cret%"),
+            reqwest::Client::new(),
+        );
+        assert!(introspector.introspect("opaque-1").await.unwrap().active);
+    }
+}
@@ -0,0 +1,227 @@
+//! JWKS của issuer, tải qua HTTP và giữ trong bộ nhớ.
+//!
+//! Bộ nhớ đệm được làm mới định kỳ ở background; gặp `kid` lạ (issuer vừa xoay
+//! key) thì tải lại ngay, nhưng không quá một lần mỗi [`MIN_REFETCH_INTERVAL`].
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{Algorithm, DecodingKey};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::error::BearerError;
+
+/// Khoảng cách tối thiểu giữa hai lần tải lại do gặp `kid` lạ.
+pub const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Một key công khai trong JWKS.
+#[derive(Clone)]
+pub struct VerificationKey {
+    pub key: DecodingKey,
+    /// Thuật toán được phép dùng với key này: `alg` của JWK nếu có, không thì
+    /// mọi thuật toán cùng họ với `kty`
+    pub algorithms: Vec<Algorithm>,
+}
+
+#[derive(Default)]
+struct Cache {
+    keys: HashMap<String, Arc<VerificationKey>>,
+    fetched_at: Option<Instant>,
+}
+
+#[derive(Deserialize)]
+struct JwkSetDocument {
+    keys: Vec<Value>,
+}
+
+/// JWKS tải từ `uri`.
+pub struct RemoteJwks {
+    uri: String,
+    http: reqwest::Client,
+    cache: RwLock<Cache>,
+    /// Gộp các lần tải lại đồng thời thành một request
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+impl std::fmt::Debug for RemoteJwks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cache = self.cache.read().unwrap();
+        f.debug_struct("RemoteJwks")
+            .field("uri", &self.uri)
+            .field("kids", &cache.keys.keys().collect::<Vec<_>>())
+            .field("fetched_at", &cache.fetched_at)
+            .finish()
+    }
+}
+
+impl RemoteJwks {
+    pub fn new(uri: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            uri: uri.into(),
+            http,
+            cache: RwLock::new(Cache::default()),
+            refresh_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Key có `kid`, tải lại JWKS nếu chưa có trong bộ nhớ đệm.
+    pub async fn key(&self, kid: &str) -> Result<Arc<VerificationKey>, BearerError> {
+        if let Some(key) = self.cached(kid) {
+            return Ok(key);
+        }
+
+        let fetched_at = {
+            let _guard = self.refresh_lock.lock().await;
+            // Một request khác có thể vừa tải xong trong lúc chờ lock
+            if let Some(key) = self.cached(kid) {
+                return Ok(key);
+            }
+            let fetched_at = self.cache.read().unwrap().fetched_at;
+            if fetched_at.is_none_or(|at| at.elapsed() >= MIN_REFETCH_INTERVAL) {
+                self.fetch().await?;
+            }
+            fetched_at
+        };
+        self.cached(kid).ok_or_else(|| {
+            tracing::debug!(kid, ?fetched_at, "kid not found in JWKS {}", self.uri);
+            BearerError::InvalidToken(format!("unknown kid '{}'", kid))
+        })
+    }
+
+    /// Tải lại JWKS ngay.
+    pub async fn refresh(&self) -> Result<(), BearerError> {
+        let _guard = self.refresh_lock.lock().await;
+        self.fetch().await
+    }
+
+    /// Làm mới JWKS mỗi `interval` cho tới khi `self` bị drop. Lần tải đầu tiên
+    /// chạy ngay. Lỗi chỉ được log, bộ nhớ đệm giữ key cũ.
+    pub fn spawn_refresh(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let jwks: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(jwks) = jwks.upgrade() else {
+                    break;
+                };
+                if let Err(e) = jwks.refresh().await {
+                    tracing::warn!("Failed to refresh JWKS from {}: {}", jwks.uri, e);
+                }
+            }
+        })
+    }
+
+    fn cached(&self, kid: &str) -> Option<Arc<VerificationKey>> {
+        self.cache.read().unwrap().keys.get(kid).cloned()
+    }
+
+    /// Gọi khi đang giữ `refresh_lock`.
+    async fn fetch(&self) -> Result<(), BearerError> {
+        let unavailable = |e: reqwest::Error| BearerError::Unavailable(e.to_string());
+        let document: JwkSetDocument = self
+            .http
+            .get(&self.uri)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(unavailable)?
+            .json()
+            .await
+            .map_err(unavailable)?;
+
+        let mut keys = HashMap::new();
+        for value in document.keys {
+            match parse_key(&value) {
+                Ok(Some((kid, key))) => {
+                    keys.insert(kid, Arc::new(key));
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Skipping JWK from {}: {}", self.uri, e),
+            }
+        }
+        tracing::debug!("Loaded {} keys from JWKS {}", keys.len(), self.uri);
+
+        let mut cache = self.cache.write().unwrap();
+        cache.keys = keys;
+        cache.fetched_at = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// Chuyển một JWK thành key kiểm tra chữ ký. Bỏ qua key không có `kid`, key
+/// chỉ dùng để mã hóa (`use: enc`) và key đối xứng.
+fn parse_key(value: &Value) -> Result<Option<(String, VerificationKey)>, String> {
+    let Some(kid) = value.get("kid").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    if value.get("use").and_then(Value::as_str) == Some("enc") {
+        return Ok(None);
+    }
+    let family: &[Algorithm] = match value.get("kty").and_then(Value::as_str) {
+        Some("RSA") => &[
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        Some("EC") => &[Algorithm::ES256, Algorithm::ES384],
+        Some("OKP") => &[Algorithm::EdDSA],
+        _ => return Ok(None),
+    };
+    let algorithms = match value.get("alg").and_then(Value::as_str) {
+        Some(alg) => {
+            let alg = Algorithm::from_str(alg).map_err(|e| format!("kid '{}': {}", kid, e))?;
+            if !family.contains(&alg) {
+                return Err(format!("kid '{}': alg {:?} does not match kty", kid, alg));
+            }
+            vec![alg]
+        }
+        None => family.to_vec(),
+    };
+    let jwk: Jwk =
+        serde_json::from_value(value.clone()).map_err(|e| format!("kid '{}': {}", kid, e))?;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|e| format!("kid '{}': {}", kid, e))?;
+    Ok(Some((kid.to_string(), VerificationKey { key, algorithms })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn skips_keys_that_cannot_verify_signatures() {
+        let ec = json!({
+            "kty": "EC", "crv": "P-256", "kid": "ec-1", "alg": "ES256",
+            "x": "8J5cccTF7kW2apq_HrcK36Ena8lpSwSu08jF6jl6wUk",
+            "y": "gmqaaepQnqraWlRjSGfbJtTs--ROMjuT9QkgFC9ijwk",
+        });
+        let (kid, key) = parse_key(&ec).unwrap().unwrap();
+        assert_eq!(kid, "ec-1");
+        assert_eq!(key.algorithms, [Algorithm::ES256]);
+
+        let mut mismatched = ec.clone();
+        mismatched["alg"] = json!("RS256");
+        assert!(parse_key(&mismatched).is_err());
+
+        let mut encryption = ec.clone();
+        encryption["use"] = json!("enc");
+        assert!(parse_key(&encryption).unwrap().is_none());
+
+        let symmetric = json!({ "kty": "oct", "kid": "hs", "k": "c2VjcmV0" });
+        assert!(parse_key(&symmetric).unwrap().is_none());
+    }
+}
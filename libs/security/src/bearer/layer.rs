@@ -0,0 +1,306 @@
+//! Tower layer kiểm tra header `Authorization: Bearer` trước khi tới handler.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::Json;
+use axum::body::Body;
+use axum::http::{HeaderValue, Request, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::BearerValidator;
+use crate::error::BearerError;
+
+/// Bọc service bằng [`BearerAuth`].
+#[derive(Debug, Clone)]
+pub struct BearerAuthLayer {
+    validator: Arc<BearerValidator>,
+}
+
+impl BearerAuthLayer {
+    pub fn new(validator: Arc<BearerValidator>) -> Self {
+        Self { validator }
+    }
+}
+
+impl<S> Layer<S> for BearerAuthLayer {
+    type Service = BearerAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BearerAuth {
+            inner,
+            validator: self.validator.clone(),
+        }
+    }
+}
+
+/// Từ chối request không có bearer token hợp lệ; request hợp lệ được chuyển
+/// tiếp kèm [`super::Principal`] trong extensions.
+#[derive(Debug, Clone)]
+pub struct BearerAuth<S> {
+    inner: S,
+    validator: Arc<BearerValidator>,
+}
+
+impl<S> Service<Request<Body>> for BearerAuth<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        // Dùng service đã sẵn sàng, để lại bản clone cho lần gọi sau
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let validator = self.validator.clone();
+        Box::pin(async move {
+            let token = match bearer_token(&request) {
+                Ok(token) => token.to_string(),
+                Err(e) => return Ok(e.into_response()),
+            };
+            match validator.validate(&token).await {
+                Ok(principal) => {
+                    request.extensions_mut().insert(principal);
+                    inner.call(request).await
+                }
+                Err(e) => {
+                    tracing::debug!("Rejected bearer token: {}", e);
+                    Ok(e.into_response())
+                }
+            }
+        })
+    }
+}
+
+fn bearer_token(request: &Request<Body>) -> Result<&str, BearerError> {
+    let Some(value) = request.headers().get(header::AUTHORIZATION) else {
+        return Err(BearerError::MissingToken);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| BearerError::InvalidToken("malformed Authorization header".to_string()))?;
+    match value.split_once(' ') {
+        Some((scheme, token))
+            if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() =>
+        {
+            Ok(token.trim())
+        }
+        _ => Err(BearerError::MissingToken),
+    }
+}
+
+impl IntoResponse for BearerError {
+    fn into_response(self) -> Response {
+        let (status, challenge, error) = match &self {
+            BearerError::MissingToken => (StatusCode::UNAUTHORIZED, "Bearer".to_string(), None),
+            BearerError::InvalidToken(_) => (
+                StatusCode::UNAUTHORIZED,
+                "Bearer error=\"invalid_token\"".to_string(),
+                Some("invalid_token"),
+            ),
+            BearerError::InsufficientScope(scope) => (
+                StatusCode::FORBIDDEN,
+                format!("Bearer error=\"insufficient_scope\", scope=\"{}\"", scope),
+                Some("insufficient_scope"),
+            ),
+            BearerError::Unavailable(e) => {
+                tracing::error!("Bearer token validation unavailable: {}", e);
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(json!({ "error": "temporarily_unavailable" })),
+                )
+                    .into_response();
+            }
+        };
+        let body = match error {
+            Some(error) => json!({ "error": error, "error_description": self.to_string() }),
+            None => json!({ "error": "unauthorized" }),
+        };
+        let mut response = (status, Json(body)).into_response();
+        if let Ok(challenge) = HeaderValue::from_str(&challenge) {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::bearer::{Introspector, Principal, RemoteJwks};
+    use crate::internal_token::{TokenGrant, TokenIssuer};
+    use crate::keystore::KeyStore;
+    use crate::test_support;
+    use axum::Router;
+    use axum::extract::State;
+    use axum::routing::{get, post};
+    use config_lib::Secret;
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+
+    /// Issuer giả: JWKS của gateway và endpoint introspection biết một opaque token.
+    async fn issuer() -> (String, TokenIssuer) {
+        let (store, token_issuer) = test_support::token_issuer().await;
+
+        let app = Router::new()
+            .route(
+                "/.well-known/jwks.json",
+                get(|State(store): State<Arc<KeyStore>>| async move {
+                    Json(store.jwks(OffsetDateTime::now_utc()))
+                }),
+            )
+            .route(
+                "/oauth2/introspect",
+                post(|body: String| async move {
+                    if body.starts_with("token=opaque-1&") {
+                        Json(json!({
+                            "active": true,
+                            "sub": "practitioner-1",
+                            "scope": "patient/*.read",
+                            "aud": "patient-summary",
+                            "patient": "erXuFYUfucBZaryVksYEcMg3",
+                        }))
+                    } else {
+                        Json(json!({ "active": false }))
+                    }
+                }),
+            )
+            .with_state(store);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, token_issuer)
+    }
+
+    fn app(url: &str) -> Router {
+        let http = reqwest::Client::new();
+        let jwks = Arc::new(RemoteJwks::new(
+            format!("{}/.well-known/jwks.json", url),
+            http.clone(),
+        ));
+        let validator =
+            BearerValidator::new("https://gateway.example.org", "patient-summary", jwks)
+                .with_required_scopes(vec!["patient/Patient.read".to_string()])
+                .with_introspection(
+                    Introspector::new(
+                        format!("{}/oauth2/introspect", url),
+                        "patient-summary",
+                        Secret::new("s3cret"),
+                        http,
+                    )
+                    .with_cache_ttl(Duration::from_secs(60)),
+                );
+        Router::new()
+            .route(
+                "/whoami",
+                get(|principal: Principal| async move {
+                    format!("{} {:?}", principal.subject, principal.patient)
+                }),
+            )
+            .layer(BearerAuthLayer::new(Arc::new(validator)))
+    }
+
+    async fn call(app: Router, token: Option<&str>) -> (StatusCode, Option<String>, String) {
+        let mut request = Request::get("/whoami");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let challenge = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, challenge, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn grant(audience: &str, scope: &str) -> TokenGrant {
+        TokenGrant {
+            subject: "practitioner-1".to_string(),
+            audience: audience.to_string(),
+            scope: scope.to_string(),
+            patient: Some("erXuFYUfucBZaryVksYEcMg3".to_string()),
+            ..TokenGrant::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn validates_jwts_against_remote_jwks() {
+        let (url, issuer) = issuer().await;
+        let now = OffsetDateTime::now_utc();
+
+        let (status, challenge, _) = call(app(&url), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge.as_deref(), Some("Bearer"));
+
+        let (token, _) = issuer
+            .issue(grant("patient-summary", "openid patient/*.read"), now, None)
+            .await
+            .unwrap();
+        let (status, _, body) = call(app(&url), Some(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "practitioner-1 Some(\"erXuFYUfucBZaryVksYEcMg3\")");
+
+        let (token, _) = issuer
+            .issue(grant("workflow", "patient/*.read"), now, None)
+            .await
+            .unwrap();
+        let (status, challenge, _) = call(app(&url), Some(&token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge.as_deref(), Some("Bearer error=\"invalid_token\""));
+
+        let (token, _) = issuer
+            .issue(grant("patient-summary", "openid"), now, None)
+            .await
+            .unwrap();
+        let (status, challenge, _) = call(app(&url), Some(&token)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            challenge.as_deref(),
+            Some("Bearer error=\"insufficient_scope\", scope=\"patient/Patient.read\"")
+        );
+
+        let expired_at = now - time::Duration::minutes(10);
+        let (token, _) = issuer
+            .issue(grant("patient-summary", "patient/*.read"), expired_at, None)
+            .await
+            .unwrap();
+        let (status, _, _) = call(app(&url), Some(&token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn validates_opaque_tokens_by_introspection() {
+        let (url, _) = issuer().await;
+
+        let (status, _, body) = call(app(&url), Some("opaque-1")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("practitioner-1 "));
+
+        let (status, _, body) = call(app(&url), Some("revoked")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(body.contains("not active"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Kiểm tra bearer token ở các service phía sau gateway.
+//!
+//! [`BearerValidator`] chấp nhận JWT ký bằng một key trong JWKS của issuer
+//! (token nội bộ của gateway, token của authorization server) và opaque token
+//! qua introspection. [`BearerAuthLayer`] áp dụng nó cho một router axum và đặt
+//! [`Principal`] vào request extensions cho handler.
+
+pub mod introspection;
+pub mod jwks;
+pub mod layer;
+pub mod principal;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_lib::Secret;
+use config_lib::settings::BearerAuthSettings;
+use jsonwebtoken::{Validation, decode, decode_header};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::error::BearerError;
+use crate::internal_token::Actor;
+
+pub use introspection::{Audience, IntrospectionResponse, Introspector};
+pub use jwks::RemoteJwks;
+pub use layer::{BearerAuth, BearerAuthLayer};
+pub use principal::{Principal, TokenKind};
+
+/// Claims đọc từ JWT; ngoài các claim chuẩn, nhận cả `scp` (mảng) và bối cảnh SMART.
+#[derive(Debug, Deserialize)]
+struct JwtClaims {
+    iss: String,
+    #[serde(default)]
+    sub: Option<String>,
+    aud: Audience,
+    exp: i64,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    scp: Option<Vec<String>>,
+    #[serde(default)]
+    client_id: Option<String>,
+    #[serde(default)]
+    azp: Option<String>,
+    #[serde(default)]
+    patient: Option<String>,
+    #[serde(default)]
+    sid: Option<String>,
+    #[serde(default)]
+    act: Option<Actor>,
+}
+
+/// Kiểm tra bearer token cho một service (một audience).
+#[derive(Debug)]
+pub struct BearerValidator {
+    issuer: String,
+    audience: String,
+    leeway: Duration,
+    required_scopes: Vec<String>,
+    jwks: Arc<RemoteJwks>,
+    introspector: Option<Introspector>,
+}
+
+impl BearerValidator {
+    pub fn new(
+        issuer: impl Into<String>,
+        audience: impl Into<String>,
+        jwks: Arc<RemoteJwks>,
+    ) -> Self {
+        Self {
+            issuer: issuer.into(),
+            audience: audience.into(),
+            leeway: Duration::from_secs(30),
+            required_scopes: Vec::new(),
+            jwks,
+            introspector: None,
+        }
+    }
+
+    /// Dựng validator từ cấu hình và bắt đầu làm mới JWKS ở background.
+    /// Phải gọi trong tokio runtime.
+    pub fn from_settings(settings: &BearerAuthSettings, http: reqwest::Client) -> Self {
+        let jwks = Arc::new(RemoteJwks::new(settings.jwks_uri(), http.clone()));
+        jwks.spawn_refresh(Duration::from_secs(settings.jwks_refresh_secs));
+
+        let mut validator = Self::new(&settings.issuer, &settings.audience, jwks)
+            .with_leeway(Duration::from_secs(settings.leeway_secs))
+            .with_required_scopes(settings.required_scopes.clone());
+        if let Some(introspection) = &settings.introspection {
+            let introspector = Introspector::new(
+                &introspection.endpoint,
+                &introspection.client_id,
+                introspection.client_secret.clone().unwrap_or_default(),
+                http,
+            )
+            .with_cache_ttl(Duration::from_secs(introspection.cache_secs));
+            validator = validator.with_introspection(introspector);
+        }
+        validator
+    }
+
+    /// Độ lệch đồng hồ cho phép khi kiểm tra `exp`/`nbf`.
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Scope mà mọi token phải có.
+    pub fn with_required_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.required_scopes = scopes;
+        self
+    }
+
+    /// Kiểm tra token không phải JWT bằng introspection.
+    pub fn with_introspection(mut self, introspector: Introspector) -> Self {
+        self.introspector = Some(introspector);
+        self
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    pub fn jwks(&self) -> &Arc<RemoteJwks> {
+        &self.jwks
+    }
+
+    /// Kiểm tra `token` và trả về chủ thể của nó.
+    pub async fn validate(&self, token: &str) -> Result<Principal, BearerError> {
+        let is_jwt = token.split('.').count() == 3 && decode_header(token).is_ok();
+        let principal = match (&self.introspector, is_jwt) {
+            (_, true) => self.validate_jwt(token).await?,
+            (Some(introspector), false) => self.validate_opaque(introspector, token).await?,
+            (None, false) => {
+                return Err(BearerError::InvalidToken("token is not a JWT".to_string()));
+            }
+        };
+        for scope in &self.required_scopes {
+            principal.require_scope(scope)?;
+        }
+        Ok(principal)
+    }
+
+    async fn validate_jwt(&self, token: &str) -> Result<Principal, BearerError> {
+        let invalid = |e: jsonwebtoken::errors::Error| BearerError::InvalidToken(e.to_string());
+        let header = decode_header(token).map_err(invalid)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| BearerError::InvalidToken("token has no kid".to_string()))?;
+        let key = self.jwks.key(&kid).await?;
+        if !key.algorithms.contains(&header.alg) {
+            return Err(BearerError::InvalidToken(format!(
+                "algorithm {:?} is not allowed for kid '{}'",
+                header.alg, kid
+            )));
+        }
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway.as_secs();
+        validation.validate_nbf = true;
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["iss", "aud", "exp"]);
+        let claims = decode::<JwtClaims>(token, &key.key, &validation)
+            .map_err(invalid)?
+            .claims;
+
+        let client_id = claims.client_id.or(claims.azp);
+        let subject = claims
+            .sub
+            .or_else(|| client_id.clone())
+            .ok_or_else(|| BearerError::InvalidToken("token has no sub".to_string()))?;
+        let scopes = match (claims.scope, claims.scp) {
+            (Some(scope), _) => scope.split_whitespace().map(str::to_string).collect(),
+            (None, Some(scp)) => scp,
+            (None, None) => Vec::new(),
+        };
+        Ok(Principal {
+            kind: TokenKind::Jwt,
+            issuer: claims.iss,
+            subject,
+            audience: claims.aud.into_vec(),
+            scopes,
+            client_id,
+            patient: claims.patient,
+            sid: claims.sid,
+            act: claims.act,
+            expires_at: OffsetDateTime::from_unix_timestamp(claims.exp).ok(),
+            token: Secret::new(token),
+        })
+    }
+
+    async fn validate_opaque(
+        &self,
+        introspector: &Introspector,
+        token: &str,
+    ) -> Result<Principal, BearerError> {
+        let response = introspector.introspect(token).await?;
+        if !response.active {
+            return Err(BearerError::InvalidToken("token is not active".to_string()));
+        }
+        // Các field trong response đều là tùy chọn (RFC 7662 mục 2.2): chỉ kiểm tra khi có
+        if let Some(iss) = &response.iss
+            && iss.trim_end_matches('/') != self.issuer.trim_end_matches('/')
+        {
+            return Err(BearerError::InvalidToken(format!(
+                "unexpected issuer '{}'",
+                iss
+            )));
+        }
+        let audience = response.aud.map(Audience::into_vec).unwrap_or_default();
+        if !audience.is_empty() && !audience.contains(&self.audience) {
+            return Err(BearerError::InvalidToken(format!(
+                "token is not intended for '{}'",
+                self.audience
+            )));
+        }
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        if let Some(exp) = response.exp
+            && exp + self.leeway.as_secs() as i64 <= now
+        {
+            return Err(BearerError::InvalidToken("token has expired".to_string()));
+        }
+
+        let subject = response
+            .sub
+            .or(response.username)
+            .or_else(|| response.client_id.clone())
+            .ok_or_else(|| BearerError::InvalidToken("token has no subject".to_string()))?;
+        Ok(Principal {
+            kind: TokenKind::Opaque,
+            issuer: response.iss.unwrap_or_else(|| self.issuer.clone()),
+            subject,
+            audience,
+            scopes: response
+                .scope
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            client_id: response.client_id,
+            patient: response.patient,
+            sid: response.sid,
+            act: response.act,
+            expires_at: response
+                .exp
+                .and_then(|exp| OffsetDateTime::from_unix_timestamp(exp).ok()),
+            token: Secret::new(token),
+        })
+    }
+}
@@ -0,0 +1,114 @@
+//! Người gọi đã được xác thực, truyền cho handler qua request extensions.
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use config_lib::Secret;
+use time::OffsetDateTime;
+
+use crate::error::BearerError;
+use crate::internal_token::Actor;
+
+/// Cách token đã được kiểm tra.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// JWT, kiểm tra chữ ký bằng JWKS của issuer
+    Jwt,
+    /// Opaque token, kiểm tra bằng introspection (RFC 7662)
+    Opaque,
+}
+
+/// Chủ thể của một bearer token hợp lệ.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub kind: TokenKind,
+    pub issuer: String,
+    /// `sub`; với token client credentials là client id
+    pub subject: String,
+    pub audience: Vec<String>,
+    pub scopes: Vec<String>,
+    /// Client đã xin token (`client_id` hoặc `azp`)
+    pub client_id: Option<String>,
+    /// Bệnh nhân trong bối cảnh SMART launch
+    pub patient: Option<String>,
+    /// Mã grant phía gateway (token nội bộ)
+    pub sid: Option<String>,
+    /// Chuỗi ủy quyền của token đổi qua RFC 8693
+    pub act: Option<Actor>,
+    pub expires_at: Option<OffsetDateTime>,
+    /// Token gốc, để service đổi (RFC 8693) hoặc chuyển tiếp
+    pub token: Secret,
+}
+
+impl Principal {
+    /// Token có scope `required` không. Scope SMART dạng `patient/*.read` bao
+    /// gồm mọi resource cùng bối cảnh và quyền, ví dụ `patient/Observation.read`.
+    pub fn has_scope(&self, required: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|granted| scope_satisfies(granted, required))
+    }
+
+    /// Như [`Principal::has_scope`], trả lỗi `insufficient_scope` nếu thiếu.
+    pub fn require_scope(&self, required: &str) -> Result<(), BearerError> {
+        if self.has_scope(required) {
+            Ok(())
+        } else {
+            Err(BearerError::InsufficientScope(required.to_string()))
+        }
+    }
+}
+
+fn scope_satisfies(granted: &str, required: &str) -> bool {
+    if granted == required {
+        return true;
+    }
+    // `<bối cảnh>/<resource>.<quyền>`: wildcard chỉ áp dụng cho phần resource
+    let split = |scope: &str| {
+        let (context, rest) = scope.split_once('/')?;
+        let (resource, permission) = rest.split_once('.')?;
+        Some((
+            context.to_string(),
+            resource.to_string(),
+            permission.to_string(),
+        ))
+    };
+    match (split(granted), split(required)) {
+        (Some((g_context, g_resource, g_permission)), Some((r_context, _, r_permission))) => {
+            g_resource == "*" && g_context == r_context && g_permission == r_permission
+        }
+        _ => false,
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = BearerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Route không nằm sau `BearerAuthLayer` thì không có principal
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or(BearerError::MissingToken)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_wildcard_scopes() {
+        assert!(scope_satisfies(
+            "patient/*.read",
+            "patient/Observation.read"
+        ));
+        assert!(scope_satisfies("openid", "openid"));
+        assert!(!scope_satisfies("patient/*.read", "user/Observation.read"));
+        assert!(!scope_satisfies(
+            "patient/*.read",
+            "patient/Observation.write"
+        ));
+        assert!(!scope_satisfies("patient/Patient.read", "patient/*.read"));
+    }
+}
@@ -0,0 +1,45 @@
+//! Lỗi khi nạp key, ký và kiểm tra bearer token.
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeyError {
+    /// Thuật toán ký không được hỗ trợ
+    #[error("unsupported JWT algorithm '{0}'")]
+    UnsupportedAlgorithm(String),
+    /// Private key không đọc được hoặc không khớp với thuật toán
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+    /// KMS không có key với tên này
+    #[error("key '{0}' not found in the KMS")]
+    UnknownKey(String),
+    /// Không có key nào đang active để ký
+    #[error("no signing key is active for client '{0}'")]
+    NoActiveKey(String),
+    /// Lỗi từ KMS/HSM hoặc thư viện ký
+    #[error("signing failed: {0}")]
+    Signing(String),
+    /// Token không do issuer này phát hành, sai chữ ký hoặc đã hết hạn
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    /// Không serialize được header/claims của JWT
+    #[error("failed to encode JWT: {0}")]
+    Encoding(#[from] serde_json::Error),
+}
+
+/// Lỗi khi kiểm tra bearer token của request đến (RFC 6750 mục 3.1).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BearerError {
+    /// Request không có header `Authorization: Bearer`
+    #[error("missing bearer token")]
+    MissingToken,
+    /// Token sai định dạng, sai chữ ký, hết hạn, sai `iss`/`aud` hoặc đã bị thu hồi
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    /// Token hợp lệ nhưng thiếu scope
+    #[error("insufficient scope: '{0}' is required")]
+    InsufficientScope(String),
+    /// Không tải được JWKS hoặc không gọi được endpoint introspection
+    #[error("token validation unavailable: {0}")]
+    Unavailable(String),
+}
@@ -0,0 +1,232 @@
+//! Token nội bộ: JWT ngắn hạn do gateway phát hành cho các service phía sau.
+//!
+//! Token mang người dùng (`sub`), bối cảnh bệnh nhân SMART (`patient`) và scope,
+//! được ký bằng key công bố trên JWKS của gateway. Khi một service đổi token
+//! (RFC 8693), service đó được ghi vào claim `act`.
+
+use std::sync::Arc;
+
+use config_lib::settings::KeyStatus;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{DecodingKey, Validation, decode, decode_header};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::error::KeyError;
+use crate::keystore::ClientKeys;
+
+/// `grant_type` của token exchange (RFC 8693 mục 2.1).
+pub const GRANT_TYPE_TOKEN_EXCHANGE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+/// `subject_token_type` / `issued_token_type` cho JWT (RFC 8693 mục 3).
+pub const TOKEN_TYPE_JWT: &str = "urn:ietf:params:oauth:token-type:jwt";
+/// `subject_token_type` / `issued_token_type` cho access token (RFC 8693 mục 3).
+pub const TOKEN_TYPE_ACCESS_TOKEN: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// Bên đang hành động thay cho `sub` (claim `act`, RFC 8693 mục 4.1).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String,
+    /// Bên đã hành động trước đó trong chuỗi ủy quyền
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+/// Claims của token nội bộ.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InternalClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub jti: String,
+    /// Scope cách nhau bởi dấu cách, theo kiểu OAuth
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub scope: String,
+    /// Bệnh nhân trong bối cảnh SMART launch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patient: Option<String>,
+    /// Mã grant phía gateway, dùng để đổi lấy access token của provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+}
+
+impl InternalClaims {
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.split_whitespace()
+    }
+
+    pub fn expires_at(&self) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(self.exp).unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    }
+}
+
+/// Nội dung của token cần phát hành; issuer, thời gian và `jti` do [`TokenIssuer`] điền.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenGrant {
+    pub subject: String,
+    pub audience: String,
+    pub scope: String,
+    pub patient: Option<String>,
+    pub sid: Option<String>,
+    pub act: Option<Actor>,
+}
+
+/// Phát hành và kiểm tra token nội bộ của gateway.
+#[derive(Debug, Clone)]
+pub struct TokenIssuer {
+    issuer: String,
+    lifetime: Duration,
+    keys: Arc<ClientKeys>,
+}
+
+impl TokenIssuer {
+    pub fn new(issuer: impl Into<String>, lifetime: Duration, keys: Arc<ClientKeys>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            lifetime,
+            keys,
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Ký token cho `grant`. `exp` không vượt quá `not_after` nếu có (token đổi
+    /// được không sống lâu hơn token gốc).
+    pub async fn issue(
+        &self,
+        grant: TokenGrant,
+        now: OffsetDateTime,
+        not_after: Option<OffsetDateTime>,
+    ) -> Result<(String, InternalClaims), KeyError> {
+        let expires_at = match not_after {
+            Some(limit) => (now + self.lifetime).min(limit),
+            None => now + self.lifetime,
+        };
+        let claims = InternalClaims {
+            iss: self.issuer.clone(),
+            sub: grant.subject,
+            aud: grant.audience,
+            exp: expires_at.unix_timestamp(),
+            iat: now.unix_timestamp(),
+            jti: Uuid::new_v4().to_string(),
+            scope: grant.scope,
+            patient: grant.patient,
+            sid: grant.sid,
+            act: grant.act,
+        };
+        let token = self.keys.sign_jwt(&claims, now).await?;
+        Ok((token, claims))
+    }
+
+    /// Kiểm tra token do chính issuer này phát hành: chữ ký (bằng một key còn
+    /// công bố), `iss` và `exp`. Không kiểm tra `aud`.
+    pub fn verify(&self, token: &str, now: OffsetDateTime) -> Result<InternalClaims, KeyError> {
+        let invalid = |e: jsonwebtoken::errors::Error| KeyError::InvalidToken(e.to_string());
+        let header = decode_header(token).map_err(invalid)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| KeyError::InvalidToken("token has no kid".to_string()))?;
+        let key = self
+            .keys
+            .statuses(now)
+            .find(|(key, status)| key.kid == kid && *status != KeyStatus::Retired)
+            .map(|(key, _)| key)
+            .ok_or_else(|| KeyError::InvalidToken(format!("unknown kid '{}'", kid)))?;
+        if header.alg != key.signer.algorithm().jwt_algorithm() {
+            return Err(KeyError::InvalidToken(format!(
+                "unexpected algorithm {:?}",
+                header.alg
+            )));
+        }
+        let jwk: Jwk = serde_json::from_value(
+            key.signer
+                .public_jwk()
+                .to_json(&key.kid, key.signer.algorithm()),
+        )?;
+        let decoding_key = DecodingKey::from_jwk(&jwk).map_err(invalid)?;
+
+        // `exp` được so với `now` của người gọi thay vì đồng hồ hệ thống.
+        let mut validation = Validation::new(header.alg);
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["iss", "sub", "aud", "exp"]);
+        let claims = decode::<InternalClaims>(token, &decoding_key, &validation)
+            .map_err(invalid)?
+            .claims;
+        if claims.expires_at() <= now {
+            return Err(KeyError::InvalidToken("token has expired".to_string()));
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use time::macros::datetime;
+
+    async fn issuer() -> TokenIssuer {
+        let (store, issuer) = test_support::token_issuer().await;
+        assert_eq!(
+            store.jwks(OffsetDateTime::now_utc())["keys"][0]["kty"],
+            "EC"
+        );
+        issuer
+    }
+
+    #[tokio::test]
+    async fn issued_tokens_verify_until_expiry() {
+        let issuer = issuer().await;
+        let now = datetime!(2026-10-19 08:00 UTC);
+        let grant = TokenGrant {
+            subject: "practitioner-1".to_string(),
+            audience: "patient-summary".to_string(),
+            scope: "patient/*.read openid".to_string(),
+            patient: Some("erXuFYUfucBZaryVksYEcMg3".to_string()),
+            ..TokenGrant::default()
+        };
+        let (token, claims) = issuer.issue(grant, now, None).await.unwrap();
+        assert_eq!(claims.iss, "https://gateway.example.org");
+        assert_eq!(claims.exp - claims.iat, 300);
+
+        let verified = issuer.verify(&token, now + Duration::minutes(1)).unwrap();
+        assert_eq!(verified, claims);
+        assert_eq!(
+            verified.scopes().collect::<Vec<_>>(),
+            ["patient/*.read", "openid"]
+        );
+        assert!(issuer.verify(&token, now + Duration::minutes(5)).is_err());
+
+        let mut tampered = token.clone();
+        tampered.replace_range(token.len() - 4.., "AAAA");
+        assert!(issuer.verify(&tampered, now).is_err());
+    }
+
+    #[tokio::test]
+    async fn exchanged_token_is_capped_by_not_after() {
+        let issuer = issuer().await;
+        let now = datetime!(2026-10-19 08:00 UTC);
+        let grant = TokenGrant {
+            subject: "practitioner-1".to_string(),
+            audience: "workflow".to_string(),
+            act: Some(Actor {
+                sub: "patient-summary".to_string(),
+                act: None,
+            }),
+            ..TokenGrant::default()
+        };
+        let limit = now + Duration::minutes(2);
+        let (token, claims) = issuer.issue(grant, now, Some(limit)).await.unwrap();
+        assert_eq!(claims.exp, limit.unix_timestamp());
+        let verified = issuer.verify(&token, now).unwrap();
+        assert_eq!(verified.act.unwrap().sub, "patient-summary");
+    }
+}
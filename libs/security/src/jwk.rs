@@ -0,0 +1,156 @@
+//! Public JWK (RFC 7517/7518) cho các key ký client assertion, và thumbprint
+//! RFC 7638 dùng làm `kid` khi cấu hình không đặt.
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rsa::{
+    RsaPrivateKey, pkcs1::DecodeRsaPrivateKey, pkcs8::DecodePrivateKey, traits::PublicKeyParts,
+};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::error::KeyError;
+use crate::signer::SigningAlgorithm;
+
+/// Phần public của một key ký.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicJwk {
+    Rsa {
+        n: String,
+        e: String,
+    },
+    Ec {
+        crv: &'static str,
+        x: String,
+        y: String,
+    },
+}
+
+impl PublicJwk {
+    /// Lấy public key từ private key PEM, kiểm tra loại key khớp với thuật toán.
+    ///
+    /// RSA nhận PKCS#8 hoặc PKCS#1; EC nhận PKCS#8 hoặc SEC1, đường cong phải là
+    /// P-256 cho ES256 và P-384 cho ES384.
+    pub fn from_private_key_pem(
+        private_key_pem: &str,
+        algorithm: SigningAlgorithm,
+    ) -> Result<Self, KeyError> {
+        match algorithm {
+            SigningAlgorithm::RS256 | SigningAlgorithm::RS384 | SigningAlgorithm::RS512 => {
+                let key = RsaPrivateKey::from_pkcs8_pem(private_key_pem)
+                    .or_else(|_| RsaPrivateKey::from_pkcs1_pem(private_key_pem))
+                    .map_err(|e| KeyError::InvalidKey(format!("invalid RSA private key: {}", e)))?;
+                Ok(Self::Rsa {
+                    n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+                })
+            }
+            SigningAlgorithm::ES256 => {
+                let point = p256_private_key(private_key_pem)?
+                    .public_key()
+                    .to_encoded_point(false);
+                Ok(Self::ec(
+                    "P-256",
+                    point.x().map(|x| x.as_slice()),
+                    point.y().map(|y| y.as_slice()),
+                ))
+            }
+            SigningAlgorithm::ES384 => {
+                let point = p384_private_key(private_key_pem)?
+                    .public_key()
+                    .to_encoded_point(false);
+                Ok(Self::ec(
+                    "P-384",
+                    point.x().map(|x| x.as_slice()),
+                    point.y().map(|y| y.as_slice()),
+                ))
+            }
+        }
+    }
+
+    fn ec(crv: &'static str, x: Option<&[u8]>, y: Option<&[u8]>) -> Self {
+        // Điểm không nén luôn có cả x và y
+        Self::Ec {
+            crv,
+            x: URL_SAFE_NO_PAD.encode(x.unwrap_or_default()),
+            y: URL_SAFE_NO_PAD.encode(y.unwrap_or_default()),
+        }
+    }
+
+    /// Thumbprint RFC 7638: SHA-256 của các member bắt buộc, theo thứ tự từ điển, không khoảng trắng.
+    pub fn thumbprint(&self) -> String {
+        let canonical = match self {
+            Self::Rsa { n, e } => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n),
+            Self::Ec { crv, x, y } => {
+                format!(r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#, crv, x, y)
+            }
+        };
+        URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+    }
+
+    /// JWK để công bố trên JWKS.
+    pub fn to_json(&self, kid: &str, algorithm: SigningAlgorithm) -> Value {
+        let alg = algorithm.as_str();
+        match self {
+            Self::Rsa { n, e } => json!({
+                "kty": "RSA",
+                "n": n,
+                "e": e,
+                "kid": kid,
+                "alg": alg,
+                "use": "sig",
+            }),
+            Self::Ec { crv, x, y } => json!({
+                "kty": "EC",
+                "crv": crv,
+                "x": x,
+                "y": y,
+                "kid": kid,
+                "alg": alg,
+                "use": "sig",
+            }),
+        }
+    }
+}
+
+/// Private key P-256 dạng PKCS#8 hoặc SEC1 (`BEGIN EC PRIVATE KEY`).
+pub(crate) fn p256_private_key(private_key_pem: &str) -> Result<p256::SecretKey, KeyError> {
+    p256::SecretKey::from_pkcs8_pem(private_key_pem)
+        .or_else(|_| p256::SecretKey::from_sec1_pem(private_key_pem))
+        .map_err(|e| KeyError::InvalidKey(format!("invalid P-256 private key: {}", e)))
+}
+
+/// Private key P-384 dạng PKCS#8 hoặc SEC1.
+pub(crate) fn p384_private_key(private_key_pem: &str) -> Result<p384::SecretKey, KeyError> {
+    p384::SecretKey::from_pkcs8_pem(private_key_pem)
+        .or_else(|_| p384::SecretKey::from_sec1_pem(private_key_pem))
+        .map_err(|e| KeyError::InvalidKey(format!("invalid P-384 private key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::P256_PEM;
+
+    #[test]
+    fn rsa_thumbprint_matches_rfc7638_example() {
+        let jwk = PublicJwk::Rsa {
+            n: "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_string(),
+            e: "AQAB".to_string(),
+        };
+        assert_eq!(
+            jwk.thumbprint(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn ec_key_must_match_algorithm_curve() {
+        let jwk = PublicJwk::from_private_key_pem(P256_PEM, SigningAlgorithm::ES256).unwrap();
+        let value = jwk.to_json(&jwk.thumbprint(), SigningAlgorithm::ES256);
+        assert_eq!(value["kty"], "EC");
+        assert_eq!(value["crv"], "P-256");
+        assert_eq!(value["alg"], "ES256");
+        assert!(PublicJwk::from_private_key_pem(P256_PEM, SigningAlgorithm::ES384).is_err());
+    }
+}
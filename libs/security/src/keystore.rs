@@ -0,0 +1,281 @@
+//! Key ký của các OAuth client, dùng chung cho client assertion và JWKS.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use config_lib::Settings;
+use config_lib::settings::{KeyStatus, SigningKeySettings, signing_key_statuses};
+use serde::Serialize;
+use serde_json::{Value, json};
+use time::OffsetDateTime;
+
+use crate::error::KeyError;
+use crate::kms::{KeyManagementService, KmsSigner, kms_from_settings};
+use crate::signer::{Signer, SigningAlgorithm, SoftwareSigner, sign_jwt};
+
+/// Một key đã nạp, cùng `kid` của nó (cấu hình hoặc thumbprint RFC 7638).
+#[derive(Debug, Clone)]
+pub struct LoadedKey {
+    pub kid: String,
+    pub signer: Arc<dyn Signer>,
+}
+
+/// Các key của một client, theo lịch xoay vòng trong cấu hình.
+#[derive(Debug)]
+pub struct ClientKeys {
+    client: String,
+    /// Lịch của từng key (không chứa private key), cùng thứ tự với `keys`
+    schedule: Vec<SigningKeySettings>,
+    keys: Vec<LoadedKey>,
+}
+
+impl ClientKeys {
+    /// Trạng thái của từng key tại thời điểm `now`.
+    pub fn statuses(&self, now: OffsetDateTime) -> impl Iterator<Item = (&LoadedKey, KeyStatus)> {
+        self.keys
+            .iter()
+            .zip(signing_key_statuses(&self.schedule, now))
+    }
+
+    /// Key dùng để ký tại thời điểm `now`.
+    pub fn current(&self, now: OffsetDateTime) -> Option<&LoadedKey> {
+        self.statuses(now)
+            .find(|(_, status)| *status == KeyStatus::Current)
+            .map(|(key, _)| key)
+    }
+
+    /// Ký `claims` thành JWT bằng key hiện tại.
+    pub async fn sign_jwt<T: Serialize + Sync>(
+        &self,
+        claims: &T,
+        now: OffsetDateTime,
+    ) -> Result<String, KeyError> {
+        let key = self
+            .current(now)
+            .ok_or_else(|| KeyError::NoActiveKey(self.client.clone()))?;
+        sign_jwt(key.signer.as_ref(), &key.kid, claims).await
+    }
+
+    /// JWK của các key còn được công bố (next, current, retiring).
+    pub fn published_jwks(&self, now: OffsetDateTime) -> Vec<Value> {
+        self.statuses(now)
+            .filter(|(_, status)| *status != KeyStatus::Retired)
+            .map(|(key, _)| {
+                key.signer
+                    .public_jwk()
+                    .to_json(&key.kid, key.signer.algorithm())
+            })
+            .collect()
+    }
+}
+
+/// Key của mọi OAuth client và của token nội bộ, nạp từ PEM (file, biến môi
+/// trường) hoặc KMS.
+#[derive(Debug, Default)]
+pub struct KeyStore {
+    clients: HashMap<String, Arc<ClientKeys>>,
+    internal: Option<Arc<ClientKeys>>,
+}
+
+impl KeyStore {
+    /// Nạp key từ cấu hình; KMS được dựng từ mục `kms` nếu có.
+    pub async fn from_settings(settings: &Settings) -> Result<Self, KeyError> {
+        let kms = settings.kms.as_ref().map(kms_from_settings).transpose()?;
+        Self::load(settings, kms).await
+    }
+
+    /// Nạp key từ cấu hình, dùng `kms` cho các key khai báo bằng `kms_key`.
+    pub async fn load(
+        settings: &Settings,
+        kms: Option<Arc<dyn KeyManagementService>>,
+    ) -> Result<Self, KeyError> {
+        let mut clients = HashMap::new();
+        for (name, client) in &settings.oauth_clients {
+            if let Some(keys) = load_keys(name, client.all_signing_keys(), kms.as_ref()).await? {
+                clients.insert(name.clone(), Arc::new(keys));
+            }
+        }
+        let internal = match &settings.internal_tokens {
+            Some(internal) => {
+                load_keys(
+                    "internal_tokens",
+                    internal.signing_keys.clone(),
+                    kms.as_ref(),
+                )
+                .await?
+            }
+            None => None,
+        };
+        Ok(Self {
+            clients,
+            internal: internal.map(Arc::new),
+        })
+    }
+
+    /// Key của một client; `None` nếu client không dùng `private_key_jwt`.
+    pub fn client(&self, name: &str) -> Option<Arc<ClientKeys>> {
+        self.clients.get(name).cloned()
+    }
+
+    /// Key ký token nội bộ; `None` nếu không cấu hình `internal_tokens`.
+    pub fn internal(&self) -> Option<Arc<ClientKeys>> {
+        self.internal.clone()
+    }
+
+    /// Nội dung của `/.well-known/jwks.json`.
+    pub fn jwks(&self, now: OffsetDateTime) -> Value {
+        let mut names: Vec<_> = self.clients.keys().collect();
+        names.sort();
+        let keys: Vec<Value> = names
+            .into_iter()
+            .map(|name| &self.clients[name])
+            .chain(&self.internal)
+            .flat_map(|keys| keys.published_jwks(now))
+            .collect();
+        json!({ "keys": keys })
+    }
+}
+
+/// Nạp các key của `owner`; `None` nếu không có key nào.
+async fn load_keys(
+    owner: &str,
+    settings: Vec<SigningKeySettings>,
+    kms: Option<&Arc<dyn KeyManagementService>>,
+) -> Result<Option<ClientKeys>, KeyError> {
+    let mut schedule = Vec::new();
+    let mut keys = Vec::new();
+    for key in settings {
+        let signer = load_signer(&key, kms)
+            .await
+            .map_err(|e| KeyError::InvalidKey(format!("{}: {}", owner, e)))?;
+        let kid = match key.key_id.as_deref().map(str::trim) {
+            Some(kid) if !kid.is_empty() => kid.to_string(),
+            _ => signer.public_jwk().thumbprint(),
+        };
+        keys.push(LoadedKey { kid, signer });
+        schedule.push(SigningKeySettings {
+            private_key_pem: None,
+            ..key
+        });
+    }
+    if keys.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(ClientKeys {
+        client: owner.to_string(),
+        schedule,
+        keys,
+    }))
+}
+
+async fn load_signer(
+    key: &SigningKeySettings,
+    kms: Option<&Arc<dyn KeyManagementService>>,
+) -> Result<Arc<dyn Signer>, KeyError> {
+    let algorithm: SigningAlgorithm = key.algorithm.parse()?;
+    match (&key.private_key_pem, &key.kms_key) {
+        (Some(pem), None) => Ok(Arc::new(SoftwareSigner::from_pem(pem.expose(), algorithm)?)),
+        (None, Some(key_ref)) => {
+            let kms = kms.ok_or_else(|| {
+                KeyError::Signing(format!("key '{}' needs a configured KMS", key_ref))
+            })?;
+            Ok(Arc::new(
+                KmsSigner::new(kms.clone(), key_ref, algorithm).await?,
+            ))
+        }
+        _ => Err(KeyError::InvalidKey(
+            "a signing key needs exactly one of private_key_pem or kms_key".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kms::SoftwareKms;
+    use crate::test_support::P256_PEM;
+    use config_lib::Secret;
+    use jsonwebtoken::{DecodingKey, Validation, decode, decode_header};
+    use time::macros::datetime;
+
+    fn settings() -> Settings {
+        serde_json::from_value(json!({
+            "port": 3000,
+            "base_url": "http://localhost:3000",
+            "oauth_clients": {
+                "epic": {
+                    "client_id": "cid",
+                    "auth_url": "https://fhir.example.org/authorize",
+                    "token_url": "https://fhir.example.org/token",
+                    "redirect_uri": "http://localhost:3000/callback",
+                    "scopes": [],
+                    "audience": "https://fhir.example.org/R4",
+                    "signing_keys": [
+                        {
+                            "kms_key": "epic-2026",
+                            "algorithm": "ES256",
+                            "key_id": "old",
+                            "retire_at": "2026-11-02T00:00:00Z",
+                        },
+                        {
+                            "private_key_pem": P256_PEM,
+                            "algorithm": "ES256",
+                            "active_from": "2026-11-01T00:00:00Z",
+                        },
+                    ],
+                },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[derive(Serialize, serde::Deserialize)]
+    struct Claims {
+        sub: String,
+        exp: u64,
+    }
+
+    #[tokio::test]
+    async fn signs_with_current_key_and_publishes_rotation() {
+        let mut kms = SoftwareKms::new();
+        kms.insert("epic-2026", Secret::new(P256_PEM));
+        let store = KeyStore::load(&settings(), Some(Arc::new(kms)))
+            .await
+            .unwrap();
+        let keys = store.client("epic").unwrap();
+
+        let before = datetime!(2026-10-31 00:00 UTC);
+        assert_eq!(store.jwks(before)["keys"].as_array().unwrap().len(), 2);
+        assert_eq!(keys.current(before).unwrap().kid, "old");
+
+        let after = datetime!(2026-11-03 00:00 UTC);
+        let jwks = store.jwks(after);
+        let published = jwks["keys"].as_array().unwrap();
+        assert_eq!(published.len(), 1);
+        let new_kid = keys.current(after).unwrap().kid.clone();
+        assert_eq!(published[0]["kid"], new_kid.as_str());
+
+        let claims = Claims {
+            sub: "cid".to_string(),
+            exp: 4_000_000_000,
+        };
+        let jwt = keys.sign_jwt(&claims, after).await.unwrap();
+        assert_eq!(
+            decode_header(&jwt).unwrap().kid.as_deref(),
+            Some(new_kid.as_str())
+        );
+        let jwk: jsonwebtoken::jwk::Jwk = serde_json::from_value(published[0].clone()).unwrap();
+        let decoded = decode::<Claims>(
+            &jwt,
+            &DecodingKey::from_jwk(&jwk).unwrap(),
+            &Validation::new(jsonwebtoken::Algorithm::ES256),
+        )
+        .unwrap();
+        assert_eq!(decoded.claims.sub, "cid");
+    }
+
+    #[tokio::test]
+    async fn kms_key_without_kms_is_rejected() {
+        assert!(KeyStore::load(&settings(), None).await.is_err());
+    }
+}
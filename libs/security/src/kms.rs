@@ -0,0 +1,236 @@
+//! Giao diện ký kiểu PKCS#11/KMS và một stand-in chạy cục bộ.
+//!
+//! Với KMS/HSM thật, private key không bao giờ rời thiết bị: gateway chỉ gửi
+//! dữ liệu cần ký và nhận lại chữ ký. [`SoftwareKms`] mô phỏng giao diện đó
+//! bằng các file PEM trong một thư mục, dùng cho dev và test.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use config_lib::Secret;
+use config_lib::settings::KmsSettings;
+
+use crate::error::KeyError;
+use crate::jwk::PublicJwk;
+use crate::signer::{Signer, SigningAlgorithm, SoftwareSigner};
+
+/// Dịch vụ quản lý key (KMS, HSM qua PKCS#11, ...), key được tham chiếu bằng tên.
+#[async_trait]
+pub trait KeyManagementService: Send + Sync {
+    /// Public key của key `key_ref`
+    async fn public_jwk(
+        &self,
+        key_ref: &str,
+        algorithm: SigningAlgorithm,
+    ) -> Result<PublicJwk, KeyError>;
+
+    /// Ký `message` bằng key `key_ref`, trả về chữ ký theo định dạng JWS.
+    async fn sign(
+        &self,
+        key_ref: &str,
+        algorithm: SigningAlgorithm,
+        message: &[u8],
+    ) -> Result<Vec<u8>, KeyError>;
+}
+
+/// KMS từ mục `kms` trong cấu hình.
+pub fn kms_from_settings(
+    settings: &KmsSettings,
+) -> Result<Arc<dyn KeyManagementService>, KeyError> {
+    match (settings.provider.as_str(), settings.key_dir.as_deref()) {
+        ("software", Some(dir)) => Ok(Arc::new(SoftwareKms::from_dir(dir)?)),
+        (provider, _) => Err(KeyError::Signing(format!(
+            "unsupported KMS provider '{}'",
+            provider
+        ))),
+    }
+}
+
+/// Stand-in cục bộ cho KMS: key `<tên>` là file `<key_dir>/<tên>.pem`.
+#[derive(Default)]
+pub struct SoftwareKms {
+    keys: HashMap<String, Secret>,
+    /// Signer đã dựng theo key và thuật toán, để không parse PEM ở mỗi lần ký
+    signers: Mutex<HashMap<(String, SigningAlgorithm), Arc<SoftwareSigner>>>,
+}
+
+impl SoftwareKms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Đọc mọi file `*.pem` trong `dir`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, KeyError> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            KeyError::InvalidKey(format!(
+                "cannot read key directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+        let mut kms = Self::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "pem") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let pem = std::fs::read_to_string(&path).map_err(|e| {
+                KeyError::InvalidKey(format!("cannot read {}: {}", path.display(), e))
+            })?;
+            kms.insert(name, Secret::new(pem));
+        }
+        Ok(kms)
+    }
+
+    pub fn insert(&mut self, key_ref: impl Into<String>, private_key_pem: Secret) {
+        let key_ref = key_ref.into();
+        self.signers
+            .get_mut()
+            .unwrap()
+            .retain(|(cached, _), _| *cached != key_ref);
+        self.keys.insert(key_ref, private_key_pem);
+    }
+
+    fn signer(
+        &self,
+        key_ref: &str,
+        algorithm: SigningAlgorithm,
+    ) -> Result<Arc<SoftwareSigner>, KeyError> {
+        let cache_key = (key_ref.to_string(), algorithm);
+        if let Some(signer) = self.signers.lock().unwrap().get(&cache_key) {
+            return Ok(signer.clone());
+        }
+        let pem = self
+            .keys
+            .get(key_ref)
+            .ok_or_else(|| KeyError::UnknownKey(key_ref.to_string()))?;
+        let signer = Arc::new(SoftwareSigner::from_pem(pem.expose(), algorithm)?);
+        self.signers
+            .lock()
+            .unwrap()
+            .insert(cache_key, signer.clone());
+        Ok(signer)
+    }
+}
+
+impl fmt::Debug for SoftwareKms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftwareKms")
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl KeyManagementService for SoftwareKms {
+    async fn public_jwk(
+        &self,
+        key_ref: &str,
+        algorithm: SigningAlgorithm,
+    ) -> Result<PublicJwk, KeyError> {
+        Ok(self.signer(key_ref, algorithm)?.public_jwk().clone())
+    }
+
+    async fn sign(
+        &self,
+        key_ref: &str,
+        algorithm: SigningAlgorithm,
+        message: &[u8],
+    ) -> Result<Vec<u8>, KeyError> {
+        self.signer(key_ref, algorithm)?.sign_now(message)
+    }
+}
+
+/// [`Signer`] cho một key nằm trong KMS.
+pub struct KmsSigner {
+    kms: Arc<dyn KeyManagementService>,
+    key_ref: String,
+    algorithm: SigningAlgorithm,
+    jwk: PublicJwk,
+}
+
+impl KmsSigner {
+    /// Lấy public key từ KMS một lần; các lần ký sau chỉ gửi dữ liệu cần ký.
+    pub async fn new(
+        kms: Arc<dyn KeyManagementService>,
+        key_ref: &str,
+        algorithm: SigningAlgorithm,
+    ) -> Result<Self, KeyError> {
+        let jwk = kms.public_jwk(key_ref, algorithm).await?;
+        Ok(Self {
+            kms,
+            key_ref: key_ref.to_string(),
+            algorithm,
+            jwk,
+        })
+    }
+}
+
+impl fmt::Debug for KmsSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KmsSigner")
+            .field("key_ref", &self.key_ref)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Signer for KmsSigner {
+    fn algorithm(&self) -> SigningAlgorithm {
+        self.algorithm
+    }
+
+    fn public_jwk(&self) -> &PublicJwk {
+        &self.jwk
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<Vec<u8>, KeyError> {
+        self.kms.sign(&self.key_ref, self.algorithm, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replacing_a_key_drops_its_cached_signer() {
+        let pem = || {
+            p256::SecretKey::random(&mut rand_core::OsRng)
+                .to_sec1_pem(p256::pkcs8::LineEnding::LF)
+                .unwrap()
+                .to_string()
+        };
+        let mut kms = SoftwareKms::new();
+        kms.insert("epic", Secret::new(pem()));
+        let first = kms
+            .public_jwk("epic", SigningAlgorithm::ES256)
+            .await
+            .unwrap();
+        assert_eq!(
+            kms.public_jwk("epic", SigningAlgorithm::ES256)
+                .await
+                .unwrap(),
+            first
+        );
+        kms.sign("epic", SigningAlgorithm::ES256, b"message")
+            .await
+            .unwrap();
+
+        kms.insert("epic", Secret::new(pem()));
+        assert_ne!(
+            kms.public_jwk("epic", SigningAlgorithm::ES256)
+                .await
+                .unwrap(),
+            first
+        );
+    }
+}
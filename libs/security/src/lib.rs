@@ -1,3 +1,23 @@
+//! Bảo mật dùng chung: quản lý key ký (PEM, KMS/HSM) cho client assertion, token
+//! nội bộ và JWKS; kiểm tra bearer token ở các service phía sau gateway.
+
+pub mod bearer;
+pub mod error;
+pub mod internal_token;
+pub mod jwk;
+pub mod keystore;
+pub mod kms;
+pub mod signer;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_support;
+
+pub use bearer::{BearerAuthLayer, BearerValidator, Principal};
+pub use error::{BearerError, KeyError};
+pub use internal_token::{Actor, InternalClaims, TokenGrant, TokenIssuer};
+pub use keystore::{ClientKeys, KeyStore, LoadedKey};
+pub use kms::{KeyManagementService, KmsSigner, SoftwareKms};
+pub use signer::{Signer, SigningAlgorithm, SoftwareSigner, sign_jwt};
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
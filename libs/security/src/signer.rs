@@ -0,0 +1,235 @@
+//! Ký JWT qua [`Signer`]: người gọi chỉ thấy chữ ký, không thấy private key.
+
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey};
+use p256::pkcs8::{EncodePrivateKey, LineEnding, SecretDocument};
+use rand_core::OsRng;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::error::KeyError;
+use crate::jwk::{PublicJwk, p256_private_key, p384_private_key};
+
+/// Thuật toán ký JWT được hỗ trợ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SigningAlgorithm {
+    RS256,
+    RS384,
+    RS512,
+    ES256,
+    ES384,
+}
+
+impl SigningAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RS256 => "RS256",
+            Self::RS384 => "RS384",
+            Self::RS512 => "RS512",
+            Self::ES256 => "ES256",
+            Self::ES384 => "ES384",
+        }
+    }
+
+    pub(crate) fn jwt_algorithm(self) -> Algorithm {
+        match self {
+            Self::RS256 => Algorithm::RS256,
+            Self::RS384 => Algorithm::RS384,
+            Self::RS512 => Algorithm::RS512,
+            Self::ES256 => Algorithm::ES256,
+            Self::ES384 => Algorithm::ES384,
+        }
+    }
+}
+
+impl FromStr for SigningAlgorithm {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "RS256" => Ok(Self::RS256),
+            "RS384" => Ok(Self::RS384),
+            "RS512" => Ok(Self::RS512),
+            "ES256" => Ok(Self::ES256),
+            "ES384" => Ok(Self::ES384),
+            _ => Err(KeyError::UnsupportedAlgorithm(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for SigningAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Một key có thể ký, theo kiểu PKCS#11/KMS: chỉ trả về chữ ký.
+#[async_trait]
+pub trait Signer: Send + Sync + fmt::Debug {
+    fn algorithm(&self) -> SigningAlgorithm;
+
+    /// Public key để công bố trên JWKS
+    fn public_jwk(&self) -> &PublicJwk;
+
+    /// Chữ ký của `message` theo định dạng JWS (với EC là `r || s`, không phải DER).
+    async fn sign(&self, message: &[u8]) -> Result<Vec<u8>, KeyError>;
+}
+
+/// Key nằm trong bộ nhớ của process, đọc từ PEM (file hoặc biến môi trường).
+pub struct SoftwareSigner {
+    algorithm: SigningAlgorithm,
+    jwk: PublicJwk,
+    key: EncodingKey,
+}
+
+impl SoftwareSigner {
+    /// RSA nhận PKCS#8 hoặc PKCS#1, EC nhận PKCS#8 hoặc SEC1, như
+    /// [`PublicJwk::from_private_key_pem`].
+    pub fn from_pem(private_key_pem: &str, algorithm: SigningAlgorithm) -> Result<Self, KeyError> {
+        let jwk = PublicJwk::from_private_key_pem(private_key_pem, algorithm)?;
+        let key = match algorithm {
+            SigningAlgorithm::RS256 | SigningAlgorithm::RS384 | SigningAlgorithm::RS512 => {
+                EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
+                    .map_err(|e| KeyError::InvalidKey(e.to_string()))?
+            }
+            // jsonwebtoken chỉ đọc key EC dạng PKCS#8: key SEC1 được chuyển đổi trước
+            SigningAlgorithm::ES256 => {
+                ec_encoding_key(p256_private_key(private_key_pem)?.to_pkcs8_der())?
+            }
+            SigningAlgorithm::ES384 => {
+                ec_encoding_key(p384_private_key(private_key_pem)?.to_pkcs8_der())?
+            }
+        };
+        Ok(Self {
+            algorithm,
+            jwk,
+            key,
+        })
+    }
+
+    /// Tạo key P-256 (ES256) ngẫu nhiên, chỉ nằm trong bộ nhớ, ví dụ key DPoP của một phiên.
+    pub fn generate_es256() -> Result<Self, KeyError> {
+        let pem = p256::SecretKey::random(&mut OsRng)
+            .to_pkcs8_pem(LineEnding::LF)
+            .map_err(|e| KeyError::InvalidKey(e.to_string()))?;
+        Self::from_pem(&pem, SigningAlgorithm::ES256)
+    }
+
+    /// Ký ngay, không cần `await`: key nằm trong bộ nhớ.
+    pub fn sign_now(&self, message: &[u8]) -> Result<Vec<u8>, KeyError> {
+        let signature =
+            jsonwebtoken::crypto::sign(message, &self.key, self.algorithm.jwt_algorithm())
+                .map_err(|e| KeyError::Signing(e.to_string()))?;
+        URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|e| KeyError::Signing(e.to_string()))
+    }
+}
+
+fn ec_encoding_key(der: p256::pkcs8::Result<SecretDocument>) -> Result<EncodingKey, KeyError> {
+    let der = der.map_err(|e| KeyError::InvalidKey(e.to_string()))?;
+    Ok(EncodingKey::from_ec_der(der.as_bytes()))
+}
+
+impl fmt::Debug for SoftwareSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftwareSigner")
+            .field("algorithm", &self.algorithm)
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Signer for SoftwareSigner {
+    fn algorithm(&self) -> SigningAlgorithm {
+        self.algorithm
+    }
+
+    fn public_jwk(&self) -> &PublicJwk {
+        &self.jwk
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<Vec<u8>, KeyError> {
+        self.sign_now(message)
+    }
+}
+
+/// Tạo JWT (JWS compact) với header `alg`/`typ`/`kid` và ký bằng `signer`.
+pub async fn sign_jwt<T: Serialize + Sync>(
+    signer: &dyn Signer,
+    kid: &str,
+    claims: &T,
+) -> Result<String, KeyError> {
+    let header = json!({
+        "alg": signer.algorithm().as_str(),
+        "typ": "JWT",
+        "kid": kid,
+    });
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?),
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?)
+    );
+    let signature = signer.sign(signing_input.as_bytes()).await?;
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{DecodingKey, Validation, decode};
+
+    #[derive(Serialize, serde::Deserialize)]
+    struct Claims {
+        sub: String,
+        exp: u64,
+    }
+
+    fn verify(token: &str, jwk: &PublicJwk, algorithm: Algorithm) -> Claims {
+        let PublicJwk::Ec { x, y, .. } = jwk else {
+            panic!("expected an EC key");
+        };
+        let key = DecodingKey::from_ec_components(x, y).unwrap();
+        decode::<Claims>(token, &key, &Validation::new(algorithm))
+            .unwrap()
+            .claims
+    }
+
+    #[tokio::test]
+    async fn signs_with_sec1_and_pkcs8_ec_keys() {
+        let claims = Claims {
+            sub: "cid".to_string(),
+            exp: 4_102_444_800,
+        };
+        let p256 = p256::SecretKey::random(&mut OsRng);
+        let p384 = p384::SecretKey::random(&mut OsRng);
+        for (pem, algorithm) in [
+            (
+                p256.to_sec1_pem(LineEnding::LF).unwrap().to_string(),
+                SigningAlgorithm::ES256,
+            ),
+            (
+                p256.to_pkcs8_pem(LineEnding::LF).unwrap().to_string(),
+                SigningAlgorithm::ES256,
+            ),
+            (
+                p384.to_sec1_pem(LineEnding::LF).unwrap().to_string(),
+                SigningAlgorithm::ES384,
+            ),
+        ] {
+            let signer = SoftwareSigner::from_pem(&pem, algorithm).unwrap();
+            let token = sign_jwt(&signer, "k1", &claims).await.unwrap();
+            let verified = verify(&token, signer.public_jwk(), algorithm.jwt_algorithm());
+            assert_eq!(verified.sub, "cid");
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Client đăng ký với server giả.
+
+use serde_json::Value;
+
+/// Cách client xác thực tại token endpoint.
+#[derive(Debug, Clone)]
+pub enum ClientAuth {
+    /// Public client: chỉ có PKCE
+    None,
+    /// `client_secret_basic` hoặc `client_secret_post`
+    Secret(String),
+    /// `private_key_jwt`, kiểm tra assertion bằng JWKS này
+    PrivateKeyJwt(Value),
+}
+
+/// Một ứng dụng đã đăng ký, như trên trang Epic "Build Apps".
+#[derive(Debug, Clone)]
+pub struct MockClient {
+    pub client_id: String,
+    pub redirect_uris: Vec<String>,
+    pub auth: ClientAuth,
+}
+
+impl MockClient {
+    pub fn public(client_id: impl Into<String>, redirect_uri: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            redirect_uris: vec![redirect_uri.into()],
+            auth: ClientAuth::None,
+        }
+    }
+
+    pub fn with_secret(
+        client_id: impl Into<String>,
+        redirect_uri: impl Into<String>,
+        secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            auth: ClientAuth::Secret(secret.into()),
+            ..Self::public(client_id, redirect_uri)
+        }
+    }
+
+    /// `jwks` là JWKS của client (ví dụ `KeyStore::jwks`).
+    pub fn with_jwks(
+        client_id: impl Into<String>,
+        redirect_uri: impl Into<String>,
+        jwks: Value,
+    ) -> Self {
+        Self {
+            auth: ClientAuth::PrivateKeyJwt(jwks),
+            ..Self::public(client_id, redirect_uri)
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Lỗi có chủ đích, để kiểm thử cách client xử lý khi authorization server gặp sự cố.
+
+use std::time::Duration;
+
+/// Lỗi áp dụng cho request kế tiếp tới endpoint tương ứng.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// Authorize: người dùng từ chối, redirect về với `error=access_denied`
+    AccessDenied,
+    /// Token: `400 invalid_grant` (code hết hạn, refresh token bị thu hồi)
+    InvalidGrant,
+    /// Token: `401 invalid_client` (assertion bị từ chối)
+    InvalidClient,
+    /// Token: `500 server_error`
+    ServerError,
+    /// Token: `503` kèm `Retry-After`
+    Unavailable,
+    /// Token: `200` nhưng body không phải JSON
+    MalformedResponse,
+    /// Token: trả lời bình thường sau khoảng chờ này (kiểm thử timeout)
+    Delay(Duration),
+}
+
+impl Failure {
+    /// Lỗi của authorize endpoint; các lỗi khác thuộc token endpoint.
+    pub(crate) fn is_authorize(&self) -> bool {
+        matches!(self, Failure::AccessDenied)
+    }
+}
@@ -0,0 +1,949 @@
+//! Các endpoint của server giả.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{Form, Path, Query, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use jsonwebtoken::jwk::JwkSet;
+use oauth2_lib::clock::MonotonicClock;
+use oauth2_lib::dpop::{
+    DPOP_HEADER, DPOP_NONCE_ERROR, DPOP_NONCE_HEADER, TOKEN_TYPE_DPOP, verify_proof,
+};
+use oauth2_lib::jwt_bearer::{CLIENT_ASSERTION_TYPE, JwtBearerVerifier};
+use security::{Signer, sign_jwt};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+use url::Url;
+use uuid::Uuid;
+
+use crate::client::ClientAuth;
+use crate::failure::Failure;
+use crate::{
+    AuthorizationGrant, CODE_LIFETIME, DEFAULT_PATIENT, ID_TOKEN_KID, IssuedToken, MockState,
+    PRACTITIONER, PushedRequest, REQUEST_URI_LIFETIME,
+};
+
+/// Thời hạn refresh token.
+const REFRESH_TOKEN_LIFETIME: Duration = Duration::days(30);
+/// Proof DPoP cũ hơn khoảng này bị từ chối.
+const DPOP_PROOF_MAX_AGE: Duration = Duration::seconds(60);
+/// Tiền tố `request_uri` do PAR endpoint cấp (RFC 9126 mục 2.2).
+const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+/// Tham số xác thực client, không được lưu cùng request đã đẩy.
+const CLIENT_AUTH_PARAMS: [&str; 3] =
+    ["client_assertion", "client_assertion_type", "client_secret"];
+
+type Params = HashMap<String, String>;
+
+pub(crate) fn router(state: Arc<MockState>) -> Router {
+    Router::new()
+        .route("/.well-known/smart-configuration", get(smart_configuration))
+        .route(
+            "/api/FHIR/R4/.well-known/smart-configuration",
+            get(smart_configuration),
+        )
+        .route("/.well-known/jwks.json", get(jwks))
+        .route("/oauth2/authorize", get(authorize))
+        .route("/oauth2/par", post(pushed_authorization))
+        .route("/oauth2/token", post(token))
+        .route("/oauth2/revoke", post(revoke))
+        .route("/oauth2/introspect", post(introspect))
+        .route("/api/FHIR/R4/Patient/{id}", get(read_patient))
+        .route("/api/FHIR/R4/Observation", get(search_observations))
+        .with_state(state)
+}
+
+async fn smart_configuration(State(state): State<Arc<MockState>>) -> Json<Value> {
+    let url = &state.url;
+    Json(json!({
+        "issuer": url,
+        "authorization_endpoint": format!("{}/oauth2/authorize", url),
+        "pushed_authorization_request_endpoint": state.par_url(),
+        "token_endpoint": state.token_url(),
+        "revocation_endpoint": format!("{}/oauth2/revoke", url),
+        "introspection_endpoint": format!("{}/oauth2/introspect", url),
+        "jwks_uri": format!("{}/.well-known/jwks.json", url),
+        "grant_types_supported": ["authorization_code", "refresh_token", "client_credentials"],
+        "response_types_supported": ["code"],
+        "code_challenge_methods_supported": ["S256"],
+        "token_endpoint_auth_methods_supported": [
+            "private_key_jwt", "client_secret_basic", "client_secret_post", "none"
+        ],
+        "token_endpoint_auth_signing_alg_values_supported": [
+            "RS256", "RS384", "RS512", "ES256", "ES384"
+        ],
+        "dpop_signing_alg_values_supported": ["RS256", "RS384", "RS512", "ES256", "ES384"],
+        "scopes_supported": [
+            "openid", "fhirUser", "launch", "launch/patient", "offline_access", "patient/*.read",
+            "system/*.read"
+        ],
+        "capabilities": [
+            "launch-standalone",
+            "client-public",
+            "client-confidential-symmetric",
+            "client-confidential-asymmetric",
+            "context-standalone-patient",
+            "permission-offline",
+            "permission-patient",
+            "sso-openid-connect"
+        ],
+    }))
+}
+
+async fn jwks(State(state): State<Arc<MockState>>) -> Json<Value> {
+    let jwk = state
+        .signer
+        .public_jwk()
+        .to_json(ID_TOKEN_KID, state.signer.algorithm());
+    Json(json!({ "keys": [jwk] }))
+}
+
+/// Authorize endpoint: người dùng luôn đồng ý, trừ khi có [`Failure::AccessDenied`].
+///
+/// Với `request_uri`, tham số lấy từ request đã đẩy lên PAR endpoint.
+async fn authorize(State(state): State<Arc<MockState>>, Query(query): Query<Params>) -> Response {
+    let mut inner = state.inner.lock().unwrap();
+    let params = match query.get("request_uri") {
+        Some(request_uri) => match inner.pushed_requests.remove(request_uri) {
+            Some(pushed)
+                if pushed.expires_at > OffsetDateTime::now_utc()
+                    && query.get("client_id") == Some(&pushed.client_id) =>
+            {
+                pushed.params
+            }
+            _ => {
+                return (StatusCode::BAD_REQUEST, "unknown or expired request_uri").into_response();
+            }
+        },
+        None if inner.require_par => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "pushed authorization request is required",
+            )
+                .into_response();
+        }
+        None => query,
+    };
+    let param = |name: &str| params.get(name).map(String::as_str);
+
+    // Không bao giờ redirect tới URI chưa đăng ký (RFC 6749 mục 4.1.2.1)
+    let Some(client) = param("client_id").and_then(|id| inner.clients.get(id)) else {
+        return (StatusCode::BAD_REQUEST, "unknown client_id").into_response();
+    };
+    let Some(redirect_uri) = param("redirect_uri").filter(|uri| {
+        client
+            .redirect_uris
+            .iter()
+            .any(|registered| registered == uri)
+    }) else {
+        return (StatusCode::BAD_REQUEST, "redirect_uri is not registered").into_response();
+    };
+    let client_id = client.client_id.clone();
+    let redirect_uri = redirect_uri.to_string();
+    let error = |error: &str, description: &str| {
+        redirect(
+            &redirect_uri,
+            &[
+                ("error", error),
+                ("error_description", description),
+                ("state", param("state").unwrap_or_default()),
+            ],
+        )
+    };
+
+    if param("response_type") != Some("code") {
+        return error("unsupported_response_type", "response_type must be code");
+    }
+    if param("code_challenge_method") != Some("S256") {
+        return error(
+            "invalid_request",
+            "PKCE with code_challenge_method=S256 is required",
+        );
+    }
+    let Some(code_challenge) = param("code_challenge") else {
+        return error("invalid_request", "code_challenge is required");
+    };
+    if param("aud") != Some(state.fhir_base_url().as_str()) {
+        return error("invalid_request", "aud must be the FHIR base URL");
+    }
+    if inner.authorize_failures.pop_front() == Some(Failure::AccessDenied) {
+        return error("access_denied", "the user denied the request");
+    }
+
+    let code = Uuid::new_v4().to_string();
+    inner.codes.insert(
+        code.clone(),
+        AuthorizationGrant {
+            client_id,
+            redirect_uri: redirect_uri.clone(),
+            code_challenge: code_challenge.to_string(),
+            scope: param("scope").unwrap_or_default().to_string(),
+            expires_at: OffsetDateTime::now_utc() + CODE_LIFETIME,
+        },
+    );
+    redirect(
+        &redirect_uri,
+        &[
+            ("code", &code),
+            ("state", param("state").unwrap_or_default()),
+        ],
+    )
+}
+
+/// PAR endpoint (RFC 9126): xác thực client như token endpoint, lưu tham số
+/// authorize và trả về `request_uri` dùng một lần.
+async fn pushed_authorization(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Form(mut params): Form<Params>,
+) -> Response {
+    let client_id = match authenticate(&state, &headers, &params).await {
+        Ok(client_id) => client_id,
+        Err(response) => return response,
+    };
+    if params.contains_key("request_uri") {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "request_uri must not be pushed",
+        );
+    }
+    let mut inner = state.inner.lock().unwrap();
+    let registered = params.get("redirect_uri").is_some_and(|uri| {
+        inner.clients[&client_id]
+            .redirect_uris
+            .iter()
+            .any(|registered| registered == uri)
+    });
+    if !registered {
+        return oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "redirect_uri is not registered",
+        );
+    }
+    for name in CLIENT_AUTH_PARAMS {
+        params.remove(name);
+    }
+    params.insert("client_id".to_string(), client_id.clone());
+
+    let request_uri = format!("{}{}", REQUEST_URI_PREFIX, Uuid::new_v4());
+    inner.pushed_requests.insert(
+        request_uri.clone(),
+        PushedRequest {
+            client_id,
+            params,
+            expires_at: OffsetDateTime::now_utc() + REQUEST_URI_LIFETIME,
+        },
+    );
+    (
+        StatusCode::CREATED,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(json!({
+            "request_uri": request_uri,
+            "expires_in": REQUEST_URI_LIFETIME.whole_seconds(),
+        })),
+    )
+        .into_response()
+}
+
+async fn token(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Form(params): Form<Params>,
+) -> Response {
+    let failure = state.inner.lock().unwrap().token_failures.pop_front();
+    match failure {
+        Some(Failure::Delay(delay)) => tokio::time::sleep(delay).await,
+        Some(failure) => return failure_response(failure),
+        None => {}
+    }
+
+    let client_id = match authenticate(&state, &headers, &params).await {
+        Ok(client_id) => client_id,
+        Err(response) => return response,
+    };
+    let param = |name: &str| params.get(name).map(String::as_str);
+    let now = OffsetDateTime::now_utc();
+
+    // Kiểm tra proof trước khi tiêu thụ code, để client thử lại được với nonce mới
+    let jkt = match check_dpop(
+        &state,
+        &headers,
+        &Method::POST,
+        &state.token_url(),
+        None,
+        now,
+    ) {
+        Ok(jkt) => jkt,
+        Err(rejection) => return rejection.token_response(&state),
+    };
+
+    let scope = match param("grant_type") {
+        Some("authorization_code") => {
+            let grant =
+                param("code").and_then(|code| state.inner.lock().unwrap().codes.remove(code));
+            let Some(grant) = grant.filter(|grant| grant.expires_at > now) else {
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_grant",
+                    "unknown or expired code",
+                );
+            };
+            if grant.client_id != client_id || param("redirect_uri") != Some(&grant.redirect_uri) {
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_grant",
+                    "code was issued to another client or redirect_uri",
+                );
+            }
+            let challenge = param("code_verifier")
+                .map(|verifier| URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())));
+            if challenge.as_deref() != Some(grant.code_challenge.as_str()) {
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_grant",
+                    "code_verifier does not match code_challenge",
+                );
+            }
+            grant.scope
+        }
+        Some("refresh_token") => {
+            // Refresh token chỉ dùng được một lần: mỗi lần refresh trả về token mới
+            let issued = param("refresh_token")
+                .and_then(|token| state.inner.lock().unwrap().refresh_tokens.remove(token));
+            match issued {
+                // Refresh token gắn key DPoP chỉ dùng được với proof của đúng key đó
+                Some(issued)
+                    if issued.client_id == client_id
+                        && issued.expires_at > now
+                        && (issued.jkt.is_none() || issued.jkt == jkt) =>
+                {
+                    issued.scope
+                }
+                _ => {
+                    return oauth_error(
+                        StatusCode::BAD_REQUEST,
+                        "invalid_grant",
+                        "unknown or revoked refresh token",
+                    );
+                }
+            }
+        }
+        // SMART Backend Services: chỉ client `private_key_jwt`, chỉ scope `system/`
+        Some("client_credentials") => {
+            if !params.contains_key("client_assertion") {
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "unauthorized_client",
+                    "client_credentials requires private_key_jwt",
+                );
+            }
+            let scope = param("scope").unwrap_or_default();
+            if scope.is_empty()
+                || scope
+                    .split_whitespace()
+                    .any(|scope| !scope.starts_with("system/"))
+            {
+                return oauth_error(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_scope",
+                    "backend services clients may only request system/ scopes",
+                );
+            }
+            return token_response(&state, &client_id, scope.to_string(), jkt, true, now).await;
+        }
+        _ => {
+            return oauth_error(
+                StatusCode::BAD_REQUEST,
+                "unsupported_grant_type",
+                "grant_type must be authorization_code, refresh_token or client_credentials",
+            );
+        }
+    };
+
+    token_response(&state, &client_id, scope, jkt, false, now).await
+}
+
+/// Response thành công của token endpoint.
+async fn token_response(
+    state: &MockState,
+    client_id: &str,
+    scope: String,
+    jkt: Option<String>,
+    backend: bool,
+    now: OffsetDateTime,
+) -> Response {
+    match issue_tokens(state, client_id, scope, jkt, backend, now).await {
+        Ok(body) => (
+            [
+                (header::CACHE_CONTROL, "no-store"),
+                (header::PRAGMA, "no-cache"),
+            ],
+            Json(body),
+        )
+            .into_response(),
+        Err(e) => oauth_error(StatusCode::INTERNAL_SERVER_ERROR, "server_error", &e),
+    }
+}
+
+/// Revocation (RFC 7009): luôn trả 200, kể cả khi token không tồn tại.
+async fn revoke(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Form(params): Form<Params>,
+) -> Response {
+    let client_id = match authenticate(&state, &headers, &params).await {
+        Ok(client_id) => client_id,
+        Err(response) => return response,
+    };
+    if let Some(token) = params.get("token") {
+        let inner = &mut *state.inner.lock().unwrap();
+        for tokens in [&mut inner.access_tokens, &mut inner.refresh_tokens] {
+            if tokens
+                .get(token)
+                .is_some_and(|issued| issued.client_id == client_id)
+            {
+                tokens.remove(token);
+            }
+        }
+    }
+    StatusCode::OK.into_response()
+}
+
+/// Introspection (RFC 7662) cho mọi client đã đăng ký.
+async fn introspect(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Form(params): Form<Params>,
+) -> Response {
+    if let Err(response) = authenticate(&state, &headers, &params).await {
+        return response;
+    }
+    let now = OffsetDateTime::now_utc();
+    let issued = params.get("token").and_then(|token| {
+        state
+            .inner
+            .lock()
+            .unwrap()
+            .access_tokens
+            .get(token)
+            .filter(|issued| issued.expires_at > now)
+            .cloned()
+    });
+    let body = match issued {
+        Some(issued) => {
+            let mut body = json!({
+                "active": true,
+                "iss": state.url,
+                "aud": state.fhir_base_url(),
+                "sub": issued.subject,
+                "client_id": issued.client_id,
+                "scope": issued.scope,
+                "patient": issued.patient,
+                "token_type": "Bearer",
+                "exp": issued.expires_at.unix_timestamp(),
+            });
+            if let Some(jkt) = &issued.jkt {
+                body["token_type"] = json!(TOKEN_TYPE_DPOP);
+                body["cnf"] = json!({ "jkt": jkt });
+            }
+            body
+        }
+        None => json!({ "active": false }),
+    };
+    Json(body).into_response()
+}
+
+/// Xác thực client tại token endpoint theo cách client đã đăng ký; trả về client id.
+async fn authenticate(
+    state: &MockState,
+    headers: &HeaderMap,
+    params: &Params,
+) -> Result<String, Response> {
+    let invalid_client = |description: &str| {
+        let mut response = oauth_error(StatusCode::UNAUTHORIZED, "invalid_client", description);
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            header::HeaderValue::from_static("Basic realm=\"token\""),
+        );
+        response
+    };
+    let clients = state.inner.lock().unwrap().clients.clone();
+
+    if let Some(assertion) = params.get("client_assertion") {
+        if params.get("client_assertion_type").map(String::as_str) != Some(CLIENT_ASSERTION_TYPE) {
+            return Err(invalid_client("unsupported client_assertion_type"));
+        }
+        let mut verifier = JwtBearerVerifier::new(
+            vec![state.token_url()],
+            Arc::new(MonotonicClock::new()),
+            state.jti_store.clone(),
+        );
+        for client in clients.values() {
+            if let ClientAuth::PrivateKeyJwt(jwks) = &client.auth {
+                let keys: JwkSet = serde_json::from_value(jwks.clone())
+                    .map_err(|e| invalid_client(&format!("client JWKS is invalid: {}", e)))?;
+                verifier.register_issuer(&client.client_id, keys);
+            }
+        }
+        let verified = verifier
+            .verify(assertion)
+            .await
+            .map_err(|e| invalid_client(&e.to_string()))?;
+        if verified.issuer != verified.subject
+            || params
+                .get("client_id")
+                .is_some_and(|client_id| *client_id != verified.issuer)
+        {
+            return Err(invalid_client("assertion iss/sub do not match the client"));
+        }
+        return Ok(verified.issuer);
+    }
+
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|decoded| {
+            let (id, secret) = decoded.split_once(':')?;
+            Some((id.to_string(), Some(secret.to_string())))
+        });
+    let (client_id, secret) = match basic {
+        Some(credentials) => credentials,
+        None => match params.get("client_id") {
+            Some(client_id) => (client_id.clone(), params.get("client_secret").cloned()),
+            None => return Err(invalid_client("client authentication is required")),
+        },
+    };
+    let Some(client) = clients.get(&client_id) else {
+        return Err(invalid_client("unknown client"));
+    };
+    match (&client.auth, secret) {
+        (ClientAuth::None, None) => Ok(client_id),
+        (ClientAuth::Secret(expected), Some(secret)) if *expected == secret => Ok(client_id),
+        (ClientAuth::Secret(_), _) => Err(invalid_client("wrong client secret")),
+        (ClientAuth::PrivateKeyJwt(_), _) => Err(invalid_client("client must use private_key_jwt")),
+        (ClientAuth::None, Some(_)) => Err(invalid_client("public client has no secret")),
+    }
+}
+
+/// Phát hành access token, refresh token và (với scope `openid`) ID token.
+/// Client `backend` (client credentials) không có người dùng: token mang
+/// `sub` là chính client và không kèm refresh token.
+async fn issue_tokens(
+    state: &MockState,
+    client_id: &str,
+    scope: String,
+    jkt: Option<String>,
+    backend: bool,
+    now: OffsetDateTime,
+) -> Result<Value, String> {
+    let scopes: Vec<&str> = scope.split_whitespace().collect();
+    let access_token = format!("mock-at-{}", Uuid::new_v4());
+    let refresh_token = (!backend).then(|| format!("mock-rt-{}", Uuid::new_v4()));
+    let issued = {
+        let mut inner = state.inner.lock().unwrap();
+        let patient = scopes
+            .iter()
+            .any(|scope| *scope == "launch/patient" || *scope == "launch")
+            .then(|| inner.patient.clone())
+            .flatten();
+        let issued = IssuedToken {
+            client_id: client_id.to_string(),
+            subject: if backend {
+                client_id.to_string()
+            } else {
+                PRACTITIONER.to_string()
+            },
+            scope: scope.clone(),
+            patient,
+            expires_at: now + inner.token_lifetime,
+            jkt,
+        };
+        inner
+            .access_tokens
+            .insert(access_token.clone(), issued.clone());
+        if let Some(refresh_token) = &refresh_token {
+            inner.refresh_tokens.insert(
+                refresh_token.clone(),
+                IssuedToken {
+                    expires_at: now + REFRESH_TOKEN_LIFETIME,
+                    ..issued.clone()
+                },
+            );
+        }
+        issued
+    };
+
+    let mut body = json!({
+        "access_token": access_token,
+        "token_type": if issued.jkt.is_some() { TOKEN_TYPE_DPOP } else { "Bearer" },
+        "expires_in": (issued.expires_at - now).whole_seconds(),
+        "scope": scope,
+    });
+    if let Some(refresh_token) = &refresh_token {
+        body["refresh_token"] = json!(refresh_token);
+    }
+    if let Some(patient) = &issued.patient {
+        body["patient"] = json!(patient);
+    }
+    if scopes.contains(&"openid") {
+        let claims = json!({
+            "iss": state.url,
+            "sub": issued.subject,
+            "aud": client_id,
+            "iat": now.unix_timestamp(),
+            "exp": issued.expires_at.unix_timestamp(),
+            "fhirUser": format!("{}/Practitioner/{}", state.fhir_base_url(), issued.subject),
+        });
+        let id_token = sign_jwt(&state.signer, ID_TOKEN_KID, &claims)
+            .await
+            .map_err(|e| e.to_string())?;
+        body["id_token"] = json!(id_token);
+    }
+    Ok(body)
+}
+
+/// FHIR `Patient` read: kiểm tra access token như Epic. Token gắn DPoP phải đi
+/// với `Authorization: DPoP` và proof có `ath`.
+async fn read_patient(
+    State(state): State<Arc<MockState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let url = format!("{}/Patient/{}", state.fhir_base_url(), id);
+    let issued = match authorize_resource(&state, &headers, &url) {
+        Ok(issued) => issued,
+        Err(rejection) => return rejection.resource_response(&state),
+    };
+    if issued
+        .patient
+        .as_ref()
+        .is_some_and(|patient| *patient != id)
+        || id != DEFAULT_PATIENT
+    {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "resourceType": "OperationOutcome",
+                "issue": [{ "severity": "error", "code": "not-found" }],
+            })),
+        )
+            .into_response();
+    }
+    Json(json!({
+        "resourceType": "Patient",
+        "id": id,
+        "name": [{ "use": "official", "family": "Lopez", "given": ["Camila", "Maria"] }],
+        "gender": "female",
+        "birthDate": "1987-09-12",
+    }))
+    .into_response()
+}
+
+/// `GET Observation?patient=...`: hai trang, mỗi trang một Observation kèm
+/// Practitioner `_include`; trang đầu có link `next`. Với `_format=xml` hoặc
+/// `Accept` XML thì trả về Bundle dạng XML.
+async fn search_observations(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<Params>,
+    headers: HeaderMap,
+) -> Response {
+    let base = state.fhir_base_url();
+    let url = format!("{}/Observation", base);
+    let issued = match authorize_resource(&state, &headers, &url) {
+        Ok(issued) => issued,
+        Err(rejection) => return rejection.resource_response(&state),
+    };
+    let patient = params.get("patient").cloned().unwrap_or_default();
+    if issued
+        .patient
+        .as_ref()
+        .is_some_and(|launch| *launch != patient)
+    {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "resourceType": "OperationOutcome",
+                "issue": [{ "severity": "error", "code": "forbidden" }],
+            })),
+        )
+            .into_response();
+    }
+    let wants_xml = params
+        .get("_format")
+        .is_some_and(|format| format.contains("xml"))
+        || headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("xml"));
+    if wants_xml {
+        let bundle = format!(
+            "<Bundle xmlns=\"http://hl7.org/fhir\"><type value=\"searchset\"/>\
+             <link><relation value=\"next\"/><url value=\"{}?patient={}&amp;page=2\"/></link>\
+             <entry><resource><Practitioner><id value=\"{}\"/></Practitioner></resource></entry>\
+             </Bundle>",
+            url, patient, PRACTITIONER
+        );
+        return ([(header::CONTENT_TYPE, "application/fhir+xml")], bundle).into_response();
+    }
+    let page: u32 = params
+        .get("page")
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1);
+    let mut link = vec![json!({
+        "relation": "self",
+        "url": format!("{}?patient={}&page={}", url, patient, page),
+    })];
+    if page == 1 {
+        link.push(json!({
+            "relation": "next",
+            "url": format!("{}?patient={}&page=2", url, patient),
+        }));
+    }
+    let id = format!("obs-{}", page);
+    Json(json!({
+        "resourceType": "Bundle",
+        "type": "searchset",
+        "total": 2,
+        "link": link,
+        "entry": [
+            {
+                "fullUrl": format!("{}/Observation/{}", base, id),
+                "resource": {
+                    "resourceType": "Observation",
+                    "id": id,
+                    "status": "final",
+                    "code": { "text": "Hemoglobin" },
+                    "subject": { "reference": format!("Patient/{}", patient) },
+                    "performer": [{ "reference": format!("Practitioner/{}", PRACTITIONER) }],
+                },
+                "search": { "mode": "match" },
+            },
+            {
+                "fullUrl": format!("{}/Practitioner/{}", base, PRACTITIONER),
+                "resource": { "resourceType": "Practitioner", "id": PRACTITIONER },
+                "search": { "mode": "include" },
+            },
+        ],
+    }))
+    .into_response()
+}
+
+/// Access token (và proof DPoP nếu token gắn key) của một request `GET` tới `url`.
+fn authorize_resource(
+    state: &MockState,
+    headers: &HeaderMap,
+    url: &str,
+) -> Result<IssuedToken, DpopRejection> {
+    let now = OffsetDateTime::now_utc();
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '));
+    let Some((scheme, access_token)) = authorization else {
+        return Err(DpopRejection::InvalidToken("missing access token".into()));
+    };
+    let issued = state
+        .inner
+        .lock()
+        .unwrap()
+        .access_tokens
+        .get(access_token)
+        .filter(|issued| issued.expires_at > now)
+        .cloned();
+    let Some(issued) = issued else {
+        return Err(DpopRejection::InvalidToken(
+            "unknown or expired access token".into(),
+        ));
+    };
+    match (&issued.jkt, scheme) {
+        (None, scheme) if scheme.eq_ignore_ascii_case("Bearer") => {}
+        (Some(jkt), scheme) if scheme.eq_ignore_ascii_case(TOKEN_TYPE_DPOP) => {
+            let proof_jkt = check_dpop(state, headers, &Method::GET, url, Some(access_token), now)?;
+            if proof_jkt.as_ref() != Some(jkt) {
+                return Err(DpopRejection::InvalidProof(
+                    "proof key does not match the token".into(),
+                ));
+            }
+        }
+        _ => {
+            return Err(DpopRejection::InvalidToken(
+                "wrong authorization scheme for the token".into(),
+            ));
+        }
+    }
+    Ok(issued)
+}
+
+/// Lý do từ chối một request mang (hoặc thiếu) proof DPoP.
+enum DpopRejection {
+    InvalidProof(String),
+    UseNonce,
+    InvalidToken(String),
+}
+
+impl DpopRejection {
+    /// Lỗi tại token endpoint: 400 (RFC 9449 mục 5, 8).
+    fn token_response(self, state: &MockState) -> Response {
+        let mut response = match self {
+            Self::UseNonce => oauth_error(
+                StatusCode::BAD_REQUEST,
+                DPOP_NONCE_ERROR,
+                "authorization server requires nonce in DPoP proof",
+            ),
+            Self::InvalidProof(description) | Self::InvalidToken(description) => {
+                oauth_error(StatusCode::BAD_REQUEST, "invalid_dpop_proof", &description)
+            }
+        };
+        with_dpop_nonce(state, &mut response);
+        response
+    }
+
+    /// Lỗi tại resource server: 401 với `WWW-Authenticate` (RFC 9449 mục 7.1, 9).
+    fn resource_response(self, state: &MockState) -> Response {
+        let (error, description) = match self {
+            Self::UseNonce => (
+                DPOP_NONCE_ERROR,
+                "resource server requires nonce in DPoP proof".to_string(),
+            ),
+            Self::InvalidProof(description) => ("invalid_dpop_proof", description),
+            Self::InvalidToken(description) => ("invalid_token", description),
+        };
+        let challenge = format!(
+            "DPoP error=\"{}\", error_description=\"{}\", algs=\"ES256 RS256\"",
+            error, description
+        );
+        let mut response = (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, challenge)],
+        )
+            .into_response();
+        with_dpop_nonce(state, &mut response);
+        response
+    }
+}
+
+/// Kiểm tra proof trong header `DPoP` của request `method` tới `url`; trả về
+/// thumbprint key của proof, hoặc `None` nếu request không mang proof.
+fn check_dpop(
+    state: &MockState,
+    headers: &HeaderMap,
+    method: &Method,
+    url: &str,
+    access_token: Option<&str>,
+    now: OffsetDateTime,
+) -> Result<Option<String>, DpopRejection> {
+    let mut proofs = headers.get_all(DPOP_HEADER).iter();
+    let (proof, None) = (proofs.next(), proofs.next()) else {
+        return Err(DpopRejection::InvalidProof(
+            "more than one DPoP header".into(),
+        ));
+    };
+    let Some(proof) = proof else {
+        if access_token.is_some() {
+            return Err(DpopRejection::InvalidProof("DPoP proof is required".into()));
+        }
+        return Ok(None);
+    };
+    let proof = proof
+        .to_str()
+        .map_err(|e| DpopRejection::InvalidProof(e.to_string()))?;
+    let verified = verify_proof(
+        proof,
+        method.as_str(),
+        url,
+        access_token,
+        now,
+        DPOP_PROOF_MAX_AGE,
+    )
+    .map_err(|e| DpopRejection::InvalidProof(e.to_string()))?;
+
+    let mut inner = state.inner.lock().unwrap();
+    if inner.dpop_nonce.is_some() && verified.claims.nonce != inner.dpop_nonce {
+        return Err(DpopRejection::UseNonce);
+    }
+    if !inner.dpop_jtis.insert(verified.claims.jti) {
+        return Err(DpopRejection::InvalidProof(
+            "proof jti was already used".into(),
+        ));
+    }
+    Ok(Some(verified.jkt))
+}
+
+/// Gửi kèm nonce hiện hành để client ký lại proof.
+fn with_dpop_nonce(state: &MockState, response: &mut Response) {
+    let nonce = state.inner.lock().unwrap().dpop_nonce.clone();
+    if let Some(value) = nonce.and_then(|nonce| HeaderValue::from_str(&nonce).ok()) {
+        response.headers_mut().insert(DPOP_NONCE_HEADER, value);
+    }
+}
+
+fn failure_response(failure: Failure) -> Response {
+    match failure {
+        Failure::InvalidGrant => oauth_error(
+            StatusCode::BAD_REQUEST,
+            "invalid_grant",
+            "the grant is invalid or has expired",
+        ),
+        Failure::InvalidClient => oauth_error(
+            StatusCode::UNAUTHORIZED,
+            "invalid_client",
+            "client authentication failed",
+        ),
+        Failure::ServerError => oauth_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "server_error",
+            "internal error",
+        ),
+        Failure::Unavailable => {
+            let mut response = oauth_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "temporarily_unavailable",
+                "try again later",
+            );
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, header::HeaderValue::from_static("1"));
+            response
+        }
+        Failure::MalformedResponse => (
+            [(header::CONTENT_TYPE, "text/html")],
+            "<html><body>502 Bad Gateway</body></html>",
+        )
+            .into_response(),
+        Failure::AccessDenied | Failure::Delay(_) => unreachable!("handled by the caller"),
+    }
+}
+
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> Response {
+    (
+        status,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(json!({ "error": error, "error_description": description })),
+    )
+        .into_response()
+}
+
+fn redirect(uri: &str, params: &[(&str, &str)]) -> Response {
+    let mut location = match Url::parse(uri) {
+        Ok(location) => location,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    location.query_pairs_mut().extend_pairs(params);
+    (
+        StatusCode::FOUND,
+        [(header::LOCATION, location.to_string())],
+    )
+        .into_response()
+}
@@ -1 +1 @@
-pub use config_lib::{load_settings, settings::OAuth2ClientSettings, Settings};
+pub use config_lib::load_settings;
@@ -1,35 +1,87 @@
+use crate::health::HealthRegistry;
+use crate::identity::UpstreamGrants;
+use crate::tls;
 use anyhow::Context;
-use axum::extract::FromRef;
-use axum::response::{IntoResponse, Response};
+use arc_swap::ArcSwap;
 use config_lib::Settings;
-use oauth2::basic::BasicClient;
-use oauth2::{ClientId, RedirectUrl};
 use oauth2_lib::epic::client::EpicFhirClient;
 use oauth2_lib::epic::config::EpicFhirConfig;
+use oauth2_lib::epic::error;
+use performance::SummaryCache;
 use reqwest::StatusCode;
+use security::{KeyStore, TokenIssuer};
 use std::collections::HashMap;
-use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use time::Duration;
-use tokio::sync::Mutex;
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+use tower_sessions::MemoryStore;
 
 pub type SharedState = Arc<AppState>;
 
 pub struct AppState {
-    pub settings: Settings,
+    /// Cấu hình hiện tại; được thay nguyên khối khi nạp lại cấu hình
+    snapshot: ArcSwap<Snapshot>,
+    /// Store của session layer (cùng instance với `main`); chưa handler nào đọc
+    #[allow(dead_code)]
     pub store: MemoryStore,
-    pub oauth_clients: HashMap<String, Arc<Mutex<EpicFhirClient>>>, // Hoặc một kiểu client chung hơn
+    /// Access token của provider theo phiên đăng nhập, dùng cho token exchange
+    pub grants: UpstreamGrants,
+    /// Cache summary bệnh nhân; dựng một lần lúc khởi động, nạp lại cấu hình không đổi nó
+    pub summary_cache: Option<SummaryCache>,
+    shutting_down: AtomicBool,
 }
 
-#[derive(Clone)]
-pub struct AppMemoryStore(pub MemoryStore);
+/// Mọi thứ được dựng từ một phiên bản cấu hình.
+///
+/// Handler lấy snapshot một lần ở đầu request ([`AppState::snapshot`]) nên
+/// request đang chạy vẫn dùng cấu hình cũ khi có bản mới được nạp.
+pub struct Snapshot {
+    pub settings: Settings,
+    /// OAuth client theo tên; dùng chung cho mọi request, không cần lock
+    pub oauth_clients: HashMap<String, Arc<EpicFhirClient>>,
+    pub health: HealthRegistry,
+    /// HTTP client cho các call tới upstream (mTLS nếu có `upstream_tls`)
+    pub upstream_client: reqwest::Client,
+    /// Key ký client assertion và token nội bộ, cũng là nguồn của JWKS
+    pub keys: KeyStore,
+    /// Phát hành token nội bộ; `None` nếu không cấu hình `internal_tokens`
+    pub token_issuer: Option<TokenIssuer>,
+}
 
-#[derive(Clone)]
-pub struct AppSettings(pub Settings); // Newtype bao bọc Settings
+impl Snapshot {
+    /// Base URL của một upstream đã cấu hình trong `upstreams`
+    pub fn upstream_url(&self, name: &str) -> Result<&str, error::AxumAppError> {
+        self.settings
+            .upstreams
+            .get(name)
+            .map(|upstream| upstream.url.trim_end_matches('/'))
+            .ok_or_else(|| {
+                error::AxumAppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Upstream '{}' is not configured", name),
+                )
+            })
+    }
+}
 
-#[derive(Clone)]
-pub struct AppEpicClient(pub Arc<Mutex<EpicFhirClient>>);
+impl AppState {
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// Thay cấu hình đang dùng; request mới nhận snapshot mới ngay sau lời gọi này.
+    pub fn replace_snapshot(&self, snapshot: Snapshot) {
+        self.snapshot.store(Arc::new(snapshot));
+        // Snapshot mới phải tiếp tục báo not-ready nếu gateway đang tắt
+        if self.shutting_down.load(Ordering::SeqCst) {
+            self.snapshot.load().health.mark_shutting_down();
+        }
+    }
+
+    pub fn mark_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        self.snapshot.load().health.mark_shutting_down();
+    }
+}
 
 // pub fn session_layer(_settings: &Settings) -> SessionManagerLayer<MemoryStore> {
 //     let store = MemoryStore::default();
@@ -38,7 +90,11 @@ pub struct AppEpicClient(pub Arc<Mutex<EpicFhirClient>>);
 //         .with_expiry(Expiry::OnInactivity(Duration::seconds(600)))
 // }
 
-pub async fn build_state(settings: Settings, store: MemoryStore) -> anyhow::Result<SharedState> {
+/// Dựng OAuth clients, HTTP client và health checks từ một phiên bản cấu hình.
+pub async fn build_snapshot(settings: Settings) -> anyhow::Result<Snapshot> {
+    let keys = KeyStore::from_settings(&settings)
+        .await
+        .context("failed to load signing keys")?;
     let mut oauth_clients_map = HashMap::new();
 
     for (client_name, client_config_values) in &settings.oauth_clients {
@@ -47,31 +103,71 @@ pub async fn build_state(settings: Settings, store: MemoryStore) -> anyhow::Resu
         let epic_config = EpicFhirConfig {
             // Bạn có thể cần một struct config chung hơn nếu các provider khác nhau nhiều
             client_id: client_config_values.client_id.clone(),
-            client_secret: client_config_values.client_secret.clone().expect("REASON"),
+            client_secret: client_config_values.client_secret.clone(),
             auth_url: client_config_values.auth_url.clone(),
             token_url: client_config_values.token_url.clone(),
             redirect_url: client_config_values.redirect_uri.clone(), // Chú ý tên trường
             scopes: client_config_values.scopes.clone(),
             audience: client_config_values.audience.clone(),
-            private_key_pem: client_config_values.private_key_pem.clone(),
-            key_id: client_config_values.key_id.clone(),
-            jwt_algorithm: client_config_values.private_key_algorithm.clone(), // Chú ý tên trường
+            signing_keys: keys.client(client_name),
+            assertion: client_config_values.assertion.clone(),
+            par_url: client_config_values.par_url.clone(),
+            dpop: client_config_values.dpop,
         };
 
         let client = EpicFhirClient::new(epic_config).map_err(|e| {
             anyhow::anyhow!("Failed to create OAuth client for {}: {:?}", client_name, e)
         })?;
-        oauth_clients_map.insert(client_name.clone(), Arc::new(Mutex::new(client)));
+        oauth_clients_map.insert(client_name.clone(), Arc::new(client));
     }
 
-    let state = AppState {
+    let token_issuer = match (&settings.internal_tokens, keys.internal()) {
+        (Some(internal), Some(internal_keys)) => Some(TokenIssuer::new(
+            settings.internal_token_issuer().unwrap_or_default(),
+            time::Duration::seconds(internal.lifetime_secs as i64),
+            internal_keys,
+        )),
+        _ => None,
+    };
+
+    let upstream_client = tls::upstream_client(settings.upstream_tls.as_ref())?;
+    let health =
+        HealthRegistry::from_settings(&settings, upstream_client.clone(), health_check_client()?);
+    Ok(Snapshot {
         settings,
-        store,
         oauth_clients: oauth_clients_map,
+        health,
+        upstream_client,
+        keys,
+        token_issuer,
+    })
+}
+
+pub async fn build_state(settings: Settings, store: MemoryStore) -> anyhow::Result<SharedState> {
+    let summary_cache = settings
+        .summary_cache
+        .as_ref()
+        .map(SummaryCache::from_settings)
+        .transpose()
+        .context("failed to open summary cache store")?;
+    let state = AppState {
+        snapshot: ArcSwap::from_pointee(build_snapshot(settings).await?),
+        store,
+        grants: UpstreamGrants::default(),
+        summary_cache,
+        shutting_down: AtomicBool::new(false),
     };
     Ok(Arc::new(state))
 }
 
+/// Client dùng cho health check tới OAuth provider: không follow redirect, timeout do HealthRegistry quản lý
+fn health_check_client() -> anyhow::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .context("failed to build health check HTTP client")
+}
+
 // #[derive(Clone)]
 // struct AxumAppState {
 //     store: MemoryStore,
@@ -90,83 +186,40 @@ pub async fn build_state(settings: Settings, store: MemoryStore) -> anyhow::Resu
 //     }
 // }
 
-#[derive(Debug)]
-pub struct AxumAppError(anyhow::Error);
-
-// Tell axum how to convert `AppError` into a response.
-impl IntoResponse for AxumAppError {
-    fn into_response(self) -> Response {
-        tracing::error!("Application error: {:#}", self.0);
-
-        (StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong").into_response()
-    }
-}
-
-// This enables using `?` on functions that return `Result<_, anyhow::Error>` to turn them into
-// `Result<_, AppError>`. That way you don't need to do that manually.
-impl<E> From<E> for AxumAppError
-where
-    E: Into<anyhow::Error>,
-{
-    fn from(err: E) -> Self {
-        Self(err.into())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings(patient_summary_url: &str) -> Settings {
+        serde_json::from_value(json!({
+            "port": 3000,
+            "base_url": "http://localhost:3000",
+            "oauth_clients": {},
+            "upstreams": { "patient_summary": { "url": patient_summary_url } },
+        }))
+        .unwrap()
     }
-}
 
-pub async fn axum_build_state(
-    settings: Settings,
-    store: MemoryStore,
-) -> anyhow::Result<SharedState> {
-    let mut oauth_clients_map = HashMap::new();
-
-    for (client_name, client_config_values) in &settings.oauth_clients {
-        let epic_config = EpicFhirConfig {
-            client_id: client_config_values.client_id.clone(),
-            client_secret: client_config_values.client_secret.clone().expect("REASON"),
-            auth_url: client_config_values.auth_url.clone(),
-            token_url: client_config_values.token_url.clone(),
-            redirect_url: client_config_values.redirect_uri.clone(), // Chú ý tên trường
-            scopes: client_config_values.scopes.clone(),
-            audience: client_config_values.audience.clone(),
-            private_key_pem: client_config_values.private_key_pem.clone(),
-            key_id: client_config_values.key_id.clone(),
-            jwt_algorithm: client_config_values.private_key_algorithm.clone(),
-        };
-        let client = EpicFhirClient::new(epic_config).map_err(|e| {
-            anyhow::anyhow!("Failed to create OAuth client for {}: {:?}", client_name, e)
-        })?;
-        oauth_clients_map.insert(client_name.clone(), Arc::new(Mutex::new(client)));
+    #[tokio::test]
+    async fn replaced_snapshot_does_not_affect_in_flight_requests() {
+        let state = build_state(settings("http://old:3010"), MemoryStore::default())
+            .await
+            .unwrap();
+        let in_flight = state.snapshot();
+
+        state.mark_shutting_down();
+        state.replace_snapshot(build_snapshot(settings("http://new:3010/")).await.unwrap());
+
+        assert_eq!(
+            in_flight.upstream_url("patient_summary").unwrap(),
+            "http://old:3010"
+        );
+        let current = state.snapshot();
+        assert_eq!(
+            current.upstream_url("patient_summary").unwrap(),
+            "http://new:3010"
+        );
+        assert!(current.health.report().await.shutting_down);
     }
-
-    let state = AppState {
-        settings,
-        store,
-        oauth_clients: oauth_clients_map,
-    };
-    Ok(Arc::new(state))
-}
-
-fn axum_oauth_client() -> Result<BasicClient, AxumAppError> {
-    // Environment variables (* = required):
-    // *"CLIENT_ID"     "REPLACE_ME";
-    // *"CLIENT_SECRET" "REPLACE_ME";
-    //  "REDIRECT_URL"  "http://127.0.0.1:3000/auth/authorized";
-    //  "AUTH_URL"      "https://discord.com/api/oauth2/authorize?response_type=code";
-    //  "TOKEN_URL"     "https://discord.com/api/oauth2/token";
-
-    let client_id = env::var("CLIENT_ID").context("Missing CLIENT_ID!")?;
-    let client_secret = env::var("CLIENT_SECRET").context("Missing CLIENT_SECRET!")?;
-    let redirect_url = env::var("REDIRECT_URL")
-        .unwrap_or_else(|_| "http://127.0.0.1:3000/auth/authorized".to_string());
-
-    let auth_url = env::var("AUTH_URL").unwrap_or_else(|_| {
-        "https://discord.com/api/oauth2/authorize?response_type=code".to_string()
-    });
-
-    let token_url = env::var("TOKEN_URL")
-        .unwrap_or_else(|_| "https://discord.com/api/oauth2/token".to_string());
-
-    Ok(BasicClient::new(ClientId::new(client_id)).set_redirect_uri(
-        RedirectUrl::new(redirect_url).context("failed to create new redirection URL")?,
-    ))
 }
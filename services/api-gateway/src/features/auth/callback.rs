@@ -1,20 +1,25 @@
-use crate::di::{AppState, SharedState};
+use crate::di::AppState;
+use crate::identity::{SessionIdentity, SESSION_IDENTITY_KEY};
+use super::relogin::ReloginPage;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
     response::{IntoResponse, Redirect},
 };
 use axum_macros::debug_handler;
-use oauth2_lib::epic::error::AxumAppError;
+use oauth2_lib::epic::error::{AxumAppError, Error as EpicError};
 use serde::Deserialize;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use time::OffsetDateTime;
 use tower_sessions::Session;
 
 #[derive(Deserialize)]
 pub struct CallbackQuery {
-    pub code: String,
-    pub state: String,
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Epic redirect về với `error` thay cho `code` khi không cấp quyền (RFC 6749 mục 4.1.2.1)
+    pub error: Option<String>,
+    pub error_description: Option<String>,
 }
 #[debug_handler]
 pub async fn epic_callback_handler(
@@ -26,46 +31,38 @@ pub async fn epic_callback_handler(
     let stored_state: Option<String> = match session.get("csrf_token").await {
         Ok(token) => token,
         Err(e) => {
-            eprintln!("Session error while getting csrf_token: {e:?}");
+            tracing::warn!("Session error while getting csrf_token: {}", e);
             // Trả về lỗi hoặc redirect đến trang login/error
-            return Err((AxumAppError::from(e)));
+            return Err(AxumAppError::from(e));
         }
     };
 
-    if stored_state.as_deref() != Some(&query.state) {
-        eprintln!(
-            "CSRF token mismatch. Stored: {:?}, Received: {:?}",
-            stored_state, query.state
+    // `state` được kiểm tra trước cả khi hiển thị lỗi Epic trả về: callback không
+    // khớp phiên có thể do bên thứ ba dựng lên.
+    if stored_state.is_none() || stored_state != query.state {
+        tracing::warn!("Callback state does not match the session");
+        return Ok(ReloginPage::state_mismatch().into_response());
+    }
+    if let Some(error) = &query.error {
+        tracing::warn!(
+            "Authorization error {:?}: {:?}",
+            error,
+            query.error_description
         );
-        // Redirect về trang login hoặc trả lỗi rõ ràng hơn
-        return Ok(Redirect::to("/auth/error2").into_response());
+        return Ok(ReloginPage::from_authorization_error(error).into_response());
     }
+    let (Some(received_state), Some(code)) = (query.state, query.code) else {
+        return Ok(ReloginPage::from_authorization_error("invalid_request").into_response());
+    };
 
-    // Lấy mutable EpicFhirClient từ state
-    // let mut epic_client = match state.epic_client.lock().await {
-    //     Ok(client) => client,
-    //     Err(poison_error) => {
-    //         // When the lock is poisoned, don't try to convert the PoisonError directly
-    //         // as it contains the non-Send MutexGuard.
-    //         // Instead, create a specific error for this situation.
-    //         eprintln!("Failed to lock EpicFhirClient due to mutex poisoning: {:?}", poison_error);
-    //         // Assuming AxumAppError has a constructor like `new(StatusCode, String)`
-    //         // Try using From<(StatusCode, String)>
-    //             return Err(AxumAppError::new(
-    //             StatusCode::INTERNAL_SERVER_ERROR,
-    //             "Internal server error: Could not acquire resource lock.".to_string()
-    //         ))
-    //      }
-    // };
-    let app_state = state.as_ref(); // Nếu SharedState là Arc<AppState>
-    let epic_client_arc = app_state.oauth_clients.get("epic_sandbox").ok_or_else(|| {
+    let snapshot = state.snapshot(); // Giữ nguyên cấu hình trong suốt request
+    let epic_client_arc = snapshot.oauth_clients.get("epic_sandbox").ok_or_else(|| {
         AxumAppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
             "Epic client not found in state".to_string(),
         )
     })?;
 
-    let mut epic_client = epic_client_arc.lock().await;
     // Đổi code lấy access tokenlet csrf_token: String = session.get("csrf_token").await?.ok_or(...)?;
     let csrf_token: String = session.get("csrf_token").await?.ok_or_else(|| {
         AxumAppError::new(
@@ -79,24 +76,39 @@ pub async fn epic_callback_handler(
             "PKCE verifier not found in session".to_string(),
         )
     })?;
-    let session_id = session.id();
-    tracing::info!("CALLBACK: session_id={:?}, csrf_token={:?}, pkce_verifier={:?}", session_id, csrf_token, pkce_verifier);
-    match epic_client
-        .exchange_code(query.code, csrf_token, pkce_verifier, query.state)
+    // Key DPoP riêng cho phiên này (nếu client bật `dpop`), dùng lại khi refresh
+    let dpop = match epic_client_arc.new_dpop_key() {
+        Ok(dpop) => dpop,
+        Err(e) => return Ok(ReloginPage::from_epic_error(&e).into_response()),
+    };
+    match epic_client_arc
+        .exchange_code(code, csrf_token, pkce_verifier, received_state, dpop.as_deref())
         .await
     {
         Ok(token) => {
-            // Lưu access token vào session nếu muốn
-            if let Err(e) = session.insert("access_token", token.secret()).await {
-                eprintln!("Session error while inserting access_token: {e:?}");
-                return Err((AxumAppError::from(e)));
-            }
+            // Access token của Epic được giữ trong gateway; session chỉ trỏ tới grant
+            let client_name = "epic_sandbox";
+            let grant_id = state
+                .grants
+                .insert(client_name, &token, dpop, OffsetDateTime::now_utc());
+            let identity = SessionIdentity::from_token_response(
+                client_name,
+                &snapshot.settings.oauth_clients[client_name].scopes,
+                &token,
+                grant_id,
+            );
+            session.insert(SESSION_IDENTITY_KEY, &identity).await?;
             Ok(Redirect::to("/patientsummary").into_response()) // Hoặc trang dashboard
         }
         Err(e) => {
-            // Xử lý lỗi, có thể redirect về trang lỗi hoặc login lại
-            eprintln!("OAuth2 callback error: {e:?}");
-            return Err((AxumAppError::from(e)));
+            // Lỗi token endpoint: hiển thị trang đăng nhập lại thay cho 500. Chỉ log
+            // loại lỗi và mã OAuth: `Debug` của lỗi có thể chứa body của token endpoint
+            let code = match &e {
+                EpicError::TokenEndpoint(response) => response.error.as_ref(),
+                _ => "-",
+            };
+            tracing::warn!("OAuth2 callback error: class={:?} code={}", e.class(), code);
+            Ok(ReloginPage::from_epic_error(&e).into_response())
         }
     }
     // return Ok(Redirect::to("/auth/login").into_response());
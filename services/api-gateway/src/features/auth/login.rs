@@ -1,38 +1,42 @@
 use std::sync::Arc;
 
-use crate::di::{AppState, SharedState};
+use crate::di::AppState;
+use super::relogin::ReloginPage;
 use axum::{
     extract::State,
     response::{IntoResponse, Redirect},
 };
-use oauth2_lib::epic::{client::EpicFhirClient, error::AxumAppError};
+use oauth2_lib::epic::error::AxumAppError;
 use reqwest::StatusCode;
-use tower_sessions::{MemoryStore, Session};
+use tower_sessions::Session;
 
 /// Route /auth/login sử dụng tower_sessions::Session
 pub async fn epic_login_handler(
     State(state): State<Arc<AppState>>,
     session: Session,
 ) -> Result<impl IntoResponse, AxumAppError> {
-    let app_state = state.as_ref(); // Nếu SharedState là Arc<AppState>
-    let epic_client_arc = app_state.oauth_clients.get("epic_sandbox").ok_or_else(|| {
+    let snapshot = state.snapshot(); // Giữ nguyên cấu hình trong suốt request
+    let epic_client_arc = snapshot.oauth_clients.get("epic_sandbox").ok_or_else(|| {
         AxumAppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
             "Epic client not found in state".to_string(),
         )
     })?;
 
-    let mut epic_client = epic_client_arc.lock().await; // Lấy MutexGuard
-
-    let (auth_url, csrf_token, pkce_verifier) = epic_client.get_authorization_url()?;
+    // Với PAR, tham số được đẩy lên Epic trước; lỗi ở bước này hiển thị trang đăng nhập lại
+    let (auth_url, csrf_token, pkce_verifier) = match epic_client_arc.authorization_url().await {
+        Ok(authorization) => authorization,
+        Err(e) => {
+            tracing::error!("Pushed authorization request failed: {}", e);
+            return Ok(ReloginPage::from_epic_error(&e).into_response());
+        }
+    };
     session.insert("pkce_verifier", &pkce_verifier).await?;
     session
         .insert("csrf_token", &csrf_token)
         .await
         .map_err(AxumAppError::from)?;
-    let session_id = session.id();
-    tracing::info!("LOGIN: session_id={:?}, csrf_token={:?}, pkce_verifier={:?}", session_id, csrf_token, pkce_verifier);
 
     // Ok(Redirect::to("/patientsummary"))
-    Ok(Redirect::to(auth_url.as_ref()))
+    Ok(Redirect::to(auth_url.as_ref()).into_response())
 }
@@ -0,0 +1,29 @@
+//! Kết thúc phiên đăng nhập ở gateway.
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Redirect},
+};
+use oauth2_lib::epic::error::AxumAppError;
+use tower_sessions::Session;
+
+use crate::di::SharedState;
+use crate::identity::{SessionIdentity, SESSION_IDENTITY_KEY};
+
+/// Bỏ token của provider, xóa các summary người dùng đã xem khỏi cache rồi xóa session.
+pub async fn logout_handler(
+    State(state): State<SharedState>,
+    session: Session,
+) -> Result<impl IntoResponse, AxumAppError> {
+    if let Some(identity) = session.get::<SessionIdentity>(SESSION_IDENTITY_KEY).await? {
+        state.grants.remove(&identity.grant_id);
+        if let Some(cache) = &state.summary_cache {
+            cache
+                .invalidate_user(&identity.client, &identity.subject)
+                .await;
+        }
+        tracing::info!("User logged out of {}", identity.client);
+    }
+    session.flush().await?;
+    Ok(Redirect::to("/"))
+}
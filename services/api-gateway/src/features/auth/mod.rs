@@ -1,3 +1,6 @@
 pub mod callback;
 pub mod login;
+pub mod logout;
+pub mod relogin;
 pub mod routes; // Declare the routes submodule
+pub mod token_exchange;
@@ -0,0 +1,187 @@
+//! Trang lỗi của luồng đăng nhập, kèm link đăng nhập lại thay cho lỗi 500.
+
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use oauth2_lib::epic::error::{Error as EpicError, ErrorClass};
+
+/// Bắt đầu lại luồng đăng nhập.
+const LOGIN_PATH: &str = "/epic-sandbox/login";
+/// `Retry-After` (giây) khi Epic tạm thời không phản hồi.
+const RETRY_AFTER_SECS: u64 = 30;
+
+/// Lỗi kết thúc callback, trả về người dùng dưới dạng trang HTML.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReloginPage {
+    status: StatusCode,
+    title: &'static str,
+    detail: String,
+    /// Mã lỗi OAuth, để người dùng báo lại cho quản trị viên
+    code: Option<String>,
+    retryable: bool,
+}
+
+impl ReloginPage {
+    /// Đổi code lấy token thất bại; nội dung tuỳ theo [`ErrorClass`].
+    pub fn from_epic_error(err: &EpicError) -> Self {
+        let code = match err {
+            EpicError::TokenEndpoint(response) => Some(response.error.to_string()),
+            _ => None,
+        };
+        match err.class() {
+            ErrorClass::UserActionable => Self {
+                status: StatusCode::BAD_REQUEST,
+                title: "Phiên đăng nhập không còn hợp lệ",
+                detail: "Mã xác thực đã hết hạn hoặc đã được sử dụng. Vui lòng đăng nhập lại."
+                    .to_string(),
+                code,
+                retryable: false,
+            },
+            ErrorClass::Retryable => Self {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                title: "Không kết nối được tới Epic",
+                detail: "Máy chủ xác thực tạm thời không phản hồi. Vui lòng thử lại sau ít phút."
+                    .to_string(),
+                code,
+                retryable: true,
+            },
+            ErrorClass::Fatal => Self {
+                status: StatusCode::BAD_GATEWAY,
+                title: "Epic từ chối ứng dụng",
+                detail:
+                    "Ứng dụng chưa được cấu hình đúng với Epic. Vui lòng liên hệ quản trị viên."
+                        .to_string(),
+                code,
+                retryable: false,
+            },
+        }
+    }
+
+    /// Epic redirect về callback với `error` (ví dụ người dùng từ chối cấp quyền).
+    ///
+    /// Chỉ hiển thị nội dung cố định theo mã lỗi: `error_description` và mã lỗi lạ
+    /// do người gọi tự đặt nên không được đưa lên trang.
+    pub fn from_authorization_error(error: &str) -> Self {
+        let (title, detail) = match error {
+            "access_denied" => (
+                "Bạn chưa cấp quyền truy cập",
+                "Ứng dụng cần quyền đọc hồ sơ bệnh nhân để tiếp tục.",
+            ),
+            "temporarily_unavailable" | "server_error" => (
+                "Không kết nối được tới Epic",
+                "Máy chủ xác thực tạm thời không phản hồi. Vui lòng thử lại sau ít phút.",
+            ),
+            _ => (
+                "Đăng nhập không thành công",
+                "Epic không hoàn tất yêu cầu đăng nhập.",
+            ),
+        };
+        // Mã lỗi của authorization endpoint (RFC 6749 mục 4.1.2.1)
+        let known = matches!(
+            error,
+            "invalid_request"
+                | "unauthorized_client"
+                | "access_denied"
+                | "unsupported_response_type"
+                | "invalid_scope"
+                | "server_error"
+                | "temporarily_unavailable"
+        );
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            title,
+            detail: detail.to_string(),
+            code: known.then(|| error.to_string()),
+            retryable: matches!(error, "temporarily_unavailable" | "server_error"),
+        }
+    }
+
+    /// `state` không khớp session: phiên đã hết hạn hoặc callback bị giả mạo.
+    pub fn state_mismatch() -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            title: "Phiên đăng nhập đã hết hạn",
+            detail: "Không tìm thấy yêu cầu đăng nhập tương ứng. Vui lòng đăng nhập lại."
+                .to_string(),
+            code: None,
+            retryable: false,
+        }
+    }
+}
+
+impl IntoResponse for ReloginPage {
+    fn into_response(self) -> Response {
+        tracing::warn!(
+            "Login failed ({}): {} {:?}",
+            self.status,
+            self.title,
+            self.code
+        );
+        let code = self
+            .code
+            .as_deref()
+            .map(|code| format!("<p><small>Mã lỗi: {}</small></p>", escape_html(code)))
+            .unwrap_or_default();
+        let action = if self.retryable {
+            "Thử lại"
+        } else {
+            "Đăng nhập lại"
+        };
+        let body = format!(
+            "<h1>{}</h1><p>{}</p>{}<p><a href=\"{}\">{}</a></p>",
+            escape_html(self.title),
+            escape_html(&self.detail),
+            code,
+            LOGIN_PATH,
+            action
+        );
+        let mut response = (self.status, Html(body)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        if self.retryable {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+        }
+        response
+    }
+}
+
+/// `error_description` đến từ provider, không được chèn nguyên vào HTML.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorization_errors_show_fixed_text() {
+        let page = ReloginPage::from_authorization_error("access_denied");
+        assert_eq!(page.status, StatusCode::BAD_REQUEST);
+        assert_eq!(page.code.as_deref(), Some("access_denied"));
+
+        let page = ReloginPage::from_authorization_error("<script>alert(1)</script>");
+        assert_eq!(page.title, "Đăng nhập không thành công");
+        assert_eq!(page.code, None);
+    }
+
+    #[test]
+    fn transport_errors_ask_to_retry() {
+        let page = ReloginPage::from_epic_error(&EpicError::InvalidResponse("html".into()));
+        assert_eq!(page.status, StatusCode::SERVICE_UNAVAILABLE);
+        let response = page.into_response();
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+    }
+}
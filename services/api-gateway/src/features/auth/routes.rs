@@ -1,16 +1,13 @@
-use crate::di::{AxumAppError, SharedState}; // Adjust path if AppError is elsewhere
+use crate::di::SharedState;
 use axum::{
-    extract::State,
-    response::{Html, IntoResponse, Redirect},
-    routing::get,
+    routing::{get, post},
     Router,
 };
-use serde::Deserialize;
-use std::sync::Arc;
-use tower_sessions::Session;
 
 use super::callback::epic_callback_handler;
 use super::login::epic_login_handler; // Adjust path if handlers are elsewhere
+use super::logout::logout_handler;
+use super::token_exchange::token_exchange_handler;
 
 // If AuthCallbackParams is in auth/mod.rs or auth.rs, you might need:
 // use super::AuthCallbackParams;
@@ -22,6 +19,8 @@ pub fn auth_routes(state: &SharedState) -> Router {
     Router::new()
         .route("/epic-sandbox/login", get(epic_login_handler)) // Assuming handlers are in auth/handlers.rs
         .route("/epic-sandbox/callback", get(epic_callback_handler))
+        .route("/oauth2/token", post(token_exchange_handler))
+        .route("/logout", post(logout_handler))
         // ... other auth routes ...
         .with_state(state.clone())
 }
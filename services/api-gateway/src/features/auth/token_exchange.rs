@@ -0,0 +1,462 @@
+//! `POST /oauth2/token`: token exchange (RFC 8693) cho các service nội bộ.
+//!
+//! Service gửi token nội bộ nhận được (`subject_token`) và `audience` cần gọi.
+//! `aud` của `subject_token` phải là chính service đó: token cấp cho service
+//! khác không đổi được, kể cả khi bị lộ ra ngoài.
+//! - audience của một service khác: gateway ký token nội bộ mới, scope không
+//!   rộng hơn token gốc, service gọi được ghi vào claim `act`;
+//! - `audience` của một OAuth client (FHIR server của Epic): gateway trả access
+//!   token của provider mà nó giữ cho phiên đăng nhập trong claim `sid`.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use config_lib::settings::ExchangeClientSettings;
+use security::internal_token::{
+    GRANT_TYPE_TOKEN_EXCHANGE, TOKEN_TYPE_ACCESS_TOKEN, TOKEN_TYPE_JWT,
+};
+use security::{Actor, InternalClaims, TokenGrant};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::di::{SharedState, Snapshot};
+
+#[derive(Debug, Deserialize)]
+pub struct TokenExchangeRequest {
+    pub grant_type: String,
+    pub subject_token: Option<String>,
+    pub subject_token_type: Option<String>,
+    pub audience: Option<String>,
+    pub scope: Option<String>,
+    pub requested_token_type: Option<String>,
+    /// Xác thực bằng `client_secret_post`; `client_secret_basic` dùng header Authorization
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenExchangeResponse {
+    pub access_token: String,
+    pub issued_token_type: &'static str,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// Lỗi theo RFC 6749 mục 5.2 (và RFC 8693 mục 2.2.2).
+#[derive(Debug, PartialEq, Eq)]
+pub enum TokenExchangeError {
+    InvalidClient,
+    InvalidRequest(String),
+    InvalidGrant(String),
+    InvalidScope,
+    InvalidTarget(String),
+    UnsupportedGrantType,
+    ServerError(String),
+}
+
+impl IntoResponse for TokenExchangeError {
+    fn into_response(self) -> Response {
+        let (status, error, description) = match self {
+            Self::InvalidClient => (
+                StatusCode::UNAUTHORIZED,
+                "invalid_client",
+                "client authentication failed".to_string(),
+            ),
+            Self::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, "invalid_request", msg),
+            Self::InvalidGrant(msg) => (StatusCode::BAD_REQUEST, "invalid_grant", msg),
+            Self::InvalidScope => (
+                StatusCode::BAD_REQUEST,
+                "invalid_scope",
+                "requested scope exceeds the subject token's scope".to_string(),
+            ),
+            Self::InvalidTarget(msg) => (StatusCode::BAD_REQUEST, "invalid_target", msg),
+            Self::UnsupportedGrantType => (
+                StatusCode::BAD_REQUEST,
+                "unsupported_grant_type",
+                format!("only {} is supported", GRANT_TYPE_TOKEN_EXCHANGE),
+            ),
+            Self::ServerError(msg) => {
+                tracing::error!("Token exchange failed: {}", msg);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "server_error",
+                    "token exchange failed".to_string(),
+                )
+            }
+        };
+        let body = Json(serde_json::json!({
+            "error": error,
+            "error_description": description,
+        }));
+        let mut response = (status, body).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Basic realm=\"token\""),
+            );
+        }
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            header::HeaderValue::from_static("no-store"),
+        );
+        response
+    }
+}
+
+pub async fn token_exchange_handler(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Form(request): Form<TokenExchangeRequest>,
+) -> Result<impl IntoResponse, TokenExchangeError> {
+    let snapshot = state.snapshot();
+    let now = OffsetDateTime::now_utc();
+    let (client_id, client) = authenticate_client(&snapshot, &headers, &request)?;
+
+    if request.grant_type != GRANT_TYPE_TOKEN_EXCHANGE {
+        return Err(TokenExchangeError::UnsupportedGrantType);
+    }
+    let subject_token = request
+        .subject_token
+        .as_deref()
+        .ok_or_else(|| TokenExchangeError::InvalidRequest("subject_token is required".into()))?;
+    match request.subject_token_type.as_deref() {
+        Some(TOKEN_TYPE_JWT) | Some(TOKEN_TYPE_ACCESS_TOKEN) => {}
+        _ => {
+            return Err(TokenExchangeError::InvalidRequest(format!(
+                "subject_token_type must be {} or {}",
+                TOKEN_TYPE_JWT, TOKEN_TYPE_ACCESS_TOKEN
+            )))
+        }
+    }
+    if let Some(requested) = request.requested_token_type.as_deref() {
+        if requested != TOKEN_TYPE_ACCESS_TOKEN && requested != TOKEN_TYPE_JWT {
+            return Err(TokenExchangeError::InvalidRequest(format!(
+                "unsupported requested_token_type '{}'",
+                requested
+            )));
+        }
+    }
+    let audience = request
+        .audience
+        .clone()
+        .ok_or_else(|| TokenExchangeError::InvalidRequest("audience is required".into()))?;
+    if !client.audiences.contains(&audience) {
+        return Err(TokenExchangeError::InvalidTarget(format!(
+            "client '{}' may not request audience '{}'",
+            client_id, audience
+        )));
+    }
+
+    let issuer = snapshot.token_issuer.as_ref().ok_or_else(|| {
+        TokenExchangeError::ServerError("internal_tokens is not configured".into())
+    })?;
+    let subject = issuer
+        .verify(subject_token, now)
+        .map_err(|e| TokenExchangeError::InvalidGrant(e.to_string()))?;
+    if subject.aud != client_id {
+        tracing::warn!(
+            "Token exchange: '{}' presented a token issued for '{}'",
+            client_id,
+            subject.aud
+        );
+        return Err(TokenExchangeError::InvalidGrant(
+            "subject_token was not issued to the client".into(),
+        ));
+    }
+
+    // Audience là FHIR server của một provider: trả access token gateway đang giữ
+    let provider = snapshot
+        .settings
+        .oauth_clients
+        .iter()
+        .find(|(_, client)| client.audience == audience)
+        .map(|(name, _)| name.clone());
+    if let Some(provider) = provider {
+        let oauth_client = snapshot.oauth_clients.get(&provider).ok_or_else(|| {
+            TokenExchangeError::ServerError(format!("OAuth client '{}' is not loaded", provider))
+        })?;
+        let grant = match subject.sid.as_deref() {
+            Some(sid) => state
+                .grants
+                .fresh(sid, oauth_client, now)
+                .await
+                .map_err(|e| {
+                    // Epic không phản hồi thì grant vẫn còn, không phải lỗi của client
+                    if e.is_retryable() {
+                        TokenExchangeError::ServerError(e.to_string())
+                    } else {
+                        TokenExchangeError::InvalidGrant(e.to_string())
+                    }
+                })?,
+            None => None,
+        }
+        .filter(|grant| grant.client == provider)
+        .ok_or_else(|| {
+            TokenExchangeError::InvalidGrant(
+                "no active provider grant for the subject token".into(),
+            )
+        })?;
+        // Token gắn với key DPoP của gateway: không dùng được ở nơi khác, service
+        // phải gọi provider qua gateway
+        if grant.tokens.dpop.is_some() {
+            return Err(TokenExchangeError::InvalidRequest(format!(
+                "the {} access token is DPoP-bound and cannot leave the gateway",
+                provider
+            )));
+        }
+        tracing::info!(
+            "Token exchange: '{}' received the {} access token of '{}'",
+            client_id,
+            provider,
+            subject.sub
+        );
+        return Ok(token_response(TokenExchangeResponse {
+            access_token: grant.tokens.access_token.expose().to_string(),
+            issued_token_type: TOKEN_TYPE_ACCESS_TOKEN,
+            token_type: "Bearer",
+            expires_in: (grant.tokens.expires_at - now).whole_seconds(),
+            scope: None,
+        }));
+    }
+
+    let scope = downscope(&subject, request.scope.as_deref())?;
+    let grant = TokenGrant {
+        subject: subject.sub.clone(),
+        audience,
+        scope: scope.clone(),
+        patient: subject.patient.clone(),
+        sid: subject.sid.clone(),
+        act: Some(Actor {
+            sub: client_id.clone(),
+            act: subject.act.clone().map(Box::new),
+        }),
+    };
+    let (token, claims) = issuer
+        .issue(grant, now, Some(subject.expires_at()))
+        .await
+        .map_err(|e| TokenExchangeError::ServerError(e.to_string()))?;
+    tracing::info!(
+        "Token exchange: '{}' received a token for '{}' acting for '{}'",
+        client_id,
+        claims.aud,
+        claims.sub
+    );
+    Ok(token_response(TokenExchangeResponse {
+        access_token: token,
+        issued_token_type: TOKEN_TYPE_JWT,
+        token_type: "Bearer",
+        expires_in: claims.exp - claims.iat,
+        scope: Some(scope).filter(|scope| !scope.is_empty()),
+    }))
+}
+
+/// Response thành công; token không được cache (RFC 6749 mục 5.1).
+fn token_response(body: TokenExchangeResponse) -> impl IntoResponse {
+    ([(header::CACHE_CONTROL, "no-store")], Json(body))
+}
+
+/// Xác thực service bằng `client_secret_basic` hoặc `client_secret_post`.
+fn authenticate_client<'a>(
+    snapshot: &'a Snapshot,
+    headers: &HeaderMap,
+    request: &TokenExchangeRequest,
+) -> Result<(String, &'a ExchangeClientSettings), TokenExchangeError> {
+    let (client_id, secret) = match basic_credentials(headers) {
+        Some(credentials) => credentials,
+        None => match (&request.client_id, &request.client_secret) {
+            (Some(id), Some(secret)) => (id.clone(), secret.clone()),
+            _ => return Err(TokenExchangeError::InvalidClient),
+        },
+    };
+    let client = snapshot
+        .settings
+        .internal_tokens
+        .as_ref()
+        .and_then(|internal| internal.exchange_clients.get(&client_id))
+        .ok_or(TokenExchangeError::InvalidClient)?;
+    let expected = client
+        .client_secret
+        .as_ref()
+        .ok_or(TokenExchangeError::InvalidClient)?;
+    if !constant_time_eq(expected.expose().as_bytes(), secret.as_bytes()) {
+        tracing::warn!("Token exchange: wrong secret for client '{}'", client_id);
+        return Err(TokenExchangeError::InvalidClient);
+    }
+    Ok((client_id, client))
+}
+
+/// `client_id`/`client_secret` từ header `Authorization: Basic` (RFC 6749 mục 2.3.1).
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (id, secret) = decoded.split_once(':')?;
+    let unescape = |s: &str| {
+        url::form_urlencoded::parse(format!("v={}", s).as_bytes())
+            .next()
+            .map(|(_, v)| v.into_owned())
+    };
+    Some((unescape(id)?, unescape(secret)?))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Scope của token mới: mặc định bằng token gốc, không được rộng hơn.
+fn downscope(
+    subject: &InternalClaims,
+    requested: Option<&str>,
+) -> Result<String, TokenExchangeError> {
+    let Some(requested) = requested.filter(|scope| !scope.trim().is_empty()) else {
+        return Ok(subject.scope.clone());
+    };
+    let granted: Vec<&str> = subject.scopes().collect();
+    let requested: Vec<&str> = requested.split_whitespace().collect();
+    if requested.iter().all(|scope| granted.contains(scope)) {
+        Ok(requested.join(" "))
+    } else {
+        Err(TokenExchangeError::InvalidScope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::build_state;
+    use axum::body::Body;
+    use axum::http::Request;
+    use config_lib::Settings;
+    use security::test_support::P256_PEM;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+    use tower_sessions::MemoryStore;
+
+    const EPIC_FHIR: &str = "https://fhir.epic.com/interconnect-fhir-oauth/api/FHIR/R4";
+
+    fn claims(scope: &str) -> InternalClaims {
+        InternalClaims {
+            iss: "http://localhost:3000".to_string(),
+            sub: "practitioner-1".to_string(),
+            aud: "patient-summary".to_string(),
+            exp: 0,
+            iat: 0,
+            jti: "j".to_string(),
+            scope: scope.to_string(),
+            patient: None,
+            sid: None,
+            act: None,
+        }
+    }
+
+    #[test]
+    fn exchanged_scope_cannot_grow() {
+        let subject = claims("openid patient/*.read");
+        assert_eq!(downscope(&subject, None).unwrap(), "openid patient/*.read");
+        assert_eq!(
+            downscope(&subject, Some("patient/*.read")).unwrap(),
+            "patient/*.read"
+        );
+        assert_eq!(
+            downscope(&subject, Some("patient/*.write")),
+            Err(TokenExchangeError::InvalidScope)
+        );
+    }
+
+    #[test]
+    fn reads_basic_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode("summary%20svc:s3cr%3At"))
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            basic_credentials(&headers),
+            Some(("summary svc".to_string(), "s3cr:t".to_string()))
+        );
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+    }
+
+    /// Exchange của `client_id` cho `subject_token`, trả về status và body.
+    async fn exchange(
+        state: &SharedState,
+        client_id: &str,
+        subject_token: &str,
+        audience: &str,
+    ) -> (StatusCode, Value) {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", GRANT_TYPE_TOKEN_EXCHANGE)
+            .append_pair("subject_token", subject_token)
+            .append_pair("subject_token_type", TOKEN_TYPE_JWT)
+            .append_pair("audience", audience)
+            .append_pair("client_id", client_id)
+            .append_pair("client_secret", "s3cret")
+            .finish();
+        let request = Request::post("/oauth2/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap();
+        let response = crate::features::auth::routes::auth_routes(state)
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn subject_token_must_be_issued_to_the_client() {
+        let settings: Settings = serde_json::from_value(json!({
+            "port": 3000,
+            "base_url": "https://gateway.example.org",
+            "oauth_clients": {},
+            "internal_tokens": {
+                "signing_keys": [{ "private_key_pem": P256_PEM, "algorithm": "ES256" }],
+                "exchange_clients": {
+                    "patient-summary": { "client_secret": "s3cret", "audiences": ["workflow"] },
+                    "workflow": { "client_secret": "s3cret", "audiences": ["workflow", EPIC_FHIR] },
+                },
+            },
+        }))
+        .unwrap();
+        let state = build_state(settings, MemoryStore::default()).await.unwrap();
+        let grant = TokenGrant {
+            subject: "practitioner-1".to_string(),
+            audience: "patient-summary".to_string(),
+            scope: "patient/*.read".to_string(),
+            sid: Some("grant-1".to_string()),
+            ..TokenGrant::default()
+        };
+        let (token, _) = state
+            .snapshot()
+            .token_issuer
+            .as_ref()
+            .unwrap()
+            .issue(grant, OffsetDateTime::now_utc(), None)
+            .await
+            .unwrap();
+
+        let (status, body) = exchange(&state, "patient-summary", &token, "workflow").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+
+        // Token cấp cho patient-summary không đổi được bởi service khác
+        let (status, body) = exchange(&state, "workflow", &token, EPIC_FHIR).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+        let (status, body) = exchange(&state, "workflow", &token, "workflow").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grant");
+    }
+}
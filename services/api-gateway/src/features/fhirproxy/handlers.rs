@@ -0,0 +1,474 @@
+//! `/fhir/{tenant}/*`: chuyển tiếp FHIR REST tới FHIR server của một OAuth client.
+//!
+//! `tenant` là tên OAuth client mà người dùng đã đăng nhập; request được gửi tới
+//! `audience` của client đó với access token của phiên (làm mới nếu sắp hết hạn,
+//! kèm DPoP proof nếu token gắn key). Frontend và công cụ đối tác không bao giờ
+//! thấy access token của Epic.
+//!
+//! Service phía sau (ví dụ patient-summary-service khi token của Epic gắn DPoP)
+//! gọi proxy bằng token nội bộ có `aud` là URL `/fhir/{tenant}` của gateway,
+//! đổi tại `/oauth2/token`; `sid` của token chọn grant thay cho cookie phiên.
+//!
+//! Chỉ các loại resource trong `fhir_resource_types` của client được gọi; entry
+//! loại khác trong Bundle (ví dụ từ `_include`) bị bỏ. Link của Bundle và header
+//! `Location` được viết lại về `/fhir/{tenant}` của gateway. Việc lọc này cần
+//! đọc được body, nên request luôn đòi JSON (`_format` của client bị bỏ) và
+//! response không phải JSON không được trả về.
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Path, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use oauth2_lib::epic::error::ErrorClass;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+use tower_sessions::Session;
+use url::Url;
+
+use crate::di::SharedState;
+use crate::identity::{SessionIdentity, SESSION_IDENTITY_KEY};
+
+/// Media type của FHIR JSON
+const FHIR_JSON: &str = "application/fhir+json";
+/// Kích thước tối đa của body gửi lên (create/update, `_search` bằng POST)
+const MAX_REQUEST_BODY: usize = 1024 * 1024;
+/// Kích thước tối đa của body FHIR server trả về
+const MAX_RESPONSE_BODY: usize = 16 * 1024 * 1024;
+/// Header của client được chuyển tiếp; cookie và `Authorization` của client thì
+/// không. `Accept` luôn là FHIR JSON.
+const FORWARDED_REQUEST_HEADERS: [HeaderName; 5] = [
+    header::CONTENT_TYPE,
+    header::IF_MATCH,
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+    HeaderName::from_static("prefer"),
+];
+/// Header của FHIR server được trả lại cho client
+const FORWARDED_RESPONSE_HEADERS: [HeaderName; 3] =
+    [header::CONTENT_TYPE, header::ETAG, header::LAST_MODIFIED];
+
+/// Lỗi của proxy, trả về dạng `OperationOutcome` như một FHIR server.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FhirProxyError {
+    /// Không có OAuth client tên này, hoặc client không mở proxy
+    UnknownTenant(String),
+    InvalidPath,
+    ResourceNotAllowed(String),
+    NotLoggedIn(String),
+    BodyTooLarge,
+    /// FHIR server hoặc token endpoint không phản hồi được
+    Upstream(String),
+}
+
+impl IntoResponse for FhirProxyError {
+    fn into_response(self) -> Response {
+        let (status, code, diagnostics) = match self {
+            Self::UnknownTenant(tenant) => (
+                StatusCode::NOT_FOUND,
+                "not-found",
+                format!("unknown FHIR tenant '{}'", tenant),
+            ),
+            Self::InvalidPath => (
+                StatusCode::BAD_REQUEST,
+                "invalid",
+                "invalid FHIR request path".to_string(),
+            ),
+            Self::ResourceNotAllowed(resource_type) => (
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                format!("resource type '{}' is not allowed", resource_type),
+            ),
+            Self::NotLoggedIn(msg) => (StatusCode::UNAUTHORIZED, "login", msg),
+            Self::BodyTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "too-costly",
+                format!("request body exceeds {} bytes", MAX_REQUEST_BODY),
+            ),
+            Self::Upstream(msg) => {
+                tracing::error!("FHIR proxy upstream error: {}", msg);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    "transient",
+                    "FHIR server is unavailable".to_string(),
+                )
+            }
+        };
+        let body = Json(json!({
+            "resourceType": "OperationOutcome",
+            "issue": [{ "severity": "error", "code": code, "diagnostics": diagnostics }],
+        }));
+        let mut response = (status, body).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(FHIR_JSON));
+        response
+    }
+}
+
+pub async fn fhir_proxy_handler(
+    State(state): State<SharedState>,
+    session: Session,
+    Path((tenant, path)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, FhirProxyError> {
+    let snapshot = state.snapshot();
+    let (Some(settings), Some(client)) = (
+        snapshot.settings.oauth_clients.get(&tenant),
+        snapshot.oauth_clients.get(&tenant),
+    ) else {
+        return Err(FhirProxyError::UnknownTenant(tenant));
+    };
+    if settings.fhir_resource_types.is_empty() {
+        return Err(FhirProxyError::UnknownTenant(tenant));
+    }
+    let allowlist = &settings.fhir_resource_types;
+
+    let segments = path_segments(&path)?;
+    if !allowlist.iter().any(|allowed| allowed == segments[0]) {
+        return Err(FhirProxyError::ResourceNotAllowed(segments[0].to_string()));
+    }
+
+    let gateway_base = format!(
+        "{}/fhir/{}",
+        snapshot.settings.base_url.trim_end_matches('/'),
+        tenant
+    );
+    let caller = match bearer_token(request.headers()) {
+        Some(token) => {
+            let issuer = snapshot.token_issuer.as_ref().ok_or_else(|| {
+                FhirProxyError::NotLoggedIn("internal tokens are not enabled".to_string())
+            })?;
+            let claims = issuer
+                .verify(token, OffsetDateTime::now_utc())
+                .map_err(|e| FhirProxyError::NotLoggedIn(e.to_string()))?;
+            if claims.aud != gateway_base {
+                return Err(FhirProxyError::NotLoggedIn(format!(
+                    "token is not issued for {}",
+                    gateway_base
+                )));
+            }
+            let grant_id = claims.sid.ok_or_else(|| {
+                FhirProxyError::NotLoggedIn("token carries no session".to_string())
+            })?;
+            Caller {
+                grant_id,
+                patient: claims.patient,
+            }
+        }
+        // Token của phiên đăng nhập vào đúng tenant này
+        None => session
+            .get(SESSION_IDENTITY_KEY)
+            .await
+            .ok()
+            .flatten()
+            .filter(|identity: &SessionIdentity| identity.client == tenant)
+            .map(|identity| Caller {
+                grant_id: identity.grant_id,
+                patient: identity.patient,
+            })
+            .ok_or_else(|| {
+                FhirProxyError::NotLoggedIn(format!("not signed in to '{}'", tenant))
+            })?,
+    };
+    let now = OffsetDateTime::now_utc();
+    // Grant của tenant khác không được làm mới bằng client của tenant này
+    if state
+        .grants
+        .get(&caller.grant_id, now)
+        .is_some_and(|grant| grant.client != tenant)
+    {
+        return Err(FhirProxyError::NotLoggedIn(format!(
+            "not signed in to '{}'",
+            tenant
+        )));
+    }
+    let grant = state
+        .grants
+        .fresh(&caller.grant_id, client, now)
+        .await
+        .map_err(|e| match e.class() {
+            ErrorClass::UserActionable => {
+                FhirProxyError::NotLoggedIn("session expired, sign in again".to_string())
+            }
+            _ => FhirProxyError::Upstream(format!("token refresh failed: {}", e)),
+        })?
+        .ok_or_else(|| FhirProxyError::NotLoggedIn("session expired, sign in again".to_string()))?;
+
+    let upstream_base = client.fhir_base_url().trim_end_matches('/');
+    let mut url = Url::parse(upstream_base)
+        .map_err(|e| FhirProxyError::Upstream(format!("invalid audience: {}", e)))?;
+    url.path_segments_mut()
+        .map_err(|_| FhirProxyError::Upstream("audience cannot be a base URL".to_string()))?
+        .pop_if_empty()
+        .extend(&segments);
+    url.set_query(request.uri().query().map(without_format).as_deref());
+
+    let (parts, body) = request.into_parts();
+    let writes = !parts.method.is_safe();
+    let body = to_bytes(body, MAX_REQUEST_BODY)
+        .await
+        .map_err(|_| FhirProxyError::BodyTooLarge)?;
+    let mut upstream_request = reqwest::Request::new(parts.method, url);
+    for name in FORWARDED_REQUEST_HEADERS {
+        if let Some(value) = parts.headers.get(&name) {
+            upstream_request.headers_mut().insert(name, value.clone());
+        }
+    }
+    upstream_request
+        .headers_mut()
+        .insert(header::ACCEPT, HeaderValue::from_static(FHIR_JSON));
+    if !body.is_empty() {
+        *upstream_request.body_mut() = Some(body.into());
+    }
+
+    let response = client
+        .send(&grant.tokens, upstream_request)
+        .await
+        .map_err(|e| FhirProxyError::Upstream(e.to_string()))?;
+
+    let status = response.status();
+    // Dữ liệu bệnh nhân vừa đổi: summary đang cache không còn đúng
+    if writes && status.is_success() {
+        let patient = match segments.as_slice() {
+            ["Patient", id, ..] => Some(*id),
+            _ => caller.patient.as_deref(),
+        };
+        if let (Some(cache), Some(patient)) = (&state.summary_cache, patient) {
+            cache.invalidate_patient(&tenant, patient).await;
+        }
+    }
+    let mut headers = HeaderMap::new();
+    for name in FORWARDED_RESPONSE_HEADERS {
+        if let Some(value) = response.headers().get(&name) {
+            headers.insert(name, value.clone());
+        }
+    }
+    for name in [header::LOCATION, header::CONTENT_LOCATION] {
+        let rewritten = response
+            .headers()
+            .get(&name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| rewrite_url(value, upstream_base, &gateway_base))
+            .and_then(|value| HeaderValue::from_str(&value).ok());
+        if let Some(value) = rewritten {
+            headers.insert(name, value);
+        }
+    }
+    let body = read_body(response).await?;
+    let body = if body.is_empty() {
+        body
+    } else {
+        filter_bundle(&body, allowlist, upstream_base, &gateway_base)?.unwrap_or(body)
+    };
+    Ok((status, headers, Body::from(body)).into_response())
+}
+
+/// Người gọi proxy: grant chứa token của provider và bệnh nhân của launch.
+struct Caller {
+    grant_id: String,
+    patient: Option<String>,
+}
+
+/// Token trong `Authorization: Bearer` của request, nếu có.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Query của client bỏ `_format`: response phải là JSON để lọc được.
+fn without_format(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| pair.split('=').next() != Some("_format"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Body của response, tối đa [`MAX_RESPONSE_BODY`] byte.
+async fn read_body(mut response: reqwest::Response) -> Result<Bytes, FhirProxyError> {
+    let too_large = || {
+        FhirProxyError::Upstream(format!(
+            "response body exceeds {} bytes",
+            MAX_RESPONSE_BODY
+        ))
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_RESPONSE_BODY as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| FhirProxyError::Upstream(e.to_string()))?
+    {
+        if body.len() + chunk.len() > MAX_RESPONSE_BODY {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(body))
+}
+
+/// Các segment của path FHIR; segment đầu là loại resource.
+fn path_segments(path: &str) -> Result<Vec<&str>, FhirProxyError> {
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let valid = segments
+        .iter()
+        .all(|segment| !segment.is_empty() && *segment != "." && *segment != "..");
+    if valid {
+        Ok(segments)
+    } else {
+        Err(FhirProxyError::InvalidPath)
+    }
+}
+
+/// URL dưới `upstream_base` chuyển thành URL tương ứng dưới `gateway_base`.
+fn rewrite_url(url: &str, upstream_base: &str, gateway_base: &str) -> Option<String> {
+    let rest = url.strip_prefix(upstream_base)?;
+    (rest.is_empty() || rest.starts_with('/') || rest.starts_with('?'))
+        .then(|| format!("{}{}", gateway_base, rest))
+}
+
+/// Bundle với link trỏ về gateway và không còn entry ngoài allowlist;
+/// `None` nếu body là resource khác (được trả nguyên văn). Body không phải JSON
+/// thì không kiểm tra được nên bị từ chối.
+fn filter_bundle(
+    body: &[u8],
+    allowlist: &[String],
+    upstream_base: &str,
+    gateway_base: &str,
+) -> Result<Option<Bytes>, FhirProxyError> {
+    let mut bundle: Value = serde_json::from_slice(body).map_err(|_| {
+        FhirProxyError::Upstream("FHIR server returned a non-JSON response".to_string())
+    })?;
+    if bundle["resourceType"] != "Bundle" {
+        return Ok(None);
+    }
+    if let Some(links) = bundle["link"].as_array_mut() {
+        for link in links {
+            let rewritten = link["url"]
+                .as_str()
+                .and_then(|url| rewrite_url(url, upstream_base, gateway_base));
+            if let Some(url) = rewritten {
+                link["url"] = Value::String(url);
+            }
+        }
+    }
+    if let Some(entries) = bundle["entry"].as_array_mut() {
+        entries.retain(|entry| match entry["resource"]["resourceType"].as_str() {
+            Some(resource_type) => {
+                resource_type == "OperationOutcome"
+                    || allowlist.iter().any(|allowed| allowed == resource_type)
+            }
+            None => true,
+        });
+        for entry in entries {
+            let rewritten = entry["fullUrl"]
+                .as_str()
+                .and_then(|url| rewrite_url(url, upstream_base, gateway_base));
+            if let Some(url) = rewritten {
+                entry["fullUrl"] = Value::String(url);
+            }
+        }
+    }
+    serde_json::to_vec(&bundle)
+        .map(|bundle| Some(Bytes::from(bundle)))
+        .map_err(|e| FhirProxyError::Upstream(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPSTREAM: &str = "https://fhir.example.org/api/FHIR/R4";
+    const GATEWAY: &str = "https://gateway.example.org/fhir/epic";
+
+    #[test]
+    fn rejects_traversal_and_empty_segments() {
+        assert_eq!(path_segments("Patient/123/").unwrap(), ["Patient", "123"]);
+        assert_eq!(
+            path_segments("Patient/../metadata"),
+            Err(FhirProxyError::InvalidPath)
+        );
+        assert_eq!(
+            path_segments("Patient//123"),
+            Err(FhirProxyError::InvalidPath)
+        );
+    }
+
+    #[test]
+    fn rewrites_links_and_drops_disallowed_entries() {
+        let body = json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "link": [
+                { "relation": "next", "url": format!("{}/Observation?patient=p1&page=2", UPSTREAM) },
+                { "relation": "related", "url": "https://other.example.org/R4/Observation" },
+                { "relation": "prefix", "url": format!("{}-admin/Observation", UPSTREAM) }
+            ],
+            "entry": [
+                { "fullUrl": format!("{}/Observation/o1", UPSTREAM), "resource": { "resourceType": "Observation", "id": "o1" } },
+                { "resource": { "resourceType": "Practitioner", "id": "pr1" } },
+                { "resource": { "resourceType": "OperationOutcome", "issue": [] } }
+            ]
+        });
+        let allowlist = ["Observation".to_string()];
+        let filtered =
+            filter_bundle(body.to_string().as_bytes(), &allowlist, UPSTREAM, GATEWAY)
+                .unwrap()
+                .unwrap();
+        let filtered: Value = serde_json::from_slice(&filtered).unwrap();
+        assert_eq!(
+            filtered["link"][0]["url"],
+            format!("{}/Observation?patient=p1&page=2", GATEWAY)
+        );
+        assert_eq!(
+            filtered["link"][1]["url"],
+            "https://other.example.org/R4/Observation"
+        );
+        assert_eq!(
+            filtered["link"][2]["url"],
+            format!("{}-admin/Observation", UPSTREAM)
+        );
+        let types: Vec<_> = filtered["entry"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["resource"]["resourceType"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["Observation", "OperationOutcome"]);
+        assert_eq!(
+            filtered["entry"][0]["fullUrl"],
+            format!("{}/Observation/o1", GATEWAY)
+        );
+
+        let patient = json!({ "resourceType": "Patient", "id": "p1" }).to_string();
+        assert_eq!(
+            filter_bundle(patient.as_bytes(), &allowlist, UPSTREAM, GATEWAY),
+            Ok(None)
+        );
+
+        // Bundle XML không lọc được entry nên không được trả về
+        let xml = b"<Bundle xmlns=\"http://hl7.org/fhir\"><type value=\"searchset\"/></Bundle>";
+        assert!(filter_bundle(xml, &allowlist, UPSTREAM, GATEWAY).is_err());
+    }
+
+    #[test]
+    fn drops_format_from_query() {
+        assert_eq!(
+            without_format("patient=p1&_format=xml&_count=10"),
+            "patient=p1&_count=10"
+        );
+        assert_eq!(without_format("_format=application/fhir%2Bxml"), "");
+    }
+}
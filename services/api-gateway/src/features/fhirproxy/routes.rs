@@ -0,0 +1,9 @@
+use axum::{routing::any, Router};
+
+use crate::{di::SharedState, features::fhirproxy::handlers::fhir_proxy_handler};
+
+pub fn fhir_proxy_routes(state: &SharedState) -> Router {
+    Router::new()
+        .route("/fhir/{tenant}/{*path}", any(fhir_proxy_handler))
+        .with_state(state.clone())
+}
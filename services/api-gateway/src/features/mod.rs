@@ -1,2 +1,3 @@
 pub mod auth;
+pub mod fhirproxy;
 pub mod patientsummary;
@@ -1,38 +1,201 @@
 use anyhow::Ok;
-use axum::{extract::Path, http::{HeaderMap, HeaderValue}, response::IntoResponse};
+use axum::{body::Bytes, extract::{Path, State}, http::{HeaderMap, HeaderName, HeaderValue}, response::IntoResponse};
 use oauth2_lib::epic::error::AxumAppError;
-use reqwest::{header, Client, StatusCode};
+use performance::CacheKey;
+use reqwest::{header, StatusCode};
+use time::OffsetDateTime;
 use tower_sessions::Session;
 
+use crate::di::SharedState;
+use crate::identity::{upstream_bearer, SessionIdentity, SESSION_IDENTITY_KEY};
+
+/// Loại resource của summary trong cache, dùng để chọn TTL (`summary_cache.ttl_secs`)
+const SUMMARY_CACHE_TYPE: &str = "PatientSummary";
+
 pub async fn patient_summary_handler(
+    State(state): State<SharedState>,
     session: Session,
     Path(patient_id): Path<String>,
 ) -> Result<impl IntoResponse, AxumAppError> {
-    // Extract the access token from the session
-    // let token = String::from("demo-token");
-    let token = session.get::<String>("access_token").await
-        .map_err(|err| {
-            tracing::error!("Failed to retrieve access token from session: {}", err);
-            AxumAppError::new(
-                StatusCode::UNAUTHORIZED,
-                format!("Failed to retrieve access token from session: {}", err),
-            )
-        })?
-        .ok_or_else(|| {
-            tracing::error!("Access token not found in session");
-            AxumAppError::new(
-                StatusCode::UNAUTHORIZED,
-                "Access token not found in session".to_string(),
-            )
-        })?;
+    check_patient_id(&patient_id)?;
+    // Token nội bộ nếu upstream có `audience`, không thì access token của Epic trong grant của phiên
+    let snapshot = state.snapshot();
+    let token = upstream_bearer(&snapshot, &state.grants, &session, "patient_summary").await?;
 
     // Create the HTTP client and construct the URL
-    let client = Client::new();
-    let url = format!("http://0.0.0.0:3010/patient_summary/{}", patient_id);
+    let client = snapshot.upstream_client.clone();
+    let url = format!(
+        "{}/patient_summary/{}",
+        snapshot.upstream_url("patient_summary")?,
+        patient_id
+    );
+
+    // Cache theo người xem: chỉ dùng khi session có thông tin đăng nhập
+    let identity: Option<SessionIdentity> = session.get(SESSION_IDENTITY_KEY).await?;
+    let (Some(cache), Some(identity)) = (state.summary_cache.as_ref(), identity) else {
+        return fetch_summary(&client, &url, &token, &patient_id)
+            .await
+            .map(|(content_type, body)| ([(header::CONTENT_TYPE, content_type)], body).into_response());
+    };
+    let key = CacheKey::new(
+        identity.client,
+        patient_id.clone(),
+        identity.subject,
+        identity.scope,
+        SUMMARY_CACHE_TYPE,
+    );
+    cache
+        .get_or_refresh(&key, OffsetDateTime::now_utc(), move || async move {
+            fetch_summary(&client, &url, &token, &patient_id)
+                .await
+                .map(|(_, body)| body.to_vec())
+        })
+        .await
+        .map(|cached| {
+            (
+                [
+                    (header::CONTENT_TYPE, HeaderValue::from_static("application/json")),
+                    (
+                        HeaderName::from_static("x-cache"),
+                        HeaderValue::from_static(cached.status.as_str()),
+                    ),
+                ],
+                cached.value,
+            )
+                .into_response()
+        })
+}
+
+/// Định dạng bản in của patient summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryExportFormat {
+    Html,
+    Pdf,
+}
+
+impl SummaryExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            SummaryExportFormat::Html => "html",
+            SummaryExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+pub async fn patient_summary_pdf_handler(
+    State(state): State<SharedState>,
+    session: Session,
+    Path(patient_id): Path<String>,
+) -> Result<impl IntoResponse, AxumAppError> {
+    export_summary(&state, &session, &patient_id, SummaryExportFormat::Pdf).await
+}
+
+pub async fn patient_summary_html_handler(
+    State(state): State<SharedState>,
+    session: Session,
+    Path(patient_id): Path<String>,
+) -> Result<impl IntoResponse, AxumAppError> {
+    export_summary(&state, &session, &patient_id, SummaryExportFormat::Html).await
+}
+
+/// Lấy bản in từ patient summary service. Mỗi lần xuất, thành công hay không,
+/// đều ghi một dòng audit (target `audit`) với người xuất và bệnh nhân.
+async fn export_summary(
+    state: &SharedState,
+    session: &Session,
+    patient_id: &str,
+    format: SummaryExportFormat,
+) -> Result<axum::response::Response, AxumAppError> {
+    let snapshot = state.snapshot();
+    let identity: Option<SessionIdentity> = session.get(SESSION_IDENTITY_KEY).await?;
+    let (client, subject) = identity
+        .map(|identity| (identity.client, identity.subject))
+        .unwrap_or_else(|| ("-".to_string(), "-".to_string()));
+    if let Err(err) = check_patient_id(patient_id) {
+        // Id do người gọi gửi: escape để không chèn được dòng vào log audit
+        tracing::warn!(
+            target: "audit",
+            "Patient summary export rejected: client={} subject={} patient=\"{}\" format={}",
+            client,
+            subject,
+            patient_id.escape_debug(),
+            format.extension()
+        );
+        return Err(err);
+    }
+    let result = async {
+        let token = upstream_bearer(&snapshot, &state.grants, session, "patient_summary").await?;
+        let url = format!(
+            "{}/patient_summary/{}/summary.{}",
+            snapshot.upstream_url("patient_summary")?,
+            patient_id,
+            format.extension()
+        );
+        fetch_summary(&snapshot.upstream_client, &url, &token, patient_id).await
+    }
+    .await;
+
+    match result.as_ref().err() {
+        None => tracing::info!(
+            target: "audit",
+            "Patient summary exported: client={} subject={} patient={} format={} bytes={}",
+            client,
+            subject,
+            patient_id,
+            format.extension(),
+            result.as_ref().map_or(0, |(_, body)| body.len())
+        ),
+        Some(err) => tracing::warn!(
+            target: "audit",
+            "Patient summary export failed: client={} subject={} patient={} format={} error={:?}",
+            client,
+            subject,
+            patient_id,
+            format.extension(),
+            err
+        ),
+    }
+
+    result.map(|(content_type, body)| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type);
+        // Bản in chứa dữ liệu bệnh nhân: không để proxy hay trình duyệt lưu lại
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        if format == SummaryExportFormat::Pdf {
+            let disposition = format!("attachment; filename=\"patient-summary-{}.pdf\"", patient_id);
+            headers.insert(
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_str(&disposition)
+                    .unwrap_or_else(|_| HeaderValue::from_static("attachment")),
+            );
+        }
+        (headers, body).into_response()
+    })
+}
 
+/// Id bệnh nhân phải là id FHIR (`[A-Za-z0-9\-.]{1,64}`) và không chỉ gồm dấu
+/// chấm: id được ghép vào URL của upstream, khóa cache, log audit và tên file.
+fn check_patient_id(patient_id: &str) -> Result<(), AxumAppError> {
+    let valid = (1..=64).contains(&patient_id.len())
+        && patient_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+        && patient_id.bytes().any(|b| b != b'.');
+    valid
+        .then_some(())
+        .ok_or_else(|| AxumAppError::new(StatusCode::BAD_REQUEST, "Invalid patient id".to_string()))
+}
+
+/// Gọi patient summary service, trả về `Content-Type` và body của response 200.
+async fn fetch_summary(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+    patient_id: &str,
+) -> Result<(HeaderValue, Bytes), AxumAppError> {
     // Send the request to the downstream service
     let resp = client
-        .get(&url)
+        .get(url)
         .bearer_auth(token)
         .send()
         .await
@@ -53,23 +216,16 @@ pub async fn patient_summary_handler(
                 .cloned()
                 .unwrap_or_else(|| HeaderValue::from_static("application/json"));
 
-            let body = resp.bytes().await.map_err(|err| {
-                tracing::error!("Failed to read response body: {}", err);
-                AxumAppError::new(
-                    StatusCode::BAD_GATEWAY,
-                    format!("Failed to read response body: {}", err),
-                )
-            })?;
-
-            let mut headers = HeaderMap::new();
-            headers.insert(header::CONTENT_TYPE, content_type);
-
-            Ok((headers, body).into_response()).map_err(|err| {
-                AxumAppError::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to construct response: {}", err),
-                )
-            })
+            resp.bytes()
+                .await
+                .map(|body| (content_type, body))
+                .map_err(|err| {
+                    tracing::error!("Failed to read response body: {}", err);
+                    AxumAppError::new(
+                        StatusCode::BAD_GATEWAY,
+                        format!("Failed to read response body: {}", err),
+                    )
+                })
         }
         status => {
             let error_message = format!(
@@ -82,10 +238,13 @@ pub async fn patient_summary_handler(
     }
 }
 
-pub async fn get_demo_patients() -> Result<impl IntoResponse, AxumAppError> {
+pub async fn get_demo_patients(
+    State(state): State<SharedState>,
+) -> Result<impl IntoResponse, AxumAppError> {
     // Create the HTTP client and construct the URL
-    let client = Client::new();
-    let url = format!("http://0.0.0.0:3010/demo/patients");
+    let snapshot = state.snapshot();
+    let client = &snapshot.upstream_client;
+    let url = format!("{}/demo/patients", snapshot.upstream_url("patient_summary")?);
     // Send the request to the downstream service
     let resp = client
         .get(&url)
@@ -126,17 +285,26 @@ pub async fn get_demo_patients() -> Result<impl IntoResponse, AxumAppError> {
             })
         }
         status => {
-            let error_message = format!("Downstream service returned error.");
+            let error_message = "Downstream service returned error.".to_string();
             tracing::warn!("{}", error_message);
             Err(AxumAppError::new(status, error_message))
         }
     }
 }
 
-pub async fn get_demo_summary(Path(patient_id): Path<String>) -> Result<impl IntoResponse, AxumAppError> {
+pub async fn get_demo_summary(
+    State(state): State<SharedState>,
+    Path(patient_id): Path<String>,
+) -> Result<impl IntoResponse, AxumAppError> {
+    check_patient_id(&patient_id)?;
     // Create the HTTP client and construct the URL
-    let client = Client::new();
-    let url = format!("http://0.0.0.0:3010/demo/patients/{}/summary", patient_id);
+    let snapshot = state.snapshot();
+    let client = &snapshot.upstream_client;
+    let url = format!(
+        "{}/demo/patients/{}/summary",
+        snapshot.upstream_url("patient_summary")?,
+        patient_id
+    );
     // Send the request to the downstream service
     let resp = client
         .get(&url)
@@ -177,7 +345,7 @@ pub async fn get_demo_summary(Path(patient_id): Path<String>) -> Result<impl Int
             })
         }
         status => {
-            let error_message = format!("Downstream service returned error.");
+            let error_message = "Downstream service returned error.".to_string();
             tracing::warn!("{}", error_message);
             Err(AxumAppError::new(status, error_message))
         }
@@ -1,10 +1,12 @@
 use axum::{routing::get, Router};
 
-use crate::{di::SharedState, features::patientsummary::handlers::{get_demo_patients, get_demo_summary, patient_summary_handler}};
+use crate::{di::SharedState, features::patientsummary::handlers::{get_demo_patients, get_demo_summary, patient_summary_handler, patient_summary_html_handler, patient_summary_pdf_handler}};
 
 pub fn patient_summary_routes(state: &SharedState) -> Router {
     Router::new()
         .route("/api/patient/{id}/summary", get(patient_summary_handler))
+        .route("/api/patient/{id}/summary.pdf", get(patient_summary_pdf_handler))
+        .route("/api/patient/{id}/summary.html", get(patient_summary_html_handler))
         .route("/demo/patients", get(get_demo_patients))
         .route("/demo/patients/{id}/summary", get(get_demo_summary))
         .with_state(state.clone())
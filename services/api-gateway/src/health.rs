@@ -0,0 +1,391 @@
+//! Dependency checks used by `/health/ready`.
+//!
+//! Each upstream service and OAuth provider gets a [`HealthCheck`]. Results are
+//! cached for `health.cache_ttl_ms` and every probe is bounded by
+//! `health.check_timeout_ms`, so a flood of probes from the orchestrator never
+//! turns into a flood of requests against Epic or the backends. Concurrent
+//! cache misses for one check wait for a single in-flight probe.
+
+use async_trait::async_trait;
+use config_lib::Settings;
+use futures::future::join_all;
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// A single dependency the gateway can probe.
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    /// Component name shown in the report, e.g. `upstream:patient_summary`.
+    fn name(&self) -> &str;
+    /// Returns `Err` with a short reason when the component is unavailable.
+    async fn check(&self) -> Result<(), String>;
+}
+
+/// Probes an HTTP endpoint and decides from the status code whether it is up.
+pub struct HttpCheck {
+    name: String,
+    url: String,
+    client: Client,
+    is_up: fn(StatusCode) -> bool,
+}
+
+impl HttpCheck {
+    /// An upstream service is up when its health path answers with 2xx/3xx.
+    pub fn upstream(name: &str, url: String, client: Client) -> Self {
+        Self {
+            name: format!("upstream:{}", name),
+            url,
+            client,
+            is_up: |status| status.is_success() || status.is_redirection(),
+        }
+    }
+
+    /// A token endpoint rejects a bare GET (405/400), so any answer below 500 means reachable.
+    pub fn oauth_provider(name: &str, token_url: String, client: Client) -> Self {
+        Self {
+            name: format!("oauth:{}", name),
+            url: token_url,
+            client,
+            is_up: |status| !status.is_server_error(),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for HttpCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let resp = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .map_err(|e| format!("request to {} failed: {}", self.url, e))?;
+        if (self.is_up)(resp.status()) {
+            Ok(())
+        } else {
+            Err(format!("{} returned {}", self.url, resp.status()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+/// Status of one component as rendered in `/health/ready`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentHealth {
+    pub status: Status,
+    /// Whether this component gates readiness.
+    pub critical: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Aggregated result of all checks.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub ready: bool,
+    pub shutting_down: bool,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+struct CachedResult {
+    at: Instant,
+    health: ComponentHealth,
+}
+
+struct RegisteredCheck {
+    check: Arc<dyn HealthCheck>,
+    /// Held while the check is probed, so concurrent misses share one probe.
+    probing: Mutex<()>,
+}
+
+/// Holds the registered checks and their cached results.
+pub struct HealthRegistry {
+    checks: Vec<RegisteredCheck>,
+    /// `None` means every registered check gates readiness.
+    critical: Option<HashSet<String>>,
+    timeout: Duration,
+    cache_ttl: Duration,
+    cache: RwLock<HashMap<String, CachedResult>>,
+    shutting_down: AtomicBool,
+}
+
+impl HealthRegistry {
+    pub fn new(timeout: Duration, cache_ttl: Duration, critical: Option<HashSet<String>>) -> Self {
+        Self {
+            checks: Vec::new(),
+            critical,
+            timeout,
+            cache_ttl,
+            cache: RwLock::new(HashMap::new()),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    /// Builds the registry with one check per configured upstream and OAuth provider.
+    ///
+    /// Upstreams are probed with `upstream_client` (which may carry the mTLS
+    /// identity), OAuth providers with `external_client`.
+    pub fn from_settings(
+        settings: &Settings,
+        upstream_client: Client,
+        external_client: Client,
+    ) -> Self {
+        let health = &settings.health;
+        let mut registry = Self::new(
+            Duration::from_millis(health.check_timeout_ms),
+            Duration::from_millis(health.cache_ttl_ms),
+            health
+                .readiness
+                .as_ref()
+                .map(|names| names.iter().cloned().collect()),
+        );
+
+        for (name, upstream) in &settings.upstreams {
+            let url = format!(
+                "{}{}",
+                upstream.url.trim_end_matches('/'),
+                upstream.health_path
+            );
+            registry.register(HttpCheck::upstream(name, url, upstream_client.clone()));
+        }
+        if health.check_oauth_providers {
+            for (name, oauth) in &settings.oauth_clients {
+                registry.register(HttpCheck::oauth_provider(
+                    name,
+                    oauth.token_url.clone(),
+                    external_client.clone(),
+                ));
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, check: impl HealthCheck + 'static) {
+        self.checks.push(RegisteredCheck {
+            check: Arc::new(check),
+            probing: Mutex::new(()),
+        });
+    }
+
+    /// Makes readiness fail from now on, regardless of dependency status.
+    pub fn mark_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    fn is_critical(&self, name: &str) -> bool {
+        self.critical
+            .as_ref()
+            .is_none_or(|critical| critical.contains(name))
+    }
+
+    /// Runs (or reuses cached results of) every check concurrently.
+    pub async fn report(&self) -> HealthReport {
+        let results = join_all(self.checks.iter().map(|check| self.run_check(check))).await;
+
+        let components: BTreeMap<String, ComponentHealth> = results.into_iter().collect();
+        let shutting_down = self.shutting_down.load(Ordering::SeqCst);
+        let ready = !shutting_down
+            && components
+                .values()
+                .all(|c| !c.critical || c.status == Status::Up);
+        HealthReport {
+            ready,
+            shutting_down,
+            components,
+        }
+    }
+
+    async fn cached(&self, name: &str) -> Option<ComponentHealth> {
+        self.cache
+            .read()
+            .await
+            .get(name)
+            .filter(|cached| cached.at.elapsed() < self.cache_ttl)
+            .map(|cached| cached.health.clone())
+    }
+
+    async fn run_check(&self, registered: &RegisteredCheck) -> (String, ComponentHealth) {
+        let check = &registered.check;
+        let name = check.name().to_string();
+        if let Some(health) = self.cached(&name).await {
+            return (name, health);
+        }
+        let _probing = registered.probing.lock().await;
+        // Another caller may have refreshed the result while we waited.
+        if let Some(health) = self.cached(&name).await {
+            return (name, health);
+        }
+
+        let started = Instant::now();
+        let outcome = match tokio::time::timeout(self.timeout, check.check()).await {
+            Ok(result) => result,
+            Err(_) => Err(format!("timed out after {} ms", self.timeout.as_millis())),
+        };
+        let health = ComponentHealth {
+            status: if outcome.is_ok() {
+                Status::Up
+            } else {
+                Status::Down
+            },
+            critical: self.is_critical(&name),
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: outcome.err(),
+        };
+        if let Some(error) = &health.error {
+            tracing::warn!("Health check {} failed: {}", name, error);
+        }
+
+        self.cache.write().await.insert(
+            name.clone(),
+            CachedResult {
+                at: Instant::now(),
+                health: health.clone(),
+            },
+        );
+        (name, health)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    struct FixedCheck {
+        name: &'static str,
+        up: bool,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl HealthCheck for FixedCheck {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.up {
+                Ok(())
+            } else {
+                Err("down".to_string())
+            }
+        }
+    }
+
+    struct SlowCheck;
+
+    struct CountingCheck {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl HealthCheck for CountingCheck {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl HealthCheck for SlowCheck {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn non_critical_failure_keeps_gateway_ready() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = HealthRegistry::new(
+            Duration::from_secs(1),
+            Duration::from_secs(60),
+            Some(HashSet::from(["backend".to_string()])),
+        );
+        registry.register(FixedCheck {
+            name: "backend",
+            up: true,
+            calls: calls.clone(),
+        });
+        registry.register(FixedCheck {
+            name: "agent",
+            up: false,
+            calls: calls.clone(),
+        });
+
+        let report = registry.report().await;
+        assert!(report.ready);
+        assert_eq!(report.components["agent"].status, Status::Down);
+        assert!(!report.components["agent"].critical);
+
+        // Second report is served from the cache.
+        registry.report().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn slow_critical_check_times_out_and_fails_readiness() {
+        let mut registry =
+            HealthRegistry::new(Duration::from_millis(50), Duration::from_secs(60), None);
+        registry.register(SlowCheck);
+
+        let report = registry.report().await;
+        assert!(!report.ready);
+        assert!(report.components["slow"]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn concurrent_cache_misses_share_one_probe() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry =
+            HealthRegistry::new(Duration::from_secs(1), Duration::from_secs(60), None);
+        registry.register(CountingCheck {
+            calls: calls.clone(),
+        });
+
+        let reports = join_all((0..5).map(|_| registry.report())).await;
+        assert!(reports.iter().all(|report| report.ready));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn shutting_down_fails_readiness() {
+        let registry = HealthRegistry::new(Duration::from_secs(1), Duration::from_secs(60), None);
+        assert!(registry.report().await.ready);
+
+        registry.mark_shutting_down();
+        let report = registry.report().await;
+        assert!(!report.ready);
+        assert!(report.shutting_down);
+    }
+}
@@ -0,0 +1,358 @@
+//! Người dùng đã đăng nhập và token gateway giữ thay cho họ.
+//!
+//! Access token của provider (Epic) không rời gateway, trừ khi một service được
+//! phép đổi lấy nó qua `/oauth2/token`. Các service phía sau nhận token nội bộ
+//! do gateway ký, mang `sid` trỏ tới grant lưu ở đây.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use oauth2::TokenResponse;
+use oauth2_lib::dpop::DpopKey;
+use oauth2_lib::epic::client::EpicFhirClient;
+use oauth2_lib::epic::error::{AxumAppError, Error as EpicError, ErrorClass};
+use oauth2_lib::epic::smart::SmartTokenResponse;
+use oauth2_lib::epic::tokens::TokenSet;
+use reqwest::StatusCode;
+use security::TokenGrant;
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::di::Snapshot;
+
+/// Key trong session chứa [`SessionIdentity`]
+pub const SESSION_IDENTITY_KEY: &str = "identity";
+
+/// Làm mới access token của provider khi còn ít hơn khoảng này
+const REFRESH_MARGIN: Duration = Duration::seconds(60);
+/// Session hết hạn khi không có request trong khoảng này; grant không được dùng
+/// trong cùng khoảng đó cũng bị bỏ
+pub const SESSION_INACTIVITY: Duration = Duration::seconds(6000);
+/// Tuổi tối đa của một grant kể từ lúc đăng nhập, kể cả khi refresh token vẫn dùng
+/// được: sau đó người dùng phải đăng nhập lại
+pub const MAX_GRANT_AGE: Duration = Duration::hours(12);
+
+/// Thông tin đăng nhập lưu trong session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionIdentity {
+    /// Tên OAuth client đã dùng để đăng nhập
+    pub client: String,
+    /// `sub` của ID token, hoặc mã grant nếu provider không trả ID token
+    pub subject: String,
+    /// Bệnh nhân trong bối cảnh SMART launch
+    pub patient: Option<String>,
+    /// Scope được cấp, cách nhau bởi dấu cách
+    pub scope: String,
+    /// Mã grant trong [`UpstreamGrants`]
+    pub grant_id: String,
+}
+
+impl SessionIdentity {
+    pub fn from_token_response(
+        client: &str,
+        requested_scopes: &[String],
+        token: &SmartTokenResponse,
+        grant_id: String,
+    ) -> Self {
+        // Không có `scope` trong response nghĩa là được cấp đúng scope đã xin (RFC 6749 mục 5.1)
+        let scope = match token.scopes() {
+            Some(scopes) => scopes
+                .iter()
+                .map(|scope| scope.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => requested_scopes.join(" "),
+        };
+        let fields = token.extra_fields();
+        Self {
+            client: client.to_string(),
+            subject: fields
+                .id_token_subject()
+                .unwrap_or_else(|| grant_id.clone()),
+            patient: fields.patient.clone(),
+            scope,
+            grant_id,
+        }
+    }
+}
+
+/// Token của provider mà gateway giữ cho một phiên đăng nhập.
+#[derive(Debug, Clone)]
+pub struct UpstreamGrant {
+    /// Tên OAuth client đã cấp token
+    pub client: String,
+    pub tokens: TokenSet,
+    created_at: OffsetDateTime,
+    last_used: OffsetDateTime,
+}
+
+impl UpstreamGrant {
+    /// Grant còn dùng được: chưa quá [`MAX_GRANT_AGE`] hay [`SESSION_INACTIVITY`],
+    /// và access token còn hạn hoặc làm mới được bằng refresh token.
+    fn is_usable(&self, now: OffsetDateTime) -> bool {
+        if now - self.created_at >= MAX_GRANT_AGE || now - self.last_used >= SESSION_INACTIVITY {
+            return false;
+        }
+        self.tokens.expires_at > now || self.tokens.refresh_token.is_some()
+    }
+}
+
+/// Các grant đang dùng được, theo mã grant. Nằm ngoài snapshot nên không mất khi
+/// nạp lại cấu hình. Đây là nơi giữ token của từng phiên; `EpicFhirClient` dùng
+/// chung và không giữ token.
+#[derive(Debug, Default)]
+pub struct UpstreamGrants {
+    grants: Mutex<HashMap<String, UpstreamGrant>>,
+    /// Lock làm mới của từng grant: refresh token của Epic chỉ dùng được một
+    /// lần, nên mỗi grant chỉ có một request refresh tại một thời điểm
+    refreshing: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl UpstreamGrants {
+    /// Lưu access token vừa nhận từ provider, trả về mã grant mới. `dpop` là key
+    /// DPoP của phiên đã dùng khi đổi code, nếu có.
+    pub fn insert(
+        &self,
+        client: &str,
+        token: &SmartTokenResponse,
+        dpop: Option<Arc<DpopKey>>,
+        now: OffsetDateTime,
+    ) -> String {
+        let grant = UpstreamGrant {
+            client: client.to_string(),
+            tokens: TokenSet::from_response(token, now).bound_to(token, dpop),
+            created_at: now,
+            last_used: now,
+        };
+        let id = Uuid::new_v4().to_string();
+        let mut grants = self.grants.lock().unwrap();
+        grants.retain(|_, grant| grant.is_usable(now));
+        grants.insert(id.clone(), grant);
+        self.refreshing
+            .lock()
+            .unwrap()
+            .retain(|id, _| grants.contains_key(id));
+        id
+    }
+
+    /// Grant dùng được với mã `id`; access token có thể đã hết hạn (xem [`UpstreamGrants::fresh`]).
+    /// Mỗi lần đọc tính là một lần dùng grant.
+    pub fn get(&self, id: &str, now: OffsetDateTime) -> Option<UpstreamGrant> {
+        let mut grants = self.grants.lock().unwrap();
+        let grant = grants.get_mut(id).filter(|grant| grant.is_usable(now))?;
+        grant.last_used = grant.last_used.max(now);
+        Some(grant.clone())
+    }
+
+    /// Bỏ grant `id`, ví dụ khi người dùng logout.
+    pub fn remove(&self, id: &str) -> Option<UpstreamGrant> {
+        self.refreshing.lock().unwrap().remove(id);
+        self.grants.lock().unwrap().remove(id)
+    }
+
+    fn refresh_lock(&self, id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.refreshing
+            .lock()
+            .unwrap()
+            .entry(id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Grant với access token còn hạn, làm mới bằng refresh token nếu sắp hết hạn.
+    /// Các request đồng thời của cùng grant chờ một lần làm mới duy nhất; lock
+    /// của map không bị giữ trong lúc gọi token endpoint.
+    pub async fn fresh(
+        &self,
+        id: &str,
+        client: &EpicFhirClient,
+        now: OffsetDateTime,
+    ) -> Result<Option<UpstreamGrant>, EpicError> {
+        match self.get(id, now) {
+            None => return Ok(None),
+            Some(grant) if !grant.tokens.expires_within(now, REFRESH_MARGIN) => {
+                return Ok(Some(grant));
+            }
+            Some(_) => {}
+        }
+
+        let lock = self.refresh_lock(id);
+        let _refreshing = lock.lock().await;
+        // Request khác có thể đã làm mới grant trong lúc chờ
+        let Some(mut grant) = self.get(id, now) else {
+            return Ok(None);
+        };
+        if !grant.tokens.expires_within(now, REFRESH_MARGIN) {
+            return Ok(Some(grant));
+        }
+        let Some(refresh_token) = grant.tokens.refresh_token.clone() else {
+            return Ok((grant.tokens.expires_at > now).then_some(grant));
+        };
+        let response = client
+            .refresh_token(&refresh_token, grant.tokens.dpop.as_deref())
+            .await?;
+        grant.tokens = grant.tokens.refreshed(&response, now);
+        if let Some(stored) = self.grants.lock().unwrap().get_mut(id) {
+            stored.tokens = grant.tokens.clone();
+        }
+        Ok(Some(grant))
+    }
+}
+
+/// Bearer token gửi kèm request tới upstream `upstream`.
+///
+/// Nếu upstream có `audience` thì gateway ký token nội bộ cho người dùng của
+/// session; nếu không thì chuyển tiếp access token của provider lấy từ grant
+/// của phiên, làm mới khi sắp hết hạn.
+pub async fn upstream_bearer(
+    snapshot: &Snapshot,
+    grants: &UpstreamGrants,
+    session: &Session,
+    upstream: &str,
+) -> Result<String, AxumAppError> {
+    let identity: SessionIdentity = session
+        .get(SESSION_IDENTITY_KEY)
+        .await?
+        .ok_or_else(|| not_logged_in("No signed-in user in session"))?;
+    let audience = snapshot
+        .settings
+        .upstreams
+        .get(upstream)
+        .and_then(|upstream| upstream.audience.clone());
+    let (Some(audience), Some(issuer)) = (audience, snapshot.token_issuer.as_ref()) else {
+        return provider_access_token(snapshot, grants, &identity).await;
+    };
+
+    let grant = TokenGrant {
+        subject: identity.subject,
+        audience,
+        scope: identity.scope,
+        patient: identity.patient,
+        sid: Some(identity.grant_id),
+        act: None,
+    };
+    let (token, _) = issuer
+        .issue(grant, OffsetDateTime::now_utc(), None)
+        .await
+        .map_err(|e| {
+            AxumAppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to issue internal token: {}", e),
+            )
+        })?;
+    Ok(token)
+}
+
+/// Access token của provider trong grant của phiên `identity`.
+async fn provider_access_token(
+    snapshot: &Snapshot,
+    grants: &UpstreamGrants,
+    identity: &SessionIdentity,
+) -> Result<String, AxumAppError> {
+    let client = snapshot.oauth_clients.get(&identity.client).ok_or_else(|| {
+        AxumAppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("OAuth client '{}' is not loaded", identity.client),
+        )
+    })?;
+    let grant = grants
+        .fresh(&identity.grant_id, client, OffsetDateTime::now_utc())
+        .await
+        .map_err(|e| match e.class() {
+            ErrorClass::UserActionable => not_logged_in("Session expired, sign in again"),
+            _ => AxumAppError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Token refresh failed: {}", e),
+            ),
+        })?
+        .filter(|grant| grant.client == identity.client)
+        .ok_or_else(|| not_logged_in("Session expired, sign in again"))?;
+    // Token gắn DPoP chỉ dùng được kèm proof của gateway (cấu hình bắt buộc `audience` khi bật dpop)
+    if grant.tokens.dpop.is_some() {
+        return Err(AxumAppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DPoP-bound access token cannot be forwarded".to_string(),
+        ));
+    }
+    Ok(grant.tokens.access_token.expose().to_string())
+}
+
+fn not_logged_in(message: &str) -> AxumAppError {
+    tracing::error!("{}", message);
+    AxumAppError::new(StatusCode::UNAUTHORIZED, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_response(expires_in: u64) -> SmartTokenResponse {
+        serde_json::from_value(serde_json::json!({
+            "access_token": "epic-at",
+            "token_type": "Bearer",
+            "expires_in": expires_in,
+            "patient": "erXuFYUfucBZaryVksYEcMg3",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn grants_expire_with_the_provider_token() {
+        let grants = UpstreamGrants::default();
+        let now = OffsetDateTime::now_utc();
+        let token = token_response(60);
+        let id = grants.insert("epic_sandbox", &token, None, now);
+
+        let grant = grants.get(&id, now).unwrap();
+        assert_eq!(grant.tokens.access_token.expose(), "epic-at");
+        assert!(grants.get(&id, now + Duration::minutes(2)).is_none());
+
+        let other = grants.insert("epic_sandbox", &token, None, now);
+        assert!(grants.remove(&other).is_some());
+        assert!(grants.get(&other, now).is_none());
+
+        let identity = SessionIdentity::from_token_response(
+            "epic_sandbox",
+            &["openid".to_string(), "launch/patient".to_string()],
+            &token,
+            id.clone(),
+        );
+        assert_eq!(identity.subject, id);
+        assert_eq!(identity.scope, "openid launch/patient");
+        assert_eq!(
+            identity.patient.as_deref(),
+            Some("erXuFYUfucBZaryVksYEcMg3")
+        );
+    }
+
+    #[test]
+    fn refreshable_grants_expire_when_idle_or_too_old() {
+        let grants = UpstreamGrants::default();
+        let now = OffsetDateTime::now_utc();
+        let token: SmartTokenResponse = serde_json::from_value(serde_json::json!({
+            "access_token": "epic-at",
+            "token_type": "Bearer",
+            "expires_in": 300,
+            "refresh_token": "epic-rt",
+        }))
+        .unwrap();
+
+        let idle = grants.insert("epic_sandbox", &token, None, now);
+        assert!(grants.get(&idle, now + SESSION_INACTIVITY).is_none());
+
+        // Grant được dùng đều đặn vẫn hết hạn sau MAX_GRANT_AGE
+        let active = grants.insert("epic_sandbox", &token, None, now);
+        let mut at = now;
+        while at + SESSION_INACTIVITY / 2 < now + MAX_GRANT_AGE {
+            at += SESSION_INACTIVITY / 2;
+            assert!(grants.get(&active, at).is_some());
+        }
+        assert!(grants.get(&active, now + MAX_GRANT_AGE).is_none());
+
+        // Grant hết hạn bị bỏ khỏi bộ nhớ ở lần đăng nhập kế tiếp
+        grants.insert("epic_sandbox", &token, None, now + MAX_GRANT_AGE);
+        assert_eq!(grants.grants.lock().unwrap().len(), 1);
+    }
+}
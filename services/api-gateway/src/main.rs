@@ -1,29 +1,35 @@
 mod config;
 mod di;
 mod features;
+mod health;
+mod identity;
 mod observability;
+mod reload;
 mod resilience;
 mod routes;
+mod server;
+mod shutdown;
+mod tls;
 
-use axum::Router;
 use config::load_settings;
+use config_lib::settings::signing_key_statuses;
 use di::SharedState;
-use observability::init_tracing;
-use time::Duration;
-use tokio::net::TcpListener;
+use observability::{init_tracing, LogFilter};
+use tokio_util::sync::CancellationToken;
 use tower_sessions::cookie::SameSite;
 use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
-    // 1. Init tracing/logging
-    init_tracing();
+    // 1. Init tracing/logging (giữ guard để flush log khi tắt)
+    let (telemetry_guard, log_filter) = init_tracing();
     tracing::info!("Starting API Gateway...");
 
     let store = MemoryStore::default();
-    // 2. Load settings
-    let settings = load_settings();
+    // 2. Load settings (đã được kiểm tra; lỗi cấu hình dừng gateway ngay tại đây)
+    let settings = load_settings()?;
+    log_filter.apply(LogFilter::parse(settings.log_filter.as_deref())?);
 
     // --- KIỂM TRA CẤU HÌNH OAUTH CLIENTS ---
     tracing::debug!("Loaded Settings: {:#?}", settings);
@@ -31,13 +37,18 @@ async fn main() -> anyhow::Result<()> {
     if let Some(epic_config_values) = settings.oauth_clients.get("epic_sandbox") {
         tracing::info!("Epic Sandbox OAuth2 Client Configuration Loaded:");
         tracing::info!("  Client ID: {}", epic_config_values.client_id);
-        tracing::info!("  Key ID: {:?}", epic_config_values.key_id);
-        match &epic_config_values.private_key_pem {
-            Some(key_pem) if !key_pem.is_empty() => {
-                tracing::info!("  Private Key PEM: Loaded (length: {})", key_pem.len())
-            }
-            Some(_) => tracing::warn!("  Private Key PEM: Loaded but is an empty string!"),
-            None => tracing::warn!("  Private Key PEM: NOT loaded (is None)"),
+        let keys = epic_config_values.all_signing_keys();
+        if keys.is_empty() {
+            tracing::warn!("  Signing keys: none (private_key_jwt disabled)");
+        }
+        let statuses = signing_key_statuses(&keys, time::OffsetDateTime::now_utc());
+        for (key, status) in keys.iter().zip(statuses) {
+            tracing::info!(
+                "  Signing key {:?} ({}): {:?}",
+                key.key_id,
+                key.algorithm,
+                status
+            );
         }
     } else {
         tracing::error!("Epic Sandbox OAuth2 Client Configuration (epic_sandbox) NOT found in settings.oauth_clients");
@@ -48,19 +59,25 @@ async fn main() -> anyhow::Result<()> {
     let state: SharedState = di::build_state(settings, store.clone()).await?;
 
     // Tạo session layer dùng đúng instance store này
+    let settings_tls_enabled = state.snapshot().settings.tls.is_some();
     let session_layer = SessionManagerLayer::new(store.clone())
-        .with_secure(false) // Để false khi phát triển local, true khi production
+        .with_secure(settings_tls_enabled) // Chỉ gửi cookie qua HTTPS khi gateway tự terminate TLS
         .with_same_site(SameSite::Lax) // hoặc .with_same_site(SameSite::None) nếu cần cross-site
         .with_path("/") // Đảm bảo cookie dùng cho toàn bộ app
-        .with_expiry(Expiry::OnInactivity(Duration::seconds(6000)));
+        .with_expiry(Expiry::OnInactivity(identity::SESSION_INACTIVITY));
 
     // 4. Build router
     let app = routes::create_router(&state).layer(session_layer);
 
-    // 5. Start server (Axum 0.8+)
-    let addr = format!("0.0.0.0:{}", state.settings.port);
-    tracing::info!("Listening on {}", addr);
-    let listener = TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    // 5. Start server (HTTP hoặc HTTPS), drain khi nhận SIGTERM/SIGINT
+    let stop = CancellationToken::new();
+    let servers = server::start(&state, app, stop.clone()).await?;
+    // Nạp lại cấu hình khi nhận SIGHUP hoặc file cấu hình thay đổi
+    reload::spawn_config_reloader(state.clone(), log_filter, stop.clone());
+    shutdown::run_until_signal(state, stop, servers).await?;
+
+    // 6. Flush telemetry trước khi thoát
+    tracing::info!("API Gateway stopped");
+    drop(telemetry_guard);
     Ok(())
 }
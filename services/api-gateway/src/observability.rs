@@ -1,7 +1,47 @@
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use anyhow::Context;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
 
-/// Khởi tạo tracing/logging với env filter
-pub fn init_tracing() {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    fmt::fmt().with_env_filter(filter).init();
+/// Handle để thay bộ lọc log khi đang chạy (khi `log_filter` trong cấu hình đổi).
+#[derive(Clone)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogFilter {
+    /// Tạo bộ lọc từ `log_filter`; `None` nghĩa là dùng `RUST_LOG` (mặc định "info").
+    pub fn parse(directives: Option<&str>) -> anyhow::Result<EnvFilter> {
+        match directives {
+            Some(directives) => EnvFilter::try_new(directives)
+                .with_context(|| format!("invalid log_filter '{}'", directives)),
+            None => Ok(default_filter()),
+        }
+    }
+
+    /// Áp dụng bộ lọc mới cho mọi log từ thời điểm này.
+    pub fn apply(&self, filter: EnvFilter) {
+        if let Err(e) = self.handle.reload(filter) {
+            tracing::error!("Failed to update log filter: {}", e);
+        }
+    }
+}
+
+fn default_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+}
+
+/// Khởi tạo tracing/logging với env filter.
+///
+/// Log được ghi qua một writer non-blocking; giữ `WorkerGuard` tới lúc tắt
+/// gateway, drop nó sẽ flush các log còn trong buffer.
+pub fn init_tracing() -> (WorkerGuard, LogFilter) {
+    let (filter, handle) = reload::Layer::new(default_filter());
+    let (writer, guard) = tracing_appender::non_blocking(std::io::stdout());
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(writer))
+        .init();
+    (guard, LogFilter { handle })
 }
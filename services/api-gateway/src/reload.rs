@@ -0,0 +1,112 @@
+//! Nạp lại cấu hình khi đang chạy, không cần restart.
+//!
+//! Việc nạp lại được kích hoạt bởi SIGHUP hoặc khi một file cấu hình / file
+//! secret thay đổi trên đĩa. Cấu hình mới được load và kiểm tra đầy đủ, OAuth
+//! clients và bộ lọc log được dựng sẵn, rồi mới thay snapshot trong một bước;
+//! nếu có lỗi thì gateway tiếp tục chạy với cấu hình cũ.
+//!
+//! `host`, `port`, `tls` và `summary_cache` chỉ được đọc lúc khởi động: thay
+//! đổi các mục này vẫn cần restart.
+
+use crate::config::load_settings;
+use crate::di::{self, SharedState};
+use crate::observability::LogFilter;
+use config_lib::Settings;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+/// Load, kiểm tra và áp dụng cấu hình mới.
+pub async fn reload(state: &SharedState, log_filter: &LogFilter) -> anyhow::Result<()> {
+    let settings = load_settings()?;
+    let filter = LogFilter::parse(settings.log_filter.as_deref())?;
+    let snapshot = di::build_snapshot(settings).await?;
+
+    warn_restart_required(&state.snapshot().settings, &snapshot.settings);
+    log_filter.apply(filter);
+    state.replace_snapshot(snapshot);
+    Ok(())
+}
+
+fn warn_restart_required(old: &Settings, new: &Settings) {
+    if old.host != new.host || old.port != new.port {
+        tracing::warn!(
+            "Bind address changed to {}:{}; it takes effect after a restart",
+            new.host,
+            new.port
+        );
+    }
+    if old.tls != new.tls {
+        tracing::warn!("TLS listener settings changed; they take effect after a restart");
+    }
+    if old.summary_cache != new.summary_cache {
+        tracing::warn!("Summary cache settings changed; they take effect after a restart");
+    }
+}
+
+fn modified_at(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Nạp lại cấu hình khi nhận SIGHUP hoặc khi file cấu hình / file secret thay đổi.
+pub fn spawn_config_reloader(state: SharedState, log_filter: LogFilter, stop: CancellationToken) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let mut sighup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(sighup) => Some(sighup),
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGHUP: {}", e);
+                None
+            }
+        };
+
+        let mut files = config_lib::source_files(&state.snapshot().settings);
+        let mut last_seen = modified_at(&files);
+        loop {
+            let interval_secs = state.snapshot().settings.reload.watch_interval_secs;
+            let poll = async {
+                if interval_secs == 0 {
+                    std::future::pending::<()>().await;
+                }
+                tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+            };
+            #[cfg(unix)]
+            let hangup = async {
+                match sighup.as_mut() {
+                    Some(sighup) => {
+                        sighup.recv().await;
+                    }
+                    None => std::future::pending::<()>().await,
+                }
+            };
+            #[cfg(not(unix))]
+            let hangup = std::future::pending::<()>();
+
+            tokio::select! {
+                _ = stop.cancelled() => return,
+                _ = hangup => tracing::info!("Received SIGHUP, reloading configuration"),
+                _ = poll => {
+                    if modified_at(&files) == last_seen {
+                        continue;
+                    }
+                    tracing::info!("Configuration files changed, reloading configuration");
+                }
+            }
+
+            match reload(&state, &log_filter).await {
+                Ok(()) => tracing::info!("Configuration reloaded"),
+                Err(e) => tracing::error!(
+                    "Failed to reload configuration, keeping the current one: {:#}",
+                    e
+                ),
+            }
+            // Cũng ghi nhận sau khi lỗi: chỉ thử lại ở lần sửa file tiếp theo hoặc SIGHUP
+            files = config_lib::source_files(&state.snapshot().settings);
+            last_seen = modified_at(&files);
+        }
+    });
+}
@@ -1,3 +1,5 @@
+// Chỗ gắn retry/timeout cho upstream; chưa có route nào dùng
+#[allow(dead_code)]
 pub fn wrap<T>(service: T) -> T {
     service
 }
@@ -1,15 +1,36 @@
 use crate::di::SharedState;
-use axum::{routing::get, Json, Router};
-use serde_json::{json, Value}; // Cần thiết nếu bạn muốn truy cập state, dù ở đây không dùng
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use serde_json::{json, Value};
 
 // Hàm tạo router cho health check
-pub fn health_routes(_state: &SharedState) -> Router<()> {
-    // _state không được sử dụng ở đây, nhưng giữ signature để nhất quán
-    Router::new().route("/health", get(health_check_handler))
-    // Không cần .with_state ở đây nếu handler không dùng state
+pub fn health_routes(state: &SharedState) -> Router<()> {
+    Router::new()
+        .route("/health", get(liveness_handler))
+        .route("/health/live", get(liveness_handler))
+        .route("/health/ready", get(readiness_handler))
+        .with_state(state.clone())
 }
 
-// Handler cho health check
-async fn health_check_handler() -> Json<Value> {
+// Liveness: process còn chạy, không kiểm tra dependency
+async fn liveness_handler() -> Json<Value> {
     Json(json!({ "status": "ok" }))
 }
+
+// Readiness: 503 nếu một component bắt buộc không khả dụng
+async fn readiness_handler(State(state): State<SharedState>) -> impl IntoResponse {
+    let report = state.snapshot().health.report().await;
+    let (status, label) = if report.ready {
+        (StatusCode::OK, "ready")
+    } else if report.shutting_down {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting_down")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    };
+    (
+        status,
+        Json(json!({
+            "status": label,
+            "components": report.components,
+        })),
+    )
+}
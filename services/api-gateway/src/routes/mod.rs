@@ -1,13 +1,9 @@
-use std::sync::Arc;
-
-use crate::{di::{AppState, SharedState}, features::patientsummary};
+use crate::{di::SharedState, features::{fhirproxy, patientsummary}};
 use axum::{
     response::Html, // Thêm Html để trả về nội dung HTML đơn giản cho root
     routing::{any, get},
     Router,
 };
-use time::Duration;
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer}; // Đảm bảo time crate được import đúng cách
 
 use crate::features::auth;
 mod health;
@@ -18,16 +14,566 @@ pub fn create_router(state: &SharedState) -> Router {
     // Đảm bảo SharedState là Arc<AppState>
     Router::new() // Router<()>
         .route("/", get(root_handler)) // Router<()>
-        .route("/{*wildcard}", any(proxy::proxy_fresh))
+        .route("/{*wildcard}", any(proxy::proxy_fresh).with_state(state.clone()))
         .merge(health::health_routes(state)) // health_routes giờ trả về Router<()>, merge thành công -> Router<()>
         .merge(auth::routes::auth_routes(state)) // Tương tự -> Router<()>
         .merge(jwks::jwks_routes(state)) // Tương tự -> Router<()>// Áp dụng layer, vẫn là Router<()>
                                          // .with_state(state.clone().) // Bây giờ self là Router<()>, state.clone() là Arc<AppState>
                                          // Kết quả sẽ là Router<Arc<AppState>>, khớp với kiểu trả về.
         .merge(patientsummary::routes::patient_summary_routes(state))
+        .merge(fhirproxy::routes::fhir_proxy_routes(state))
 }
 
 /// Handler cho root endpoint ("/")
 async fn root_handler() -> Html<&'static str> {
     Html("<h1>Welcome to SEDS API Gateway</h1><p><a href=\"/epic-sandbox/login\">Login with Epic Sandbox</a></p>")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::build_state;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::Json;
+    use config_lib::Settings;
+    use serde_json::json;
+    use security::internal_token::{GRANT_TYPE_TOKEN_EXCHANGE, TOKEN_TYPE_JWT};
+    use security::test_support::P256_PEM;
+    use smart_mock::{Failure, MockClient, MockSmartServer, DEFAULT_PATIENT};
+    use std::sync::{Arc, OnceLock};
+    use time::{Duration, OffsetDateTime};
+    use tower::ServiceExt;
+    use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+
+    const REDIRECT: &str = "http://localhost:3000/epic-sandbox/callback";
+    /// Proxy FHIR của gateway cho tenant `epic_sandbox`
+    const GATEWAY_FHIR: &str = "http://localhost:3000/fhir/epic_sandbox";
+    const SUMMARY_SECRET: &str = "summary-s3cret";
+
+    /// Patient summary service giả. Bearer là access token mock server đã phát
+    /// hành thì trả lời ngay, trừ token gắn DPoP (Epic cũng từ chối token đó khi
+    /// thiếu proof); bearer là token nội bộ thì đổi token rồi đọc Patient qua proxy
+    /// FHIR của `gateway`.
+    async fn patient_summary_service(
+        server: Arc<MockSmartServer>,
+        gateway: Arc<OnceLock<Router>>,
+    ) -> String {
+        let pdf_server = server.clone();
+        let app = Router::new()
+            .route(
+                "/patient_summary/{id}",
+                get(
+                    |axum::extract::Path(id): axum::extract::Path<String>,
+                     headers: axum::http::HeaderMap| async move {
+                        let token = bearer(&headers);
+                        if let Some(issued) = server.access_token(token) {
+                            return match issued.jkt {
+                                None if issued.patient.as_deref() == Some(&id) => {
+                                    Json(json!({ "patient": { "id": id } })).into_response()
+                                }
+                                _ => StatusCode::UNAUTHORIZED.into_response(),
+                            };
+                        }
+                        let gateway = gateway.get().unwrap();
+                        let Some(fhir_token) = exchange(gateway, token, GATEWAY_FHIR).await else {
+                            return StatusCode::UNAUTHORIZED.into_response();
+                        };
+                        let request = Request::get(format!("/fhir/epic_sandbox/Patient/{}", id))
+                            .header(header::AUTHORIZATION, format!("Bearer {}", fhir_token))
+                            .body(Body::empty())
+                            .unwrap();
+                        let response = gateway.clone().oneshot(request).await.unwrap();
+                        if response.status() != StatusCode::OK {
+                            return response.status().into_response();
+                        }
+                        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                            .await
+                            .unwrap();
+                        let patient: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                        Json(json!({ "patient": patient })).into_response()
+                    },
+                ),
+            )
+            .route(
+                "/patient_summary/{id}/summary.pdf",
+                get(
+                    |axum::extract::Path(id): axum::extract::Path<String>,
+                     headers: axum::http::HeaderMap| async move {
+                        match pdf_server.access_token(bearer(&headers)) {
+                            Some(issued)
+                                if issued.jkt.is_none()
+                                    && issued.patient.as_deref() == Some(&id) =>
+                            {
+                                (
+                                    [(header::CONTENT_TYPE, "application/pdf")],
+                                    "%PDF-1.4\n%%EOF\n",
+                                )
+                                    .into_response()
+                            }
+                            _ => StatusCode::UNAUTHORIZED.into_response(),
+                        }
+                    },
+                ),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn bearer(headers: &axum::http::HeaderMap) -> &str {
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default()
+    }
+
+    /// Token exchange của client `patient-summary` tại `gateway`.
+    async fn exchange(gateway: &Router, subject_token: &str, audience: &str) -> Option<String> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", GRANT_TYPE_TOKEN_EXCHANGE)
+            .append_pair("subject_token", subject_token)
+            .append_pair("subject_token_type", TOKEN_TYPE_JWT)
+            .append_pair("audience", audience)
+            .append_pair("client_id", "patient-summary")
+            .append_pair("client_secret", SUMMARY_SECRET)
+            .finish();
+        let request = Request::post("/oauth2/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap();
+        let response = gateway.clone().oneshot(request).await.unwrap();
+        if response.status() != StatusCode::OK {
+            return None;
+        }
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        body["access_token"].as_str().map(str::to_string)
+    }
+
+    /// Gateway cấu hình client `epic_sandbox` trỏ vào mock server, có session layer như `main`.
+    async fn gateway(server: &Arc<MockSmartServer>) -> Router {
+        gateway_with(server, json!({})).await
+    }
+
+    /// Như [`gateway`], thêm các trường trong `client` vào cấu hình `epic_sandbox`.
+    async fn gateway_with(server: &Arc<MockSmartServer>, client: serde_json::Value) -> Router {
+        gateway_with_settings(server, client, json!({})).await
+    }
+
+    /// Như [`gateway_with`], gộp `extra` vào cấu hình.
+    async fn gateway_with_settings(
+        server: &Arc<MockSmartServer>,
+        client: serde_json::Value,
+        extra: serde_json::Value,
+    ) -> Router {
+        let gateway = Arc::new(OnceLock::new());
+        let mut settings = json!({
+            "port": 3000,
+            "base_url": "http://localhost:3000",
+            "oauth_clients": {
+                "epic_sandbox": {
+                    "client_id": "gateway",
+                    "token_url": server.token_url(),
+                    "auth_url": server.authorize_url(),
+                    "redirect_uri": REDIRECT,
+                    "scopes": ["openid", "fhirUser", "launch/patient", "patient/*.read"],
+                    "audience": server.fhir_base_url(),
+                    "signing_keys": [{ "private_key_pem": P256_PEM, "algorithm": "ES256" }],
+                },
+            },
+            "upstreams": {
+                "patient_summary": {
+                    "url": patient_summary_service(server.clone(), gateway.clone()).await,
+                },
+            },
+        });
+        if let serde_json::Value::Object(fields) = client {
+            for (name, value) in fields {
+                settings["oauth_clients"]["epic_sandbox"][name] = value;
+            }
+        }
+        merge(&mut settings, extra);
+        let settings: Settings = serde_json::from_value(settings).unwrap();
+        let store = MemoryStore::default();
+        let state = build_state(settings, store.clone()).await.unwrap();
+        server.register_client(MockClient::with_jwks(
+            "gateway",
+            REDIRECT,
+            state.snapshot().keys.jwks(OffsetDateTime::now_utc()),
+        ));
+        let session_layer = SessionManagerLayer::new(store)
+            .with_secure(false)
+            .with_path("/")
+            .with_expiry(Expiry::OnInactivity(Duration::seconds(600)));
+        let router = create_router(&state).layer(session_layer);
+        gateway.set(router.clone()).unwrap();
+        router
+    }
+
+    /// Gộp `patch` vào `target`: object được gộp theo từng trường, giá trị khác thay thế.
+    fn merge(target: &mut serde_json::Value, patch: serde_json::Value) {
+        match (target, patch) {
+            (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+                for (name, value) in patch {
+                    merge(target.entry(name).or_insert(serde_json::Value::Null), value);
+                }
+            }
+            (target, patch) => *target = patch,
+        }
+    }
+
+    async fn send(app: &Router, uri: &str, cookie: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        app.clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn location(response: &Response) -> String {
+        response.headers()[header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// Login ở gateway, rồi để mock server tự đồng ý; trả về URL callback và cookie session.
+    async fn login(app: &Router) -> (String, String) {
+        let response = send(app, "/epic-sandbox/login", None).await;
+        assert!(response.status().is_redirection());
+        let cookie = response.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let authorize = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(location(&response))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(authorize.status(), reqwest::StatusCode::FOUND);
+        let callback = authorize.headers()[header::LOCATION].to_str().unwrap();
+        let callback = callback.strip_prefix("http://localhost:3000").unwrap();
+        (callback.to_string(), cookie)
+    }
+
+    #[tokio::test]
+    async fn login_callback_and_summary_against_mock_smart_server() {
+        let server = Arc::new(MockSmartServer::start().await);
+        let app = gateway(&server).await;
+
+        let (callback, cookie) = login(&app).await;
+        let response = send(&app, &callback, Some(&cookie)).await;
+        assert!(response.status().is_redirection());
+        assert_eq!(location(&response), "/patientsummary");
+
+        let summary = format!("/api/patient/{}/summary", DEFAULT_PATIENT);
+        let response = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Không có session thì không có token để chuyển tiếp
+        let response = send(&app, &summary, None).await;
+        assert_ne!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn login_pushes_the_request_and_binds_tokens_to_a_session_key() {
+        let server = Arc::new(MockSmartServer::start().await);
+        server.require_par(true);
+        server.require_dpop_nonce("n-1");
+        let app = gateway_with_settings(
+            &server,
+            json!({ "par_url": server.par_url(), "dpop": true, "fhir_resource_types": ["Patient"] }),
+            json!({
+                "upstreams": { "patient_summary": { "audience": "patient-summary" } },
+                "internal_tokens": {
+                    "signing_keys": [{ "private_key_pem": P256_PEM, "algorithm": "ES256" }],
+                    "exchange_clients": {
+                        "patient-summary": {
+                            "client_secret": SUMMARY_SECRET,
+                            "audiences": [GATEWAY_FHIR],
+                        },
+                    },
+                },
+            }),
+        )
+        .await;
+
+        let (callback, cookie) = login(&app).await;
+        let response = send(&app, &callback, Some(&cookie)).await;
+        assert_eq!(location(&response), "/patientsummary");
+
+        // Token Epic cấp gắn với key DPoP của phiên, key không rời gateway: service
+        // nhận token nội bộ và đọc FHIR qua proxy của gateway
+        let summary = format!("/api/patient/{}/summary", DEFAULT_PATIENT);
+        let response = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["patient"]["name"][0]["family"], "Lopez", "{body}");
+    }
+
+    #[tokio::test]
+    async fn summary_is_cached_per_user_until_logout() {
+        let server = Arc::new(MockSmartServer::start().await);
+        let app = gateway_with_settings(
+            &server,
+            json!({}),
+            json!({ "summary_cache": { "max_entries": 10 } }),
+        )
+        .await;
+        let summary = format!("/api/patient/{}/summary", DEFAULT_PATIENT);
+
+        let (callback, cookie) = login(&app).await;
+        send(&app, &callback, Some(&cookie)).await;
+        let first = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["x-cache"], "miss");
+        let second = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(second.headers()["x-cache"], "hit");
+
+        let logout = app
+            .clone()
+            .oneshot(
+                Request::post("/logout")
+                    .header(header::COOKIE, &cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(logout.status().is_redirection());
+        assert_eq!(location(&logout), "/");
+        let response = send(&app, &summary, Some(&cookie)).await;
+        assert_ne!(response.status(), StatusCode::OK);
+
+        // Cùng người dùng đăng nhập lại: entry cũ đã bị xóa khi logout
+        let (callback, cookie) = login(&app).await;
+        send(&app, &callback, Some(&cookie)).await;
+        let again = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(again.status(), StatusCode::OK);
+        assert_eq!(again.headers()["x-cache"], "miss");
+    }
+
+    #[tokio::test]
+    async fn exports_the_summary_as_pdf() {
+        let server = Arc::new(MockSmartServer::start().await);
+        let app = gateway(&server).await;
+        let export = format!("/api/patient/{}/summary.pdf", DEFAULT_PATIENT);
+
+        let response = send(&app, &export, None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let (callback, cookie) = login(&app).await;
+        send(&app, &callback, Some(&cookie)).await;
+        let response = send(&app, &export, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/pdf");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            format!("attachment; filename=\"patient-summary-{}.pdf\"", DEFAULT_PATIENT)
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.starts_with(b"%PDF-"));
+
+        // Bệnh nhân khác bệnh nhân của launch: service từ chối
+        let response = send(&app, "/api/patient/other/summary.pdf", Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Id không phải id FHIR bị từ chối trước khi gọi upstream
+        for uri in [
+            "/api/patient/x%2F..%2F..%2Fdemo%2Fpatients/summary.pdf",
+            "/api/patient/%2E%2E/summary.html",
+            "/api/patient/p1%0Aforged/summary.pdf",
+            "/api/patient/x%2F..%2F..%2Fdemo%2Fpatients/summary",
+            "/demo/patients/..%2F..%2Fpatient_summary%2Fp1/summary",
+        ] {
+            let response = send(&app, uri, Some(&cookie)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn fhir_proxy_injects_session_token_and_rewrites_links() {
+        let server = Arc::new(MockSmartServer::start().await);
+        let app = gateway_with(
+            &server,
+            json!({ "dpop": true, "fhir_resource_types": ["Patient", "Observation"] }),
+        )
+        .await;
+        let search = format!("/fhir/epic_sandbox/Observation?patient={}", DEFAULT_PATIENT);
+
+        // Chưa đăng nhập: không có token để gắn vào
+        let response = send(&app, &search, None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let (callback, cookie) = login(&app).await;
+        send(&app, &callback, Some(&cookie)).await;
+
+        let response = send(&app, &search, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let bundle: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        // Practitioner từ `_include` không nằm trong allowlist
+        assert_eq!(bundle["entry"].as_array().unwrap().len(), 1, "{bundle}");
+        let next = bundle["link"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|link| link["relation"] == "next")
+            .and_then(|link| link["url"].as_str())
+            .unwrap();
+        let next = next.strip_prefix("http://localhost:3000").unwrap();
+        assert!(next.starts_with("/fhir/epic_sandbox/Observation?"), "{next}");
+
+        // `_format=xml` bị bỏ: vẫn nhận JSON đã lọc thay cho Bundle XML chưa lọc
+        let response = send(&app, &format!("{}&_format=xml", search), Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let bundle: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(bundle["entry"].as_array().unwrap().len(), 1, "{bundle}");
+
+        let response = send(&app, next, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let bundle: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(bundle["entry"][0]["resource"]["id"], "obs-2");
+
+        let patient = format!("/fhir/epic_sandbox/Patient/{}", DEFAULT_PATIENT);
+        let response = send(&app, &patient, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&app, "/fhir/epic_sandbox/Practitioner/x", Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(&app, "/fhir/google/Patient/x", Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_token_refresh() {
+        let server = Arc::new(MockSmartServer::start().await);
+        // Token sắp hết hạn ngay khi cấp: mọi request đều phải làm mới
+        server.set_token_lifetime(Duration::seconds(30));
+        let app = gateway_with(&server, json!({ "fhir_resource_types": ["Patient"] })).await;
+        let (callback, cookie) = login(&app).await;
+        send(&app, &callback, Some(&cookie)).await;
+        server.set_token_lifetime(Duration::hours(1));
+
+        // Refresh token chỉ dùng được một lần: request thứ hai phải dùng kết quả của request đầu
+        let patient = format!("/fhir/epic_sandbox/Patient/{}", DEFAULT_PATIENT);
+        let (first, second) = tokio::join!(
+            send(&app, &patient, Some(&cookie)),
+            send(&app, &patient, Some(&cookie))
+        );
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(server.refresh_token_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn summary_forwards_the_refreshed_provider_token() {
+        let server = Arc::new(MockSmartServer::start().await);
+        // Token sắp hết hạn ngay khi cấp: gateway phải làm mới trước khi chuyển tiếp
+        server.set_token_lifetime(Duration::seconds(30));
+        let app = gateway(&server).await;
+        let (callback, cookie) = login(&app).await;
+        send(&app, &callback, Some(&cookie)).await;
+        server.set_token_lifetime(Duration::hours(1));
+
+        let summary = format!("/api/patient/{}/summary", DEFAULT_PATIENT);
+        let response = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.refresh_token_count(), 1);
+        let response = send(&app, &summary, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.refresh_token_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn callback_fails_when_token_endpoint_rejects_the_code() {
+        let server = Arc::new(MockSmartServer::start().await);
+        let app = gateway(&server).await;
+
+        let (callback, cookie) = login(&app).await;
+        server.fail_next(Failure::InvalidGrant);
+        let response = send(&app, &callback, Some(&cookie)).await;
+        // Trang đăng nhập lại thay cho 500
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("invalid_grant"), "{body}");
+        assert!(body.contains("href=\"/epic-sandbox/login\""), "{body}");
+
+        let summary = format!("/api/patient/{}/summary", DEFAULT_PATIENT);
+        let response = send(&app, &summary, Some(&cookie)).await;
+        assert_ne!(response.status(), StatusCode::OK);
+
+        // Epic tạm thời không phản hồi: cùng code vẫn dùng được khi thử lại
+        server.fail_next(Failure::Unavailable);
+        let response = send(&app, &callback, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn callback_shows_relogin_page_when_consent_is_denied() {
+        let server = Arc::new(MockSmartServer::start().await);
+        let app = gateway(&server).await;
+
+        server.fail_next(Failure::AccessDenied);
+        let (callback, cookie) = login(&app).await;
+        assert!(callback.contains("error=access_denied"));
+        let response = send(&app, &callback, Some(&cookie)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("Bạn chưa cấp quyền truy cập"), "{body}");
+
+        // `error_description` do người gọi đặt không được hiển thị; `state` sai
+        // thì chỉ thấy trang phiên hết hạn
+        let forged = format!("{}&error_description=Call%20555-0100", callback);
+        let response = send(&app, &forged, Some(&cookie)).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("555-0100"));
+
+        let forged = "/epic-sandbox/callback?error=access_denied&state=forged&error_description=Call%20555-0100";
+        let response = send(&app, forged, Some(&cookie)).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("Phiên đăng nhập đã hết hạn"), "{body}");
+        assert!(!body.contains("555-0100"), "{body}");
+    }
+}
@@ -1,10 +1,17 @@
-use axum::{body::{Body, to_bytes}, extract::Request, http::{HeaderMap, Uri}, response::IntoResponse};
-use reqwest::{Client, StatusCode};
+use axum::{body::{Body, to_bytes}, extract::{Request, State}, http::{HeaderMap, Uri}, response::IntoResponse};
+use reqwest::StatusCode;
 
-pub async fn proxy_fresh(uri: Uri, req: Request<Body>) -> impl IntoResponse {
-    let client = Client::new();
+use crate::di::SharedState;
+
+pub async fn proxy_fresh(State(state): State<SharedState>, uri: Uri, req: Request<Body>) -> impl IntoResponse {
+    let snapshot = state.snapshot();
+    let client = &snapshot.upstream_client;
     let path = uri.path();
-    let full_uri = format!("http://localhost:8000{}", path);
+    let frontend_url = match snapshot.upstream_url("frontend") {
+        Ok(url) => url,
+        Err(e) => return e.into_response(),
+    };
+    let full_uri = format!("{}{}", frontend_url, path);
     let (parts, body) = req.into_parts();
     let max_body_size = 1024 * 1024; // 1 MB
     let body_bytes = match to_bytes(body, max_body_size).await {
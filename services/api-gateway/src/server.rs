@@ -0,0 +1,83 @@
+//! Khởi tạo listener HTTP hoặc HTTPS cho gateway.
+
+use crate::di::SharedState;
+use crate::tls;
+use anyhow::Context;
+use axum::Router;
+use futures::future::BoxFuture;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+/// Địa chỉ bind từ `host` (IP hoặc hostname) và port.
+async fn resolve(host: &str, port: u16) -> anyhow::Result<SocketAddr> {
+    tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("failed to resolve bind address {}:{}", host, port))?
+        .next()
+        .with_context(|| format!("no address found for host '{}'", host))
+}
+
+/// Bind các listener và trả về future chạy server.
+///
+/// Server ngừng nhận kết nối khi `stop` bị cancel và kết thúc khi kết nối cuối cùng đóng.
+pub async fn start(
+    state: &SharedState,
+    app: Router,
+    stop: CancellationToken,
+) -> anyhow::Result<BoxFuture<'static, anyhow::Result<()>>> {
+    let snapshot = state.snapshot();
+    let settings = &snapshot.settings;
+    let addr = resolve(&settings.host, settings.port).await?;
+
+    let Some(tls_settings) = settings.tls.clone() else {
+        let listener = TcpListener::bind(addr).await?;
+        tracing::info!("Listening on http://{}", addr);
+        return Ok(Box::pin(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(stop.cancelled_owned())
+                .await
+                .context("HTTP server error")
+        }));
+    };
+
+    let rustls_config = tls::load_server_config(&tls_settings).await?;
+    tls::spawn_cert_reloader(rustls_config.clone(), tls_settings.clone(), stop.clone());
+
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        let stop = stop.clone();
+        async move {
+            stop.cancelled().await;
+            // Deadline do shutdown::run_until_signal quản lý
+            handle.graceful_shutdown(None);
+        }
+    });
+    tracing::info!("Listening on https://{}", addr);
+    let https = axum_server::bind_rustls(addr, rustls_config)
+        .handle(handle)
+        .serve(app.into_make_service());
+
+    let redirect: BoxFuture<'static, anyhow::Result<()>> = match tls_settings.http_redirect_port {
+        Some(http_port) => {
+            let http_addr = resolve(&settings.host, http_port).await?;
+            let listener = TcpListener::bind(http_addr).await?;
+            tracing::info!("Redirecting http://{} to HTTPS", http_addr);
+            let router = tls::redirect_router(settings.port);
+            Box::pin(async move {
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(stop.cancelled_owned())
+                    .await
+                    .context("HTTP redirect server error")
+            })
+        }
+        None => Box::pin(async { Ok(()) }),
+    };
+
+    Ok(Box::pin(async move {
+        let (https_result, redirect_result) = tokio::join!(https, redirect);
+        https_result.context("HTTPS server error")?;
+        redirect_result
+    }))
+}
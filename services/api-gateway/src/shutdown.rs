@@ -0,0 +1,97 @@
+//! Graceful shutdown: SIGTERM/SIGINT handling and connection draining.
+//!
+//! On a signal the gateway first reports not-ready, waits `readiness_grace_ms`
+//! so the orchestrator stops routing to it, then stops accepting connections
+//! and gives in-flight requests up to `drain_timeout_ms` to finish. Session
+//! writes happen inside the request (tower-sessions saves before the response
+//! is sent), so drained requests have their sessions persisted.
+
+use crate::di::SharedState;
+use anyhow::Context;
+use std::future::Future;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Resolves on the first SIGINT (Ctrl+C) or SIGTERM.
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
+
+/// Runs `servers` until a shutdown signal arrives, then drains in-flight requests.
+///
+/// `servers` must stop accepting connections once `stop` is cancelled and
+/// resolve when their last connection has closed.
+pub async fn run_until_signal<F>(
+    state: SharedState,
+    stop: CancellationToken,
+    servers: F,
+) -> anyhow::Result<()>
+where
+    F: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let shutdown_settings = state.snapshot().settings.shutdown.clone();
+    let mut server = tokio::spawn(servers);
+
+    tokio::select! {
+        result = &mut server => {
+            // Server stopped on its own (e.g. accept error): nothing to drain.
+            stop.cancel();
+            return result.context("server task panicked")?;
+        }
+        _ = wait_for_signal() => {}
+    }
+
+    tracing::info!("Shutting down: marking gateway not ready");
+    state.mark_shutting_down();
+    if shutdown_settings.readiness_grace_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(shutdown_settings.readiness_grace_ms)).await;
+    }
+
+    tracing::info!(
+        "Draining in-flight requests (deadline {} ms)",
+        shutdown_settings.drain_timeout_ms
+    );
+    stop.cancel();
+    match tokio::time::timeout(
+        Duration::from_millis(shutdown_settings.drain_timeout_ms),
+        &mut server,
+    )
+    .await
+    {
+        Ok(result) => {
+            result.context("server task panicked")??;
+            tracing::info!("All connections drained");
+        }
+        Err(_) => {
+            tracing::warn!("Drain deadline exceeded, dropping remaining connections");
+            server.abort();
+        }
+    }
+    Ok(())
+}
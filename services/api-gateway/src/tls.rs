@@ -0,0 +1,162 @@
+//! TLS cho gateway: HTTPS termination bằng rustls (nạp lại cert khi file thay
+//! đổi), listener HTTP chỉ để redirect sang HTTPS, và HTTP client mTLS dùng cho
+//! các call tới service nội bộ.
+
+use anyhow::Context;
+use axum::{
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use config_lib::settings::{TlsSettings, UpstreamTlsSettings};
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+/// Đọc cert/key từ đĩa để tạo cấu hình rustls cho listener HTTPS.
+pub async fn load_server_config(tls: &TlsSettings) -> anyhow::Result<RustlsConfig> {
+    // reqwest và axum-server cùng dùng rustls; chọn ring làm crypto provider mặc định.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to load TLS certificate '{}' / key '{}'",
+                tls.cert_path, tls.key_path
+            )
+        })
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Định kỳ kiểm tra cert/key và nạp lại khi chúng thay đổi (ví dụ sau khi gia hạn).
+///
+/// Nếu cert mới không hợp lệ thì giữ nguyên cert đang dùng.
+pub fn spawn_cert_reloader(config: RustlsConfig, tls: TlsSettings, stop: CancellationToken) {
+    if tls.reload_interval_secs == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(tls.reload_interval_secs));
+        interval.tick().await;
+        let mut last_seen = (modified_at(&tls.cert_path), modified_at(&tls.key_path));
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => return,
+                _ = interval.tick() => {}
+            }
+            let current = (modified_at(&tls.cert_path), modified_at(&tls.key_path));
+            if current == last_seen {
+                continue;
+            }
+            match config
+                .reload_from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+            {
+                Ok(()) => {
+                    tracing::info!("Reloaded TLS certificate from {}", tls.cert_path);
+                    last_seen = current;
+                }
+                Err(e) => {
+                    // Cert và key có thể đang được ghi dở; thử lại ở lần kiểm tra sau.
+                    tracing::error!(
+                        "Failed to reload TLS certificate, keeping the old one: {}",
+                        e
+                    )
+                }
+            }
+        }
+    });
+}
+
+/// URL HTTPS tương ứng với request HTTP, giữ nguyên path và query.
+fn https_redirect_target(host: &str, uri: &Uri, https_port: u16) -> String {
+    let hostname = match host.rsplit_once(':') {
+        // Không cắt nhầm IPv6 không có port, ví dụ "[::1]"
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+    if https_port == 443 {
+        format!("https://{}{}", hostname, path_and_query)
+    } else {
+        format!("https://{}:{}{}", hostname, https_port, path_and_query)
+    }
+}
+
+/// Router cho listener HTTP: mọi request đều được redirect sang HTTPS.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        match headers.get(header::HOST).and_then(|h| h.to_str().ok()) {
+            Some(host) => {
+                Redirect::permanent(&https_redirect_target(host, &uri, https_port)).into_response()
+            }
+            None => (StatusCode::BAD_REQUEST, "Missing Host header").into_response(),
+        }
+    })
+}
+
+/// HTTP client cho các call tới upstream; dùng mTLS khi có `upstream_tls`.
+pub fn upstream_client(
+    upstream_tls: Option<&UpstreamTlsSettings>,
+) -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(tls) = upstream_tls {
+        let mut identity_pem = std::fs::read(&tls.client_cert_path).with_context(|| {
+            format!(
+                "failed to read client certificate '{}'",
+                tls.client_cert_path
+            )
+        })?;
+        identity_pem.push(b'\n');
+        identity_pem.extend(
+            std::fs::read(&tls.client_key_path)
+                .with_context(|| format!("failed to read client key '{}'", tls.client_key_path))?,
+        );
+        let identity = reqwest::Identity::from_pem(&identity_pem)
+            .context("invalid client certificate/key for upstream mTLS")?;
+
+        builder = builder
+            .use_rustls_tls()
+            .identity(identity)
+            // PHI không được đi qua kết nối cleartext khi đã bật mTLS
+            .https_only(true);
+
+        if let Some(ca_path) = &tls.ca_cert_path {
+            let ca_pem = std::fs::read(ca_path)
+                .with_context(|| format!("failed to read upstream CA '{}'", ca_path))?;
+            let ca = reqwest::Certificate::from_pem(&ca_pem)
+                .with_context(|| format!("invalid upstream CA certificate '{}'", ca_path))?;
+            builder = builder
+                .tls_built_in_root_certs(false)
+                .add_root_certificate(ca);
+        }
+    }
+    builder
+        .build()
+        .context("failed to build upstream HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_keeps_path_and_query_and_swaps_port() {
+        let uri: Uri = "/epic-sandbox/callback?code=abc&state=xyz".parse().unwrap();
+        assert_eq!(
+            https_redirect_target("gateway.local:8080", &uri, 3000),
+            "https://gateway.local:3000/epic-sandbox/callback?code=abc&state=xyz"
+        );
+        assert_eq!(
+            https_redirect_target("gateway.local", &uri, 443),
+            "https://gateway.local/epic-sandbox/callback?code=abc&state=xyz"
+        );
+        assert_eq!(
+            https_redirect_target("[::1]", &Uri::from_static("/"), 443),
+            "https://[::1]/"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! Lời khuyên sinh tự động từ patient-summary-agent (`POST /advice`), cho bản
+//! in. Agent nhận các CarePlan nội trú theo hình dạng frontend gửi
+//! (`inpatientCarePlansRecord` với `Addresses` và `Goal`).
+
+use std::time::Duration;
+
+use fhir::{CarePlan, Reference};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Agent sinh text bằng model chạy trên CPU, có thể chậm
+const ADVICE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct AdviceResponse {
+    advice: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdviceClient {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl AdviceClient {
+    /// `base_url` của agent, ví dụ `http://localhost:3020`.
+    pub fn new(http: reqwest::Client, base_url: &str) -> Self {
+        Self {
+            http,
+            url: format!("{}/advice", base_url.trim_end_matches('/')),
+        }
+    }
+
+    /// Lời khuyên cho các CarePlan; `None` khi agent lỗi, để bản in vẫn được
+    /// tạo mà chỉ thiếu phần này.
+    pub async fn advise(&self, careplans: &[CarePlan]) -> Option<String> {
+        let displays = |references: &[Reference]| -> Vec<String> {
+            references
+                .iter()
+                .filter_map(|reference| reference.display.clone())
+                .collect()
+        };
+        let records: Vec<_> = careplans
+            .iter()
+            .map(|careplan| {
+                json!({
+                    "Addresses": displays(&careplan.addresses),
+                    "Goal": displays(&careplan.goal),
+                })
+            })
+            .collect();
+        let response = self
+            .http
+            .post(&self.url)
+            .timeout(ADVICE_TIMEOUT)
+            .json(&json!({ "summary": { "inpatientCarePlansRecord": records } }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        let advice = match response {
+            Ok(response) => response.json::<AdviceResponse>().await,
+            Err(e) => Err(e),
+        };
+        match advice {
+            Ok(advice) => Some(advice.advice),
+            Err(e) => {
+                tracing::warn!("Advice agent request failed: {}", e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,1074 @@
+//! Đọc document C-CDA (CCD, Discharge Summary) của hệ thống bên ngoài thành
+//! resource của crate `fhir`.
+//!
+//! Chỉ các section Problems, Medications, Allergies, Results và Encounters được
+//! chuyển. Section nhận theo templateId hoặc mã LOINC; entry nhận theo cấu trúc
+//! (act bọc observation, organizer bọc component) vì nhiều hệ thống gửi
+//! document thiếu templateId của entry. Mỗi resource có `meta.source` là
+//! DocumentReference gốc và tag [`EXTERNAL`] để phân biệt với dữ liệu của Epic.
+
+use std::collections::HashMap;
+
+use fhir::datatypes::{
+    Dosage, Dose, DoseAndRate, Effective, Identifier, Meta, Onset, Timing, TimingRepeat,
+};
+use fhir::resources::composition::LOINC;
+use fhir::resources::condition::CONDITION_CLINICAL;
+use fhir::resources::{
+    AllergyIntoleranceReaction, ConditionAbatement, EncounterLocation, EncounterParticipant,
+    MedicationRequestMedication, ObservationValue,
+};
+use fhir::{
+    AllergyIntolerance, CodeableConcept, Coding, Condition, Encounter, MedicationRequest,
+    Observation, Period, Quantity, Reference,
+};
+use roxmltree::{Document, Node};
+use thiserror::Error;
+
+/// Namespace của CDA R2
+const V3: &str = "urn:hl7-org:v3";
+const XSI: &str = "http://www.w3.org/2001/XMLSchema-instance";
+/// Tiền tố templateId của section và entry trong C-CDA R2.1
+const SECTION_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.2.";
+const ENTRY_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.4.";
+
+/// Hệ thống của tag đánh dấu nguồn dữ liệu trong `meta.tag`.
+pub const PROVENANCE_SYSTEM: &str = "urn:seds:provenance";
+/// Code của tag cho dữ liệu lấy từ document bên ngoài.
+pub const EXTERNAL: &str = "external";
+
+const SNOMED: &str = "http://snomed.info/sct";
+const UCUM: &str = "http://unitsofmeasure.org";
+const ACT_CODE: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+const CONDITION_VERIFICATION: &str = "http://terminology.hl7.org/CodeSystem/condition-ver-status";
+const CONDITION_CATEGORY: &str = "http://terminology.hl7.org/CodeSystem/condition-category";
+const ALLERGY_CLINICAL: &str = "http://terminology.hl7.org/CodeSystem/allergyintolerance-clinical";
+const ALLERGY_VERIFICATION: &str =
+    "http://terminology.hl7.org/CodeSystem/allergyintolerance-verification";
+const OBSERVATION_CATEGORY: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+
+#[derive(Debug, Error)]
+pub enum CcdaError {
+    #[error("invalid XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+    /// Phần tử gốc không phải `ClinicalDocument` của CDA
+    #[error("expected a CDA ClinicalDocument, got <{0}>")]
+    NotClinicalDocument(String),
+}
+
+/// Resource chuyển từ một hoặc nhiều document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CcdaRecords {
+    pub problems: Vec<Condition>,
+    pub medications: Vec<MedicationRequest>,
+    pub allergies: Vec<AllergyIntolerance>,
+    pub results: Vec<Observation>,
+    pub encounters: Vec<Encounter>,
+}
+
+impl CcdaRecords {
+    pub fn extend(&mut self, other: CcdaRecords) {
+        self.problems.extend(other.problems);
+        self.medications.extend(other.medications);
+        self.allergies.extend(other.allergies);
+        self.results.extend(other.results);
+        self.encounters.extend(other.encounters);
+    }
+}
+
+/// Chuyển một document C-CDA. `source` là URL của DocumentReference gốc, ghi
+/// vào `meta.source`; id của resource có dạng `{id_prefix}-problem1`.
+pub fn parse(
+    xml: &str,
+    patient_id: &str,
+    source: &str,
+    id_prefix: &str,
+) -> Result<CcdaRecords, CcdaError> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+    if !root.has_tag_name((V3, "ClinicalDocument")) {
+        return Err(CcdaError::NotClinicalDocument(
+            root.tag_name().name().to_string(),
+        ));
+    }
+
+    let mut converter = Converter {
+        patient: Reference::to("Patient", patient_id),
+        source,
+        id_prefix,
+        narrative: root
+            .descendants()
+            .filter_map(|node| Some((node.attribute("ID")?, node_text(node)?)))
+            .collect(),
+        records: CcdaRecords::default(),
+    };
+    for section in root.descendants().filter(|node| is(*node, "section")) {
+        let Some(kind) = Section::of(section) else {
+            continue;
+        };
+        for entry in children(section, "entry") {
+            let Some(top) = entry.first_element_child() else {
+                continue;
+            };
+            match kind {
+                Section::Problems => converter.problem_concern(top),
+                Section::Medications => converter.medications(top),
+                Section::Allergies => converter.allergy_concern(top),
+                Section::Results => converter.results(top),
+                Section::Encounters if is(top, "encounter") => converter.encounter(top),
+                Section::Encounters => {}
+            }
+        }
+    }
+    Ok(converter.records)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Problems,
+    Medications,
+    Allergies,
+    Results,
+    Encounters,
+}
+
+impl Section {
+    fn of(section: Node) -> Option<Self> {
+        let by_template = children(section, "templateId")
+            .filter_map(|template| template.attribute("root")?.strip_prefix(SECTION_TEMPLATE))
+            .find_map(|template| match template {
+                "5" | "5.1" => Some(Self::Problems),
+                // Medications và Discharge Medications
+                "1" | "1.1" | "11" | "11.1" => Some(Self::Medications),
+                "6" | "6.1" => Some(Self::Allergies),
+                "3" | "3.1" => Some(Self::Results),
+                "22" | "22.1" => Some(Self::Encounters),
+                _ => None,
+            });
+        by_template.or_else(|| match child(section, "code")?.attribute("code")? {
+            "11450-4" => Some(Self::Problems),
+            "10160-0" | "10183-2" => Some(Self::Medications),
+            "48765-2" => Some(Self::Allergies),
+            "30954-2" => Some(Self::Results),
+            "46240-8" => Some(Self::Encounters),
+            _ => None,
+        })
+    }
+}
+
+struct Converter<'a> {
+    patient: Reference,
+    source: &'a str,
+    id_prefix: &'a str,
+    /// Text của narrative theo `ID`, cho `originalText/reference`
+    narrative: HashMap<&'a str, String>,
+    records: CcdaRecords,
+}
+
+impl Converter<'_> {
+    fn problem_concern(&mut self, top: Node) {
+        let concern = concern_status(top);
+        for observation in wrapped(top, "observation") {
+            self.problem(observation, concern);
+        }
+    }
+
+    fn problem(&mut self, observation: Node, concern: Option<&str>) {
+        // `negationInd`: khẳng định bệnh nhân không có vấn đề này
+        if negated(observation) {
+            return;
+        }
+        let Some(code) = child(observation, "value").and_then(|value| self.concept(value)) else {
+            return;
+        };
+        let (onset, abatement) = effective_bounds(observation);
+        let status = status_observation(observation, "6")
+            .and_then(clinical_status)
+            .or(abatement.as_ref().map(|_| "resolved"))
+            .or(concern)
+            .unwrap_or("active");
+        let id = format!(
+            "{}-problem{}",
+            self.id_prefix,
+            self.records.problems.len() + 1
+        );
+        self.records.problems.push(Condition {
+            id: Some(id),
+            meta: Some(self.meta()),
+            text: None,
+            contained: Vec::new(),
+            extension: Vec::new(),
+            modifier_extension: Vec::new(),
+            identifier: identifiers(observation),
+            clinical_status: Some(CodeableConcept::from_coding(Coding::new(
+                CONDITION_CLINICAL,
+                status,
+            ))),
+            verification_status: Some(CodeableConcept::from_coding(Coding::new(
+                CONDITION_VERIFICATION,
+                "confirmed",
+            ))),
+            category: vec![CodeableConcept::from_coding(
+                Coding::new(CONDITION_CATEGORY, "problem-list-item")
+                    .with_display("Problem List Item"),
+            )],
+            severity: None,
+            code: Some(code),
+            body_site: Vec::new(),
+            subject: self.patient.clone(),
+            encounter: None,
+            onset: onset.map(Onset::DateTime),
+            abatement: abatement.map(ConditionAbatement::DateTime),
+            recorded_date: author_time(observation),
+            recorder: None,
+            asserter: None,
+            note: Vec::new(),
+        });
+    }
+
+    fn medications(&mut self, top: Node) {
+        for administration in wrapped(top, "substanceAdministration") {
+            self.medication(administration);
+        }
+    }
+
+    fn medication(&mut self, administration: Node) {
+        if negated(administration) {
+            return;
+        }
+        let Some(code) = path(
+            administration,
+            &[
+                "consumable",
+                "manufacturedProduct",
+                "manufacturedMaterial",
+                "code",
+            ],
+        )
+        .and_then(|code| self.concept(code)) else {
+            return;
+        };
+        let status = match status_code(administration) {
+            Some("active") => "active",
+            Some("completed") => "completed",
+            Some("aborted" | "cancelled") => "stopped",
+            Some("suspended" | "held") => "on-hold",
+            Some("nullified") => "entered-in-error",
+            _ => "unknown",
+        };
+        // INT: thuốc được kê; EVN: thuốc bệnh nhân đã/đang dùng theo ghi nhận
+        let intent = match administration.attribute("moodCode") {
+            Some("INT") => "order",
+            _ => "plan",
+        };
+        let id = format!(
+            "{}-medication{}",
+            self.id_prefix,
+            self.records.medications.len() + 1
+        );
+        let dosage = self.dosage(administration);
+        self.records.medications.push(MedicationRequest {
+            id: Some(id),
+            meta: Some(self.meta()),
+            text: None,
+            contained: Vec::new(),
+            extension: Vec::new(),
+            modifier_extension: Vec::new(),
+            identifier: identifiers(administration),
+            status: status.to_string(),
+            intent: intent.to_string(),
+            category: Vec::new(),
+            priority: None,
+            medication: MedicationRequestMedication::CodeableConcept(code),
+            subject: self.patient.clone(),
+            encounter: None,
+            authored_on: author_time(administration),
+            requester: None,
+            reason_code: Vec::new(),
+            reason_reference: Vec::new(),
+            note: Vec::new(),
+            dosage_instruction: (dosage != Dosage::default())
+                .then_some(dosage)
+                .into_iter()
+                .collect(),
+            dispense_request: None,
+        });
+    }
+
+    /// Sig (`text`), khoảng thời gian dùng (IVL_TS), tần suất (PIVL_TS), liều và
+    /// đường dùng.
+    fn dosage(&self, administration: Node) -> Dosage {
+        let mut repeat = TimingRepeat::default();
+        for time in children(administration, "effectiveTime") {
+            if let Some(period) = child(time, "period") {
+                repeat.frequency = Some(1);
+                repeat.period = period
+                    .attribute("value")
+                    .and_then(|value| value.parse().ok());
+                repeat.period_unit = period
+                    .attribute("unit")
+                    .filter(|unit| ["s", "min", "h", "d", "wk", "mo", "a"].contains(unit))
+                    .map(str::to_string);
+            } else {
+                let (start, end) = effective_bounds_of(time);
+                if start.is_some() || end.is_some() {
+                    repeat.bounds_period = Some(Period { start, end });
+                }
+            }
+        }
+        let dose = child(administration, "doseQuantity")
+            .and_then(quantity)
+            .map(|dose| DoseAndRate {
+                type_: None,
+                dose: Some(Dose::Quantity(dose)),
+            });
+        Dosage {
+            text: child(administration, "text").and_then(|text| self.text(text)),
+            timing: (repeat != TimingRepeat::default()).then(|| Timing {
+                repeat: Some(repeat),
+                ..Timing::default()
+            }),
+            route: child(administration, "routeCode").and_then(|route| self.concept(route)),
+            dose_and_rate: dose.into_iter().collect(),
+            ..Dosage::default()
+        }
+    }
+
+    fn allergy_concern(&mut self, top: Node) {
+        let concern = concern_status(top);
+        for observation in wrapped(top, "observation") {
+            self.allergy(observation, concern);
+        }
+    }
+
+    fn allergy(&mut self, observation: Node, concern: Option<&str>) {
+        // `negationInd` trên allergy observation: "không có dị ứng đã biết"
+        if negated(observation) {
+            return;
+        }
+        let Some(substance) = children(observation, "participant")
+            .filter(|participant| participant.attribute("typeCode") == Some("CSM"))
+            .find_map(|participant| {
+                path(participant, &["participantRole", "playingEntity", "code"])
+            })
+            .and_then(|code| self.concept(code))
+        else {
+            return;
+        };
+        // Loại và nhóm dị ứng suy ra từ `value` (SNOMED)
+        let (kind, category) = match child(observation, "value").and_then(|v| v.attribute("code")) {
+            Some("419199007") => (Some("allergy"), None),
+            Some("416098002") => (Some("allergy"), Some("medication")),
+            Some("414285001") => (Some("allergy"), Some("food")),
+            Some("426232007") => (Some("allergy"), Some("environment")),
+            Some("59037007") => (Some("intolerance"), Some("medication")),
+            Some("235719002") => (Some("intolerance"), Some("food")),
+            Some("419511003") => (None, Some("medication")),
+            Some("418471000") => (None, Some("food")),
+            _ => (None, None),
+        };
+        let status = status_observation(observation, "28")
+            .and_then(clinical_status)
+            .or(concern)
+            .unwrap_or("active");
+        let criticality = status_observation(observation, "145")
+            .and_then(|value| value.attribute("code"))
+            .and_then(|code| match code {
+                "CRITL" => Some("low"),
+                "CRITH" => Some("high"),
+                "CRITU" => Some("unable-to-assess"),
+                _ => None,
+            });
+        let reaction = related_observations(observation)
+            .filter(|related| {
+                has_template(*related, "9")
+                    || related.parent().and_then(|r| r.attribute("typeCode")) == Some("MFST")
+            })
+            .filter_map(|related| self.reaction(related))
+            .collect();
+        let (onset, _) = effective_bounds(observation);
+        let id = format!(
+            "{}-allergy{}",
+            self.id_prefix,
+            self.records.allergies.len() + 1
+        );
+        self.records.allergies.push(AllergyIntolerance {
+            id: Some(id),
+            meta: Some(self.meta()),
+            text: None,
+            contained: Vec::new(),
+            extension: Vec::new(),
+            modifier_extension: Vec::new(),
+            identifier: identifiers(observation),
+            clinical_status: Some(CodeableConcept::from_coding(Coding::new(
+                ALLERGY_CLINICAL,
+                status,
+            ))),
+            verification_status: Some(CodeableConcept::from_coding(Coding::new(
+                ALLERGY_VERIFICATION,
+                "confirmed",
+            ))),
+            type_: kind.map(str::to_string),
+            category: category.map(str::to_string).into_iter().collect(),
+            criticality: criticality.map(str::to_string),
+            code: Some(substance),
+            patient: self.patient.clone(),
+            encounter: None,
+            onset: onset.map(Onset::DateTime),
+            recorded_date: author_time(observation),
+            recorder: None,
+            asserter: None,
+            last_occurrence: None,
+            note: Vec::new(),
+            reaction,
+        });
+    }
+
+    fn reaction(&self, observation: Node) -> Option<AllergyIntoleranceReaction> {
+        let manifestation = child(observation, "value").and_then(|value| self.concept(value))?;
+        let severity = status_observation(observation, "8")
+            .and_then(|value| value.attribute("code"))
+            .and_then(|code| match code {
+                "255604002" => Some("mild"),
+                "6736007" => Some("moderate"),
+                "24484000" => Some("severe"),
+                _ => None,
+            });
+        Some(AllergyIntoleranceReaction {
+            manifestation: vec![manifestation],
+            onset: effective_bounds(observation).0,
+            severity: severity.map(str::to_string),
+            ..AllergyIntoleranceReaction::default()
+        })
+    }
+
+    fn results(&mut self, top: Node) {
+        if is(top, "organizer") {
+            let organizer_time = effective_bounds(top).0;
+            for observation in
+                children(top, "component").filter_map(|component| child(component, "observation"))
+            {
+                self.result(observation, organizer_time.as_deref());
+            }
+        } else if is(top, "observation") {
+            self.result(top, None);
+        }
+    }
+
+    fn result(&mut self, observation: Node, organizer_time: Option<&str>) {
+        if negated(observation) {
+            return;
+        }
+        let Some(code) = child(observation, "code").and_then(|code| self.concept(code)) else {
+            return;
+        };
+        let status = match status_code(observation) {
+            Some("active") => "preliminary",
+            Some("aborted" | "cancelled") => "cancelled",
+            Some("nullified") => "entered-in-error",
+            _ => "final",
+        };
+        let effective = effective_bounds(observation)
+            .0
+            .or_else(|| organizer_time.map(str::to_string));
+        let id = format!(
+            "{}-result{}",
+            self.id_prefix,
+            self.records.results.len() + 1
+        );
+        self.records.results.push(Observation {
+            id: Some(id),
+            meta: Some(self.meta()),
+            text: None,
+            contained: Vec::new(),
+            extension: Vec::new(),
+            modifier_extension: Vec::new(),
+            identifier: identifiers(observation),
+            based_on: Vec::new(),
+            status: status.to_string(),
+            category: vec![CodeableConcept::from_coding(
+                Coding::new(OBSERVATION_CATEGORY, "laboratory").with_display("Laboratory"),
+            )],
+            code,
+            subject: Some(self.patient.clone()),
+            encounter: None,
+            effective: effective.map(Effective::DateTime),
+            issued: None,
+            performer: Vec::new(),
+            value: child(observation, "value").and_then(|value| self.value(value)),
+            data_absent_reason: None,
+            interpretation: children(observation, "interpretationCode")
+                .filter_map(|code| self.concept(code))
+                .collect(),
+            note: Vec::new(),
+            body_site: None,
+            method: None,
+            reference_range: Vec::new(),
+            has_member: Vec::new(),
+            derived_from: Vec::new(),
+            component: Vec::new(),
+        });
+    }
+
+    /// `value` theo `xsi:type`; kiểu không hỗ trợ bị bỏ.
+    fn value(&self, value: Node) -> Option<ObservationValue> {
+        match value.attribute((XSI, "type"))? {
+            "PQ" => quantity(value).map(ObservationValue::Quantity),
+            "CD" | "CE" | "CV" | "CO" => self.concept(value).map(ObservationValue::CodeableConcept),
+            "ST" | "ED" => self.text(value).map(ObservationValue::String),
+            "INT" => value
+                .attribute("value")?
+                .parse()
+                .ok()
+                .map(ObservationValue::Integer),
+            "BL" => match value.attribute("value")? {
+                "true" => Some(ObservationValue::Boolean(true)),
+                "false" => Some(ObservationValue::Boolean(false)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn encounter(&mut self, encounter: Node) {
+        let code = child(encounter, "code");
+        // Class lấy từ translation ActCode (`AMB`, `EMER`, `IMP`, ...)
+        let class = code
+            .into_iter()
+            .flat_map(|code| std::iter::once(code).chain(children(code, "translation")))
+            .find(|coding| coding.attribute("codeSystem") == Some("2.16.840.1.113883.5.4"))
+            .and_then(|coding| {
+                let mut class = Coding::new(ACT_CODE, coding.attribute("code")?);
+                class.display = coding.attribute("displayName").map(str::to_string);
+                Some(class)
+            })
+            .unwrap_or_else(|| Coding::new(ACT_CODE, "AMB").with_display("ambulatory"));
+        let (start, end) = effective_bounds(encounter);
+        let status = match status_code(encounter) {
+            Some("active") => "in-progress",
+            Some("cancelled" | "aborted") => "cancelled",
+            Some("nullified") => "entered-in-error",
+            Some("completed") => "finished",
+            _ if end.is_some() => "finished",
+            _ => "unknown",
+        };
+        let participant = children(encounter, "performer")
+            .filter_map(|performer| path(performer, &["assignedEntity", "assignedPerson", "name"]))
+            .filter_map(node_text)
+            .map(|name| EncounterParticipant {
+                individual: Some(Reference {
+                    display: Some(name),
+                    ..Reference::default()
+                }),
+                ..EncounterParticipant::default()
+            })
+            .collect();
+        let location = children(encounter, "participant")
+            .filter(|participant| participant.attribute("typeCode") == Some("LOC"))
+            .filter_map(|participant| {
+                let role = child(participant, "participantRole")?;
+                path(role, &["playingEntity", "name"])
+                    .or_else(|| path(role, &["addr"]))
+                    .and_then(node_text)
+            })
+            .map(|name| EncounterLocation {
+                location: Reference {
+                    display: Some(name),
+                    ..Reference::default()
+                },
+                status: None,
+                period: None,
+            })
+            .collect();
+        let reason_code = related_observations(encounter)
+            .filter(|related| has_template(*related, "19"))
+            .filter_map(|indication| child(indication, "value"))
+            .filter_map(|value| self.concept(value))
+            .collect();
+        let id = format!(
+            "{}-encounter{}",
+            self.id_prefix,
+            self.records.encounters.len() + 1
+        );
+        self.records.encounters.push(Encounter {
+            id: Some(id),
+            meta: Some(self.meta()),
+            text: None,
+            contained: Vec::new(),
+            extension: Vec::new(),
+            modifier_extension: Vec::new(),
+            identifier: identifiers(encounter),
+            status: status.to_string(),
+            class,
+            type_: code
+                .and_then(|code| self.concept(code))
+                .into_iter()
+                .collect(),
+            service_type: None,
+            priority: None,
+            subject: Some(self.patient.clone()),
+            participant,
+            period: (start.is_some() || end.is_some()).then_some(Period { start, end }),
+            length: None,
+            reason_code,
+            reason_reference: Vec::new(),
+            diagnosis: Vec::new(),
+            hospitalization: None,
+            location,
+            service_provider: None,
+            part_of: None,
+        });
+    }
+
+    fn meta(&self) -> Meta {
+        Meta {
+            source: Some(self.source.to_string()),
+            tag: vec![
+                Coding::new(PROVENANCE_SYSTEM, EXTERNAL).with_display("External record (C-CDA)"),
+            ],
+            ..Meta::default()
+        }
+    }
+
+    /// Một phần tử kiểu CD/CE: code chính và các `translation`, text từ
+    /// `originalText`. `None` khi không có code lẫn text.
+    fn concept(&self, node: Node) -> Option<CodeableConcept> {
+        let coding: Vec<Coding> = std::iter::once(node)
+            .chain(children(node, "translation"))
+            .filter_map(coding)
+            .collect();
+        let text = child(node, "originalText").and_then(|text| self.text(text));
+        (!coding.is_empty() || text.is_some()).then_some(CodeableConcept { coding, text })
+    }
+
+    /// Text trực tiếp của phần tử, hoặc đoạn narrative mà `reference` trỏ tới.
+    fn text(&self, node: Node) -> Option<String> {
+        node_text(node).or_else(|| {
+            let id = child(node, "reference")?
+                .attribute("value")?
+                .strip_prefix('#')?;
+            self.narrative.get(id).cloned()
+        })
+    }
+}
+
+fn is(node: Node, name: &str) -> bool {
+    node.has_tag_name((V3, name))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is(*child, name))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| is(*child, name))
+}
+
+fn path<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>> {
+    names.iter().try_fold(node, |node, name| child(node, name))
+}
+
+/// Text của phần tử và con cháu, gộp khoảng trắng.
+fn node_text(node: Node) -> Option<String> {
+    let text = node
+        .descendants()
+        .filter_map(|node| node.text().filter(|_| node.is_text()))
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn has_template(node: Node, suffix: &str) -> bool {
+    children(node, "templateId").any(|template| {
+        template
+            .attribute("root")
+            .and_then(|root| root.strip_prefix(ENTRY_TEMPLATE))
+            == Some(suffix)
+    })
+}
+
+fn negated(node: Node) -> bool {
+    node.attribute("negationInd") == Some("true")
+}
+
+fn status_code<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    child(node, "statusCode")?.attribute("code")
+}
+
+/// Phần tử `name` là chính `top` hoặc nằm trong act bọc nó (concern act,
+/// discharge medication).
+fn wrapped<'a, 'input: 'a>(
+    top: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    let direct = is(top, name).then_some(top);
+    let nested = is(top, "act")
+        .then(move || {
+            children(top, "entryRelationship")
+                .filter_map(move |relationship| child(relationship, name))
+        })
+        .into_iter()
+        .flatten();
+    direct.into_iter().chain(nested)
+}
+
+fn related_observations<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    children(node, "entryRelationship")
+        .filter_map(|relationship| child(relationship, "observation"))
+}
+
+/// `value` của observation con có templateId `ENTRY_TEMPLATE + suffix` (status,
+/// severity, criticality).
+fn status_observation<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    suffix: &str,
+) -> Option<Node<'a, 'input>> {
+    related_observations(node)
+        .find(|related| has_template(*related, suffix))
+        .and_then(|related| child(related, "value"))
+}
+
+/// Trạng thái lâm sàng từ SNOMED của Problem/Allergy Status Observation.
+fn clinical_status(value: Node) -> Option<&'static str> {
+    match value.attribute("code")? {
+        "55561003" => Some("active"),
+        "73425007" => Some("inactive"),
+        "413322009" => Some("resolved"),
+        _ => None,
+    }
+}
+
+/// Trạng thái suy ra từ `statusCode` của concern act.
+fn concern_status(top: Node) -> Option<&'static str> {
+    if !is(top, "act") {
+        return None;
+    }
+    match status_code(top)? {
+        "active" | "suspended" => Some("active"),
+        "completed" | "aborted" => Some("inactive"),
+        _ => None,
+    }
+}
+
+/// `low`/`high` (hoặc `value`) của `effectiveTime` đầu tiên.
+fn effective_bounds(node: Node) -> (Option<String>, Option<String>) {
+    child(node, "effectiveTime")
+        .map(effective_bounds_of)
+        .unwrap_or_default()
+}
+
+fn effective_bounds_of(time: Node) -> (Option<String>, Option<String>) {
+    let at = |name: &str| {
+        child(time, name)
+            .and_then(|bound| bound.attribute("value"))
+            .and_then(hl7_time)
+    };
+    match time.attribute("value").and_then(hl7_time) {
+        Some(value) => (Some(value), None),
+        None => (at("low"), at("high")),
+    }
+}
+
+fn author_time(node: Node) -> Option<String> {
+    path(node, &["author", "time"])?
+        .attribute("value")
+        .and_then(hl7_time)
+}
+
+/// HL7 TS (`YYYYMMDDHHMMSS.UUUU[+|-ZZZZ]`) sang `dateTime` của FHIR. Giờ không
+/// có múi giờ không biểu diễn được trong FHIR nên chỉ giữ ngày.
+fn hl7_time(value: &str) -> Option<String> {
+    let (local, zone) = match value.find(['+', '-']) {
+        Some(index) => (&value[..index], Some(&value[index..])),
+        None => (value, None),
+    };
+    let digits = local.split('.').next().unwrap_or_default();
+    let is_digits = |text: &str| text.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(digits) || zone.is_some_and(|zone| zone.len() != 5 || !is_digits(&zone[1..])) {
+        return None;
+    }
+    let part = |range: std::ops::Range<usize>| digits.get(range).unwrap_or("00");
+    let date = match digits.len() {
+        4 => return Some(digits.to_string()),
+        6 => return Some(format!("{}-{}", &digits[..4], &digits[4..])),
+        8.. => format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..8]),
+        _ => return None,
+    };
+    match zone {
+        Some(zone) if digits.len() >= 10 => Some(format!(
+            "{}T{}:{}:{}{}:{}",
+            date,
+            part(8..10),
+            part(10..12),
+            part(12..14),
+            &zone[..3],
+            &zone[3..]
+        )),
+        _ => Some(date),
+    }
+}
+
+/// OID của code system sang URI mà FHIR dùng.
+fn code_system(oid: &str) -> String {
+    let uri = match oid {
+        "2.16.840.1.113883.6.96" => SNOMED,
+        "2.16.840.1.113883.6.1" => LOINC,
+        "2.16.840.1.113883.6.88" => "http://www.nlm.nih.gov/research/umls/rxnorm",
+        "2.16.840.1.113883.6.12" => "http://www.ama-assn.org/go/cpt",
+        "2.16.840.1.113883.6.90" => "http://hl7.org/fhir/sid/icd-10-cm",
+        "2.16.840.1.113883.6.103" => "http://hl7.org/fhir/sid/icd-9-cm",
+        "2.16.840.1.113883.12.292" => "http://hl7.org/fhir/sid/cvx",
+        "2.16.840.1.113883.4.9" => "http://fdasis.nlm.nih.gov",
+        "2.16.840.1.113883.3.26.1.1" => "http://ncimeta.nci.nih.gov",
+        "2.16.840.1.113883.6.8" => UCUM,
+        "2.16.840.1.113883.5.4" => ACT_CODE,
+        "2.16.840.1.113883.5.83" => {
+            "http://terminology.hl7.org/CodeSystem/v3-ObservationInterpretation"
+        }
+        _ => return format!("urn:oid:{}", oid),
+    };
+    uri.to_string()
+}
+
+fn coding(node: Node) -> Option<Coding> {
+    Some(Coding {
+        code: Some(node.attribute("code")?.to_string()),
+        system: node.attribute("codeSystem").map(code_system),
+        display: node.attribute("displayName").map(str::to_string),
+        ..Coding::default()
+    })
+}
+
+/// Kiểu PQ: `value` và đơn vị UCUM (`1` là không có đơn vị).
+fn quantity(node: Node) -> Option<Quantity> {
+    let value = node.attribute("value")?.parse().ok()?;
+    let unit = node.attribute("unit").filter(|unit| *unit != "1");
+    Some(Quantity {
+        value: Some(value),
+        unit: unit.map(str::to_string),
+        system: unit.map(|_| UCUM.to_string()),
+        code: unit.map(str::to_string),
+        ..Quantity::default()
+    })
+}
+
+/// `id` của CDA: `root` + `extension`, hoặc chỉ `root` (UUID hay OID).
+fn identifiers(node: Node) -> Vec<Identifier> {
+    children(node, "id")
+        .filter_map(|id| {
+            let root = id.attribute("root")?;
+            let (system, value) = match id.attribute("extension") {
+                Some(extension) => (format!("urn:oid:{}", root), extension.to_string()),
+                None if root.contains('-') => (
+                    "urn:ietf:rfc:3986".to_string(),
+                    format!("urn:uuid:{}", root.to_ascii_lowercase()),
+                ),
+                None => ("urn:ietf:rfc:3986".to_string(), format!("urn:oid:{}", root)),
+            };
+            Some(Identifier {
+                system: Some(system),
+                value: Some(value),
+                ..Identifier::default()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../data/sample_ccd.xml");
+    const SOURCE: &str = "https://fhir.example.org/DocumentReference/ccd-1";
+
+    fn records() -> CcdaRecords {
+        parse(SAMPLE, "erXuFYUfucBZaryVksYEcMg3", SOURCE, "ccd-1").unwrap()
+    }
+
+    #[test]
+    fn converts_problems_and_medications() {
+        let records = records();
+
+        // Observation có `negationInd` (không bị hen) bị bỏ
+        assert_eq!(records.problems.len(), 2);
+        let hypertension = &records.problems[0];
+        assert_eq!(hypertension.id.as_deref(), Some("ccd-1-problem1"));
+        assert!(hypertension.is_active());
+        let code = hypertension.code.as_ref().unwrap();
+        // originalText trỏ vào narrative của section
+        assert_eq!(code.text.as_deref(), Some("Essential hypertension"));
+        assert!(code.has_coding(SNOMED, "59621000"));
+        assert!(code.has_coding("http://hl7.org/fhir/sid/icd-10-cm", "I10"));
+        assert_eq!(
+            hypertension.onset,
+            Some(Onset::DateTime("2019-05-28".into()))
+        );
+        assert_eq!(
+            hypertension.subject.reference.as_deref(),
+            Some("Patient/erXuFYUfucBZaryVksYEcMg3")
+        );
+        let bronchitis = &records.problems[1];
+        assert!(!bronchitis.is_active());
+        assert_eq!(
+            bronchitis.abatement,
+            Some(ConditionAbatement::DateTime("2022-01-20".into()))
+        );
+
+        assert_eq!(records.medications.len(), 2);
+        let amlodipine = &records.medications[0];
+        assert_eq!(amlodipine.status, "active");
+        assert_eq!(
+            amlodipine.medication_display(),
+            Some("amlodipine 5 MG Oral Tablet")
+        );
+        let dosage = &amlodipine.dosage_instruction[0];
+        assert_eq!(dosage.text.as_deref(), Some("Take 1 tablet by mouth daily"));
+        let repeat = dosage.timing.as_ref().unwrap().repeat.as_ref().unwrap();
+        assert_eq!(
+            (repeat.period, repeat.period_unit.as_deref()),
+            (Some(24.0), Some("h"))
+        );
+        assert_eq!(
+            repeat.bounds_period.as_ref().unwrap().start.as_deref(),
+            Some("2019-06-01")
+        );
+        assert_eq!(
+            dosage.route.as_ref().and_then(CodeableConcept::display),
+            Some("Oral")
+        );
+        let amoxicillin = &records.medications[1];
+        assert_eq!(
+            (amoxicillin.status.as_str(), amoxicillin.intent.as_str()),
+            ("completed", "order")
+        );
+        // Code `nullFlavor`: chỉ còn originalText
+        assert_eq!(
+            amoxicillin.medication_display(),
+            Some("Amoxicillin 500 mg capsule")
+        );
+        assert!(matches!(
+            &amoxicillin.dosage_instruction[0].dose_and_rate[0].dose,
+            Some(Dose::Quantity(Quantity {
+                value: Some(500.0),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn converts_allergies_results_and_encounters() {
+        let records = records();
+
+        let allergy = &records.allergies[0];
+        assert_eq!(
+            allergy.code.as_ref().and_then(CodeableConcept::display),
+            Some("Penicillin G")
+        );
+        assert_eq!(allergy.type_.as_deref(), Some("allergy"));
+        assert_eq!(allergy.category, ["medication"]);
+        assert_eq!(allergy.criticality.as_deref(), Some("high"));
+        assert_eq!(
+            allergy
+                .clinical_status
+                .as_ref()
+                .unwrap()
+                .code_in(ALLERGY_CLINICAL),
+            Some("active")
+        );
+        let reaction = &allergy.reaction[0];
+        assert_eq!(reaction.manifestation[0].display(), Some("Hives"));
+        assert_eq!(reaction.severity.as_deref(), Some("moderate"));
+
+        assert_eq!(records.results.len(), 2);
+        let hemoglobin = &records.results[0];
+        assert_eq!(
+            hemoglobin
+                .value
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("13.2 g/dL")
+        );
+        assert_eq!(
+            hemoglobin.effective,
+            Some(Effective::DateTime("2023-09-10T08:30:00-05:00".into()))
+        );
+        assert_eq!(
+            hemoglobin.interpretation[0]
+                .code_in("http://terminology.hl7.org/CodeSystem/v3-ObservationInterpretation"),
+            Some("N")
+        );
+        let blood_group = &records.results[1];
+        assert!(
+            matches!(&blood_group.value, Some(ObservationValue::CodeableConcept(concept)) if concept.display() == Some("Blood group A"))
+        );
+        // Không có effectiveTime: lấy của organizer
+        assert_eq!(
+            blood_group.effective,
+            Some(Effective::DateTime("2023-09-10T08:30:00-05:00".into()))
+        );
+
+        let encounter = &records.encounters[0];
+        assert!(encounter.class.is(ACT_CODE, "EMER"));
+        assert_eq!(encounter.status, "finished");
+        assert_eq!(
+            encounter.period.as_ref().unwrap().end.as_deref(),
+            Some("2022-01-02T18:30:00-05:00")
+        );
+        assert_eq!(
+            encounter.participant[0]
+                .individual
+                .as_ref()
+                .unwrap()
+                .display
+                .as_deref(),
+            Some("Dr. Henry Seven")
+        );
+        assert_eq!(
+            encounter.location[0].location.display.as_deref(),
+            Some("Community Hospital Emergency Department")
+        );
+        assert_eq!(encounter.reason_code[0].display(), Some("Acute bronchitis"));
+
+        // Mọi resource mang nguồn gốc bên ngoài
+        let meta = encounter.meta.as_ref().unwrap();
+        assert_eq!(meta.source.as_deref(), Some(SOURCE));
+        assert!(meta.tag[0].is(PROVENANCE_SYSTEM, EXTERNAL));
+    }
+
+    #[test]
+    fn converts_hl7_timestamps() {
+        assert_eq!(hl7_time("2019").as_deref(), Some("2019"));
+        assert_eq!(hl7_time("201905").as_deref(), Some("2019-05"));
+        assert_eq!(hl7_time("20190528").as_deref(), Some("2019-05-28"));
+        assert_eq!(
+            hl7_time("201905281430+0700").as_deref(),
+            Some("2019-05-28T14:30:00+07:00")
+        );
+        assert_eq!(
+            hl7_time("20190528143015.123-0500").as_deref(),
+            Some("2019-05-28T14:30:15-05:00")
+        );
+        // Không có múi giờ: chỉ giữ ngày
+        assert_eq!(hl7_time("20190528143015").as_deref(), Some("2019-05-28"));
+        assert_eq!(hl7_time("2019-05-28"), None);
+    }
+
+    #[test]
+    fn rejects_documents_that_are_not_cda() {
+        assert!(matches!(
+            parse("<Bundle xmlns=\"http://hl7.org/fhir\"/>", "p", SOURCE, "x"),
+            Err(CcdaError::NotClinicalDocument(name)) if name == "Bundle"
+        ));
+        assert!(matches!(
+            parse("<ClinicalDocument", "p", SOURCE, "x"),
+            Err(CcdaError::Xml(_))
+        ));
+    }
+}
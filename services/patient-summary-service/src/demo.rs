@@ -0,0 +1,67 @@
+//! Bệnh nhân mẫu cho trang demo của frontend, không cần đăng nhập Epic.
+//!
+//! Dữ liệu theo kiểu `Patient` trong `signals/patientSummary.ts` và được nhúng
+//! vào binary từ `data/demo_patients.json`.
+
+use serde_json::{Value, json};
+
+use crate::error::SummaryError;
+
+const DEMO_PATIENTS: &str = include_str!("../data/demo_patients.json");
+
+#[derive(Debug, Clone)]
+pub struct DemoPatients {
+    patients: Vec<Value>,
+}
+
+impl DemoPatients {
+    pub fn load() -> Self {
+        Self {
+            patients: serde_json::from_str(DEMO_PATIENTS)
+                .expect("demo_patients.json is valid JSON"),
+        }
+    }
+
+    /// `[{id, names}]` cho danh sách bên trái.
+    pub fn list(&self) -> Vec<Value> {
+        self.patients
+            .iter()
+            .map(|patient| json!({ "id": patient["id"], "names": patient["names"] }))
+            .collect()
+    }
+
+    /// Toàn bộ dữ liệu của một bệnh nhân mẫu.
+    pub fn summary(&self, id: &str) -> Result<&Value, SummaryError> {
+        self.patients
+            .iter()
+            .find(|patient| patient["id"] == id)
+            .ok_or_else(|| SummaryError::NotFound(format!("demo patient {}", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_patients_match_the_frontend_shape() {
+        let demo = DemoPatients::load();
+        let list = demo.list();
+        assert!(!list.is_empty());
+        for entry in &list {
+            let id = entry["id"].as_str().unwrap();
+            assert!(entry["names"][0]["given"].is_array());
+
+            let patient = demo.summary(id).unwrap();
+            for encounter in patient["encounters"].as_array().unwrap() {
+                for key in ["observations", "conditions", "studies", "diagnosis"] {
+                    assert!(encounter[key].is_array(), "{} of {}", key, id);
+                }
+            }
+        }
+        assert!(matches!(
+            demo.summary("unknown"),
+            Err(SummaryError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+//! Lỗi của service và cách trả về cho gateway.
+
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use fhir::FhirError;
+use serde_json::json;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SummaryError {
+    /// Request không có `Authorization: Bearer`
+    #[error("bearer token is required")]
+    MissingToken,
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    /// Gateway không đổi được token
+    #[error("token exchange failed with {status}: {message}")]
+    TokenExchange { status: StatusCode, message: String },
+    /// FHIR server trả về lỗi cho một request
+    #[error("FHIR {resource} request failed with {status}: {message}")]
+    Fhir {
+        status: StatusCode,
+        resource: String,
+        message: String,
+    },
+    /// Không gọi được FHIR server hoặc gateway
+    #[error("upstream request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    /// Response không phải JSON FHIR hợp lệ
+    #[error("invalid FHIR response: {0}")]
+    InvalidResponse(String),
+    /// Document IPS dựng ra không đạt yêu cầu của IPS
+    #[error("invalid IPS document: {0}")]
+    InvalidDocument(String),
+}
+
+impl SummaryError {
+    /// Lỗi của một request `resource` qua client của crate `fhir`.
+    pub fn from_fhir(resource: &str, error: FhirError) -> Self {
+        match error {
+            FhirError::Outcome { status, outcome } => Self::Fhir {
+                status,
+                resource: resource.to_string(),
+                message: outcome.to_string(),
+            },
+            FhirError::Status { status, body } => Self::Fhir {
+                status,
+                resource: resource.to_string(),
+                message: body,
+            },
+            FhirError::Transport(e) => Self::Transport(e),
+            error => Self::InvalidResponse(error.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MissingToken => StatusCode::UNAUTHORIZED,
+            // Token nội bộ bị từ chối (hết hạn, phiên đã đóng) khác với gateway gặp sự cố
+            Self::TokenExchange { status, .. } if status.is_client_error() => {
+                StatusCode::UNAUTHORIZED
+            }
+            Self::TokenExchange { .. } => StatusCode::BAD_GATEWAY,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            // Token hết hạn hoặc thiếu scope: gateway cần biết để đăng nhập lại
+            Self::Fhir { status, .. }
+                if matches!(
+                    *status,
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND
+                ) =>
+            {
+                *status
+            }
+            Self::Fhir { .. } | Self::Transport(_) | Self::InvalidResponse(_) => {
+                StatusCode::BAD_GATEWAY
+            }
+            Self::InvalidDocument(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for SummaryError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("{}", self);
+        } else {
+            tracing::warn!("{}", self);
+        }
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
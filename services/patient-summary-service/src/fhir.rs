@@ -0,0 +1,216 @@
+//! Gọi FHIR R4 API của Epic bằng access token của người dùng.
+//!
+//! Read và search đi qua client của crate `fhir` ([`FhirClient::resources`]);
+//! ở đây chỉ còn tải nội dung attachment.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use fhir::datatypes::Attachment;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
+use url::Url;
+
+use crate::error::SummaryError;
+
+/// `Accept` của mọi request FHIR.
+pub const FHIR_JSON: &str = "application/fhir+json";
+/// Kích thước tối đa (byte) của nội dung một attachment.
+pub const MAX_ATTACHMENT_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct FhirClient {
+    http: reqwest::Client,
+    base_url: String,
+    resources: fhir::FhirClient,
+}
+
+impl FhirClient {
+    pub fn new(
+        http: reqwest::Client,
+        base_url: impl Into<String>,
+    ) -> Result<Self, fhir::FhirError> {
+        let base_url = base_url.into();
+        Ok(Self {
+            resources: fhir::FhirClient::new(http.clone(), &base_url)?,
+            http,
+            base_url,
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Client của crate `fhir` cho cùng base URL, dùng với `Auth::Bearer`.
+    pub fn resources(&self) -> &fhir::FhirClient {
+        &self.resources
+    }
+
+    /// Nội dung của một attachment: `data` (base64), hoặc tải từ `url` (thường
+    /// là `Binary/{id}`, tương đối với base). Token chỉ được gửi tới chính FHIR
+    /// server nên `url` tuyệt đối ở nơi khác bị từ chối. Nội dung lớn hơn
+    /// [`MAX_ATTACHMENT_BYTES`] bị từ chối mà không đọc hết.
+    pub async fn attachment(
+        &self,
+        token: &str,
+        attachment: &Attachment,
+    ) -> Result<Vec<u8>, SummaryError> {
+        if let Some(data) = &attachment.data {
+            return decode_base64(data);
+        }
+        let url = attachment.url.as_deref().ok_or_else(|| {
+            SummaryError::InvalidResponse("attachment has neither data nor url".to_string())
+        })?;
+        let url = self
+            .within_base(url.trim_start_matches('/'))
+            .map_err(|url| {
+                SummaryError::InvalidResponse(format!(
+                    "attachment url '{}' is outside the FHIR server",
+                    url
+                ))
+            })?;
+
+        let mut response = self
+            .http
+            .get(url)
+            .bearer_auth(token)
+            .header(
+                ACCEPT,
+                attachment
+                    .content_type
+                    .as_deref()
+                    .unwrap_or("application/octet-stream"),
+            )
+            .send()
+            .await?;
+        let status = response.status();
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("json"));
+        // Binary trả về dạng JSON chứa base64, dài hơn nội dung khoảng 4/3
+        let limit = if is_json {
+            MAX_ATTACHMENT_BYTES / 3 * 4 + 1024
+        } else {
+            MAX_ATTACHMENT_BYTES
+        };
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        if !status.is_success() {
+            return Err(SummaryError::Fhir {
+                status,
+                resource: "Binary".to_string(),
+                message: operation_outcome_message(&body)
+                    .unwrap_or_else(|| String::from_utf8_lossy(&body).chars().take(200).collect()),
+            });
+        }
+        // Server không trả được content type gốc thì trả resource Binary
+        if is_json {
+            let binary: Value = serde_json::from_slice(&body)
+                .map_err(|e| SummaryError::InvalidResponse(e.to_string()))?;
+            if binary["resourceType"] == "Binary" {
+                return decode_base64(binary["data"].as_str().unwrap_or_default());
+            }
+        }
+        Ok(body)
+    }
+
+    /// URL của `link` (tuyệt đối, hoặc tương đối với base). Token chỉ được gửi
+    /// tới chính FHIR server, nên URL khác origin hoặc nằm ngoài base path bị
+    /// trả lại nguyên văn trong `Err`.
+    fn within_base(&self, link: &str) -> Result<Url, String> {
+        let base = Url::parse(&format!("{}/", self.base_url.trim_end_matches('/')))
+            .map_err(|_| link.to_string())?;
+        let url = base.join(link).map_err(|_| link.to_string())?;
+        if url.origin() != base.origin() || !url.path().starts_with(base.path()) {
+            return Err(url.to_string());
+        }
+        Ok(url)
+    }
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, SummaryError> {
+    if base64::decoded_len_estimate(data.len()) > MAX_ATTACHMENT_BYTES {
+        return Err(too_large());
+    }
+    STANDARD
+        .decode(data)
+        .map_err(|e| SummaryError::InvalidResponse(format!("invalid attachment data: {}", e)))
+}
+
+fn too_large() -> SummaryError {
+    SummaryError::InvalidResponse(format!(
+        "attachment is larger than {} bytes",
+        MAX_ATTACHMENT_BYTES
+    ))
+}
+
+/// Nội dung các issue trong `OperationOutcome` của một response lỗi.
+fn operation_outcome_message(body: &[u8]) -> Option<String> {
+    let outcome: Value = serde_json::from_slice(body).ok()?;
+    if outcome["resourceType"] != "OperationOutcome" {
+        return None;
+    }
+    let issues: Vec<String> = outcome["issue"]
+        .as_array()?
+        .iter()
+        .map(|issue| {
+            issue["diagnostics"]
+                .as_str()
+                .or_else(|| issue["details"]["text"].as_str())
+                .or_else(|| issue["code"].as_str())
+                .unwrap_or("unknown issue")
+                .to_string()
+        })
+        .collect();
+    Some(issues.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::get;
+
+    #[tokio::test]
+    async fn refuses_attachments_over_the_size_limit() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/Binary/{id}",
+            get(|| async { vec![b'x'; MAX_ATTACHMENT_BYTES + 1] }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = FhirClient::new(reqwest::Client::new(), base).unwrap();
+
+        let linked = Attachment {
+            url: Some("Binary/big".to_string()),
+            ..Attachment::default()
+        };
+        let inline = Attachment {
+            data: Some(STANDARD.encode(vec![b'x'; MAX_ATTACHMENT_BYTES + 1])),
+            ..Attachment::default()
+        };
+        for attachment in [linked, inline] {
+            let err = client.attachment("token", &attachment).await.unwrap_err();
+            assert!(
+                matches!(&err, SummaryError::InvalidResponse(message)
+                    if message.starts_with("attachment is larger than")),
+                "{}",
+                err
+            );
+        }
+    }
+}
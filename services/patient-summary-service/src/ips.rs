@@ -0,0 +1,598 @@
+//! International Patient Summary (IPS): `Bundle` loại `document` gồm một
+//! `Composition` và các resource mà các section của nó trỏ tới.
+//!
+//! Section nào không có dữ liệu vẫn có mặt, kèm `emptyReason`, để bên nhận
+//! phân biệt "không có" với "không gửi": `nilknown` khi server không có bản ghi
+//! nào, `unavailable` khi không đọc được dữ liệu của section.
+
+use fhir::datatypes::{Identifier, Meta, Narrative};
+use fhir::resources::composition::LOINC;
+use fhir::resources::{CompositionSection, ImmunizationOccurrence, ProcedurePerformed};
+use fhir::{
+    AllergyIntolerance, Bundle, BundleEntry, CodeableConcept, Coding, Composition, Condition,
+    FhirResource, Immunization, MedicationRequest, Observation, Patient, Procedure, Reference,
+    Resource,
+};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use uuid::Uuid;
+
+pub const IPS_BUNDLE_PROFILE: &str = "http://hl7.org/fhir/uv/ips/StructureDefinition/Bundle-uv-ips";
+pub const IPS_COMPOSITION_PROFILE: &str =
+    "http://hl7.org/fhir/uv/ips/StructureDefinition/Composition-uv-ips";
+/// LOINC "Patient summary Document"
+const PATIENT_SUMMARY_DOCUMENT: &str = "60591-5";
+const LIST_EMPTY_REASON: &str = "http://terminology.hl7.org/CodeSystem/list-empty-reason";
+
+/// Các section mà document luôn có.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpsSection {
+    Problems,
+    Medications,
+    Allergies,
+    Procedures,
+    Immunizations,
+    Results,
+}
+
+impl IpsSection {
+    pub const ALL: [IpsSection; 6] = [
+        Self::Problems,
+        Self::Medications,
+        Self::Allergies,
+        Self::Procedures,
+        Self::Immunizations,
+        Self::Results,
+    ];
+
+    /// Mã LOINC của section.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Problems => "11450-4",
+            Self::Medications => "10160-0",
+            Self::Allergies => "48765-2",
+            Self::Procedures => "47519-4",
+            Self::Immunizations => "11369-6",
+            Self::Results => "30954-2",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Problems => "Problem List",
+            Self::Medications => "Medication Summary",
+            Self::Allergies => "Allergies and Intolerances",
+            Self::Procedures => "History of Procedures",
+            Self::Immunizations => "Immunizations",
+            Self::Results => "Results",
+        }
+    }
+
+    /// IPS bắt buộc có section này; các section còn lại là khuyến nghị.
+    pub fn is_required(self) -> bool {
+        matches!(self, Self::Problems | Self::Medications | Self::Allergies)
+    }
+
+    /// Loại resource mà entry của section được trỏ tới.
+    fn resource_types(self) -> &'static [&'static str] {
+        match self {
+            Self::Problems => &["Condition"],
+            Self::Medications => &["MedicationRequest", "MedicationStatement"],
+            Self::Allergies => &["AllergyIntolerance"],
+            Self::Procedures => &["Procedure"],
+            Self::Immunizations => &["Immunization"],
+            Self::Results => &["Observation", "DiagnosticReport"],
+        }
+    }
+}
+
+/// Resource của bệnh nhân đã lấy về từ FHIR server.
+#[derive(Debug, Clone, Default)]
+pub struct IpsSources {
+    pub problems: Vec<Condition>,
+    pub medications: Vec<MedicationRequest>,
+    pub allergies: Vec<AllergyIntolerance>,
+    pub procedures: Vec<Procedure>,
+    pub immunizations: Vec<Immunization>,
+    pub results: Vec<Observation>,
+    /// Section không đọc được (ví dụ search bị 403); khác với section rỗng
+    pub unavailable: Vec<IpsSection>,
+}
+
+/// Dựng document. `fhir_base_url` là server nguồn, dùng cho `fullUrl` của các
+/// resource; Composition được đặt `urn:uuid`.
+pub fn build_document(
+    patient: Patient,
+    sources: IpsSources,
+    fhir_base_url: &str,
+    now: OffsetDateTime,
+) -> Bundle {
+    let timestamp = now.format(&Rfc3339).unwrap_or_default();
+    let patient_id = patient.id.clone().unwrap_or_default();
+    let mut subject = Reference::to(Patient::TYPE, &patient_id);
+    if let Some(name) = patient.official_name().and_then(|name| name.display()) {
+        subject = subject.with_display(name);
+    }
+
+    let unavailable = |kind: IpsSection| sources.unavailable.contains(&kind);
+    let mut entries: Vec<Resource> = Vec::new();
+    let sections = vec![
+        section(
+            IpsSection::Problems,
+            unavailable(IpsSection::Problems),
+            sources.problems,
+            &mut entries,
+            |condition| {
+                let problem = concept(condition.code.as_ref());
+                match &condition.clinical_status {
+                    Some(status) => format!("{} ({})", problem, concept(Some(status))),
+                    None => problem,
+                }
+            },
+        ),
+        section(
+            IpsSection::Medications,
+            unavailable(IpsSection::Medications),
+            without_errors(sources.medications, |request| &request.status),
+            &mut entries,
+            |request| {
+                let medication = request.medication_display().unwrap_or("Unknown medication");
+                match request
+                    .dosage_instruction
+                    .first()
+                    .and_then(|d| d.text.as_deref())
+                {
+                    Some(dosage) => format!("{}: {}", medication, dosage),
+                    None => medication.to_string(),
+                }
+            },
+        ),
+        section(
+            IpsSection::Allergies,
+            unavailable(IpsSection::Allergies),
+            sources.allergies,
+            &mut entries,
+            |allergy| {
+                let substance = concept(allergy.code.as_ref());
+                match &allergy.criticality {
+                    Some(criticality) => format!("{} (criticality: {})", substance, criticality),
+                    None => substance,
+                }
+            },
+        ),
+        section(
+            IpsSection::Procedures,
+            unavailable(IpsSection::Procedures),
+            without_errors(sources.procedures, |procedure| &procedure.status),
+            &mut entries,
+            |procedure| {
+                let name = concept(procedure.code.as_ref());
+                match &procedure.performed {
+                    Some(ProcedurePerformed::DateTime(date)) => format!("{} ({})", name, date),
+                    _ => name,
+                }
+            },
+        ),
+        section(
+            IpsSection::Immunizations,
+            unavailable(IpsSection::Immunizations),
+            without_errors(sources.immunizations, |immunization| &immunization.status),
+            &mut entries,
+            |immunization| {
+                let vaccine = concept(Some(&immunization.vaccine_code));
+                match &immunization.occurrence {
+                    ImmunizationOccurrence::DateTime(date)
+                    | ImmunizationOccurrence::String(date) => format!("{} ({})", vaccine, date),
+                }
+            },
+        ),
+        section(
+            IpsSection::Results,
+            unavailable(IpsSection::Results),
+            without_errors(sources.results, |observation| &observation.status),
+            &mut entries,
+            |observation| {
+                let name = concept(Some(&observation.code));
+                match &observation.value {
+                    Some(value) => format!("{}: {}", name, value),
+                    None => name,
+                }
+            },
+        ),
+    ];
+
+    let composition_id = Uuid::new_v4().to_string();
+    let composition = Composition {
+        id: Some(composition_id.clone()),
+        meta: Some(Meta {
+            profile: vec![IPS_COMPOSITION_PROFILE.to_string()],
+            ..Meta::default()
+        }),
+        text: None,
+        contained: Vec::new(),
+        extension: Vec::new(),
+        modifier_extension: Vec::new(),
+        identifier: None,
+        status: "final".to_string(),
+        type_: CodeableConcept::from_coding(
+            Coding::new(LOINC, PATIENT_SUMMARY_DOCUMENT).with_display("Patient summary Document"),
+        ),
+        category: Vec::new(),
+        subject: Some(subject),
+        encounter: None,
+        date: timestamp.clone(),
+        author: vec![Reference::default().with_display("SEDS patient summary service")],
+        title: format!("International Patient Summary for {}", patient_id),
+        confidentiality: None,
+        attester: Vec::new(),
+        custodian: None,
+        section: sections,
+    };
+
+    let mut bundle = Bundle::new("document");
+    bundle.meta = Some(Meta {
+        profile: vec![IPS_BUNDLE_PROFILE.to_string()],
+        ..Meta::default()
+    });
+    bundle.identifier = Some(Identifier {
+        system: Some("urn:ietf:rfc:3986".to_string()),
+        value: Some(format!("urn:uuid:{}", Uuid::new_v4())),
+        ..Identifier::default()
+    });
+    bundle.timestamp = Some(timestamp);
+    bundle.entry.push(BundleEntry {
+        full_url: Some(format!("urn:uuid:{}", composition_id)),
+        resource: Some(composition.into()),
+        ..BundleEntry::default()
+    });
+    bundle.push(patient, Some(fhir_base_url));
+    for resource in entries {
+        bundle.push(resource, Some(fhir_base_url));
+    }
+    bundle
+}
+
+/// Một lỗi khiến document không đạt yêu cầu của IPS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpsIssue {
+    /// Vị trí trong document, ví dụ `Composition.section[48765-2]`
+    pub path: String,
+    pub message: String,
+}
+
+impl IpsIssue {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for IpsIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Kiểm tra cấu trúc document theo IPS: Composition đứng đầu, có đủ section
+/// bắt buộc, mọi reference của section đều nằm trong Bundle.
+pub fn validate(bundle: &Bundle) -> Vec<IpsIssue> {
+    let mut issues = Vec::new();
+    if bundle.type_ != "document" {
+        issues.push(IpsIssue::new("Bundle.type", "must be 'document'"));
+    }
+    if bundle.identifier.is_none() {
+        issues.push(IpsIssue::new("Bundle.identifier", "is required"));
+    }
+    if bundle.timestamp.is_none() {
+        issues.push(IpsIssue::new("Bundle.timestamp", "is required"));
+    }
+    if bundle.entry.iter().any(|entry| entry.full_url.is_none()) {
+        issues.push(IpsIssue::new(
+            "Bundle.entry.fullUrl",
+            "is required for every entry",
+        ));
+    }
+    let Some(composition) = bundle
+        .entry
+        .first()
+        .and_then(|entry| entry.resource.as_ref())
+        .and_then(Composition::as_resource)
+    else {
+        issues.push(IpsIssue::new("Bundle.entry[0]", "must be a Composition"));
+        return issues;
+    };
+
+    if !composition
+        .type_
+        .has_coding(LOINC, PATIENT_SUMMARY_DOCUMENT)
+    {
+        issues.push(IpsIssue::new(
+            "Composition.type",
+            format!("must be {}|{}", LOINC, PATIENT_SUMMARY_DOCUMENT),
+        ));
+    }
+    if composition.author.is_empty() {
+        issues.push(IpsIssue::new("Composition.author", "is required"));
+    }
+    let subject = composition
+        .subject
+        .as_ref()
+        .and_then(|subject| subject.reference.as_deref())
+        .and_then(|reference| bundle.resolve(reference));
+    if subject.is_none_or(|subject| subject.resource_type() != Patient::TYPE) {
+        issues.push(IpsIssue::new(
+            "Composition.subject",
+            "must reference a Patient in the Bundle",
+        ));
+    }
+
+    for kind in IpsSection::ALL {
+        let path = format!("Composition.section[{}]", kind.code());
+        let Some(section) = composition.section(kind.code()) else {
+            if kind.is_required() {
+                issues.push(IpsIssue::new(
+                    path,
+                    format!("{} section is required", kind.title()),
+                ));
+            }
+            continue;
+        };
+        if section.text.is_none() {
+            issues.push(IpsIssue::new(format!("{}.text", path), "is required"));
+        }
+        if section.entry.is_empty() && section.empty_reason.is_none() {
+            issues.push(IpsIssue::new(
+                path.clone(),
+                "must have entries or an emptyReason",
+            ));
+        }
+        for reference in &section.entry {
+            let target = reference
+                .reference
+                .as_deref()
+                .and_then(|reference| bundle.resolve(reference));
+            match target {
+                Some(resource) if kind.resource_types().contains(&resource.resource_type()) => {}
+                Some(resource) => issues.push(IpsIssue::new(
+                    format!("{}.entry", path),
+                    format!(
+                        "{} is not allowed in the {} section",
+                        resource.resource_type(),
+                        kind.title()
+                    ),
+                )),
+                None => issues.push(IpsIssue::new(
+                    format!("{}.entry", path),
+                    format!(
+                        "'{}' is not in the Bundle",
+                        reference.reference.as_deref().unwrap_or_default()
+                    ),
+                )),
+            }
+        }
+    }
+    issues
+}
+
+/// Section gồm các resource có id; resource được chuyển vào `entries`.
+fn section<R: FhirResource>(
+    kind: IpsSection,
+    unavailable: bool,
+    resources: Vec<R>,
+    entries: &mut Vec<Resource>,
+    describe: impl Fn(&R) -> String,
+) -> CompositionSection {
+    let mut references = Vec::new();
+    let mut items = Vec::new();
+    for resource in resources {
+        let Some(id) = resource.id() else {
+            continue;
+        };
+        references.push(Reference::to(R::TYPE, id));
+        items.push(describe(&resource));
+        entries.push(resource.into());
+    }
+    let (div, empty_reason) = if unavailable {
+        (
+            "<p>Information could not be retrieved</p>".to_string(),
+            Some(CodeableConcept::from_coding(
+                Coding::new(LIST_EMPTY_REASON, "unavailable").with_display("Unavailable"),
+            )),
+        )
+    } else if items.is_empty() {
+        (
+            "<p>No information available</p>".to_string(),
+            Some(CodeableConcept::from_coding(
+                Coding::new(LIST_EMPTY_REASON, "nilknown").with_display("Nil Known"),
+            )),
+        )
+    } else {
+        let items: String = items
+            .iter()
+            .map(|item| format!("<li>{}</li>", escape_html(item)))
+            .collect();
+        (format!("<ul>{}</ul>", items), None)
+    };
+    CompositionSection {
+        title: Some(kind.title().to_string()),
+        code: Some(CodeableConcept::from_coding(
+            Coding::new(LOINC, kind.code()).with_display(kind.title()),
+        )),
+        text: Some(Narrative {
+            status: "generated".to_string(),
+            div: format!("<div xmlns=\"http://www.w3.org/1999/xhtml\">{}</div>", div),
+        }),
+        entry: references,
+        empty_reason,
+        ..CompositionSection::default()
+    }
+}
+
+/// Bỏ resource nhập nhầm (`entered-in-error`).
+fn without_errors<R>(resources: Vec<R>, status: impl Fn(&R) -> &String) -> Vec<R> {
+    resources
+        .into_iter()
+        .filter(|resource| status(resource) != "entered-in-error")
+        .collect()
+}
+
+fn concept(concept: Option<&CodeableConcept>) -> String {
+    concept
+        .and_then(CodeableConcept::display)
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BASE: &str = "https://fhir.example.org/R4";
+
+    fn patient() -> Patient {
+        serde_json::from_value(json!({
+            "resourceType": "Patient",
+            "id": "p1",
+            "name": [{ "use": "official", "family": "Lopez", "given": ["Camila"] }]
+        }))
+        .unwrap()
+    }
+
+    fn sources() -> IpsSources {
+        IpsSources {
+            problems: vec![
+                serde_json::from_value(json!({
+                    "resourceType": "Condition",
+                    "id": "c1",
+                    "subject": { "reference": "Patient/p1" },
+                    "code": { "text": "Hypertension <stage 1>" }
+                }))
+                .unwrap(),
+            ],
+            immunizations: vec![
+                serde_json::from_value(json!({
+                    "resourceType": "Immunization",
+                    "id": "i1",
+                    "status": "completed",
+                    "vaccineCode": { "text": "Influenza" },
+                    "patient": { "reference": "Patient/p1" },
+                    "occurrenceDateTime": "2023-10-01"
+                }))
+                .unwrap(),
+                serde_json::from_value(json!({
+                    "resourceType": "Immunization",
+                    "id": "i2",
+                    "status": "entered-in-error",
+                    "vaccineCode": { "text": "Tetanus" },
+                    "patient": { "reference": "Patient/p1" },
+                    "occurrenceString": "childhood"
+                }))
+                .unwrap(),
+            ],
+            ..IpsSources::default()
+        }
+    }
+
+    #[test]
+    fn builds_a_valid_document_with_empty_sections() {
+        let bundle = build_document(patient(), sources(), BASE, OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(validate(&bundle), []);
+
+        let composition =
+            Composition::as_resource(bundle.entry[0].resource.as_ref().unwrap()).unwrap();
+        assert_eq!(composition.section.len(), IpsSection::ALL.len());
+        assert_eq!(
+            composition.subject.as_ref().unwrap().display.as_deref(),
+            Some("Camila Lopez")
+        );
+
+        let problems = composition.section(IpsSection::Problems.code()).unwrap();
+        assert_eq!(problems.entry[0].reference.as_deref(), Some("Condition/c1"));
+        assert!(
+            problems
+                .text
+                .as_ref()
+                .unwrap()
+                .div
+                .contains("Hypertension &lt;stage 1&gt;")
+        );
+        let immunizations = composition
+            .section(IpsSection::Immunizations.code())
+            .unwrap();
+        assert_eq!(immunizations.entry.len(), 1);
+        let allergies = composition.section(IpsSection::Allergies.code()).unwrap();
+        assert!(allergies.entry.is_empty());
+        assert_eq!(
+            allergies.empty_reason.as_ref().unwrap().coding[0]
+                .code
+                .as_deref(),
+            Some("nilknown")
+        );
+
+        assert_eq!(
+            bundle.entry[2].full_url.as_deref(),
+            Some("https://fhir.example.org/R4/Condition/c1")
+        );
+    }
+
+    #[test]
+    fn unavailable_sections_say_so() {
+        let sources = IpsSources {
+            unavailable: vec![IpsSection::Medications],
+            ..sources()
+        };
+        let bundle = build_document(patient(), sources, BASE, OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(validate(&bundle), []);
+
+        let composition =
+            Composition::as_resource(bundle.entry[0].resource.as_ref().unwrap()).unwrap();
+        let medications = composition.section(IpsSection::Medications.code()).unwrap();
+        assert_eq!(
+            medications.empty_reason.as_ref().unwrap().coding[0]
+                .code
+                .as_deref(),
+            Some("unavailable")
+        );
+        let problems = composition.section(IpsSection::Problems.code()).unwrap();
+        assert!(problems.empty_reason.is_none());
+    }
+
+    #[test]
+    fn reports_missing_sections_and_dangling_entries() {
+        let mut bundle = build_document(patient(), sources(), BASE, OffsetDateTime::UNIX_EPOCH);
+        let Some(Resource::Composition(composition)) = &mut bundle.entry[0].resource else {
+            panic!("first entry is not a Composition");
+        };
+        composition
+            .section
+            .retain(|section| section.title.as_deref() != Some("Allergies and Intolerances"));
+        composition.section[0]
+            .entry
+            .push(Reference::to("Condition", "missing"));
+        composition.section[1].empty_reason = None;
+        bundle.entry.retain(|entry| {
+            entry.resource.as_ref().map(Resource::resource_type) != Some("Immunization")
+        });
+
+        let issues: Vec<String> = validate(&bundle).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "Composition.section[11450-4].entry: 'Condition/missing' is not in the Bundle",
+                "Composition.section[10160-0]: must have entries or an emptyReason",
+                "Composition.section[48765-2]: Allergies and Intolerances section is required",
+                "Composition.section[11369-6].entry: 'Immunization/i1' is not in the Bundle",
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Patient summary service: gộp dữ liệu FHIR R4 của Epic thành `PatientSummary`
+//! cho frontend, qua gateway.
+
+mod advice;
+mod ccda;
+mod config;
+mod demo;
+mod error;
+mod fhir;
+mod ips;
+mod printout;
+mod routes;
+mod summary;
+mod token;
+
+use std::sync::Arc;
+
+use security::BearerValidator;
+use tracing_subscriber::EnvFilter;
+
+use crate::advice::AdviceClient;
+use crate::demo::DemoPatients;
+use crate::fhir::FhirClient;
+use crate::routes::AppState;
+use crate::token::TokenExchanger;
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let config = config::load_settings().unwrap_or_else(|e| {
+        tracing::error!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    });
+    let http = reqwest::Client::builder()
+        .build()
+        .expect("Failed to build HTTP client");
+    let fhir = FhirClient::new(http.clone(), config.fhir_base_url.trim_end_matches('/'))
+        .unwrap_or_else(|e| {
+            tracing::error!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        });
+    let state = Arc::new(AppState {
+        fhir,
+        exchange: config
+            .token_exchange
+            .clone()
+            .map(|exchange| TokenExchanger::new(http.clone(), exchange)),
+        advice: config
+            .advice_url
+            .as_deref()
+            .map(|url| AdviceClient::new(http.clone(), url)),
+        demo: DemoPatients::load(),
+        bearer: config
+            .bearer_auth
+            .as_ref()
+            .map(|settings| Arc::new(BearerValidator::from_settings(settings, http))),
+    });
+
+    let addr = format!("{}:{}", config.host, config.port);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind {}: {}", addr, e));
+    tracing::info!(
+        "Patient summary service listening on {} (FHIR: {})",
+        addr,
+        config.fhir_base_url
+    );
+    axum::serve(listener, routes::router(state))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+        .expect("Server error");
+}
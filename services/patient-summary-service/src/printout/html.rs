@@ -0,0 +1,128 @@
+//! Bản in dạng HTML: một trang độc lập (CSS nhúng, không script, không tải tài
+//! nguyên ngoài) để in thẳng từ trình duyệt.
+
+use std::fmt::Write;
+
+use super::{Printout, Table};
+use crate::ips::escape_html;
+
+const STYLE: &str = "\
+body { font-family: Helvetica, Arial, sans-serif; font-size: 10pt; color: #111; margin: 2em; }
+h1 { font-size: 18pt; margin: 0; }
+h2 { font-size: 12pt; margin: 1.5em 0 0.5em; page-break-after: avoid; }
+.patient { font-size: 13pt; font-weight: bold; margin: 0.2em 0; }
+.generated, .notice { color: #666; font-size: 8.5pt; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; vertical-align: top; padding: 4px; border-bottom: 1px solid #ccc; }
+th { background: #e6e6e6; }
+thead { display: table-header-group; }
+tr { page-break-inside: avoid; }
+dl { display: grid; grid-template-columns: 30% 70%; margin: 0; }
+dt { font-weight: bold; }
+dd { margin: 0; }
+.advice { white-space: pre-wrap; }
+@media print { body { margin: 0; } @page { size: A4; margin: 50pt; } }";
+
+pub fn render(printout: &Printout) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title} - {name}</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n\
+         <header>\n<h1>{title}</h1>\n<p class=\"patient\">{name}</p>\n\
+         <p class=\"generated\">Generated {generated}</p>\n</header>\n",
+        title = Printout::TITLE,
+        name = escape_html(&printout.patient_name),
+        generated = escape_html(&printout.generated_at),
+    );
+
+    html.push_str("<section>\n<h2>Demographics</h2>\n<dl>\n");
+    for (label, value) in &printout.demographics {
+        let _ = writeln!(html, "<dt>{}</dt><dd>{}</dd>", label, escape_html(value));
+    }
+    html.push_str("</dl>\n</section>\n");
+
+    for table in printout.tables() {
+        render_table(&mut html, table);
+    }
+
+    html.push_str("<section>\n<h2>Encounter Timeline</h2>\n");
+    if printout.encounters.is_empty() {
+        html.push_str("<p>No encounters recorded.</p>\n");
+    } else {
+        html.push_str("<table>\n<tbody>\n");
+        for entry in &printout.encounters {
+            let _ = writeln!(
+                html,
+                "<tr><td style=\"width: 30%\">{}</td><td><strong>{}</strong><br>{}</td></tr>",
+                escape_html(&entry.when),
+                escape_html(&entry.title),
+                escape_html(&entry.detail),
+            );
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+    html.push_str("</section>\n");
+
+    html.push_str("<section>\n<h2>Generated Advice</h2>\n");
+    match &printout.advice {
+        Some(advice) => {
+            let _ = writeln!(
+                html,
+                "<p class=\"advice\">{}</p>\n<p class=\"notice\">{}</p>",
+                escape_html(advice),
+                Printout::ADVICE_NOTICE,
+            );
+        }
+        None => html.push_str("<p>Advice unavailable.</p>\n"),
+    }
+    html.push_str("</section>\n</body>\n</html>\n");
+    html
+}
+
+fn render_table(html: &mut String, table: &Table) {
+    let _ = writeln!(html, "<section>\n<h2>{}</h2>", table.title);
+    if table.rows.is_empty() {
+        html.push_str("<p>No information available.</p>\n</section>\n");
+        return;
+    }
+    html.push_str("<table>\n<thead><tr>");
+    for column in table.columns {
+        let _ = write!(html, "<th>{}</th>", column);
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for row in &table.rows {
+        html.push_str("<tr>");
+        for cell in row {
+            let _ = write!(html, "<td>{}</td>", escape_html(cell));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n</section>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printout::tests::printout;
+
+    #[test]
+    fn renders_a_printable_page() {
+        let mut printout = printout();
+        printout.patient_name = "Camila <Lopez>".to_string();
+        let html = render(&printout);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<p class=\"patient\">Camila &lt;Lopez&gt;</p>"));
+        assert!(html.contains("<dt>Date of birth</dt><dd>1987-09-12</dd>"));
+        assert!(html.contains(
+            "<tr><td>Essential hypertension</td><td>active</td><td>2019-05-28 14:00</td><td></td></tr>"
+        ));
+        assert!(
+            html.contains("<h2>Allergies and Intolerances</h2>\n<p>No information available.</p>")
+        );
+        assert!(html.contains("<strong>Hospital admission</strong><br>finished; Ward 3"));
+        assert!(html.contains("<p class=\"advice\">- Keep taking amlodipine daily.</p>"));
+        assert!(!html.contains("<script"));
+    }
+}
@@ -0,0 +1,437 @@
+//! Bản in của patient summary cho đội chuyển viện: thông tin hành chính, bảng
+//! vấn đề sức khỏe, thuốc và dị ứng, dòng thời gian các lần khám và lời khuyên
+//! sinh tự động. Cùng một `Printout` được xuất ra HTML và PDF.
+
+pub mod html;
+pub mod pdf;
+
+use fhir::datatypes::{Address, Onset};
+use fhir::{AllergyIntolerance, CodeableConcept, Condition, Encounter, MedicationRequest, Patient};
+use time::OffsetDateTime;
+use time::format_description;
+
+/// Bảng trong bản in; mỗi hàng có đúng `columns.len()` ô.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub title: &'static str,
+    pub columns: &'static [&'static str],
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Một lần khám trên dòng thời gian.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub when: String,
+    pub title: String,
+    pub detail: String,
+}
+
+/// Dữ liệu đã lấy về cho bản in.
+#[derive(Debug, Clone)]
+pub struct PrintSources {
+    pub patient: Patient,
+    pub problems: Vec<Condition>,
+    pub medications: Vec<MedicationRequest>,
+    pub allergies: Vec<AllergyIntolerance>,
+    pub encounters: Vec<Encounter>,
+    /// `None` khi không cấu hình agent hoặc agent lỗi
+    pub advice: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Printout {
+    pub patient_name: String,
+    /// Thời điểm tạo bản in, ví dụ `2024-05-01 09:30 UTC`
+    pub generated_at: String,
+    /// Cặp nhãn và giá trị; trường không có dữ liệu bị bỏ
+    pub demographics: Vec<(&'static str, String)>,
+    pub problems: Table,
+    pub medications: Table,
+    pub allergies: Table,
+    /// Mới nhất trước
+    pub encounters: Vec<TimelineEntry>,
+    pub advice: Option<String>,
+}
+
+impl Printout {
+    pub const TITLE: &'static str = "Patient Summary";
+    pub const ADVICE_NOTICE: &'static str =
+        "Generated automatically from the care plan. Review before clinical use.";
+
+    pub fn build(sources: PrintSources, now: OffsetDateTime) -> Self {
+        let patient = &sources.patient;
+        let patient_name = patient
+            .official_name()
+            .and_then(|name| name.display())
+            .unwrap_or_else(|| "Unknown patient".to_string());
+
+        let mut demographics = vec![(
+            "Patient ID",
+            patient.id.clone().unwrap_or_else(|| "Unknown".to_string()),
+        )];
+        let fields = [
+            ("Date of birth", patient.birth_date.clone()),
+            ("Gender", patient.gender.clone()),
+            ("Address", patient.address.first().map(address)),
+            (
+                "Phone",
+                patient
+                    .telecom
+                    .iter()
+                    .find(|contact| contact.system.as_deref() == Some("phone"))
+                    .and_then(|contact| contact.value.clone()),
+            ),
+            (
+                "Language",
+                patient
+                    .communication
+                    .first()
+                    .and_then(|communication| communication.language.display())
+                    .map(str::to_string),
+            ),
+            (
+                "General practitioner",
+                patient
+                    .general_practitioner
+                    .first()
+                    .and_then(|practitioner| practitioner.display.clone()),
+            ),
+            (
+                "Managing organization",
+                patient
+                    .managing_organization
+                    .as_ref()
+                    .and_then(|organization| organization.display.clone()),
+            ),
+        ];
+        demographics.extend(
+            fields
+                .into_iter()
+                .filter_map(|(label, value)| value.map(|value| (label, value))),
+        );
+
+        let problems = Table {
+            title: "Problems",
+            columns: &["Problem", "Status", "Onset", "Recorded"],
+            rows: sources
+                .problems
+                .iter()
+                .filter(|condition| !is_error(condition.verification_status.as_ref()))
+                .map(|condition| {
+                    vec![
+                        text(condition.code.as_ref()),
+                        text(condition.clinical_status.as_ref()),
+                        match &condition.onset {
+                            Some(Onset::DateTime(date)) => short_date(date),
+                            Some(Onset::String(onset)) => onset.clone(),
+                            _ => String::new(),
+                        },
+                        condition
+                            .recorded_date
+                            .as_deref()
+                            .map(short_date)
+                            .unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        };
+        let medications = Table {
+            title: "Medications",
+            columns: &["Medication", "Dosage", "Status", "Prescribed"],
+            rows: sources
+                .medications
+                .iter()
+                .filter(|request| request.status != "entered-in-error")
+                .map(|request| {
+                    vec![
+                        request
+                            .medication_display()
+                            .unwrap_or("Unknown medication")
+                            .to_string(),
+                        request
+                            .dosage_instruction
+                            .first()
+                            .and_then(|dosage| dosage.text.clone())
+                            .unwrap_or_default(),
+                        request.status.clone(),
+                        request
+                            .authored_on
+                            .as_deref()
+                            .map(short_date)
+                            .unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        };
+        let allergies = Table {
+            title: "Allergies and Intolerances",
+            columns: &["Substance", "Reaction", "Criticality", "Status"],
+            rows: sources
+                .allergies
+                .iter()
+                .filter(|allergy| !is_error(allergy.verification_status.as_ref()))
+                .map(|allergy| {
+                    let reactions: Vec<&str> = allergy
+                        .reaction
+                        .iter()
+                        .flat_map(|reaction| &reaction.manifestation)
+                        .filter_map(CodeableConcept::display)
+                        .collect();
+                    vec![
+                        text(allergy.code.as_ref()),
+                        reactions.join(", "),
+                        allergy.criticality.clone().unwrap_or_default(),
+                        text(allergy.clinical_status.as_ref()),
+                    ]
+                })
+                .collect(),
+        };
+
+        let mut encounters: Vec<&Encounter> = sources
+            .encounters
+            .iter()
+            .filter(|encounter| encounter.status != "entered-in-error")
+            .collect();
+        // Chuỗi dateTime của FHIR so sánh được theo thứ tự từ điển; không có ngày thì xuống cuối
+        encounters.sort_by(|a, b| start(b).cmp(&start(a)));
+        let encounters = encounters.into_iter().map(timeline_entry).collect();
+
+        let generated_at = format_description::parse("[year]-[month]-[day] [hour]:[minute] UTC")
+            .ok()
+            .and_then(|format| now.format(&format).ok())
+            .unwrap_or_default();
+
+        Self {
+            patient_name,
+            generated_at,
+            demographics,
+            problems,
+            medications,
+            allergies,
+            encounters,
+            advice: sources.advice.filter(|advice| !advice.trim().is_empty()),
+        }
+    }
+
+    /// Các bảng theo thứ tự in.
+    pub fn tables(&self) -> [&Table; 3] {
+        [&self.problems, &self.medications, &self.allergies]
+    }
+}
+
+fn timeline_entry(encounter: &Encounter) -> TimelineEntry {
+    let period = encounter.period.as_ref();
+    let when = match (
+        period.and_then(|period| period.start.as_deref()),
+        period.and_then(|period| period.end.as_deref()),
+    ) {
+        (Some(start), Some(end)) if short_date(start) != short_date(end) => {
+            format!("{} - {}", short_date(start), short_date(end))
+        }
+        (Some(start), _) => short_date(start),
+        (None, Some(end)) => short_date(end),
+        (None, None) => "Date unknown".to_string(),
+    };
+    let title = encounter
+        .type_
+        .iter()
+        .chain(&encounter.service_type)
+        .find_map(CodeableConcept::display)
+        .or(encounter.class.display.as_deref())
+        .or(encounter.class.code.as_deref())
+        .unwrap_or("Encounter")
+        .to_string();
+    let detail = [
+        Some(encounter.status.as_str()),
+        encounter
+            .location
+            .first()
+            .and_then(|location| location.location.display.as_deref()),
+        encounter
+            .participant
+            .iter()
+            .find_map(|participant| participant.individual.as_ref()?.display.as_deref()),
+        encounter
+            .service_provider
+            .as_ref()
+            .and_then(|provider| provider.display.as_deref()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("; ");
+    TimelineEntry {
+        when,
+        title,
+        detail,
+    }
+}
+
+fn start(encounter: &Encounter) -> Option<&str> {
+    encounter.period.as_ref()?.start.as_deref()
+}
+
+fn text(concept: Option<&CodeableConcept>) -> String {
+    concept
+        .and_then(CodeableConcept::display)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// `verificationStatus` là `entered-in-error`.
+fn is_error(status: Option<&CodeableConcept>) -> bool {
+    status.is_some_and(|status| {
+        status
+            .coding
+            .iter()
+            .any(|coding| coding.code.as_deref() == Some("entered-in-error"))
+    })
+}
+
+fn address(address: &Address) -> String {
+    if let Some(text) = &address.text {
+        return text.clone();
+    }
+    address
+        .line
+        .iter()
+        .chain(&address.city)
+        .chain(&address.state)
+        .chain(&address.postal_code)
+        .chain(&address.country)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `2024-05-01T09:30:00Z` thành `2024-05-01 09:30`; ngày không đầy đủ giữ nguyên.
+fn short_date(value: &str) -> String {
+    match value.split_once('T') {
+        Some((date, time)) => format!("{} {}", date, time.get(..5).unwrap_or(time)),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub(super) fn printout() -> Printout {
+        let sources = PrintSources {
+            patient: serde_json::from_value(json!({
+                "resourceType": "Patient",
+                "id": "p1",
+                "name": [{ "use": "official", "family": "Lopez", "given": ["Camila"] }],
+                "gender": "female",
+                "birthDate": "1987-09-12",
+                "address": [{ "line": ["3268 West Johnson St."], "city": "Garland", "state": "TX" }]
+            }))
+            .unwrap(),
+            problems: vec![
+                serde_json::from_value(json!({
+                    "resourceType": "Condition",
+                    "id": "c1",
+                    "subject": { "reference": "Patient/p1" },
+                    "clinicalStatus": { "coding": [{ "code": "active" }] },
+                    "code": { "text": "Essential hypertension" },
+                    "onsetDateTime": "2019-05-28T14:00:00Z"
+                }))
+                .unwrap(),
+                serde_json::from_value(json!({
+                    "resourceType": "Condition",
+                    "id": "c2",
+                    "subject": { "reference": "Patient/p1" },
+                    "verificationStatus": { "coding": [{ "code": "entered-in-error" }] },
+                    "code": { "text": "Asthma" }
+                }))
+                .unwrap(),
+            ],
+            medications: vec![
+                serde_json::from_value(json!({
+                    "resourceType": "MedicationRequest",
+                    "id": "m1",
+                    "status": "active",
+                    "intent": "order",
+                    "medicationCodeableConcept": { "text": "amlodipine 5 MG Oral Tablet" },
+                    "subject": { "reference": "Patient/p1" },
+                    "dosageInstruction": [{ "text": "1 tablet daily" }]
+                }))
+                .unwrap(),
+            ],
+            allergies: Vec::new(),
+            encounters: vec![
+                serde_json::from_value(json!({
+                    "resourceType": "Encounter",
+                    "id": "e1",
+                    "status": "finished",
+                    "class": { "code": "AMB", "display": "ambulatory" },
+                    "period": { "start": "2023-01-10T08:00:00Z", "end": "2023-01-10T09:00:00Z" }
+                }))
+                .unwrap(),
+                serde_json::from_value(json!({
+                    "resourceType": "Encounter",
+                    "id": "e2",
+                    "status": "finished",
+                    "class": { "code": "IMP" },
+                    "type": [{ "text": "Hospital admission" }],
+                    "period": { "start": "2024-02-01T10:00:00Z", "end": "2024-02-04T12:00:00Z" },
+                    "location": [{ "location": { "display": "Ward 3" } }]
+                }))
+                .unwrap(),
+            ],
+            advice: Some("- Keep taking amlodipine daily.".to_string()),
+        };
+        Printout::build(sources, OffsetDateTime::UNIX_EPOCH)
+    }
+
+    #[test]
+    fn builds_the_printout_from_fhir_resources() {
+        let printout = printout();
+        assert_eq!(printout.patient_name, "Camila Lopez");
+        assert_eq!(printout.generated_at, "1970-01-01 00:00 UTC");
+        assert_eq!(
+            printout.demographics,
+            [
+                ("Patient ID", "p1".to_string()),
+                ("Date of birth", "1987-09-12".to_string()),
+                ("Gender", "female".to_string()),
+                ("Address", "3268 West Johnson St., Garland, TX".to_string()),
+            ]
+        );
+        // Condition nhập nhầm bị bỏ
+        assert_eq!(
+            printout.problems.rows,
+            [["Essential hypertension", "active", "2019-05-28 14:00", ""]]
+        );
+        assert_eq!(printout.medications.rows[0][1], "1 tablet daily");
+        assert!(printout.allergies.rows.is_empty());
+
+        let timeline: Vec<(&str, &str, &str)> = printout
+            .encounters
+            .iter()
+            .map(|entry| {
+                (
+                    entry.when.as_str(),
+                    entry.title.as_str(),
+                    entry.detail.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            timeline,
+            [
+                (
+                    "2024-02-01 10:00 - 2024-02-04 12:00",
+                    "Hospital admission",
+                    "finished; Ward 3"
+                ),
+                (
+                    "2023-01-10 08:00 - 2023-01-10 09:00",
+                    "ambulatory",
+                    "finished"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,752 @@
+//! PDF 1.4 tối giản cho bản in, viết tay để chạy được trong container chỉ có
+//! CPU, không cần trình duyệt hay thư viện native. Chỉ dùng hai font chuẩn
+//! Helvetica và Helvetica-Bold (viewer nào cũng có, không nhúng font), text mã
+//! hóa WinAnsi. Font chuẩn không có glyph cho ký tự ngoài WinAnsi (ví dụ tiếng
+//! Việt có dấu), nên ký tự đó được thay bằng chữ gần nhất và bản in ghi chú rằng
+//! bản HTML có text nguyên văn.
+
+use unicode_normalization::char::{compose, decompose_canonical};
+
+use super::{Printout, Table};
+
+/// A4, đơn vị point
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const FOOTER_Y: f32 = 30.0;
+const LEADING: f32 = 1.35;
+const BODY_SIZE: f32 = 9.5;
+const CELL_PADDING: f32 = 4.0;
+const LINE_HEIGHT: f32 = BODY_SIZE * LEADING;
+
+/// Độ rộng glyph (phần nghìn em) của ký tự 32..=126, theo AFM chuẩn của Adobe.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    /// Tên trong `/Resources /Font` của page.
+    fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+        }
+    }
+
+    fn width(self, text: &[u8], size: f32) -> f32 {
+        let widths = match self {
+            Self::Regular => &HELVETICA_WIDTHS,
+            Self::Bold => &HELVETICA_BOLD_WIDTHS,
+        };
+        let units: u32 = text
+            .iter()
+            .map(|&byte| match byte {
+                32..=126 => u32::from(widths[usize::from(byte - 32)]),
+                // Chữ có dấu rộng xấp xỉ chữ thường
+                _ => 556,
+            })
+            .sum();
+        units as f32 * size / 1000.0
+    }
+}
+
+/// Ghi chú đầu bản in khi có ký tự bị thay thế.
+const REPLACED_NOTICE: &str = "Some characters cannot be shown with the PDF fonts and were replaced \
+     with the closest letter or symbol. The HTML printout (summary.html) has the exact text.";
+
+/// Một dòng đã ngắt, đã mã hóa WinAnsi.
+type Line = (Font, Vec<u8>);
+
+/// Hàng của bảng sau khi ngắt dòng từng ô.
+#[derive(Clone)]
+struct Row {
+    cells: Vec<Vec<Line>>,
+    height: f32,
+}
+
+pub fn render(printout: &Printout) -> Vec<u8> {
+    let mut layout = Layout::new();
+    layout.line_of_text(Font::Bold, 18.0, Printout::TITLE);
+    layout.line_of_text(Font::Bold, 13.0, &printout.patient_name);
+    layout.line_of_text(
+        Font::Regular,
+        8.5,
+        &format!("Generated {}", printout.generated_at),
+    );
+    if has_replacements(printout) {
+        layout.gray(|layout| layout.paragraph(Font::Regular, REPLACED_NOTICE));
+    }
+    layout.rule();
+
+    layout.heading("Demographics");
+    for (label, value) in &printout.demographics {
+        let row = Row::new(
+            &[0.3, 0.7],
+            &[
+                vec![(Font::Bold, *label)],
+                vec![(Font::Regular, value.as_str())],
+            ],
+        );
+        layout.row(&[0.3, 0.7], &row, false, None);
+    }
+
+    for table in printout.tables() {
+        layout.heading(table.title);
+        layout.table(table);
+    }
+
+    layout.heading("Encounter Timeline");
+    if printout.encounters.is_empty() {
+        layout.paragraph(Font::Regular, "No encounters recorded.");
+    }
+    for entry in &printout.encounters {
+        let mut encounter = vec![(Font::Bold, entry.title.as_str())];
+        if !entry.detail.is_empty() {
+            encounter.push((Font::Regular, entry.detail.as_str()));
+        }
+        let row = Row::new(
+            &[0.3, 0.7],
+            &[vec![(Font::Regular, entry.when.as_str())], encounter],
+        );
+        layout.row(&[0.3, 0.7], &row, true, None);
+    }
+
+    layout.heading("Generated Advice");
+    match &printout.advice {
+        Some(advice) => {
+            layout.paragraph(Font::Regular, advice);
+            layout.gray(|layout| layout.paragraph(Font::Regular, Printout::ADVICE_NOTICE));
+        }
+        None => layout.paragraph(Font::Regular, "Advice unavailable."),
+    }
+
+    let title = format!("{} - {}", Printout::TITLE, printout.patient_name);
+    let pages = layout.finish(&title);
+    write_document(&title, pages)
+}
+
+/// Bản in có ký tự nằm ngoài WinAnsi, phải thay thế khi in.
+fn has_replacements(printout: &Printout) -> bool {
+    let cells = printout
+        .tables()
+        .into_iter()
+        .flat_map(|table| table.rows.iter().flatten());
+    let encounters = printout
+        .encounters
+        .iter()
+        .flat_map(|entry| [&entry.when, &entry.title, &entry.detail]);
+    std::iter::once(&printout.patient_name)
+        .chain(printout.demographics.iter().map(|(_, value)| value))
+        .chain(cells)
+        .chain(encounters)
+        .chain(&printout.advice)
+        .flat_map(|text| text.chars())
+        .any(|c| !matches!(c, '\n' | '\r') && win_ansi_byte(c).is_none())
+}
+
+/// Đặt nội dung từ trên xuống, sang trang khi hết chỗ.
+struct Layout {
+    pages: Vec<Vec<u8>>,
+    content: Vec<u8>,
+    /// Mép trên của phần còn trống trên trang hiện tại
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            content: Vec::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.content));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// Sang trang nếu không còn đủ `height`.
+    fn ensure(&mut self, height: f32) -> bool {
+        let broke = self.y - height < MARGIN && self.y < PAGE_HEIGHT - MARGIN;
+        if broke {
+            self.new_page();
+        }
+        broke
+    }
+
+    fn op(&mut self, op: &str) {
+        self.content.extend_from_slice(op.as_bytes());
+        self.content.push(b'\n');
+    }
+
+    fn text(&mut self, x: f32, baseline: f32, font: Font, size: f32, text: &[u8]) {
+        self.content.extend_from_slice(
+            format!(
+                "BT /{} {:.1} Tf {:.2} {:.2} Td (",
+                font.resource(),
+                size,
+                x,
+                baseline
+            )
+            .as_bytes(),
+        );
+        for &byte in text {
+            if matches!(byte, b'(' | b')' | b'\\') {
+                self.content.push(b'\\');
+            }
+            self.content.push(byte);
+        }
+        self.content.extend_from_slice(b") Tj ET\n");
+    }
+
+    fn gray<T>(&mut self, draw: impl FnOnce(&mut Self) -> T) -> T {
+        self.op("0.4 g");
+        let result = draw(self);
+        self.op("0 g");
+        result
+    }
+
+    fn line_of_text(&mut self, font: Font, size: f32, text: &str) {
+        let text = win_ansi(text);
+        self.ensure(size * LEADING);
+        let baseline = self.y - size;
+        self.text(MARGIN, baseline, font, size, &text);
+        self.y -= size * LEADING;
+    }
+
+    fn paragraph(&mut self, font: Font, text: &str) {
+        for line in wrap(font, BODY_SIZE, text, CONTENT_WIDTH) {
+            self.ensure(LINE_HEIGHT);
+            let baseline = self.y - BODY_SIZE;
+            self.text(MARGIN, baseline, font, BODY_SIZE, &line);
+            self.y -= LINE_HEIGHT;
+        }
+    }
+
+    fn rule(&mut self) {
+        self.y -= 4.0;
+        self.op(&format!(
+            "0.6 G 0.75 w {:.2} {:.2} m {:.2} {:.2} l S 0 G",
+            MARGIN,
+            self.y,
+            PAGE_WIDTH - MARGIN,
+            self.y
+        ));
+        self.y -= 6.0;
+    }
+
+    /// Tiêu đề section; luôn nằm cùng trang với ít nhất một dòng nội dung.
+    fn heading(&mut self, title: &str) {
+        let size = 12.0;
+        self.ensure(size * LEADING + 10.0 + 3.0 * LINE_HEIGHT);
+        self.y -= 10.0;
+        self.line_of_text(Font::Bold, size, title)
+    }
+
+    fn table(&mut self, table: &Table) {
+        if table.rows.is_empty() {
+            return self.paragraph(Font::Regular, "No information available.");
+        }
+        let widths = vec![1.0 / table.columns.len() as f32; table.columns.len()];
+        let header: Vec<Vec<(Font, &str)>> = table
+            .columns
+            .iter()
+            .map(|column| vec![(Font::Bold, *column)])
+            .collect();
+        let header = Row::new(&widths, &header);
+        self.ensure(header.height);
+        self.header(&widths, &header);
+        for cells in &table.rows {
+            let cells: Vec<Vec<(Font, &str)>> = cells
+                .iter()
+                .map(|cell| vec![(Font::Regular, cell.as_str())])
+                .collect();
+            let row = Row::new(&widths, &cells);
+            // Sang trang giữa bảng thì in lại header
+            self.row(&widths, &row, true, Some(&header));
+        }
+    }
+
+    fn header(&mut self, widths: &[f32], header: &Row) {
+        self.op(&format!(
+            "0.9 g {:.2} {:.2} {:.2} {:.2} re f 0 g",
+            MARGIN,
+            self.y - header.height,
+            CONTENT_WIDTH,
+            header.height
+        ));
+        self.draw_row(widths, header, true);
+    }
+
+    /// Hàng vừa một trang thì được đưa trọn sang trang mới khi hết chỗ; hàng cao
+    /// hơn phần còn trống của một trang được chia theo dòng sang các trang sau.
+    /// `header` được in lại ở đầu mỗi trang mới.
+    fn row(&mut self, widths: &[f32], row: &Row, ruled: bool, header: Option<&Row>) {
+        let mut row = row.clone();
+        if row.height <= PAGE_HEIGHT - 2.0 * MARGIN
+            && self.ensure(row.height)
+            && let Some(header) = header
+        {
+            self.header(widths, header);
+        }
+        while row.height > self.y - MARGIN {
+            let lines = ((self.y - MARGIN - 2.0 * CELL_PADDING) / LINE_HEIGHT).floor() as usize;
+            if lines > 0 {
+                let rest = row.split_off(lines);
+                self.draw_row(widths, &row, ruled);
+                row = rest;
+            }
+            self.new_page();
+            if let Some(header) = header {
+                self.header(widths, header);
+            }
+        }
+        self.draw_row(widths, &row, ruled);
+    }
+
+    fn draw_row(&mut self, widths: &[f32], row: &Row, ruled: bool) {
+        let mut x = MARGIN;
+        for (cell, width) in row.cells.iter().zip(widths) {
+            let mut baseline = self.y - CELL_PADDING - BODY_SIZE;
+            for (font, line) in cell {
+                self.text(x + CELL_PADDING, baseline, *font, BODY_SIZE, line);
+                baseline -= LINE_HEIGHT;
+            }
+            x += width * CONTENT_WIDTH;
+        }
+        self.y -= row.height;
+        if ruled {
+            self.op(&format!(
+                "0.8 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S 0 G",
+                MARGIN,
+                self.y,
+                PAGE_WIDTH - MARGIN,
+                self.y
+            ));
+        }
+    }
+
+    /// Các trang đã đặt xong, mỗi trang có footer `footer - Page i of n`.
+    fn finish(mut self, footer: &str) -> Vec<Vec<u8>> {
+        self.new_page();
+        let count = self.pages.len();
+        let mut pages = std::mem::take(&mut self.pages);
+        for (index, page) in pages.iter_mut().enumerate() {
+            let text = win_ansi(&format!("{} - Page {} of {}", footer, index + 1, count));
+            self.content = std::mem::take(page);
+            self.gray(|layout| layout.text(MARGIN, FOOTER_Y, Font::Regular, 8.0, &text));
+            *page = std::mem::take(&mut self.content);
+        }
+        pages
+    }
+}
+
+impl Row {
+    /// Ngắt dòng từng ô theo độ rộng cột (tỉ lệ của `CONTENT_WIDTH`); mỗi ô gồm
+    /// các đoạn, mỗi đoạn một font.
+    fn new(widths: &[f32], cells: &[Vec<(Font, &str)>]) -> Self {
+        let mut wrapped = Vec::with_capacity(cells.len());
+        for (paragraphs, width) in cells.iter().zip(widths) {
+            let width = width * CONTENT_WIDTH - 2.0 * CELL_PADDING;
+            let mut lines: Vec<Line> = Vec::new();
+            for (font, text) in paragraphs {
+                lines.extend(
+                    wrap(*font, BODY_SIZE, text, width)
+                        .into_iter()
+                        .map(|line| (*font, line)),
+                );
+            }
+            wrapped.push(lines);
+        }
+        Self::from_cells(wrapped)
+    }
+
+    fn from_cells(cells: Vec<Vec<Line>>) -> Self {
+        let lines = cells.iter().map(Vec::len).max().unwrap_or(1).max(1);
+        let height = lines as f32 * LINE_HEIGHT + 2.0 * CELL_PADDING;
+        Self { cells, height }
+    }
+
+    /// Giữ `lines` dòng đầu của mỗi ô, trả về phần còn lại.
+    fn split_off(&mut self, lines: usize) -> Row {
+        let rest = self
+            .cells
+            .iter_mut()
+            .map(|cell| cell.split_off(lines.min(cell.len())))
+            .collect();
+        *self = Self::from_cells(std::mem::take(&mut self.cells));
+        Self::from_cells(rest)
+    }
+}
+
+/// Ngắt `text` thành các dòng không rộng quá `width`; xuống dòng trong text
+/// được giữ, từ dài hơn một dòng bị cắt. Text rỗng cho một dòng rỗng.
+fn wrap(font: Font, size: f32, text: &str, width: f32) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line: Vec<u8> = Vec::new();
+        for word in win_ansi(paragraph).split(|&byte| byte == b' ') {
+            let candidate = if line.is_empty() {
+                word.to_vec()
+            } else {
+                [line.as_slice(), b" ", word].concat()
+            };
+            if font.width(&candidate, size) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            for &byte in word {
+                line.push(byte);
+                if line.len() > 1 && font.width(&line, size) > width {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, vec![byte]));
+                }
+            }
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(Vec::new());
+    }
+    lines
+}
+
+/// Mã hóa theo WinAnsiEncoding (Windows-1252). Ký tự ngoài bảng mã được thay
+/// bằng [`substitute`].
+fn win_ansi(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match win_ansi_byte(c) {
+            Some(byte) => bytes.push(byte),
+            None => bytes.extend_from_slice(&substitute(c)),
+        }
+    }
+    bytes
+}
+
+fn win_ansi_byte(c: char) -> Option<u8> {
+    let byte = match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '\t' => b' ',
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+/// Thay thế cho ký tự ngoài WinAnsi: ký hiệu tương đương (`≥` thành `>=`),
+/// hoặc chữ gốc giữ lại các dấu còn có trong WinAnsi (`ễ` thành `ê`, `ư` thành
+/// `u`); còn lại là `?`.
+fn substitute(c: char) -> Vec<u8> {
+    let symbol: &[u8] = match c {
+        // Chữ mu Hy Lạp, thường dùng cho đơn vị (μg)
+        'μ' => b"\xB5",
+        'Đ' => b"\xD0",
+        'đ' => b"d",
+        '≥' => b">=",
+        '≤' => b"<=",
+        '≠' => b"!=",
+        '≈' => b"~",
+        '→' => b"->",
+        '←' => b"<-",
+        '↔' => b"<->",
+        '−' | '‐' | '‑' => b"-",
+        _ => b"",
+    };
+    if !symbol.is_empty() {
+        return symbol.to_vec();
+    }
+    let mut parts = Vec::new();
+    decompose_canonical(c, |part| parts.push(part));
+    let Some(mut letter) = parts.first().copied().filter(|&base| base != c) else {
+        return b"?".to_vec();
+    };
+    if win_ansi_byte(letter).is_none() {
+        return b"?".to_vec();
+    }
+    for &mark in &parts[1..] {
+        if let Some(composed) = compose(letter, mark).filter(|&c| win_ansi_byte(c).is_some()) {
+            letter = composed;
+        }
+    }
+    win_ansi_byte(letter).into_iter().collect()
+}
+
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut escaped = vec![b'('];
+    for byte in win_ansi(text) {
+        if matches!(byte, b'(' | b')' | b'\\') {
+            escaped.push(b'\\');
+        }
+        escaped.push(byte);
+    }
+    escaped.push(b')');
+    escaped
+}
+
+/// Ghép catalog, font, info và các trang thành file PDF có bảng xref.
+fn write_document(title: &str, pages: Vec<Vec<u8>>) -> Vec<u8> {
+    // 1 catalog, 2 page tree, 3-4 font, 5 info; mỗi trang một page và một content stream
+    let page_id = |index: usize| 6 + 2 * index;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", page_id(index)))
+        .collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        [
+            b"<< /Title ".as_slice(),
+            &pdf_string(title),
+            b" /Producer (patient-summary-service) >>",
+        ]
+        .concat(),
+    ];
+    for (index, content) in pages.into_iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id(index) + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            [
+                format!("<< /Length {} >>\nstream\n", content.len()).as_bytes(),
+                &content,
+                b"\nendstream",
+            ]
+            .concat(),
+        );
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printout::tests::printout;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn writes_a_well_formed_pdf() {
+        let pdf = render(&printout());
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert!(contains(&pdf, b"(Camila Lopez) Tj"));
+        assert!(contains(&pdf, b"(Essential hypertension) Tj"));
+        assert!(contains(&pdf, b"Camila Lopez - Page 1 of 1) Tj"));
+
+        // Mỗi offset trong xref trỏ đúng vào đầu object
+        let tail = std::str::from_utf8(&pdf[pdf.len() - 40..]).unwrap();
+        let startxref: usize = tail
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let xref = std::str::from_utf8(&pdf[startxref..]).unwrap();
+        assert!(xref.starts_with("xref\n"));
+        let offsets: Vec<usize> = xref
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 7);
+        for (index, offset) in offsets.into_iter().enumerate() {
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn breaks_long_tables_across_pages_and_repeats_the_header() {
+        let mut printout = printout();
+        printout.medications.rows = (0..120)
+            .map(|n| {
+                vec![
+                    format!("Medication {}", n),
+                    "Take one tablet (with food) twice daily".to_string(),
+                    "active".to_string(),
+                    String::new(),
+                ]
+            })
+            .collect();
+        let pdf = render(&printout);
+        let text = String::from_utf8_lossy(&pdf);
+        let pages = text.matches("/Type /Page ").count();
+        assert!(pages > 1, "expected several pages, got {}", pages);
+        assert!(text.contains(&format!("Page {} of {}", pages, pages)));
+        // Header của bảng thuốc được in lại trên mỗi trang có bảng
+        assert!(text.matches("(Dosage) Tj").count() > 1);
+        assert!(text.contains("\\(with food\\)"));
+    }
+
+    /// Tọa độ y của mọi dòng text trong PDF.
+    fn baselines(pdf: &[u8]) -> Vec<f32> {
+        String::from_utf8_lossy(pdf)
+            .split(" Td (")
+            .filter_map(|before| before.rsplit(' ').next()?.parse().ok())
+            .collect()
+    }
+
+    #[test]
+    fn splits_rows_taller_than_a_page() {
+        let mut printout = printout();
+        let instructions = vec!["Take one tablet twice daily."; 200].join("\n");
+        printout.medications.rows = vec![vec![
+            "Amlodipine".to_string(),
+            instructions,
+            "active".to_string(),
+            String::new(),
+        ]];
+        let pdf = render(&printout);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.matches("/Type /Page ").count() > 3);
+        assert_eq!(
+            text.matches("(Take one tablet twice daily.) Tj").count(),
+            200
+        );
+        // Chỉ footer nằm dưới lề dưới
+        let below_margin = baselines(&pdf)
+            .into_iter()
+            .filter(|&y| y < MARGIN && y != FOOTER_Y)
+            .count();
+        assert_eq!(below_margin, 0);
+        // Header của bảng được in lại trên trang tiếp theo của hàng
+        assert!(text.matches("(Dosage) Tj").count() > 3);
+    }
+
+    #[test]
+    fn replaces_text_outside_win_ansi() {
+        let mut printout = printout();
+        assert!(!contains(&render(&printout), b"summary.html"));
+
+        printout.patient_name = "Nguyễn Văn Hưng".to_string();
+        printout.advice = Some("Metformin 500 μg if HbA1c ≥ 7% → review".to_string());
+        let pdf = render(&printout);
+        assert!(contains(&pdf, b"(Nguy\xean Van Hung) Tj"));
+        assert!(contains(
+            &pdf,
+            b"(Metformin 500 \xb5g if HbA1c >= 7% -> review) Tj"
+        ));
+        // Ghi chú trỏ tới bản HTML
+        assert!(contains(&pdf, b"summary.html\\)"));
+    }
+
+    #[test]
+    fn substitutes_the_closest_win_ansi_text() {
+        assert_eq!(win_ansi("Đặng Thị Ánh"), b"\xd0ang Thi \xc1nh");
+        assert_eq!(win_ansi("Trường"), b"Tru\xf2ng");
+        assert_eq!(win_ansi("Œuvre – 5 €"), b"\x8cuvre \x96 5 \x80");
+        assert_eq!(win_ansi("血压"), b"??");
+    }
+
+    #[test]
+    fn wraps_and_encodes_text() {
+        let lines = wrap(Font::Regular, 10.0, "José Müller\nshort", 1000.0);
+        assert_eq!(lines, [b"Jos\xe9 M\xfcller".to_vec(), b"short".to_vec()]);
+
+        let lines = wrap(Font::Regular, 10.0, "aaaa bbbb", 30.0);
+        assert_eq!(lines, [b"aaaa".to_vec(), b"bbbb".to_vec()]);
+        // Từ dài hơn một dòng bị cắt
+        let lines = wrap(Font::Regular, 10.0, "abcdefghij", 30.0);
+        assert!(lines.len() > 1);
+        assert!(
+            lines
+                .iter()
+                .all(|line| Font::Regular.width(line, 10.0) <= 30.0)
+        );
+        assert_eq!(wrap(Font::Bold, 10.0, "", 30.0), [Vec::<u8>::new()]);
+    }
+}
@@ -0,0 +1,1187 @@
+//! HTTP API mà gateway gọi tới.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use fhir::client::{Auth, SearchParams};
+use fhir::{FhirError, FhirResource, Resource};
+use futures::future::join_all;
+use security::{BearerAuthLayer, BearerValidator};
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::advice::AdviceClient;
+use crate::ccda::{self, CcdaRecords};
+use crate::demo::DemoPatients;
+use crate::error::SummaryError;
+use crate::fhir::{FHIR_JSON, FhirClient};
+use crate::ips::{self, IpsSection, IpsSources};
+use crate::printout::{self, PrintSources, Printout};
+use crate::summary::{
+    Allergy, CarePlan, Encounter, LabResult, Medication, Patient, PatientSummary, Problem,
+    Procedure,
+};
+use crate::token::TokenExchanger;
+
+/// SNOMED CT của category CarePlan mà Epic dùng để tách nội trú và ngoại trú.
+const INPATIENT_CAREPLAN: &str = "736353004";
+const OUTPATIENT_CAREPLAN: &str = "736271009";
+/// LOINC của các document C-CDA được gộp vào summary: CCD và Discharge Summary.
+const CCDA_DOCUMENT_TYPES: &str = "http://loinc.org|34133-9,http://loinc.org|18842-5";
+/// Số document C-CDA tối đa đọc cho một summary.
+const MAX_CCDA_DOCUMENTS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub fhir: FhirClient,
+    pub exchange: Option<TokenExchanger>,
+    /// Agent sinh lời khuyên cho bản in
+    pub advice: Option<AdviceClient>,
+    pub demo: DemoPatients,
+    /// Kiểm tra bearer token trước các route `/patient_summary`
+    pub bearer: Option<Arc<BearerValidator>>,
+}
+
+pub type SharedState = Arc<AppState>;
+
+pub fn router(state: SharedState) -> Router {
+    let mut patient_summary_routes = Router::new()
+        .route("/patient_summary/{id}", get(patient_summary))
+        .route("/patient_summary/{id}/$summary", get(ips_summary))
+        .route("/patient_summary/{id}/summary.html", get(printout_html))
+        .route("/patient_summary/{id}/summary.pdf", get(printout_pdf));
+    if let Some(validator) = &state.bearer {
+        patient_summary_routes =
+            patient_summary_routes.route_layer(BearerAuthLayer::new(validator.clone()));
+    }
+
+    Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .merge(patient_summary_routes)
+        .route("/demo/patients", get(demo_patients))
+        .route("/demo/patients/{id}/summary", get(demo_summary))
+        .with_state(state)
+}
+
+/// Gộp Patient, Encounter, CarePlan, Procedure, Condition, MedicationRequest,
+/// AllergyIntolerance và kết quả xét nghiệm của một bệnh nhân, cùng dữ liệu từ
+/// các document C-CDA bên ngoài. Các request FHIR chạy song song. Patient,
+/// Encounter, CarePlan và Procedure lỗi làm cả summary lỗi; Condition,
+/// MedicationRequest, AllergyIntolerance, Observation (ví dụ 403 khi app thiếu
+/// scope) lỗi thì mục đó để trống và được liệt kê trong `unavailable`; document
+/// bên ngoài lỗi chỉ được ghi log.
+async fn patient_summary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(patient_id): Path<String>,
+) -> Result<Json<PatientSummary>, SummaryError> {
+    let token = access_token(&state, &headers, &patient_id).await?;
+
+    let fhir = &state.fhir;
+    let resources = fhir.resources();
+    let auth = Auth::Bearer(&token);
+    let careplans =
+        |category: &str| by_patient::<fhir::CarePlan>(&patient_id).param("category", category);
+    let problems =
+        by_patient::<fhir::Condition>(&patient_id).param("category", "problem-list-item");
+    let results = by_patient::<fhir::Observation>(&patient_id).param("category", "laboratory");
+    let (
+        patient,
+        encounters,
+        inpatient,
+        outpatient,
+        procedures,
+        problems,
+        medications,
+        allergies,
+        results,
+        external,
+    ) = tokio::try_join!(
+        read::<fhir::Patient>(resources, auth, &patient_id),
+        search::<fhir::Encounter>(resources, auth, by_patient::<fhir::Encounter>(&patient_id)),
+        search::<fhir::CarePlan>(resources, auth, careplans(INPATIENT_CAREPLAN)),
+        search::<fhir::CarePlan>(resources, auth, careplans(OUTPATIENT_CAREPLAN)),
+        search::<fhir::Procedure>(resources, auth, by_patient::<fhir::Procedure>(&patient_id)),
+        best_effort(search::<fhir::Condition>(resources, auth, problems)),
+        best_effort(search::<fhir::MedicationRequest>(
+            resources,
+            auth,
+            by_patient::<fhir::MedicationRequest>(&patient_id)
+        )),
+        best_effort(search::<fhir::AllergyIntolerance>(
+            resources,
+            auth,
+            by_patient::<fhir::AllergyIntolerance>(&patient_id)
+        )),
+        best_effort(search::<fhir::Observation>(resources, auth, results)),
+        async { Ok(external_records(fhir, &token, &patient_id).await) },
+    )?;
+
+    let patient =
+        serde_json::to_value(&patient).map_err(|e| SummaryError::InvalidResponse(e.to_string()))?;
+    let (inpatient, outpatient, procedures) = (
+        to_values(&inpatient),
+        to_values(&outpatient),
+        to_values(&procedures),
+    );
+    let unavailable = [
+        ("problems", problems.is_none()),
+        ("medications", medications.is_none()),
+        ("allergies", allergies.is_none()),
+        ("results", results.is_none()),
+    ]
+    .into_iter()
+    .filter_map(|(section, failed)| failed.then_some(section))
+    .collect();
+    let (problems, medications, allergies, results) = (
+        problems.unwrap_or_default(),
+        medications.unwrap_or_default(),
+        allergies.unwrap_or_default(),
+        results.unwrap_or_default(),
+    );
+    let encounters = merged(to_values(&encounters), to_values(&external.encounters));
+    let problems = merged(to_values(&problems), to_values(&external.problems));
+    let medications = merged(to_values(&medications), to_values(&external.medications));
+    let allergies = merged(to_values(&allergies), to_values(&external.allergies));
+    let results = merged(to_values(&results), to_values(&external.results));
+    Ok(Json(PatientSummary {
+        patient: Patient::from_fhir(&patient),
+        encounters: encounters.iter().map(Encounter::from_fhir).collect(),
+        inpatient_careplans: inpatient.iter().map(CarePlan::from_fhir).collect(),
+        outpatient_careplans: outpatient.iter().map(CarePlan::from_fhir).collect(),
+        procedures: procedures.iter().map(Procedure::from_fhir).collect(),
+        problems: problems.iter().map(Problem::from_fhir).collect(),
+        medications: medications.iter().map(Medication::from_fhir).collect(),
+        allergies: allergies.iter().map(Allergy::from_fhir).collect(),
+        results: results.iter().map(LabResult::from_fhir).collect(),
+        unavailable,
+    }))
+}
+
+/// Dữ liệu từ các document C-CDA (`DocumentReference` CCD, Discharge Summary)
+/// của bệnh nhân. Document không đọc hoặc không chuyển được chỉ được ghi log.
+async fn external_records(fhir: &FhirClient, token: &str, patient_id: &str) -> CcdaRecords {
+    let query = SearchParams::of::<fhir::DocumentReference>()
+        .patient(patient_id)
+        .param("type", CCDA_DOCUMENT_TYPES)
+        .param("status", "current");
+    let documents = match fhir
+        .resources()
+        .search_all::<fhir::DocumentReference>(Auth::Bearer(token), &query)
+        .await
+    {
+        Ok(documents) => documents,
+        Err(e) => {
+            tracing::warn!(
+                "Skipping external records, DocumentReference search failed: {}",
+                e
+            );
+            return CcdaRecords::default();
+        }
+    };
+
+    let attachments = documents
+        .iter()
+        .filter(|document| document.status == "current")
+        .filter_map(|document| {
+            let attachment = document
+                .attachment("application/xml")
+                .or_else(|| document.attachment("text/xml"))?;
+            Some((document.id.as_deref()?, attachment))
+        })
+        .take(MAX_CCDA_DOCUMENTS);
+    // Tải song song; kết quả giữ thứ tự của search
+    let converted = join_all(attachments.map(|(id, attachment)| async move {
+        let source = format!("{}/DocumentReference/{}", fhir.base_url(), id);
+        let converted = match fhir.attachment(token, attachment).await {
+            Ok(content) => String::from_utf8(content)
+                .map_err(|e| e.to_string())
+                .and_then(|xml| {
+                    ccda::parse(&xml, patient_id, &source, &format!("ccda-{}", id))
+                        .map_err(|e| e.to_string())
+                }),
+            Err(e) => Err(e.to_string()),
+        };
+        (source, converted)
+    }))
+    .await;
+
+    let mut records = CcdaRecords::default();
+    for (source, converted) in converted {
+        match converted {
+            Ok(converted) => records.extend(converted),
+            Err(e) => tracing::warn!("Skipping C-CDA document {}: {}", source, e),
+        }
+    }
+    records
+}
+
+/// Kết quả của một search không bắt buộc: lỗi chỉ được ghi log, `None` cho
+/// biết mục đó của summary không đọc được.
+async fn best_effort<R: FhirResource>(
+    search: impl Future<Output = Result<Vec<R>, SummaryError>>,
+) -> Result<Option<Vec<R>>, SummaryError> {
+    Ok(search
+        .await
+        .inspect_err(|e| {
+            tracing::warn!("Leaving {} out of the summary: {}", R::TYPE, e);
+        })
+        .ok())
+}
+
+/// Dữ liệu của Epic, tiếp theo là các resource bên ngoài chưa có trong đó. Một
+/// resource bên ngoài trùng khi có chung identifier với resource đã có, hoặc
+/// chung coding và cùng ngày.
+fn merged(native: Vec<Value>, external: Vec<Value>) -> Vec<Value> {
+    let mut resources = native;
+    for resource in external {
+        if !resources
+            .iter()
+            .any(|existing| same_record(existing, &resource))
+        {
+            resources.push(resource);
+        }
+    }
+    resources
+}
+
+fn same_record(a: &Value, b: &Value) -> bool {
+    let shared = |left: Vec<(&str, &str)>, right: Vec<(&str, &str)>| {
+        left.iter().any(|pair| right.contains(pair))
+    };
+    if shared(identifiers(a), identifiers(b)) {
+        return true;
+    }
+    match (record_date(a), record_date(b)) {
+        (Some(left), Some(right)) => left == right && shared(codings(a), codings(b)),
+        _ => false,
+    }
+}
+
+/// Cặp `system`/`value` của các identifier.
+fn identifiers(resource: &Value) -> Vec<(&str, &str)> {
+    system_pairs(resource["identifier"].as_array(), "value")
+}
+
+/// Cặp `system`/`code` của concept chính: `code`, `medicationCodeableConcept`
+/// hoặc `type` của Encounter.
+fn codings(resource: &Value) -> Vec<(&str, &str)> {
+    let concepts = [
+        &resource["code"],
+        &resource["medicationCodeableConcept"],
+        &resource["type"][0],
+    ];
+    concepts
+        .into_iter()
+        .flat_map(|concept| system_pairs(concept["coding"].as_array(), "code"))
+        .collect()
+}
+
+fn system_pairs<'a>(items: Option<&'a Vec<Value>>, key: &str) -> Vec<(&'a str, &'a str)> {
+    items
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|item| Some((item["system"].as_str()?, item[key].as_str()?)))
+        .collect()
+}
+
+/// Ngày (`YYYY-MM-DD`) của resource: thời điểm lâm sàng, hoặc ngày ghi nhận.
+fn record_date(resource: &Value) -> Option<&str> {
+    [
+        &resource["effectiveDateTime"],
+        &resource["effectivePeriod"]["start"],
+        &resource["onsetDateTime"],
+        &resource["authoredOn"],
+        &resource["recordedDate"],
+        &resource["period"]["start"],
+    ]
+    .into_iter()
+    .find_map(Value::as_str)
+    .map(|date| date.get(..10).unwrap_or(date))
+}
+
+fn to_values<R: Serialize>(resources: &[R]) -> Vec<Value> {
+    resources
+        .iter()
+        .filter_map(|resource| serde_json::to_value(resource).ok())
+        .collect()
+}
+
+/// `$summary`: International Patient Summary của bệnh nhân, dạng `Bundle`
+/// loại `document`. Document được kiểm tra theo IPS trước khi trả về. Chỉ
+/// Patient lỗi mới làm cả document lỗi; section không đọc được có `emptyReason`
+/// là `unavailable`.
+async fn ips_summary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(patient_id): Path<String>,
+) -> Result<Response, SummaryError> {
+    let token = access_token(&state, &headers, &patient_id).await?;
+
+    let fhir = &state.fhir;
+    let resources = fhir.resources();
+    let auth = Auth::Bearer(&token);
+    let problems = SearchParams::of::<fhir::Condition>()
+        .patient(&patient_id)
+        .param("category", "problem-list-item");
+    let results = SearchParams::of::<fhir::Observation>()
+        .patient(&patient_id)
+        .param("category", "laboratory");
+    let (patient, problems, medications, allergies, procedures, immunizations, results) = tokio::try_join!(
+        read(resources, auth, &patient_id),
+        best_effort(search(resources, auth, problems)),
+        best_effort(search(
+            resources,
+            auth,
+            by_patient::<fhir::MedicationRequest>(&patient_id)
+        )),
+        best_effort(search(
+            resources,
+            auth,
+            by_patient::<fhir::AllergyIntolerance>(&patient_id)
+        )),
+        best_effort(search(
+            resources,
+            auth,
+            by_patient::<fhir::Procedure>(&patient_id)
+        )),
+        best_effort(search(
+            resources,
+            auth,
+            by_patient::<fhir::Immunization>(&patient_id)
+        )),
+        best_effort(search(resources, auth, results)),
+    )?;
+    let mut unavailable = Vec::new();
+    let sources = IpsSources {
+        problems: available(problems, IpsSection::Problems, &mut unavailable),
+        medications: available(medications, IpsSection::Medications, &mut unavailable),
+        allergies: available(allergies, IpsSection::Allergies, &mut unavailable),
+        procedures: available(procedures, IpsSection::Procedures, &mut unavailable),
+        immunizations: available(immunizations, IpsSection::Immunizations, &mut unavailable),
+        results: available(results, IpsSection::Results, &mut unavailable),
+        unavailable,
+    };
+
+    let document =
+        ips::build_document(patient, sources, fhir.base_url(), OffsetDateTime::now_utc());
+    let issues = ips::validate(&document);
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        return Err(SummaryError::InvalidDocument(issues.join("; ")));
+    }
+    Ok((
+        [(header::CONTENT_TYPE, FHIR_JSON)],
+        Json(Resource::from(document)),
+    )
+        .into_response())
+}
+
+/// Resource của một section IPS; section không đọc được được ghi vào `unavailable`.
+fn available<R>(
+    resources: Option<Vec<R>>,
+    section: IpsSection,
+    unavailable: &mut Vec<IpsSection>,
+) -> Vec<R> {
+    resources.unwrap_or_else(|| {
+        unavailable.push(section);
+        Vec::new()
+    })
+}
+
+/// Bản in HTML của patient summary.
+async fn printout_html(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(patient_id): Path<String>,
+) -> Result<Html<String>, SummaryError> {
+    let printout = load_printout(&state, &headers, &patient_id).await?;
+    Ok(Html(printout::html::render(&printout)))
+}
+
+/// Bản in PDF của patient summary, cùng nội dung với bản HTML.
+async fn printout_pdf(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(patient_id): Path<String>,
+) -> Result<Response, SummaryError> {
+    let printout = load_printout(&state, &headers, &patient_id).await?;
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/pdf"),
+        )],
+        printout::pdf::render(&printout),
+    )
+        .into_response())
+}
+
+/// Lấy dữ liệu cho bản in. Lời khuyên chỉ có khi cấu hình agent; agent hoặc
+/// CarePlan lỗi không làm hỏng bản in.
+async fn load_printout(
+    state: &AppState,
+    headers: &HeaderMap,
+    patient_id: &str,
+) -> Result<Printout, SummaryError> {
+    let token = access_token(state, headers, patient_id).await?;
+
+    let resources = state.fhir.resources();
+    let auth = Auth::Bearer(&token);
+    let problems = SearchParams::of::<fhir::Condition>()
+        .patient(patient_id)
+        .param("category", "problem-list-item");
+    let (patient, problems, medications, allergies, encounters) = tokio::try_join!(
+        read(resources, auth, patient_id),
+        search(resources, auth, problems),
+        search(
+            resources,
+            auth,
+            by_patient::<fhir::MedicationRequest>(patient_id)
+        ),
+        search(
+            resources,
+            auth,
+            by_patient::<fhir::AllergyIntolerance>(patient_id)
+        ),
+        search(resources, auth, by_patient::<fhir::Encounter>(patient_id)),
+    )?;
+
+    let advice = match &state.advice {
+        Some(agent) => {
+            let careplans = SearchParams::of::<fhir::CarePlan>()
+                .patient(patient_id)
+                .param("category", INPATIENT_CAREPLAN);
+            match search::<fhir::CarePlan>(resources, auth, careplans).await {
+                Ok(careplans) => agent.advise(&careplans).await,
+                Err(e) => {
+                    tracing::warn!("Skipping advice, CarePlan search failed: {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let sources = PrintSources {
+        patient,
+        problems,
+        medications,
+        allergies,
+        encounters,
+        advice,
+    };
+    Ok(Printout::build(sources, OffsetDateTime::now_utc()))
+}
+
+/// `GET [base]/{type}/{id}` bằng access token của người dùng.
+async fn read<R: FhirResource>(
+    resources: &fhir::FhirClient,
+    auth: Auth<'_>,
+    id: &str,
+) -> Result<R, SummaryError> {
+    resources
+        .read::<R>(auth, id)
+        .await
+        .map(|versioned| versioned.resource)
+        .map_err(|e| SummaryError::from_fhir(R::TYPE, e))
+}
+
+/// Mọi trang của một search, chỉ lấy kết quả chính.
+async fn search<R: FhirResource>(
+    resources: &fhir::FhirClient,
+    auth: Auth<'_>,
+    query: SearchParams,
+) -> Result<Vec<R>, SummaryError> {
+    resources
+        .search_all::<R>(auth, &query)
+        .await
+        .map_err(|e: FhirError| SummaryError::from_fhir(R::TYPE, e))
+}
+
+fn by_patient<R: FhirResource>(patient_id: &str) -> SearchParams {
+    SearchParams::of::<R>().patient(patient_id)
+}
+
+async fn demo_patients(State(state): State<SharedState>) -> Json<Vec<Value>> {
+    Json(state.demo.list())
+}
+
+async fn demo_summary(
+    State(state): State<SharedState>,
+    Path(patient_id): Path<String>,
+) -> Result<Json<Value>, SummaryError> {
+    state.demo.summary(&patient_id).cloned().map(Json)
+}
+
+/// Token gửi tới FHIR server: bearer của request, đổi qua gateway nếu cấu hình.
+async fn access_token(
+    state: &AppState,
+    headers: &HeaderMap,
+    patient_id: &str,
+) -> Result<String, SummaryError> {
+    if !is_valid_id(patient_id) {
+        return Err(SummaryError::BadRequest(format!(
+            "invalid patient id '{}'",
+            patient_id
+        )));
+    }
+    let bearer = bearer_token(headers).ok_or(SummaryError::MissingToken)?;
+    match &state.exchange {
+        Some(exchange) => exchange.exchange(bearer, state.fhir.base_url()).await,
+        None => Ok(bearer.to_string()),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Kiểu `id` của FHIR: `[A-Za-z0-9\-\.]{1,64}`. Chặn id chứa `/` hoặc `?`
+/// trước khi ghép vào URL.
+fn is_valid_id(id: &str) -> bool {
+    (1..=64).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::{Query, RawQuery};
+    use axum::http::{Request, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::post;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "epic-access-token";
+    const PATIENT: &str = "erXuFYUfucBZaryVksYEcMg3";
+    /// Token của app thiếu scope `Condition.read`
+    const LIMITED_TOKEN: &str = "token-without-condition-scope";
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        matches!(bearer_token(headers), Some(TOKEN | LIMITED_TOKEN))
+    }
+
+    fn bundle(resources: Vec<Value>, next: Option<String>) -> Value {
+        let mut bundle = json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "entry": resources
+                .into_iter()
+                .map(|resource| json!({ "resource": resource }))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(next) = next {
+            bundle["link"] = json!([{ "relation": "next", "url": next }]);
+        }
+        bundle
+    }
+
+    /// Search luôn trả về `resources` trong một trang.
+    fn searchset(resources: Vec<Value>) -> axum::routing::MethodRouter {
+        get(move |headers: HeaderMap| {
+            let resources = resources.clone();
+            async move {
+                if !authorized(&headers) {
+                    return StatusCode::UNAUTHORIZED.into_response();
+                }
+                Json(bundle(resources, None)).into_response()
+            }
+        })
+    }
+
+    /// FHIR server giả: Encounter trả về hai trang, CarePlan lọc theo category.
+    async fn fhir_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let next = format!("{}/Encounter?patient={}&page=2", base, PATIENT);
+        let app = Router::new()
+            .route(
+                "/Patient/{id}",
+                get(|Path(id): Path<String>, headers: HeaderMap| async move {
+                    if !authorized(&headers) {
+                        return StatusCode::UNAUTHORIZED.into_response();
+                    }
+                    if id != PATIENT {
+                        return (
+                            StatusCode::NOT_FOUND,
+                            Json(json!({
+                                "resourceType": "OperationOutcome",
+                                "issue": [{ "severity": "error", "code": "not-found", "diagnostics": "The resource could not be found." }],
+                            })),
+                        )
+                            .into_response();
+                    }
+                    Json(json!({
+                        "resourceType": "Patient",
+                        "id": PATIENT,
+                        "name": [{ "use": "official", "text": "Camila Lopez" }],
+                        "gender": "female",
+                        "birthDate": "1987-09-12",
+                    }))
+                    .into_response()
+                }),
+            )
+            .route(
+                "/Encounter",
+                get(
+                    move |Query(query): Query<HashMap<String, String>>, headers: HeaderMap| async move {
+                        if !authorized(&headers) {
+                            return StatusCode::UNAUTHORIZED.into_response();
+                        }
+                        assert_eq!(query["patient"], PATIENT);
+                        let page = match query.get("page") {
+                            None => bundle(
+                                vec![
+                                    json!({
+                                        "resourceType": "Encounter",
+                                        "id": "enc-1",
+                                        "status": "finished",
+                                        "class": { "code": "AMB", "display": "ambulatory" },
+                                        "period": { "start": "2023-01-10T08:00:00Z" },
+                                    }),
+                                    json!({
+                                        "resourceType": "OperationOutcome",
+                                        "issue": [{ "severity": "warning", "code": "informational" }],
+                                    }),
+                                ],
+                                Some(next.clone()),
+                            ),
+                            Some(_) => bundle(
+                                vec![json!({
+                                    "resourceType": "Encounter",
+                                    "id": "enc-2",
+                                    "status": "in-progress",
+                                    "class": { "code": "IMP" },
+                                    "type": [{ "text": "Hospital admission" }],
+                                    "period": { "start": "2024-02-01T10:00:00Z" },
+                                })],
+                                None,
+                            ),
+                        };
+                        Json(page).into_response()
+                    },
+                ),
+            )
+            .route(
+                "/CarePlan",
+                get(|Query(query): Query<HashMap<String, String>>, headers: HeaderMap| async move {
+                    if !authorized(&headers) {
+                        return StatusCode::UNAUTHORIZED.into_response();
+                    }
+                    let careplan = json!({
+                        "resourceType": "CarePlan",
+                        "id": format!("cp-{}", query["category"]),
+                        "status": "active",
+                        "intent": "plan",
+                        "subject": { "reference": format!("Patient/{}", PATIENT) },
+                        "addresses": [{ "display": "Essential hypertension" }],
+                    });
+                    Json(bundle(vec![careplan], None)).into_response()
+                }),
+            )
+            .route(
+                "/Procedure",
+                get(|RawQuery(_): RawQuery, headers: HeaderMap| async move {
+                    if !authorized(&headers) {
+                        return StatusCode::UNAUTHORIZED.into_response();
+                    }
+                    Json(bundle(
+                        vec![json!({
+                            "resourceType": "Procedure",
+                            "id": "proc-1",
+                            "status": "completed",
+                            "subject": { "reference": format!("Patient/{}", PATIENT) },
+                        })],
+                        None,
+                    ))
+                    .into_response()
+                }),
+            )
+            .route(
+                "/Condition",
+                get(|headers: HeaderMap| async move {
+                    if !authorized(&headers) {
+                        return StatusCode::UNAUTHORIZED.into_response();
+                    }
+                    if bearer_token(&headers) == Some(LIMITED_TOKEN) {
+                        return (
+                            StatusCode::FORBIDDEN,
+                            Json(json!({
+                                "resourceType": "OperationOutcome",
+                                "issue": [{ "severity": "error", "code": "forbidden" }],
+                            })),
+                        )
+                            .into_response();
+                    }
+                    Json(bundle(
+                        vec![json!({
+                            "resourceType": "Condition",
+                            "id": "cond-1",
+                            "subject": { "reference": format!("Patient/{}", PATIENT) },
+                            "code": { "text": "Essential hypertension" },
+                        })],
+                        None,
+                    ))
+                    .into_response()
+                }),
+            )
+            .route(
+                "/MedicationRequest",
+                searchset(vec![json!({
+                    "resourceType": "MedicationRequest",
+                    "id": "med-1",
+                    "status": "active",
+                    "intent": "order",
+                    "medicationCodeableConcept": { "text": "amlodipine 5 MG Oral Tablet" },
+                    "subject": { "reference": format!("Patient/{}", PATIENT) },
+                })]),
+            )
+            .route("/AllergyIntolerance", searchset(Vec::new()))
+            .route("/Immunization", searchset(Vec::new()))
+            .route(
+                "/DocumentReference",
+                searchset(vec![
+                    json!({
+                        "resourceType": "DocumentReference",
+                        "id": "ccd-1",
+                        "status": "current",
+                        "type": { "coding": [{ "system": "http://loinc.org", "code": "34133-9" }] },
+                        "subject": { "reference": format!("Patient/{}", PATIENT) },
+                        "content": [{ "attachment": { "contentType": "application/xml", "url": "Binary/ccd-1" } }],
+                    }),
+                    // Không phải CDA (`<note/>`): bị bỏ qua, summary vẫn được tạo
+                    json!({
+                        "resourceType": "DocumentReference",
+                        "id": "note-1",
+                        "status": "current",
+                        "content": [{ "attachment": { "contentType": "application/xml", "data": "PG5vdGUvPg==" } }],
+                    }),
+                ]),
+            )
+            .route(
+                "/Binary/{id}",
+                get(|Path(id): Path<String>, headers: HeaderMap| async move {
+                    if !authorized(&headers) {
+                        return StatusCode::UNAUTHORIZED.into_response();
+                    }
+                    if id != "ccd-1" {
+                        return StatusCode::NOT_FOUND.into_response();
+                    }
+                    (
+                        [(header::CONTENT_TYPE, "application/xml")],
+                        include_str!("../data/sample_ccd.xml"),
+                    )
+                        .into_response()
+                }),
+            )
+            .route(
+                "/Observation",
+                searchset(vec![json!({
+                    "resourceType": "Observation",
+                    "id": "obs-1",
+                    "status": "final",
+                    "code": {
+                        "coding": [{ "system": "http://loinc.org", "code": "718-7" }],
+                        "text": "Hemoglobin",
+                    },
+                    "effectiveDateTime": "2023-09-10T08:30:00-05:00",
+                    "valueQuantity": { "value": 13.2, "unit": "g/dL" },
+                })]),
+            )
+            // patient-summary-agent giả trên cùng server
+            .route(
+                "/advice",
+                post(|Json(body): Json<Value>| async move {
+                    let careplans = &body["summary"]["inpatientCarePlansRecord"];
+                    assert_eq!(careplans[0]["Addresses"][0], "Essential hypertension");
+                    Json(json!({ "advice": "- Check blood pressure weekly." }))
+                }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base
+    }
+
+    fn service(fhir_base_url: String) -> Router {
+        router(Arc::new(AppState {
+            fhir: FhirClient::new(reqwest::Client::new(), fhir_base_url).unwrap(),
+            exchange: None,
+            advice: None,
+            demo: DemoPatients::load(),
+            bearer: None,
+        }))
+    }
+
+    async fn get_bytes(app: Router, uri: &str) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let request = Request::get(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", TOKEN))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, headers, body.to_vec())
+    }
+
+    async fn get_json(app: Router, uri: &str, token: Option<&str>) -> (StatusCode, Value) {
+        let mut request = Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response: Response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn aggregates_the_patient_summary_from_fhir() {
+        let base = fhir_server().await;
+        let (status, summary) = get_json(
+            service(base.clone()),
+            &format!("/patient_summary/{}", PATIENT),
+            Some(TOKEN),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", summary);
+        assert_eq!(summary["patient"]["id"]["@value"], PATIENT);
+        assert!(summary.get("unavailable").is_none(), "{}", summary);
+        assert_eq!(
+            summary["patient"]["name"][0]["text"]["@value"],
+            "Camila Lopez"
+        );
+        // Hai trang Encounter, entry OperationOutcome bị bỏ
+        let encounters: Vec<&str> = summary["encounters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|encounter| encounter["id"]["@value"].as_str().unwrap())
+            .collect();
+        // Encounter của document C-CDA đứng sau dữ liệu của Epic
+        assert_eq!(encounters, ["enc-1", "enc-2", "ccda-ccd-1-encounter1"]);
+        assert_eq!(
+            summary["inpatient_careplans"][0]["id"]["@value"],
+            format!("cp-{}", INPATIENT_CAREPLAN)
+        );
+        assert_eq!(
+            summary["outpatient_careplans"][0]["id"]["@value"],
+            format!("cp-{}", OUTPATIENT_CAREPLAN)
+        );
+        assert_eq!(summary["procedures"][0]["status"]["@value"], "completed");
+
+        // Dữ liệu của Epic không có provenance; dữ liệu từ C-CDA có
+        let problems = summary["problems"].as_array().unwrap();
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0]["id"]["@value"], "cond-1");
+        assert!(problems[0].get("provenance").is_none());
+        assert_eq!(
+            problems[1]["code"]["text"]["@value"],
+            "Essential hypertension"
+        );
+        assert_eq!(problems[1]["provenance"]["source"]["@value"], "external");
+        assert_eq!(
+            problems[1]["provenance"]["document"]["@value"],
+            format!("{}/DocumentReference/ccd-1", base)
+        );
+        assert_eq!(
+            summary["encounters"][2]["provenance"]["source"]["@value"],
+            "external"
+        );
+        assert_eq!(summary["medications"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            summary["allergies"][0]["code"]["text"]["@value"],
+            "Penicillin G"
+        );
+        assert_eq!(summary["allergies"][0]["reaction"][0]["@value"], "Hives");
+        // Hemoglobin trong C-CDA trùng code và ngày với obs-1 nên bị bỏ
+        let results = summary["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["id"]["@value"], "obs-1");
+        assert_eq!(results[0]["value"]["@value"], "13.2 g/dL");
+        assert_eq!(results[1]["value"]["@value"], "Blood group A");
+        assert_eq!(results[1]["provenance"]["source"]["@value"], "external");
+    }
+
+    #[tokio::test]
+    async fn leaves_out_sections_the_token_cannot_read() {
+        let base = fhir_server().await;
+        let (status, summary) = get_json(
+            service(base),
+            &format!("/patient_summary/{}", PATIENT),
+            Some(LIMITED_TOKEN),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", summary);
+        // Condition bị 403: chỉ còn problem từ C-CDA
+        let problems = summary["problems"].as_array().unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(
+            problems
+                .iter()
+                .all(|problem| { problem["provenance"]["source"]["@value"] == "external" })
+        );
+        assert_eq!(summary["medications"].as_array().unwrap().len(), 3);
+        assert_eq!(summary["unavailable"], json!(["problems"]));
+    }
+
+    #[test]
+    fn drops_external_records_already_in_epic() {
+        let native = vec![json!({
+            "resourceType": "Observation",
+            "id": "obs-1",
+            "identifier": [{ "system": "urn:oid:1.2.3", "value": "42" }],
+            "code": { "coding": [{ "system": "http://loinc.org", "code": "718-7" }] },
+            "effectiveDateTime": "2023-09-10T08:30:00-05:00",
+        })];
+        let external = vec![
+            // Cùng identifier
+            json!({ "id": "a", "identifier": [{ "system": "urn:oid:1.2.3", "value": "42" }] }),
+            // Cùng code và ngày
+            json!({
+                "id": "b",
+                "code": { "coding": [{ "system": "http://loinc.org", "code": "718-7" }] },
+                "effectiveDateTime": "2023-09-10",
+            }),
+            // Cùng code, khác ngày
+            json!({
+                "id": "c",
+                "code": { "coding": [{ "system": "http://loinc.org", "code": "718-7" }] },
+                "effectiveDateTime": "2024-01-02T09:00:00Z",
+            }),
+            // Trùng với resource bên ngoài trước đó
+            json!({
+                "id": "d",
+                "code": { "coding": [{ "system": "http://loinc.org", "code": "718-7" }] },
+                "effectiveDateTime": "2024-01-02",
+            }),
+        ];
+        let resources = merged(native, external);
+        let ids: Vec<&str> = resources
+            .iter()
+            .map(|resource| resource["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["obs-1", "c"]);
+    }
+
+    #[tokio::test]
+    async fn builds_an_ips_document() {
+        let base = fhir_server().await;
+        let (status, document) = get_json(
+            service(base.clone()),
+            &format!("/patient_summary/{}/$summary", PATIENT),
+            Some(TOKEN),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", document);
+        assert_eq!(document["resourceType"], "Bundle");
+        assert_eq!(document["type"], "document");
+
+        let composition = &document["entry"][0]["resource"];
+        assert_eq!(composition["resourceType"], "Composition");
+        assert_eq!(composition["subject"]["display"], "Camila Lopez");
+        let codes: Vec<&str> = composition["section"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|section| section["code"]["coding"][0]["code"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                "11450-4", "10160-0", "48765-2", "47519-4", "11369-6", "30954-2"
+            ]
+        );
+        assert_eq!(
+            composition["section"][1]["entry"][0]["reference"],
+            "MedicationRequest/med-1"
+        );
+        assert_eq!(
+            composition["section"][2]["emptyReason"]["coding"][0]["code"],
+            "nilknown"
+        );
+        assert_eq!(
+            document["entry"][1]["fullUrl"],
+            format!("{}/Patient/{}", base, PATIENT)
+        );
+
+        let bundle: fhir::Bundle = serde_json::from_value(document).unwrap();
+        assert_eq!(ips::validate(&bundle), []);
+
+        // Condition bị 403: document vẫn được tạo, section đó ghi rõ không đọc được
+        let (status, document) = get_json(
+            service(base.clone()),
+            &format!("/patient_summary/{}/$summary", PATIENT),
+            Some(LIMITED_TOKEN),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", document);
+        let problems = &document["entry"][0]["resource"]["section"][0];
+        assert!(problems.get("entry").is_none(), "{}", problems);
+        assert_eq!(problems["emptyReason"]["coding"][0]["code"], "unavailable");
+
+        let (status, _) = get_json(
+            service(base),
+            &format!("/patient_summary/{}/$summary", PATIENT),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn exports_printable_summaries() {
+        let base = fhir_server().await;
+        let app = router(Arc::new(AppState {
+            fhir: FhirClient::new(reqwest::Client::new(), base.clone()).unwrap(),
+            exchange: None,
+            advice: Some(AdviceClient::new(reqwest::Client::new(), &base)),
+            demo: DemoPatients::load(),
+            bearer: None,
+        }));
+
+        let (status, headers, body) = get_bytes(
+            app.clone(),
+            &format!("/patient_summary/{}/summary.html", PATIENT),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            headers[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        let html = String::from_utf8(body).unwrap();
+        assert!(html.contains("<p class=\"patient\">Camila Lopez</p>"));
+        assert!(html.contains("<td>amlodipine 5 MG Oral Tablet</td>"));
+        assert!(html.contains("- Check blood pressure weekly."));
+        // Encounter mới nhất trước
+        let admission = html.find("Hospital admission").unwrap();
+        let visit = html.find("ambulatory").unwrap();
+        assert!(admission < visit);
+
+        let (status, headers, body) =
+            get_bytes(app, &format!("/patient_summary/{}/summary.pdf", PATIENT)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/pdf");
+        assert!(body.starts_with(b"%PDF-1.4"));
+
+        // Không có agent: bản in vẫn được tạo, không có lời khuyên
+        let (status, _, body) = get_bytes(
+            service(base),
+            &format!("/patient_summary/{}/summary.html", PATIENT),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            String::from_utf8(body)
+                .unwrap()
+                .contains("Advice unavailable.")
+        );
+    }
+
+    #[tokio::test]
+    async fn surfaces_fhir_and_token_errors() {
+        let base = fhir_server().await;
+
+        let (status, _) = get_json(service(base.clone()), "/patient_summary/abc", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = get_json(
+            service(base.clone()),
+            "/patient_summary/abc",
+            Some("expired"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+
+        let (status, body) = get_json(
+            service(base.clone()),
+            "/patient_summary/unknown",
+            Some(TOKEN),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains("The resource could not be found.")
+        );
+
+        let (status, _) = get_json(service(base), "/patient_summary/a%2Fb", Some(TOKEN)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn requires_a_gateway_token_when_bearer_auth_is_configured() {
+        let base = fhir_server().await;
+        let jwks = Arc::new(security::bearer::RemoteJwks::new(
+            format!("{}/.well-known/jwks.json", base),
+            reqwest::Client::new(),
+        ));
+        let app = router(Arc::new(AppState {
+            fhir: FhirClient::new(reqwest::Client::new(), base).unwrap(),
+            exchange: None,
+            advice: None,
+            demo: DemoPatients::load(),
+            bearer: Some(Arc::new(BearerValidator::new(
+                "https://gateway.example.org",
+                "patient-summary",
+                jwks,
+            ))),
+        }));
+
+        let uri = format!("/patient_summary/{}", PATIENT);
+        let (status, _) = get_json(app.clone(), &uri, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        // Access token của Epic không phải token gateway cấp cho service này
+        let (status, _) = get_json(app.clone(), &uri, Some(TOKEN)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = get_json(app, "/demo/patients", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn serves_demo_patients() {
+        // Không cần FHIR server cho demo
+        let app = service("http://127.0.0.1:9".to_string());
+        let (status, patients) = get_json(app.clone(), "/demo/patients", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let id = patients[0]["id"].as_str().unwrap().to_string();
+
+        let (status, patient) =
+            get_json(app.clone(), &format!("/demo/patients/{}/summary", id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(patient["id"], id);
+        assert!(patient["encounters"].is_array());
+
+        let (status, _) = get_json(app, "/demo/patients/nobody/summary", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
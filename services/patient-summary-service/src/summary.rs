@@ -0,0 +1,698 @@
+//! `PatientSummary` theo đúng hình dạng frontend dùng (`models/summary.ts`).
+//!
+//! Mọi giá trị chuỗi được bọc trong `{"@value": ...}`. Field frontend khai báo
+//! `Text | null` là `Option<Text>`; field bắt buộc luôn có object, `@value` có
+//! thể là `null` (frontend hiển thị `_`).
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::ccda::PROVENANCE_SYSTEM;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Text {
+    #[serde(rename = "@value")]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Reference {
+    pub reference: Option<Text>,
+    pub display: Option<Text>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Period {
+    pub start: Option<Text>,
+    pub end: Option<Text>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Coding {
+    pub display: Option<Text>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CodeableConcept {
+    pub coding: Vec<Coding>,
+    pub text: Option<Text>,
+}
+
+/// Object chỉ có `text`, như `maritalStatus`, `category`, `reasonCode`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TextOnly {
+    pub text: Text,
+}
+
+/// Object chỉ có `display`, như `managingOrganization`, `serviceProvider`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DisplayOnly {
+    pub display: Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HumanName {
+    pub usage: Option<Text>,
+    pub text: Option<Text>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Address {
+    pub city: Option<Text>,
+    pub state: Option<Text>,
+    pub country: Option<Text>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Communication {
+    pub language: TextOnly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GeneralPractitioner {
+    #[serde(rename = "type")]
+    pub kind: Text,
+    pub display: Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Patient {
+    pub id: Text,
+    pub name: Vec<HumanName>,
+    pub gender: Option<Text>,
+    pub birth_date: Option<Text>,
+    pub address: Vec<Address>,
+    pub marital_status: TextOnly,
+    pub communication: Vec<Communication>,
+    pub general_practitioner: Vec<GeneralPractitioner>,
+    pub managing_organization: DisplayOnly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParticipantIndividual {
+    #[serde(rename = "type")]
+    pub kind: Option<CodeableConcept>,
+    pub display: Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Participant {
+    #[serde(rename = "type")]
+    pub kind: Option<CodeableConcept>,
+    pub individual: ParticipantIndividual,
+}
+
+/// Nguồn gốc của một mục không lấy trực tiếp từ Epic, ví dụ `external` cho
+/// dữ liệu chuyển từ document C-CDA.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub source: Text,
+    /// DocumentReference gốc
+    pub document: Option<Text>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EncounterLocation {
+    pub location: Reference,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Encounter {
+    pub id: Text,
+    pub status: Text,
+    pub class: Reference,
+    #[serde(rename = "type")]
+    pub kind: Option<Vec<CodeableConcept>>,
+    pub service_type: Option<CodeableConcept>,
+    pub participant: Vec<Participant>,
+    pub period: Period,
+    pub location: Vec<EncounterLocation>,
+    pub service_provider: DisplayOnly,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CarePlan {
+    pub id: Text,
+    pub status: Text,
+    pub intent: Text,
+    pub category: TextOnly,
+    pub period: Period,
+    pub created: Text,
+    pub addresses: Option<Vec<Reference>>,
+    pub goal: Option<Vec<Reference>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Procedure {
+    pub id: Text,
+    pub status: Text,
+    pub category: TextOnly,
+    pub encounter: Reference,
+    pub performed_period: Period,
+    pub recorder: Reference,
+    pub location: Reference,
+    pub reason_code: TextOnly,
+    pub body_site: TextOnly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    pub id: Text,
+    pub clinical_status: Option<Text>,
+    pub code: TextOnly,
+    pub onset: Option<Text>,
+    pub abatement: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Medication {
+    pub id: Text,
+    pub status: Text,
+    pub medication: TextOnly,
+    pub dosage: Option<Text>,
+    pub authored_on: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Allergy {
+    pub id: Text,
+    pub clinical_status: Option<Text>,
+    pub criticality: Option<Text>,
+    pub code: TextOnly,
+    pub reaction: Vec<Text>,
+    pub onset: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabResult {
+    pub id: Text,
+    pub status: Text,
+    pub code: TextOnly,
+    pub value: Option<Text>,
+    pub interpretation: Option<Text>,
+    pub effective: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PatientSummary {
+    pub patient: Patient,
+    pub encounters: Vec<Encounter>,
+    pub inpatient_careplans: Vec<CarePlan>,
+    pub outpatient_careplans: Vec<CarePlan>,
+    pub procedures: Vec<Procedure>,
+    pub problems: Vec<Problem>,
+    pub medications: Vec<Medication>,
+    pub allergies: Vec<Allergy>,
+    pub results: Vec<LabResult>,
+    /// Mục không đọc được (ví dụ 403, search quá số trang cho phép) và vì vậy
+    /// để trống, theo tên field
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unavailable: Vec<&'static str>,
+}
+
+impl Text {
+    fn new(value: Option<&str>) -> Self {
+        Self {
+            value: value.map(str::to_string),
+        }
+    }
+
+    /// `Some` chỉ khi có giá trị, cho các field `Text | null`.
+    fn optional(value: Option<&str>) -> Option<Self> {
+        value.map(|value| Self::new(Some(value)))
+    }
+}
+
+fn str_of<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn array_of<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// `text`, hoặc `display` (rồi `code`) của coding đầu tiên.
+fn concept_text(concept: &Value) -> Option<&str> {
+    str_of(concept, "text").or_else(|| {
+        array_of(concept, "coding")
+            .iter()
+            .find_map(|coding| str_of(coding, "display").or_else(|| str_of(coding, "code")))
+    })
+}
+
+fn codeable_concept(concept: &Value) -> CodeableConcept {
+    CodeableConcept {
+        coding: array_of(concept, "coding")
+            .iter()
+            .map(|coding| Coding {
+                display: Text::optional(str_of(coding, "display")),
+            })
+            .collect(),
+        text: Text::optional(str_of(concept, "text")),
+    }
+}
+
+/// Text của concept đầu tiên trong một mảng (`category`, `reasonCode`, `bodySite`).
+fn first_concept_text(value: &Value, key: &str) -> TextOnly {
+    let concept = match value.get(key) {
+        Some(Value::Array(concepts)) => concepts.first(),
+        other => other,
+    };
+    TextOnly {
+        text: Text::new(concept.and_then(concept_text)),
+    }
+}
+
+fn reference(value: Option<&Value>) -> Reference {
+    let Some(value) = value else {
+        return Reference::default();
+    };
+    Reference {
+        reference: Text::optional(str_of(value, "reference")),
+        display: Text::optional(str_of(value, "display")),
+    }
+}
+
+fn period(value: Option<&Value>) -> Period {
+    let Some(value) = value else {
+        return Period::default();
+    };
+    Period {
+        start: Text::optional(str_of(value, "start")),
+        end: Text::optional(str_of(value, "end")),
+    }
+}
+
+fn references(value: &Value, key: &str) -> Option<Vec<Reference>> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|items| items.iter().map(|item| reference(Some(item))).collect())
+}
+
+/// Code của CodeableConcept trong `key` (như `clinicalStatus`).
+fn concept_code<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(|concept| array_of(concept, "coding").first())
+        .and_then(|coding| str_of(coding, "code"))
+}
+
+/// `[x]` của một choice type dạng thời điểm, hoặc `start` nếu là khoảng.
+fn choice_time<'a>(value: &'a Value, prefix: &str) -> Option<&'a str> {
+    str_of(value, &format!("{}DateTime", prefix))
+        .or_else(|| str_of(value, &format!("{}String", prefix)))
+        .or_else(|| {
+            value
+                .get(format!("{}Period", prefix))
+                .and_then(|period| str_of(period, "start"))
+        })
+}
+
+/// `value[x]` của Observation dạng chuỗi hiển thị.
+fn observation_value(observation: &Value) -> Option<String> {
+    if let Some(quantity) = observation.get("valueQuantity") {
+        let value = quantity.get("value").map(ToString::to_string)?;
+        return Some(
+            match str_of(quantity, "unit").or_else(|| str_of(quantity, "code")) {
+                Some(unit) => format!("{} {}", value, unit),
+                None => value,
+            },
+        );
+    }
+    if let Some(concept) = observation.get("valueCodeableConcept") {
+        return concept_text(concept).map(str::to_string);
+    }
+    str_of(observation, "valueString")
+        .map(str::to_string)
+        .or_else(|| {
+            ["valueInteger", "valueBoolean"]
+                .iter()
+                .find_map(|key| observation.get(*key).map(ToString::to_string))
+        })
+}
+
+/// `text` của HumanName, hoặc ghép `given family`.
+fn name_text(name: &Value) -> Option<String> {
+    if let Some(text) = str_of(name, "text") {
+        return Some(text.to_string());
+    }
+    let parts: Vec<&str> = array_of(name, "given")
+        .iter()
+        .filter_map(Value::as_str)
+        .chain(str_of(name, "family"))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+impl Provenance {
+    /// Từ tag [`PROVENANCE_SYSTEM`] trong `meta`; `None` với dữ liệu của Epic.
+    fn from_meta(resource: &Value) -> Option<Self> {
+        let meta = resource.get("meta")?;
+        let tag = array_of(meta, "tag")
+            .iter()
+            .find(|tag| str_of(tag, "system") == Some(PROVENANCE_SYSTEM))?;
+        Some(Self {
+            source: Text::new(str_of(tag, "code")),
+            document: Text::optional(str_of(meta, "source")),
+        })
+    }
+}
+
+impl Patient {
+    pub fn from_fhir(patient: &Value) -> Self {
+        Self {
+            id: Text::new(str_of(patient, "id")),
+            name: array_of(patient, "name")
+                .iter()
+                .map(|name| HumanName {
+                    usage: Text::optional(str_of(name, "use")),
+                    text: name_text(name).map(|text| Text { value: Some(text) }),
+                })
+                .collect(),
+            gender: Text::optional(str_of(patient, "gender")),
+            birth_date: Text::optional(str_of(patient, "birthDate")),
+            address: array_of(patient, "address")
+                .iter()
+                .map(|address| Address {
+                    city: Text::optional(str_of(address, "city")),
+                    state: Text::optional(str_of(address, "state")),
+                    country: Text::optional(str_of(address, "country")),
+                })
+                .collect(),
+            marital_status: first_concept_text(patient, "maritalStatus"),
+            communication: array_of(patient, "communication")
+                .iter()
+                .map(|communication| Communication {
+                    language: first_concept_text(communication, "language"),
+                })
+                .collect(),
+            general_practitioner: array_of(patient, "generalPractitioner")
+                .iter()
+                .map(|practitioner| GeneralPractitioner {
+                    // `type` thường vắng: lấy từ `Practitioner/123`
+                    kind: Text::new(str_of(practitioner, "type").or_else(|| {
+                        str_of(practitioner, "reference")
+                            .and_then(|reference| reference.split('/').next())
+                    })),
+                    display: Text::new(str_of(practitioner, "display")),
+                })
+                .collect(),
+            managing_organization: DisplayOnly {
+                display: Text::new(
+                    patient
+                        .get("managingOrganization")
+                        .and_then(|organization| str_of(organization, "display")),
+                ),
+            },
+        }
+    }
+}
+
+impl Encounter {
+    pub fn from_fhir(encounter: &Value) -> Self {
+        // R4: `class` là một Coding
+        let class = encounter.get("class");
+        Self {
+            id: Text::new(str_of(encounter, "id")),
+            status: Text::new(str_of(encounter, "status")),
+            class: Reference {
+                reference: Text::optional(class.and_then(|class| str_of(class, "code"))),
+                display: Text::optional(
+                    class.and_then(|class| {
+                        str_of(class, "display").or_else(|| str_of(class, "code"))
+                    }),
+                ),
+            },
+            kind: encounter
+                .get("type")
+                .and_then(Value::as_array)
+                .map(|types| types.iter().map(codeable_concept).collect()),
+            service_type: encounter.get("serviceType").map(codeable_concept),
+            participant: array_of(encounter, "participant")
+                .iter()
+                .map(|participant| Participant {
+                    kind: array_of(participant, "type").first().map(codeable_concept),
+                    individual: ParticipantIndividual {
+                        kind: None,
+                        display: Text::new(
+                            participant
+                                .get("individual")
+                                .and_then(|individual| str_of(individual, "display")),
+                        ),
+                    },
+                })
+                .collect(),
+            period: period(encounter.get("period")),
+            location: array_of(encounter, "location")
+                .iter()
+                .map(|location| EncounterLocation {
+                    location: reference(location.get("location")),
+                })
+                .collect(),
+            service_provider: DisplayOnly {
+                display: Text::new(
+                    encounter
+                        .get("serviceProvider")
+                        .and_then(|provider| str_of(provider, "display")),
+                ),
+            },
+            provenance: Provenance::from_meta(encounter),
+        }
+    }
+}
+
+impl CarePlan {
+    pub fn from_fhir(careplan: &Value) -> Self {
+        Self {
+            id: Text::new(str_of(careplan, "id")),
+            status: Text::new(str_of(careplan, "status")),
+            intent: Text::new(str_of(careplan, "intent")),
+            category: first_concept_text(careplan, "category"),
+            period: period(careplan.get("period")),
+            created: Text::new(str_of(careplan, "created")),
+            addresses: references(careplan, "addresses"),
+            goal: references(careplan, "goal"),
+        }
+    }
+}
+
+impl Procedure {
+    pub fn from_fhir(procedure: &Value) -> Self {
+        // `performed[x]`: một thời điểm được hiển thị như khoảng có start = end
+        let performed_period = match str_of(procedure, "performedDateTime") {
+            Some(performed) => Period {
+                start: Text::optional(Some(performed)),
+                end: Text::optional(Some(performed)),
+            },
+            None => period(procedure.get("performedPeriod")),
+        };
+        Self {
+            id: Text::new(str_of(procedure, "id")),
+            status: Text::new(str_of(procedure, "status")),
+            category: first_concept_text(procedure, "category"),
+            encounter: reference(procedure.get("encounter")),
+            performed_period,
+            recorder: reference(procedure.get("recorder")),
+            location: reference(procedure.get("location")),
+            reason_code: first_concept_text(procedure, "reasonCode"),
+            body_site: first_concept_text(procedure, "bodySite"),
+        }
+    }
+}
+
+impl Problem {
+    pub fn from_fhir(condition: &Value) -> Self {
+        Self {
+            id: Text::new(str_of(condition, "id")),
+            clinical_status: Text::optional(concept_code(condition, "clinicalStatus")),
+            code: first_concept_text(condition, "code"),
+            onset: Text::optional(choice_time(condition, "onset")),
+            abatement: Text::optional(choice_time(condition, "abatement")),
+            provenance: Provenance::from_meta(condition),
+        }
+    }
+}
+
+impl Medication {
+    pub fn from_fhir(request: &Value) -> Self {
+        let medication = request
+            .get("medicationCodeableConcept")
+            .and_then(concept_text)
+            .or_else(|| {
+                request
+                    .get("medicationReference")
+                    .and_then(|reference| str_of(reference, "display"))
+            });
+        Self {
+            id: Text::new(str_of(request, "id")),
+            status: Text::new(str_of(request, "status")),
+            medication: TextOnly {
+                text: Text::new(medication),
+            },
+            dosage: Text::optional(
+                array_of(request, "dosageInstruction")
+                    .first()
+                    .and_then(|dosage| str_of(dosage, "text")),
+            ),
+            authored_on: Text::optional(str_of(request, "authoredOn")),
+            provenance: Provenance::from_meta(request),
+        }
+    }
+}
+
+impl Allergy {
+    pub fn from_fhir(allergy: &Value) -> Self {
+        Self {
+            id: Text::new(str_of(allergy, "id")),
+            clinical_status: Text::optional(concept_code(allergy, "clinicalStatus")),
+            criticality: Text::optional(str_of(allergy, "criticality")),
+            code: first_concept_text(allergy, "code"),
+            reaction: array_of(allergy, "reaction")
+                .iter()
+                .flat_map(|reaction| array_of(reaction, "manifestation"))
+                .map(|manifestation| Text::new(concept_text(manifestation)))
+                .collect(),
+            onset: Text::optional(choice_time(allergy, "onset")),
+            provenance: Provenance::from_meta(allergy),
+        }
+    }
+}
+
+impl LabResult {
+    pub fn from_fhir(observation: &Value) -> Self {
+        Self {
+            id: Text::new(str_of(observation, "id")),
+            status: Text::new(str_of(observation, "status")),
+            code: first_concept_text(observation, "code"),
+            value: observation_value(observation).map(|value| Text { value: Some(value) }),
+            interpretation: Text::optional(
+                array_of(observation, "interpretation")
+                    .first()
+                    .and_then(concept_text),
+            ),
+            effective: Text::optional(choice_time(observation, "effective")),
+            provenance: Provenance::from_meta(observation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maps_fhir_resources_to_the_frontend_shape() {
+        let patient = Patient::from_fhir(&json!({
+            "resourceType": "Patient",
+            "id": "erXuFYUfucBZaryVksYEcMg3",
+            "name": [{ "use": "official", "family": "Lopez", "given": ["Camila", "Maria"] }],
+            "gender": "female",
+            "maritalStatus": { "coding": [{ "code": "M", "display": "Married" }] },
+            "communication": [{ "language": { "text": "English" } }],
+            "generalPractitioner": [{ "reference": "Practitioner/eM5CWtq15N0WJeuCet5bJlQ3", "display": "Physician Family Medicine, MD" }],
+        }));
+        let json = serde_json::to_value(&patient).unwrap();
+        assert_eq!(json["id"]["@value"], "erXuFYUfucBZaryVksYEcMg3");
+        assert_eq!(json["name"][0]["usage"]["@value"], "official");
+        assert_eq!(json["name"][0]["text"]["@value"], "Camila Maria Lopez");
+        assert_eq!(json["birthDate"], Value::Null);
+        assert_eq!(json["maritalStatus"]["text"]["@value"], "Married");
+        assert_eq!(
+            json["communication"][0]["language"]["text"]["@value"],
+            "English"
+        );
+        assert_eq!(
+            json["generalPractitioner"][0]["type"]["@value"],
+            "Practitioner"
+        );
+        // Bắt buộc phía frontend: có object, giá trị null
+        assert_eq!(
+            json["managingOrganization"],
+            json!({ "display": { "@value": null } })
+        );
+
+        let encounter = serde_json::to_value(Encounter::from_fhir(&json!({
+            "resourceType": "Encounter",
+            "id": "enc-1",
+            "status": "finished",
+            "class": { "code": "AMB", "display": "Ambulatory" },
+            "participant": [{ "individual": { "display": "Dr. Smith" } }],
+            "period": { "start": "2023-06-02T08:00:00Z" },
+        })))
+        .unwrap();
+        assert_eq!(encounter["class"]["display"]["@value"], "Ambulatory");
+        assert_eq!(encounter["type"], Value::Null);
+        assert_eq!(
+            encounter["participant"][0]["individual"]["display"]["@value"],
+            "Dr. Smith"
+        );
+        assert_eq!(encounter["period"]["end"], Value::Null);
+        assert!(encounter.get("provenance").is_none());
+
+        let problem = serde_json::to_value(Problem::from_fhir(&json!({
+            "resourceType": "Condition",
+            "id": "ccda-ccd-1-problem1",
+            "meta": {
+                "source": "https://fhir.example.org/DocumentReference/ccd-1",
+                "tag": [{ "system": PROVENANCE_SYSTEM, "code": "external" }],
+            },
+            "clinicalStatus": { "coding": [{ "code": "active" }] },
+            "code": { "coding": [{ "display": "Essential hypertension" }] },
+            "onsetDateTime": "2019-05-28",
+        })))
+        .unwrap();
+        assert_eq!(problem["clinicalStatus"]["@value"], "active");
+        assert_eq!(problem["code"]["text"]["@value"], "Essential hypertension");
+        assert_eq!(problem["onset"]["@value"], "2019-05-28");
+        assert_eq!(
+            problem["provenance"],
+            json!({
+                "source": { "@value": "external" },
+                "document": { "@value": "https://fhir.example.org/DocumentReference/ccd-1" },
+            })
+        );
+
+        let procedure = serde_json::to_value(Procedure::from_fhir(&json!({
+            "resourceType": "Procedure",
+            "id": "proc-1",
+            "status": "completed",
+            "category": { "text": "Surgical procedure" },
+            "performedDateTime": "2023-06-02",
+            "reasonCode": [{ "text": "Appendicitis" }],
+        })))
+        .unwrap();
+        assert_eq!(
+            procedure["category"]["text"]["@value"],
+            "Surgical procedure"
+        );
+        assert_eq!(
+            procedure["performedPeriod"]["start"]["@value"],
+            "2023-06-02"
+        );
+        assert_eq!(procedure["reasonCode"]["text"]["@value"], "Appendicitis");
+        assert_eq!(procedure["bodySite"]["text"]["@value"], Value::Null);
+    }
+}
@@ -0,0 +1,76 @@
+//! Đổi token nội bộ của gateway lấy access token của Epic (RFC 8693).
+//!
+//! Khi upstream `patient_summary` có `audience`, gateway gửi token nội bộ thay
+//! cho access token của Epic. Service đổi token đó tại `POST /oauth2/token` của
+//! gateway, với `audience` là FHIR base URL.
+
+use config_lib::settings::TokenExchangeSettings;
+use security::internal_token::{GRANT_TYPE_TOKEN_EXCHANGE, TOKEN_TYPE_JWT};
+use serde::Deserialize;
+
+use crate::error::SummaryError;
+
+#[derive(Debug, Deserialize)]
+struct TokenExchangeResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenExchanger {
+    http: reqwest::Client,
+    config: TokenExchangeSettings,
+}
+
+impl TokenExchanger {
+    pub fn new(http: reqwest::Client, config: TokenExchangeSettings) -> Self {
+        Self { http, config }
+    }
+
+    /// Access token cho `audience` thay cho `subject_token`.
+    pub async fn exchange(
+        &self,
+        subject_token: &str,
+        audience: &str,
+    ) -> Result<String, SummaryError> {
+        let response = self
+            .http
+            .post(&self.config.url)
+            // Gateway form-urldecode client id và secret (RFC 6749 mục 2.3.1)
+            .basic_auth(
+                form_encode(&self.config.client_id),
+                // Có secret: đã kiểm tra khi load cấu hình
+                self.config
+                    .client_secret
+                    .as_ref()
+                    .map(|secret| form_encode(secret.expose())),
+            )
+            .form(&[
+                ("grant_type", GRANT_TYPE_TOKEN_EXCHANGE),
+                ("subject_token", subject_token),
+                ("subject_token_type", TOKEN_TYPE_JWT),
+                ("audience", audience),
+            ])
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(SummaryError::TokenExchange {
+                status,
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        let token: TokenExchangeResponse =
+            response
+                .json()
+                .await
+                .map_err(|e| SummaryError::TokenExchange {
+                    status,
+                    message: e.to_string(),
+                })?;
+        Ok(token.access_token)
+    }
+}
+
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
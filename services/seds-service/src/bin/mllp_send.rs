@@ -0,0 +1,75 @@
+//! Gửi file HL7 tới listener MLLP và in MSA của ACK nhận được.
+//!
+//! `cargo run --bin mllp_send -- 127.0.0.1:2575 data/adt_a01.hl7 data/oru_r01.hl7`
+//!
+//! Thoát với mã khác 0 khi có message không được `AA`.
+
+#[path = "../mllp.rs"]
+mod mllp;
+
+use std::process::ExitCode;
+
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let (Some(addr), files) = (args.next(), args.collect::<Vec<_>>()) else {
+        eprintln!("usage: mllp_send <host:port> <file.hl7>...");
+        return ExitCode::from(2);
+    };
+    if files.is_empty() {
+        eprintln!("usage: mllp_send <host:port> <file.hl7>...");
+        return ExitCode::from(2);
+    }
+
+    let stream = match TcpStream::connect(&addr).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("failed to connect to {}: {}", addr, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut accepted = true;
+    for file in &files {
+        let message = match std::fs::read_to_string(file) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                accepted = false;
+                continue;
+            }
+        };
+        // File lưu segment theo dòng; HL7 dùng CR
+        let message = message.trim_end().replace("\r\n", "\r").replace('\n', "\r");
+        let ack = async {
+            mllp::write_frame(&mut writer, message.as_bytes()).await?;
+            mllp::read_frame(&mut reader, 1024 * 1024)
+                .await?
+                .ok_or(mllp::MllpError::Truncated)
+        };
+        let ack = match ack.await {
+            Ok(ack) => String::from_utf8_lossy(&ack).into_owned(),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let msa = ack
+            .split(['\r', '\n'])
+            .find(|segment| segment.starts_with("MSA"))
+            .unwrap_or("no MSA segment");
+        println!("{}: {}", file, msa);
+        accepted &= msa.get(4..6) == Some("AA");
+    }
+
+    if accepted {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,4 @@
+//! Cấu hình của service: `config/default.yaml`, `config/{APP_ENV}.yaml` và biến
+//! môi trường `APP_*` (xem [`config_lib::load_seds_settings`]).
+
+pub use config_lib::load_seds_settings as load_settings;
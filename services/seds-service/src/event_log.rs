@@ -0,0 +1,227 @@
+//! Nhật ký sự kiện append-only trên đĩa: sự kiện của một message được bàn giao
+//! ở đây trước khi trả ACK.
+//!
+//! Mỗi message đã nhận là một dòng JSON gồm khóa của message (MSH-3, MSH-4,
+//! MSH-10) và các sự kiện của nó. Dòng được ghi xuống đĩa (`fsync`) trước khi
+//! trả `AA`, nên message đã ACK không mất khi service dừng; consumer đọc file
+//! theo thứ tự. Message có khóa đã nằm trong nhật ký, kể cả từ lần chạy trước,
+//! là message gửi lại và không được ghi thêm lần nữa.
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::events::ClinicalEvent;
+use crate::hl7::messages::MessageHeader;
+
+/// Định danh của một message: MSH-10 chỉ duy nhất trong phạm vi bên gửi.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MessageKey {
+    /// MSH-3
+    pub sending_application: Option<String>,
+    /// MSH-4
+    pub sending_facility: Option<String>,
+    /// MSH-10
+    pub control_id: String,
+}
+
+impl MessageKey {
+    pub fn of(header: &MessageHeader) -> Self {
+        Self {
+            sending_application: header.sending_application.clone(),
+            sending_facility: header.sending_facility.clone(),
+            control_id: header.control_id.clone(),
+        }
+    }
+}
+
+/// Kết quả của [`EventLog::append`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appended {
+    Stored,
+    /// Message đã có trong nhật ký; không ghi lại
+    Duplicate,
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    key: &'a MessageKey,
+    received_at: String,
+    events: &'a [ClinicalEvent],
+}
+
+/// Chỉ phần cần đọc lại khi mở nhật ký.
+#[derive(Deserialize)]
+struct StoredRecord {
+    key: MessageKey,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    file: File,
+    seen: HashSet<MessageKey>,
+}
+
+impl EventLog {
+    /// Mở (hoặc tạo) nhật ký tại `path` và nạp khóa của các message đã ghi.
+    /// Dòng cuối bị ghi dở (service dừng giữa chừng) được bỏ qua.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = OpenOptions::new();
+        options.read(true).append(true).create(true);
+        // Nội dung là PHI: chỉ user chạy service đọc được
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+
+        let len = file.metadata()?.len();
+        let mut seen = HashSet::new();
+        let mut ends_with_newline = true;
+        for line in BufReader::new((&file).take(len)).split(b'\n') {
+            let line = line?;
+            match serde_json::from_slice::<StoredRecord>(&line) {
+                Ok(record) => {
+                    seen.insert(record.key);
+                }
+                Err(e) if !line.is_empty() => {
+                    tracing::warn!("Skipping unreadable event log entry: {}", e);
+                }
+                Err(_) => {}
+            }
+        }
+        if len > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            ends_with_newline = last[0] == b'\n';
+        }
+        if !ends_with_newline {
+            // Dòng ghi dở không được dính vào bản ghi tiếp theo
+            file.write_all(b"\n")?;
+            file.sync_data()?;
+        }
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner { file, seen })),
+        })
+    }
+
+    /// Ghi các sự kiện của message `key`, xong khi dữ liệu đã xuống đĩa. Ghi lỗi
+    /// thì nhật ký được cắt về như trước và message chưa được coi là đã nhận.
+    pub async fn append(
+        &self,
+        key: MessageKey,
+        events: &[ClinicalEvent],
+        now: OffsetDateTime,
+    ) -> io::Result<Appended> {
+        let mut line = serde_json::to_vec(&Record {
+            key: &key,
+            received_at: now.format(&Rfc3339).unwrap_or_default(),
+            events,
+        })?;
+        line.push(b'\n');
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut inner = inner.lock().unwrap();
+            if inner.seen.contains(&key) {
+                return Ok(Appended::Duplicate);
+            }
+            let len = inner.file.metadata()?.len();
+            let written = inner
+                .file
+                .write_all(&line)
+                .and_then(|()| inner.file.sync_data());
+            if let Err(e) = written {
+                if let Err(truncate) = inner.file.set_len(len) {
+                    tracing::error!("Failed to roll back the event log: {}", truncate);
+                }
+                return Err(e);
+            }
+            inner.seen.insert(key);
+            Ok(Appended::Stored)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hl7::RawMessage;
+    use crate::hl7::messages::Hl7Message;
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("seds-events-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    fn message(text: &str) -> Hl7Message {
+        Hl7Message::from_raw(&RawMessage::parse(text).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stores_each_message_once_across_restarts() {
+        let path = temp_path();
+        let message = message(include_str!("../data/adt_a01.hl7"));
+        let key = MessageKey::of(message.header());
+        let events = ClinicalEvent::from_message(&message);
+        let now = OffsetDateTime::UNIX_EPOCH;
+
+        let log = EventLog::open(&path).unwrap();
+        assert_eq!(
+            log.append(key.clone(), &events, now).await.unwrap(),
+            Appended::Stored
+        );
+        assert_eq!(
+            log.append(key.clone(), &events, now).await.unwrap(),
+            Appended::Duplicate
+        );
+        // Cùng MSH-10 từ bên gửi khác là message khác
+        let other = MessageKey {
+            sending_facility: Some("OTHER_ED".to_string()),
+            ..key.clone()
+        };
+        assert_eq!(
+            log.append(other, &events, now).await.unwrap(),
+            Appended::Stored
+        );
+        drop(log);
+
+        // Dòng ghi dở ở cuối file không làm hỏng lần ghi sau
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"key\":{\"control_id\":").unwrap();
+        let log = EventLog::open(&path).unwrap();
+        assert_eq!(
+            log.append(key, &events, now).await.unwrap(),
+            Appended::Duplicate
+        );
+        let third = MessageKey::of(self::message(include_str!("../data/adt_a03.hl7")).header());
+        assert_eq!(log.append(third, &[], now).await.unwrap(), Appended::Stored);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["key"]["control_id"], "MSG00001");
+        assert_eq!(first["events"][0]["type"], "patient_admitted");
+        assert_eq!(first["received_at"], "1970-01-01T00:00:00Z");
+        let last: serde_json::Value = serde_json::from_str(lines[3]).unwrap();
+        assert_eq!(last["key"]["control_id"], "MSG00002");
+        fs::remove_file(&path).unwrap();
+    }
+}
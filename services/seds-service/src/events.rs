@@ -0,0 +1,143 @@
+//! Sự kiện nội bộ sinh ra từ message HL7 đã nhận: nhập viện, ra viện, cập nhật
+//! thông tin và kết quả xét nghiệm.
+
+use serde::Serialize;
+
+use crate::hl7::messages::{AdtEvent, Hl7Message, MessageHeader, Order, Patient, Visit};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClinicalEvent {
+    PatientAdmitted(VisitEvent),
+    PatientDischarged(VisitEvent),
+    PatientUpdated(VisitEvent),
+    /// Một sự kiện cho mỗi OBR của ORU
+    ResultReported(ResultEvent),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VisitEvent {
+    /// MSH-10 của message gốc
+    pub message_id: String,
+    /// MSH-3/MSH-4, ví dụ `EDIS@COMMUNITY_ED`
+    pub source: String,
+    /// EVN-2, hoặc MSH-7
+    pub occurred_at: Option<String>,
+    pub patient: Patient,
+    pub visit: Option<Visit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResultEvent {
+    pub message_id: String,
+    pub source: String,
+    /// OBR-7, hoặc MSH-7
+    pub occurred_at: Option<String>,
+    pub patient: Patient,
+    pub visit: Option<Visit>,
+    pub order: Order,
+}
+
+impl ClinicalEvent {
+    /// Tên loại sự kiện, giống trường `type` khi serialize.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::PatientAdmitted(_) => "patient_admitted",
+            Self::PatientDischarged(_) => "patient_discharged",
+            Self::PatientUpdated(_) => "patient_updated",
+            Self::ResultReported(_) => "result_reported",
+        }
+    }
+
+    /// MSH-10 của message gốc.
+    pub fn message_id(&self) -> &str {
+        match self {
+            Self::PatientAdmitted(event)
+            | Self::PatientDischarged(event)
+            | Self::PatientUpdated(event) => &event.message_id,
+            Self::ResultReported(event) => &event.message_id,
+        }
+    }
+
+    pub fn from_message(message: &Hl7Message) -> Vec<Self> {
+        match message {
+            Hl7Message::Adt(adt) => {
+                let event = VisitEvent {
+                    message_id: adt.header.control_id.clone(),
+                    source: source(&adt.header),
+                    occurred_at: adt
+                        .recorded_at
+                        .clone()
+                        .or_else(|| adt.header.timestamp.clone()),
+                    patient: adt.patient.clone(),
+                    visit: adt.visit.clone(),
+                };
+                vec![match adt.event {
+                    AdtEvent::Admit => Self::PatientAdmitted(event),
+                    AdtEvent::Discharge => Self::PatientDischarged(event),
+                    AdtEvent::Update => Self::PatientUpdated(event),
+                }]
+            }
+            Hl7Message::Oru(oru) => oru
+                .orders
+                .iter()
+                .map(|order| {
+                    Self::ResultReported(ResultEvent {
+                        message_id: oru.header.control_id.clone(),
+                        source: source(&oru.header),
+                        occurred_at: order
+                            .observed_at
+                            .clone()
+                            .or_else(|| oru.header.timestamp.clone()),
+                        patient: oru.patient.clone(),
+                        visit: oru.visit.clone(),
+                        order: order.clone(),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+fn source(header: &MessageHeader) -> String {
+    match (&header.sending_application, &header.sending_facility) {
+        (Some(application), Some(facility)) => format!("{}@{}", application, facility),
+        (Some(name), None) | (None, Some(name)) => name.clone(),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hl7::RawMessage;
+
+    fn message(text: &str) -> Hl7Message {
+        Hl7Message::from_raw(&RawMessage::parse(text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn maps_messages_to_events() {
+        let events = ClinicalEvent::from_message(&message(include_str!("../data/adt_a03.hl7")));
+        let [ClinicalEvent::PatientDischarged(discharge)] = &events[..] else {
+            panic!("unexpected events {:?}", events);
+        };
+        assert_eq!(discharge.message_id, "MSG00002");
+        assert_eq!(discharge.source, "EDIS@COMMUNITY_ED");
+        assert_eq!(
+            discharge.occurred_at.as_deref(),
+            Some("20240201182500+0700")
+        );
+
+        let events = ClinicalEvent::from_message(&message(include_str!("../data/oru_r01.hl7")));
+        let [ClinicalEvent::ResultReported(result)] = &events[..] else {
+            panic!("unexpected events {:?}", events);
+        };
+        assert_eq!(result.occurred_at.as_deref(), Some("20240201110000+0700"));
+        assert_eq!(result.order.results.len(), 3);
+
+        let json = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(json["type"], "result_reported");
+        assert_eq!(json["patient"]["identifiers"][0]["id"], "100234");
+    }
+}
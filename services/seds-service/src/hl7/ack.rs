@@ -0,0 +1,291 @@
+//! ACK (original acknowledgment mode): `AA` khi đã nhận, `AE`/`AR` kèm ERR khi
+//! message không xử lý được.
+
+use time::OffsetDateTime;
+
+use super::{Delimiters, ParseError, RawMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode {
+    /// AA
+    Accept,
+    /// AE: message đúng cú pháp nhưng thiếu dữ liệu, hoặc service không xử lý
+    /// được (lỗi nội bộ)
+    Error,
+    /// AR: không đọc được message hoặc loại message không nhận
+    Reject,
+}
+
+impl AckCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Accept => "AA",
+            Self::Error => "AE",
+            Self::Reject => "AR",
+        }
+    }
+
+    /// Mã ACK tương ứng với lỗi phân tích.
+    pub fn for_error(error: &ParseError) -> Self {
+        match error {
+            ParseError::MissingSegment(_) | ParseError::MissingField(_) => Self::Error,
+            _ => Self::Reject,
+        }
+    }
+}
+
+/// ACK cho `message`, dùng delimiter của message gốc. `control_id` là MSH-10
+/// của chính ACK.
+pub fn acknowledge(
+    message: &RawMessage,
+    code: AckCode,
+    error: Option<&ParseError>,
+    now: OffsetDateTime,
+    control_id: &str,
+) -> String {
+    reply(
+        message,
+        code,
+        error.map(Failure::from_parse_error),
+        now,
+        control_id,
+    )
+}
+
+/// ACK `AE` khi message hợp lệ nhưng service không xử lý được, ví dụ không
+/// chuyển tiếp được sự kiện. ERR-3 là `207` (application internal error).
+pub fn application_error(
+    message: &RawMessage,
+    text: &str,
+    now: OffsetDateTime,
+    control_id: &str,
+) -> String {
+    let failure = Failure {
+        condition: "207",
+        description: "Application internal error",
+        text: text.to_string(),
+    };
+    reply(message, AckCode::Error, Some(failure), now, control_id)
+}
+
+fn reply(
+    message: &RawMessage,
+    code: AckCode,
+    failure: Option<Failure>,
+    now: OffsetDateTime,
+    control_id: &str,
+) -> String {
+    let msh = message.header();
+    let d = message.delimiters;
+    let header = Header {
+        // Đảo chiều gửi/nhận
+        sending_application: msh.raw(5),
+        sending_facility: msh.raw(6),
+        receiving_application: msh.raw(3),
+        receiving_facility: msh.raw(4),
+        trigger_event: msh
+            .field(9)
+            .component(2)
+            .map(|event| d.escape(&event))
+            .unwrap_or_default(),
+        processing_id: msh.raw(11),
+        version: msh.raw(12),
+    };
+    let original_id = msh.value(10).map(|id| d.escape(&id)).unwrap_or_default();
+    build(d, &header, code, &original_id, failure, now, control_id)
+}
+
+/// ACK `AR` cho dữ liệu không đọc được như một message HL7 (không có MSH hợp
+/// lệ), dùng delimiter mặc định.
+pub fn reject_unparsed(error: &ParseError, now: OffsetDateTime, control_id: &str) -> String {
+    let header = Header {
+        sending_application: "SEDS",
+        sending_facility: "SEDS",
+        receiving_application: "",
+        receiving_facility: "",
+        trigger_event: String::new(),
+        processing_id: "P",
+        version: "2.5.1",
+    };
+    build(
+        Delimiters::default(),
+        &header,
+        AckCode::Reject,
+        "",
+        Some(Failure::from_parse_error(error)),
+        now,
+        control_id,
+    )
+}
+
+/// Nội dung MSA-3 và ERR của một ACK lỗi.
+struct Failure {
+    /// Mã trong bảng 0357
+    condition: &'static str,
+    description: &'static str,
+    text: String,
+}
+
+impl Failure {
+    fn from_parse_error(error: &ParseError) -> Self {
+        let (condition, description) = condition_code(error);
+        Self {
+            condition,
+            description,
+            text: error.to_string(),
+        }
+    }
+}
+
+struct Header<'a> {
+    sending_application: &'a str,
+    sending_facility: &'a str,
+    receiving_application: &'a str,
+    receiving_facility: &'a str,
+    trigger_event: String,
+    processing_id: &'a str,
+    version: &'a str,
+}
+
+fn build(
+    d: Delimiters,
+    header: &Header,
+    code: AckCode,
+    original_id: &str,
+    failure: Option<Failure>,
+    now: OffsetDateTime,
+    control_id: &str,
+) -> String {
+    let f = d.field;
+    let s = d.component;
+    let mut ack = [
+        "MSH".to_string(),
+        d.encoding_characters(),
+        header.sending_application.to_string(),
+        header.sending_facility.to_string(),
+        header.receiving_application.to_string(),
+        header.receiving_facility.to_string(),
+        timestamp(now),
+        String::new(),
+        format!("ACK{s}{}{s}ACK", header.trigger_event),
+        d.escape(control_id),
+        header.processing_id.to_string(),
+        header.version.to_string(),
+    ]
+    .join(&f.to_string());
+    ack.push('\r');
+
+    let text = failure
+        .as_ref()
+        .map(|failure| d.escape(&failure.text))
+        .unwrap_or_default();
+    ack.push_str(&format!(
+        "MSA{f}{}{f}{original_id}{f}{text}\r",
+        code.as_str()
+    ));
+
+    if let Some(Failure {
+        condition,
+        description,
+        ..
+    }) = failure
+    {
+        // ERR-3 (error code, bảng 0357), ERR-4 (severity), ERR-8 (user message)
+        ack.push_str(&format!(
+            "ERR{f}{f}{f}{condition}{s}{description}{s}HL70357{f}E{f}{f}{f}{f}{text}\r"
+        ));
+    }
+    ack
+}
+
+/// Mã lỗi trong bảng 0357.
+fn condition_code(error: &ParseError) -> (&'static str, &'static str) {
+    match error {
+        ParseError::InvalidHeader(_) | ParseError::InvalidSegment(_) => {
+            ("100", "Segment sequence error")
+        }
+        ParseError::MissingSegment(_) => ("100", "Segment sequence error"),
+        ParseError::MissingField(_) => ("101", "Required field missing"),
+        ParseError::UnsupportedMessageType(_) => ("200", "Unsupported message type"),
+        ParseError::UnsupportedEvent(_) => ("201", "Unsupported event code"),
+    }
+}
+
+/// DTM dạng `YYYYMMDDHHMMSS+ZZZZ`.
+fn timestamp(now: OffsetDateTime) -> String {
+    let offset = now.offset();
+    let (hours, minutes, _) = offset.as_hms();
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}{}{:02}{:02}",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        if offset.is_negative() { '-' } else { '+' },
+        hours.unsigned_abs(),
+        minutes.unsigned_abs(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn acknowledges_with_original_header_swapped() {
+        let message = RawMessage::parse(include_str!("../../data/adt_a01.hl7")).unwrap();
+        let ack = acknowledge(
+            &message,
+            AckCode::Accept,
+            None,
+            datetime!(2024-02-01 10:30:05 +7),
+            "ACK1",
+        );
+        assert_eq!(
+            ack,
+            "MSH|^~\\&|SEDS|SEDS|EDIS|COMMUNITY_ED|20240201103005+0700||ACK^A01^ACK|ACK1|P|2.5.1\r\
+             MSA|AA|MSG00001|\r"
+        );
+
+        let error = ParseError::MissingField("PID-3");
+        let ack = acknowledge(
+            &message,
+            AckCode::for_error(&error),
+            Some(&error),
+            datetime!(2024-02-01 10:30:05 UTC),
+            "ACK2",
+        );
+        let segments: Vec<&str> = ack.split_terminator('\r').collect();
+        assert_eq!(
+            segments[1],
+            "MSA|AE|MSG00001|required field PID-3 is missing"
+        );
+        assert_eq!(
+            segments[2],
+            "ERR|||101^Required field missing^HL70357|E||||required field PID-3 is missing"
+        );
+    }
+
+    #[test]
+    fn rejects_unparsed_data() {
+        let error = RawMessage::parse("hello").unwrap_err();
+        let ack = reject_unparsed(&error, datetime!(2024-02-01 0:00 UTC), "ACK3");
+        let reparsed = RawMessage::parse(&ack).unwrap();
+        let msa = reparsed.segment("MSA").unwrap();
+        assert_eq!(msa.value(1).as_deref(), Some("AR"));
+        assert_eq!(msa.value(2), None);
+        assert_eq!(
+            reparsed
+                .segment("ERR")
+                .unwrap()
+                .field(3)
+                .component(1)
+                .as_deref(),
+            Some("100")
+        );
+    }
+}
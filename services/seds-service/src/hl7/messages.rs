@@ -0,0 +1,441 @@
+//! Message có kiểu của feed ED: ADT A01 (nhập viện), A03 (ra viện), A08 (cập
+//! nhật thông tin) và ORU R01 (kết quả).
+//!
+//! Thời điểm giữ nguyên dạng TS của HL7 (`YYYYMMDDHHMMSS+ZZZZ`): độ chính xác
+//! thay đổi theo hệ thống gửi và không phải lúc nào cũng có múi giờ.
+
+use serde::Serialize;
+
+use super::{Field, ParseError, RawMessage, Segment};
+
+/// MSH
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MessageHeader {
+    pub sending_application: Option<String>,
+    pub sending_facility: Option<String>,
+    pub receiving_application: Option<String>,
+    pub receiving_facility: Option<String>,
+    pub timestamp: Option<String>,
+    /// MSH-9.1, ví dụ `ADT`
+    pub message_type: String,
+    /// MSH-9.2, ví dụ `A01`
+    pub trigger_event: String,
+    pub control_id: String,
+    /// `P` (production), `T` (training), `D` (debugging)
+    pub processing_id: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Một phần tử CX của PID-3.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PatientIdentifier {
+    pub id: String,
+    pub assigning_authority: Option<String>,
+    /// Bảng 0203: `MR`, `SS`, `PI`, ...
+    pub type_code: Option<String>,
+}
+
+/// PID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Patient {
+    pub identifiers: Vec<PatientIdentifier>,
+    pub family_name: Option<String>,
+    pub given_name: Option<String>,
+    pub birth_date: Option<String>,
+    /// Bảng 0001: `F`, `M`, `O`, `U`, ...
+    pub sex: Option<String>,
+}
+
+/// PV1-3
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub point_of_care: Option<String>,
+    pub room: Option<String>,
+    pub bed: Option<String>,
+    pub facility: Option<String>,
+}
+
+/// PV1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Visit {
+    /// Bảng 0004: `E` (cấp cứu), `I`, `O`, ...
+    pub patient_class: Option<String>,
+    pub location: Location,
+    pub attending_doctor: Option<String>,
+    pub visit_number: Option<String>,
+    /// Bảng 0112
+    pub discharge_disposition: Option<String>,
+    pub admitted_at: Option<String>,
+    pub discharged_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AdtEvent {
+    /// A01
+    Admit,
+    /// A03
+    Discharge,
+    /// A08
+    Update,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AdtMessage {
+    pub header: MessageHeader,
+    pub event: AdtEvent,
+    /// EVN-2
+    pub recorded_at: Option<String>,
+    pub patient: Patient,
+    pub visit: Option<Visit>,
+}
+
+/// Kiểu CE/CWE: `code^text^system`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CodedValue {
+    pub code: Option<String>,
+    pub text: Option<String>,
+    /// Ví dụ `LN` (LOINC)
+    pub system: Option<String>,
+}
+
+/// OBX
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObservationResult {
+    pub set_id: Option<String>,
+    /// OBX-2: `NM`, `ST`, `CE`, ...
+    pub value_type: Option<String>,
+    pub code: CodedValue,
+    /// OBX-5; các lần lặp được nối bằng `, `
+    pub value: Option<String>,
+    pub units: Option<String>,
+    pub reference_range: Option<String>,
+    /// Bảng 0078: `N`, `H`, `L`, `A`, ...
+    pub abnormal_flags: Vec<String>,
+    /// Bảng 0085: `F` (final), `P`, `C`, ...
+    pub status: Option<String>,
+    pub observed_at: Option<String>,
+}
+
+/// OBR và các OBX theo sau.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Order {
+    pub placer_number: Option<String>,
+    pub filler_number: Option<String>,
+    pub service: CodedValue,
+    pub observed_at: Option<String>,
+    /// Bảng 0123: `F`, `P`, `C`, ...
+    pub result_status: Option<String>,
+    pub results: Vec<ObservationResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OruMessage {
+    pub header: MessageHeader,
+    pub patient: Patient,
+    pub visit: Option<Visit>,
+    pub orders: Vec<Order>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Hl7Message {
+    Adt(AdtMessage),
+    Oru(OruMessage),
+}
+
+impl Hl7Message {
+    pub fn from_raw(message: &RawMessage) -> Result<Self, ParseError> {
+        let header = MessageHeader::from_segment(message.header())?;
+        match (header.message_type.as_str(), header.trigger_event.as_str()) {
+            ("ADT", "A01" | "A03" | "A08") => AdtMessage::from_raw(message, header).map(Self::Adt),
+            ("ORU", "R01") => OruMessage::from_raw(message, header).map(Self::Oru),
+            ("ADT" | "ORU", event) => Err(ParseError::UnsupportedEvent(event.to_string())),
+            (kind, _) => Err(ParseError::UnsupportedMessageType(kind.to_string())),
+        }
+    }
+
+    pub fn header(&self) -> &MessageHeader {
+        match self {
+            Self::Adt(message) => &message.header,
+            Self::Oru(message) => &message.header,
+        }
+    }
+}
+
+impl MessageHeader {
+    fn from_segment(msh: &Segment) -> Result<Self, ParseError> {
+        let message_type = msh.field(9);
+        Ok(Self {
+            sending_application: msh.value(3),
+            sending_facility: msh.value(4),
+            receiving_application: msh.value(5),
+            receiving_facility: msh.value(6),
+            timestamp: msh.value(7),
+            message_type: message_type
+                .component(1)
+                .ok_or(ParseError::MissingField("MSH-9"))?,
+            trigger_event: message_type
+                .component(2)
+                .ok_or(ParseError::MissingField("MSH-9"))?,
+            control_id: msh.value(10).ok_or(ParseError::MissingField("MSH-10"))?,
+            processing_id: msh.value(11),
+            version: msh.value(12),
+        })
+    }
+}
+
+impl AdtMessage {
+    fn from_raw(message: &RawMessage, header: MessageHeader) -> Result<Self, ParseError> {
+        let event = match header.trigger_event.as_str() {
+            "A01" => AdtEvent::Admit,
+            "A03" => AdtEvent::Discharge,
+            _ => AdtEvent::Update,
+        };
+        let pid = message
+            .segment("PID")
+            .ok_or(ParseError::MissingSegment("PID"))?;
+        let visit = message.segment("PV1").map(Visit::from_segment);
+        // Nhập/ra viện mà không có PV1 thì không biết lượt khám nào
+        if visit.is_none() && event != AdtEvent::Update {
+            return Err(ParseError::MissingSegment("PV1"));
+        }
+        Ok(Self {
+            recorded_at: message.segment("EVN").and_then(|evn| evn.value(2)),
+            event,
+            header,
+            patient: Patient::from_segment(pid)?,
+            visit,
+        })
+    }
+}
+
+impl OruMessage {
+    fn from_raw(message: &RawMessage, header: MessageHeader) -> Result<Self, ParseError> {
+        let pid = message
+            .segment("PID")
+            .ok_or(ParseError::MissingSegment("PID"))?;
+        let mut orders: Vec<Order> = Vec::new();
+        for segment in &message.segments {
+            match segment.id.as_str() {
+                "OBR" => orders.push(Order::from_segment(segment)),
+                "OBX" => orders
+                    .last_mut()
+                    .ok_or(ParseError::MissingSegment("OBR"))?
+                    .results
+                    .push(ObservationResult::from_segment(segment)),
+                _ => {}
+            }
+        }
+        if orders.is_empty() {
+            return Err(ParseError::MissingSegment("OBR"));
+        }
+        Ok(Self {
+            header,
+            patient: Patient::from_segment(pid)?,
+            visit: message.segment("PV1").map(Visit::from_segment),
+            orders,
+        })
+    }
+}
+
+impl Patient {
+    fn from_segment(pid: &Segment) -> Result<Self, ParseError> {
+        let identifiers: Vec<PatientIdentifier> = pid
+            .repetitions(3)
+            .filter_map(|identifier| {
+                Some(PatientIdentifier {
+                    id: identifier.component(1)?,
+                    assigning_authority: identifier.component(4),
+                    type_code: identifier.component(5),
+                })
+            })
+            .collect();
+        if identifiers.is_empty() {
+            return Err(ParseError::MissingField("PID-3"));
+        }
+        let name = pid.field(5);
+        Ok(Self {
+            identifiers,
+            family_name: name.component(1),
+            given_name: name.component(2),
+            birth_date: pid.value(7),
+            sex: pid.value(8),
+        })
+    }
+}
+
+impl Visit {
+    fn from_segment(pv1: &Segment) -> Self {
+        let location = pv1.field(3);
+        Self {
+            patient_class: pv1.value(2),
+            location: Location {
+                point_of_care: location.component(1),
+                room: location.component(2),
+                bed: location.component(3),
+                facility: location.component(4),
+            },
+            attending_doctor: person_name(pv1.field(7)),
+            visit_number: pv1.value(19),
+            discharge_disposition: pv1.value(36),
+            admitted_at: pv1.value(44),
+            discharged_at: pv1.value(45),
+        }
+    }
+}
+
+impl Order {
+    fn from_segment(obr: &Segment) -> Self {
+        Self {
+            placer_number: obr.value(2),
+            filler_number: obr.value(3),
+            service: CodedValue::from_field(obr.field(4)),
+            observed_at: obr.value(7),
+            result_status: obr.value(25),
+            results: Vec::new(),
+        }
+    }
+}
+
+impl ObservationResult {
+    fn from_segment(obx: &Segment) -> Self {
+        let values: Vec<String> = obx
+            .repetitions(5)
+            .filter_map(|value| value.text())
+            .collect();
+        Self {
+            set_id: obx.value(1),
+            value_type: obx.value(2),
+            code: CodedValue::from_field(obx.field(3)),
+            value: (!values.is_empty()).then(|| values.join(", ")),
+            units: obx.value(6),
+            reference_range: obx.value(7),
+            abnormal_flags: obx
+                .repetitions(8)
+                .filter_map(|flag| flag.component(1))
+                .collect(),
+            status: obx.value(11),
+            observed_at: obx.value(14),
+        }
+    }
+}
+
+impl CodedValue {
+    fn from_field(field: Field) -> Self {
+        Self {
+            code: field.component(1),
+            text: field.component(2),
+            system: field.component(3),
+        }
+    }
+}
+
+/// Tên từ XCN (`id^family^given^middle^suffix^prefix`), ví dụ `Dr. Henry Seven`;
+/// chỉ có id thì trả về id.
+fn person_name(field: Field) -> Option<String> {
+    let parts: Vec<String> = [6, 3, 4, 2, 5]
+        .into_iter()
+        .filter_map(|n| field.component(n))
+        .collect();
+    if parts.is_empty() {
+        field.component(1)
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Hl7Message, ParseError> {
+        Hl7Message::from_raw(&RawMessage::parse(text).unwrap())
+    }
+
+    #[test]
+    fn parses_adt_messages() {
+        let Hl7Message::Adt(admit) = parse(include_str!("../../data/adt_a01.hl7")).unwrap() else {
+            panic!("expected ADT");
+        };
+        assert_eq!(admit.event, AdtEvent::Admit);
+        assert_eq!(admit.header.control_id, "MSG00001");
+        assert_eq!(
+            admit.header.sending_facility.as_deref(),
+            Some("COMMUNITY_ED")
+        );
+        assert_eq!(admit.recorded_at.as_deref(), Some("20240201102500+0700"));
+        assert_eq!(admit.patient.identifiers[0].id, "100234");
+        assert_eq!(
+            admit.patient.identifiers[0].type_code.as_deref(),
+            Some("MR")
+        );
+        assert_eq!(admit.patient.identifiers.len(), 2);
+        assert_eq!(admit.patient.family_name.as_deref(), Some("Lopez"));
+        assert_eq!(admit.patient.birth_date.as_deref(), Some("19870912"));
+        let visit = admit.visit.unwrap();
+        assert_eq!(visit.patient_class.as_deref(), Some("E"));
+        assert_eq!(visit.location.room.as_deref(), Some("TRAUMA 2"));
+        assert_eq!(visit.attending_doctor.as_deref(), Some("Dr. Henry Seven"));
+        assert_eq!(visit.visit_number.as_deref(), Some("V0001"));
+        assert_eq!(visit.admitted_at.as_deref(), Some("20240201102000+0700"));
+
+        let Hl7Message::Adt(discharge) = parse(include_str!("../../data/adt_a03.hl7")).unwrap()
+        else {
+            panic!("expected ADT");
+        };
+        assert_eq!(discharge.event, AdtEvent::Discharge);
+        let visit = discharge.visit.unwrap();
+        assert_eq!(visit.discharge_disposition.as_deref(), Some("01"));
+        assert_eq!(visit.discharged_at.as_deref(), Some("20240201182000+0700"));
+    }
+
+    #[test]
+    fn parses_oru_results_grouped_by_order() {
+        let Hl7Message::Oru(oru) = parse(include_str!("../../data/oru_r01.hl7")).unwrap() else {
+            panic!("expected ORU");
+        };
+        assert_eq!(oru.orders.len(), 1);
+        let order = &oru.orders[0];
+        assert_eq!(order.filler_number.as_deref(), Some("LAB99812"));
+        assert_eq!(order.service.code.as_deref(), Some("57021-8"));
+        assert_eq!(order.result_status.as_deref(), Some("F"));
+        // NTE giữa các OBX không tách nhóm
+        assert_eq!(order.results.len(), 3);
+        let leukocytes = &order.results[1];
+        assert_eq!(
+            leukocytes.code.text.as_deref(),
+            Some("Leukocytes [#/volume] in Blood")
+        );
+        assert_eq!(leukocytes.value.as_deref(), Some("14.8"));
+        assert_eq!(leukocytes.units.as_deref(), Some("10*3/uL"));
+        assert_eq!(leukocytes.abnormal_flags, ["H"]);
+        assert_eq!(order.results[2].value.as_deref(), Some("A^Rh+"));
+    }
+
+    #[test]
+    fn rejects_unsupported_or_incomplete_messages() {
+        let header = "MSH|^~\\&|EDIS|ED|SEDS|SEDS|20240201||";
+        assert_eq!(
+            parse(&format!("{}ADT^A02|1|P|2.5.1", header)),
+            Err(ParseError::UnsupportedEvent("A02".to_string()))
+        );
+        assert_eq!(
+            parse(&format!("{}SIU^S12|1|P|2.5.1", header)),
+            Err(ParseError::UnsupportedMessageType("SIU".to_string()))
+        );
+        assert_eq!(
+            parse(&format!("{}ADT^A01||P|2.5.1", header)),
+            Err(ParseError::MissingField("MSH-10"))
+        );
+        assert_eq!(
+            parse(&format!("{}ADT^A01|1|P|2.5.1\rPID|1||123^^^H^MR", header)),
+            Err(ParseError::MissingSegment("PV1"))
+        );
+        assert_eq!(
+            parse(&format!(
+                "{}ORU^R01|1|P|2.5.1\rPID|1||123\rOBX|1|NM|718-7||13",
+                header
+            )),
+            Err(ParseError::MissingSegment("OBR"))
+        );
+    }
+}
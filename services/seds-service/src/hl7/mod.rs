@@ -0,0 +1,336 @@
+//! Phân tích message HL7 v2 dạng ER7 (`MSH|^~\&|...`) thành segment và field.
+//!
+//! Field được đánh số như trong HL7: `PID-3` là `segment.field(3)`, và với MSH
+//! thì `MSH-1` là chính ký tự phân cách field. Giá trị trả về đã được giải mã
+//! escape (`\F\`, `\S\`, ...).
+
+pub mod ack;
+pub mod messages;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    /// Message không bắt đầu bằng MSH hợp lệ
+    #[error("invalid MSH segment: {0}")]
+    InvalidHeader(String),
+    #[error("invalid segment at line {0}")]
+    InvalidSegment(usize),
+    #[error("unsupported message type {0}")]
+    UnsupportedMessageType(String),
+    #[error("unsupported trigger event {0}")]
+    UnsupportedEvent(String),
+    #[error("required segment {0} is missing")]
+    MissingSegment(&'static str),
+    #[error("required field {0} is missing")]
+    MissingField(&'static str),
+}
+
+/// Ký tự phân cách, khai báo trong MSH-1 và MSH-2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiters {
+    pub field: char,
+    pub component: char,
+    pub repetition: char,
+    pub escape: char,
+    pub subcomponent: char,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            field: '|',
+            component: '^',
+            repetition: '~',
+            escape: '\\',
+            subcomponent: '&',
+        }
+    }
+}
+
+impl Delimiters {
+    /// MSH-2 tương ứng, ví dụ `^~\&`.
+    pub fn encoding_characters(&self) -> String {
+        [
+            self.component,
+            self.repetition,
+            self.escape,
+            self.subcomponent,
+        ]
+        .iter()
+        .collect()
+    }
+
+    /// Escape ký tự phân cách trong một giá trị để ghi vào message.
+    pub fn escape(&self, value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            let code = match c {
+                c if c == self.field => 'F',
+                c if c == self.component => 'S',
+                c if c == self.subcomponent => 'T',
+                c if c == self.repetition => 'R',
+                c if c == self.escape => 'E',
+                '\r' | '\n' => {
+                    escaped.push(' ');
+                    continue;
+                }
+                c => {
+                    escaped.push(c);
+                    continue;
+                }
+            };
+            escaped.extend([self.escape, code, self.escape]);
+        }
+        escaped
+    }
+
+    fn unescape(&self, value: &str) -> String {
+        let mut unescaped = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(start) = rest.find(self.escape) {
+            unescaped.push_str(&rest[..start]);
+            let after = &rest[start + self.escape.len_utf8()..];
+            let Some(end) = after.find(self.escape) else {
+                // Escape không đóng: giữ nguyên
+                unescaped.push_str(&rest[start..]);
+                return unescaped;
+            };
+            match &after[..end] {
+                "F" => unescaped.push(self.field),
+                "S" => unescaped.push(self.component),
+                "T" => unescaped.push(self.subcomponent),
+                "R" => unescaped.push(self.repetition),
+                "E" => unescaped.push(self.escape),
+                ".br" => unescaped.push('\n'),
+                // Định dạng (`\H\`, `\N\`) và mã hex/charset không hỗ trợ: bỏ
+                _ => {}
+            }
+            rest = &after[end + self.escape.len_utf8()..];
+        }
+        unescaped.push_str(rest);
+        unescaped
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub id: String,
+    /// `fields[0]` là id; với MSH, `fields[1]` là ký tự phân cách field
+    fields: Vec<String>,
+    delimiters: Delimiters,
+}
+
+impl Segment {
+    /// Field thứ `n` nguyên dạng trong message (chưa tách, chưa giải mã).
+    pub fn raw(&self, n: usize) -> &str {
+        self.fields.get(n).map(String::as_str).unwrap_or_default()
+    }
+
+    /// Lần lặp đầu tiên của field thứ `n`.
+    pub fn field(&self, n: usize) -> Field<'_> {
+        self.repetitions(n).next().unwrap_or(Field {
+            raw: "",
+            delimiters: self.delimiters,
+        })
+    }
+
+    /// Các lần lặp (`~`) khác rỗng của field thứ `n`.
+    pub fn repetitions(&self, n: usize) -> impl Iterator<Item = Field<'_>> {
+        let delimiters = self.delimiters;
+        self.raw(n)
+            .split(delimiters.repetition)
+            .filter(|raw| !raw.is_empty())
+            .map(move |raw| Field { raw, delimiters })
+    }
+
+    /// Giá trị (component đầu tiên) của field thứ `n`.
+    pub fn value(&self, n: usize) -> Option<String> {
+        self.field(n).component(1)
+    }
+}
+
+/// Một lần lặp của field, tách được thành component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<'a> {
+    raw: &'a str,
+    delimiters: Delimiters,
+}
+
+impl Field<'_> {
+    /// Component thứ `n` (từ 1), subcomponent đầu tiên, đã giải mã; `None` khi
+    /// rỗng.
+    pub fn component(&self, n: usize) -> Option<String> {
+        let component = self
+            .raw
+            .split(self.delimiters.component)
+            .nth(n.checked_sub(1)?)?;
+        let value = component
+            .split(self.delimiters.subcomponent)
+            .next()
+            .unwrap_or_default();
+        let value = self.delimiters.unescape(value);
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    /// Cả field đã giải mã, giữ nguyên ký tự phân cách component.
+    pub fn text(&self) -> Option<String> {
+        let value = self.delimiters.unescape(self.raw);
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+}
+
+/// Message đã tách segment, chưa gắn với loại message cụ thể.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawMessage {
+    pub delimiters: Delimiters,
+    pub segments: Vec<Segment>,
+}
+
+impl RawMessage {
+    /// Segment ngăn cách bằng `\r`; `\n` và `\r\n` (message chép từ file) cũng
+    /// được chấp nhận.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut lines = text
+            .split(['\r', '\n'])
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty());
+        let header = lines
+            .next()
+            .ok_or_else(|| ParseError::InvalidHeader("empty message".to_string()))?;
+        let delimiters = header_delimiters(header)?;
+
+        let mut segments = Vec::new();
+        for (index, line) in std::iter::once(header).chain(lines).enumerate() {
+            let mut fields: Vec<String> =
+                line.split(delimiters.field).map(str::to_string).collect();
+            let id = fields[0].clone();
+            if id.len() != 3 || !id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return Err(ParseError::InvalidSegment(index + 1));
+            }
+            if index == 0 {
+                fields.insert(1, delimiters.field.to_string());
+            }
+            segments.push(Segment {
+                id,
+                fields,
+                delimiters,
+            });
+        }
+        Ok(Self {
+            delimiters,
+            segments,
+        })
+    }
+
+    /// Segment đầu tiên có id `id`.
+    pub fn segment(&self, id: &str) -> Option<&Segment> {
+        self.segments.iter().find(|segment| segment.id == id)
+    }
+
+    pub fn header(&self) -> &Segment {
+        &self.segments[0]
+    }
+}
+
+fn header_delimiters(header: &str) -> Result<Delimiters, ParseError> {
+    let Some(rest) = header.strip_prefix("MSH") else {
+        return Err(ParseError::InvalidHeader(
+            "message does not start with MSH".to_string(),
+        ));
+    };
+    let declared: Vec<char> = rest.chars().take(5).collect();
+    let [field, component, repetition, escape, subcomponent] = declared[..] else {
+        return Err(ParseError::InvalidHeader(
+            "missing encoding characters".to_string(),
+        ));
+    };
+    let mut unique = declared.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    // MSH-2 có 4 ký tự, hoặc 5 với ký tự truncation của v2.7
+    let encoding = rest[field.len_utf8()..]
+        .split(field)
+        .next()
+        .unwrap_or_default();
+    if unique.len() != declared.len()
+        || declared
+            .iter()
+            .any(|c| c.is_alphanumeric() || c.is_whitespace())
+        || !(4..=5).contains(&encoding.chars().count())
+    {
+        return Err(ParseError::InvalidHeader(format!(
+            "invalid encoding characters '{}'",
+            encoding
+        )));
+    }
+    Ok(Delimiters {
+        field,
+        component,
+        repetition,
+        escape,
+        subcomponent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_segments_fields_and_components() {
+        let message = RawMessage::parse(include_str!("../../data/adt_a01.hl7")).unwrap();
+        assert_eq!(message.delimiters, Delimiters::default());
+        let ids: Vec<&str> = message.segments.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["MSH", "EVN", "PID", "PV1"]);
+
+        let header = message.header();
+        assert_eq!(header.raw(1), "|");
+        assert_eq!(header.raw(2), "^~\\&");
+        assert_eq!(header.value(3).as_deref(), Some("EDIS"));
+        assert_eq!(header.field(9).component(2).as_deref(), Some("A01"));
+        assert_eq!(header.value(10).as_deref(), Some("MSG00001"));
+
+        let pid = message.segment("PID").unwrap();
+        let identifiers: Vec<_> = pid
+            .repetitions(3)
+            .map(|id| (id.component(1), id.component(5)))
+            .collect();
+        assert_eq!(
+            identifiers,
+            [
+                (Some("100234".to_string()), Some("MR".to_string())),
+                (Some("987654321".to_string()), Some("SS".to_string())),
+            ]
+        );
+        assert_eq!(pid.field(5).component(2).as_deref(), Some("Camila"));
+        assert_eq!(pid.value(30), None);
+    }
+
+    #[test]
+    fn decodes_escapes_and_custom_delimiters() {
+        let message =
+            RawMessage::parse("MSH#$*/%#APP\nOBX#1#ST#code$text##a/F/b/S/c/T/d/E/e/.br/f/H/g")
+                .unwrap();
+        assert_eq!(message.delimiters.component, '$');
+        let obx = message.segment("OBX").unwrap();
+        assert_eq!(obx.field(3).component(2).as_deref(), Some("text"));
+        assert_eq!(obx.value(5).as_deref(), Some("a#b$c%d/e\nfg"));
+        assert_eq!(message.delimiters.escape("a#b$c"), "a/F/b/S/c");
+
+        assert!(matches!(
+            RawMessage::parse("PID|1"),
+            Err(ParseError::InvalidHeader(_))
+        ));
+        assert!(matches!(
+            RawMessage::parse("MSH|^^\\&|A"),
+            Err(ParseError::InvalidHeader(_))
+        ));
+        assert_eq!(
+            RawMessage::parse("MSH|^~\\&|A\rnot a segment"),
+            Err(ParseError::InvalidSegment(2))
+        );
+    }
+}
@@ -1,3 +1,55 @@
-fn main() {
-    println!("Hello, world!");
+//! SEDS ingestion service: nhận feed HL7 v2 (ADT A01/A03/A08, ORU R01) của
+//! khoa cấp cứu qua MLLP và ghi thành sự kiện nội bộ vào nhật ký.
+
+mod config;
+mod event_log;
+mod events;
+mod hl7;
+mod mllp;
+mod server;
+
+use std::path::Path;
+use std::time::Duration;
+
+use tracing_subscriber::EnvFilter;
+
+use crate::event_log::EventLog;
+use crate::server::ServerOptions;
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let config = config::load_settings().unwrap_or_else(|e| {
+        tracing::error!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    });
+
+    let log = EventLog::open(Path::new(&config.event_log_path)).unwrap_or_else(|e| {
+        tracing::error!("Failed to open event log {}: {}", config.event_log_path, e);
+        std::process::exit(1);
+    });
+
+    let addr = format!("{}:{}", config.host, config.port);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind {}: {}", addr, e));
+    tracing::info!("SEDS MLLP listener on {}", addr);
+    server::serve(
+        listener,
+        log,
+        ServerOptions {
+            max_message_bytes: config.max_message_bytes,
+            idle_timeout: Duration::from_secs(config.idle_timeout_secs),
+        },
+        async {
+            tokio::signal::ctrl_c().await.ok();
+        },
+    )
+    .await;
 }
@@ -0,0 +1,121 @@
+//! Minimal Lower Layer Protocol: mỗi message HL7 được bọc giữa `VT` (0x0B) và
+//! `FS CR` (0x1C 0x0D) trên một kết nối TCP.
+
+use std::io;
+
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const START_BLOCK: u8 = 0x0b;
+pub const END_BLOCK: u8 = 0x1c;
+pub const CARRIAGE_RETURN: u8 = 0x0d;
+
+#[derive(Debug, Error)]
+pub enum MllpError {
+    #[error("connection error: {0}")]
+    Io(#[from] io::Error),
+    /// Frame vượt giới hạn; phần còn lại của kết nối không còn tin được
+    #[error("frame exceeds {0} bytes")]
+    TooLarge(usize),
+    /// Kết nối đóng giữa chừng một frame
+    #[error("connection closed inside a frame")]
+    Truncated,
+    #[error("frame is not terminated by FS CR")]
+    MissingTrailer,
+}
+
+/// Đọc frame tiếp theo, bỏ qua các byte trước `VT`. `None` khi kết nối đóng
+/// giữa hai frame.
+pub async fn read_frame<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> Result<Option<Vec<u8>>, MllpError> {
+    let mut skipped = Vec::new();
+    loop {
+        skipped.clear();
+        let read = (&mut *reader)
+            .take(max_size as u64)
+            .read_until(START_BLOCK, &mut skipped)
+            .await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if skipped.last() == Some(&START_BLOCK) {
+            break;
+        }
+    }
+
+    let mut frame = Vec::new();
+    (&mut *reader)
+        .take(max_size as u64 + 1)
+        .read_until(END_BLOCK, &mut frame)
+        .await?;
+    match frame.pop() {
+        Some(END_BLOCK) => {}
+        _ if frame.len() >= max_size => return Err(MllpError::TooLarge(max_size)),
+        _ => return Err(MllpError::Truncated),
+    }
+    match reader.read_u8().await {
+        Ok(CARRIAGE_RETURN) => Ok(Some(frame)),
+        Ok(_) => Err(MllpError::MissingTrailer),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(MllpError::Truncated),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 3);
+    frame.push(START_BLOCK);
+    frame.extend_from_slice(payload);
+    frame.extend_from_slice(&[END_BLOCK, CARRIAGE_RETURN]);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_consecutive_frames() {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, b"MSH|first").await.unwrap();
+        // Byte rác giữa hai frame (ví dụ newline của sender) bị bỏ qua
+        stream.extend_from_slice(b"\r\n");
+        write_frame(&mut stream, b"MSH|second").await.unwrap();
+
+        let mut reader = stream.as_slice();
+        assert_eq!(
+            read_frame(&mut reader, 1024).await.unwrap().as_deref(),
+            Some(&b"MSH|first"[..])
+        );
+        assert_eq!(
+            read_frame(&mut reader, 1024).await.unwrap().as_deref(),
+            Some(&b"MSH|second"[..])
+        );
+        assert!(read_frame(&mut reader, 1024).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_broken_frames() {
+        let mut reader = &b"\x0bMSH|unterminated"[..];
+        assert!(matches!(
+            read_frame(&mut reader, 1024).await,
+            Err(MllpError::Truncated)
+        ));
+
+        let mut reader = &b"\x0bMSH|no-cr\x1cMSH"[..];
+        assert!(matches!(
+            read_frame(&mut reader, 1024).await,
+            Err(MllpError::MissingTrailer)
+        ));
+
+        let mut large = vec![START_BLOCK];
+        large.extend(std::iter::repeat_n(b'A', 64));
+        large.extend_from_slice(&[END_BLOCK, CARRIAGE_RETURN]);
+        assert!(matches!(
+            read_frame(&mut large.as_slice(), 16).await,
+            Err(MllpError::TooLarge(16))
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+//! Listener MLLP: mỗi kết nối nhận lần lượt từng message, ghi sự kiện vào
+//! nhật ký và trả ACK trước khi đọc message tiếp theo.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::event_log::{Appended, EventLog, MessageKey};
+use crate::events::ClinicalEvent;
+use crate::hl7::RawMessage;
+use crate::hl7::ack::{self, AckCode};
+use crate::hl7::messages::Hl7Message;
+use crate::mllp;
+
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    pub max_message_bytes: usize,
+    pub idle_timeout: Duration,
+}
+
+/// Nhận kết nối cho tới khi `shutdown` hoàn tất. Kết nối đang mở được xử lý
+/// tiếp trong task riêng.
+pub async fn serve(
+    listener: TcpListener,
+    log: EventLog,
+    options: ServerOptions,
+    shutdown: impl Future<Output = ()>,
+) {
+    let options = Arc::new(options);
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    tracing::info!("MLLP connection from {}", peer);
+                    let log = log.clone();
+                    let options = options.clone();
+                    tokio::spawn(async move {
+                        handle_connection(stream, &log, &options).await;
+                        tracing::info!("MLLP connection from {} closed", peer);
+                    });
+                }
+                Err(e) => tracing::warn!("Failed to accept MLLP connection: {}", e),
+            },
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, log: &EventLog, options: &ServerOptions) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let frame = match tokio::time::timeout(
+            options.idle_timeout,
+            mllp::read_frame(&mut reader, options.max_message_bytes),
+        )
+        .await
+        {
+            Ok(Ok(Some(frame))) => frame,
+            Ok(Ok(None)) => return,
+            Ok(Err(e)) => {
+                // Không biết frame tiếp theo bắt đầu ở đâu: đóng để sender gửi lại
+                tracing::warn!("Closing MLLP connection: {}", e);
+                return;
+            }
+            Err(_) => {
+                tracing::info!("Closing idle MLLP connection");
+                return;
+            }
+        };
+        let ack = process(&frame, log).await;
+        if let Err(e) = mllp::write_frame(&mut writer, ack.as_bytes()).await {
+            tracing::warn!("Failed to send ACK: {}", e);
+            return;
+        }
+    }
+}
+
+/// Xử lý một message và trả về ACK. `AA` chỉ được trả sau khi sự kiện của
+/// message đã được ghi xuống nhật ký; ghi lỗi thì trả `AE` để sender gửi lại.
+/// Message gửi lại (cùng MSH-3, MSH-4, MSH-10) được `AA` mà không ghi thêm.
+pub async fn process(payload: &[u8], log: &EventLog) -> String {
+    let now = OffsetDateTime::now_utc();
+    let control_id = control_id();
+    let text = String::from_utf8_lossy(payload);
+    let raw = match RawMessage::parse(&text) {
+        Ok(raw) => raw,
+        Err(e) => {
+            tracing::warn!("Rejected unreadable HL7 message: {}", e);
+            return ack::reject_unparsed(&e, now, &control_id);
+        }
+    };
+    match Hl7Message::from_raw(&raw) {
+        Ok(message) => {
+            let header = message.header();
+            // MRN và dữ liệu bệnh nhân là PHI: chỉ ghi loại message và MSH-10
+            tracing::info!(
+                "Received {}^{} {}",
+                header.message_type,
+                header.trigger_event,
+                header.control_id
+            );
+            let events = ClinicalEvent::from_message(&message);
+            match log.append(MessageKey::of(header), &events, now).await {
+                Ok(Appended::Stored) => {
+                    // Nội dung sự kiện là PHI nên chỉ ghi loại và MSH-10
+                    for event in &events {
+                        tracing::info!(target: "events", "{} {}", event.kind(), event.message_id());
+                    }
+                }
+                Ok(Appended::Duplicate) => {
+                    tracing::info!("Message {} was already received", header.control_id);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to record events of {}: {}", header.control_id, e);
+                    return ack::application_error(
+                        &raw,
+                        "Failed to record events",
+                        now,
+                        &control_id,
+                    );
+                }
+            }
+            ack::acknowledge(&raw, AckCode::Accept, None, now, &control_id)
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Rejected HL7 message {}: {}",
+                raw.header().value(10).unwrap_or_default(),
+                e
+            );
+            ack::acknowledge(&raw, AckCode::for_error(&e), Some(&e), now, &control_id)
+        }
+    }
+}
+
+/// MSH-10 của ACK (ST, tối đa 20 ký tự).
+fn control_id() -> String {
+    let mut id = uuid::Uuid::new_v4().simple().to_string();
+    id.truncate(20);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    async fn exchange(stream: &mut TcpStream, message: &str) -> RawMessage {
+        let (reader, mut writer) = stream.split();
+        mllp::write_frame(&mut writer, message.replace('\n', "\r").as_bytes())
+            .await
+            .unwrap();
+        let frame = mllp::read_frame(&mut BufReader::new(reader), 4096)
+            .await
+            .unwrap()
+            .unwrap();
+        RawMessage::parse(std::str::from_utf8(&frame).unwrap()).unwrap()
+    }
+
+    fn ack_code(ack: &RawMessage) -> Option<String> {
+        ack.segment("MSA").unwrap().value(1)
+    }
+
+    fn options() -> ServerOptions {
+        ServerOptions {
+            max_message_bytes: 4096,
+            idle_timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("seds-events-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    /// Loại sự kiện của từng dòng trong nhật ký.
+    fn recorded(path: &std::path::Path) -> Vec<Vec<String>> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line).unwrap();
+                record["events"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|event| event["type"].as_str().unwrap().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn acknowledges_messages_after_recording_their_events() {
+        let path = temp_path();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let log = EventLog::open(&path).unwrap();
+        tokio::spawn(serve(listener, log, options(), std::future::pending()));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let ack = exchange(&mut stream, include_str!("../data/adt_a01.hl7")).await;
+        assert_eq!(ack_code(&ack).as_deref(), Some("AA"));
+        assert_eq!(
+            ack.segment("MSA").unwrap().value(2).as_deref(),
+            Some("MSG00001")
+        );
+        assert_eq!(recorded(&path), [["patient_admitted"]]);
+
+        // Cùng kết nối, message tiếp theo
+        let ack = exchange(&mut stream, include_str!("../data/oru_r01.hl7")).await;
+        assert_eq!(ack_code(&ack).as_deref(), Some("AA"));
+        assert_eq!(recorded(&path), [["patient_admitted"], ["result_reported"]]);
+
+        let ack = exchange(
+            &mut stream,
+            "MSH|^~\\&|EDIS|ED|SEDS|SEDS|20240201||ADT^A01|MSG9|P|2.5.1\nEVN|A01",
+        )
+        .await;
+        assert_eq!(ack_code(&ack).as_deref(), Some("AE"));
+        assert_eq!(ack.header().field(9).component(2).as_deref(), Some("A01"));
+
+        let ack = exchange(&mut stream, "not hl7").await;
+        assert_eq!(ack_code(&ack).as_deref(), Some("AR"));
+        assert_eq!(recorded(&path).len(), 2);
+
+        // Frame hỏng làm đóng kết nối
+        stream.write_all(b"\x0bMSH|^~\\&\x1cX").await.unwrap();
+        let mut rest = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut stream, &mut rest)
+            .await
+            .unwrap();
+        assert!(rest.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn acknowledges_resent_messages_without_recording_them_again() {
+        let path = temp_path();
+        let message = include_str!("../data/adt_a01.hl7").replace('\n', "\r");
+        let log = EventLog::open(&path).unwrap();
+        let ack = RawMessage::parse(&process(message.as_bytes(), &log).await).unwrap();
+        assert_eq!(ack_code(&ack).as_deref(), Some("AA"));
+        drop(log);
+
+        // Sender không nhận được ACK và gửi lại sau khi service khởi động lại
+        let log = EventLog::open(&path).unwrap();
+        let ack = RawMessage::parse(&process(message.as_bytes(), &log).await).unwrap();
+        assert_eq!(ack_code(&ack).as_deref(), Some("AA"));
+        assert_eq!(
+            ack.segment("MSA").unwrap().value(2).as_deref(),
+            Some("MSG00001")
+        );
+        assert_eq!(recorded(&path), [["patient_admitted"]]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn returns_an_application_error_when_the_log_cannot_be_written() {
+        // Mọi lần ghi vào /dev/full đều lỗi ENOSPC
+        let log = EventLog::open(std::path::Path::new("/dev/full")).unwrap();
+        let message = include_str!("../data/adt_a01.hl7").replace('\n', "\r");
+
+        let ack = process(message.as_bytes(), &log).await;
+        let ack = RawMessage::parse(&ack).unwrap();
+        assert_eq!(ack_code(&ack).as_deref(), Some("AE"));
+        assert_eq!(
+            ack.segment("MSA").unwrap().value(2).as_deref(),
+            Some("MSG00001")
+        );
+        assert_eq!(
+            ack.segment("ERR").unwrap().field(3).component(1).as_deref(),
+            Some("207")
+        );
+
+        // Message chưa được ghi nên lần gửi lại cũng không được coi là trùng
+        let ack = RawMessage::parse(&process(message.as_bytes(), &log).await).unwrap();
+        assert_eq!(ack_code(&ack).as_deref(), Some("AE"));
+    }
+}